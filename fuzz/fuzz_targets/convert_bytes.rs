@@ -0,0 +1,8 @@
+//! 任意のバイト列の変換（cargo +nightly fuzz run convert_bytes）
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    pdf2md::fuzz_convert(data);
+});
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::http;
+
+/// 画像の代替テキストを生成する外部の仕組み
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AltTextHook {
+    /// 画像のパスを最後の引数として渡したコマンドの標準出力を代替テキストにする
+    Command(String),
+    /// 画像を本文として POST した HTTP エンドポイントの応答の本文を代替テキストにする
+    Url(String),
+}
+
+impl AltTextHook {
+    /// 画像の代替テキストを生成する（改行は空白にまとめ、Markdown の画像の構文を壊す角括弧は除く）
+    pub fn generate(&self, image: &Path) -> Result<String> {
+        let text = match self {
+            AltTextHook::Command(command) => run_command(command, image)?,
+            AltTextHook::Url(url) => post_image(url, image)?,
+        };
+        Ok(text.split_whitespace().collect::<Vec<_>>().join(" ").replace(['[', ']'], ""))
+    }
+}
+
+/// コマンドを実行し、標準出力を返す（コマンドの文字列は空白で引数に分ける）
+fn run_command(command: &str, image: &Path) -> Result<String> {
+    let mut words = command.split_whitespace();
+    let program = words.next().context("代替テキストを生成するコマンドが空です")?;
+    let output = Command::new(program)
+        .args(words)
+        .arg(image)
+        .output()
+        .with_context(|| format!("代替テキストを生成するコマンドを実行できません: {}", program))?;
+    if !output.status.success() {
+        bail!("代替テキストを生成するコマンドが失敗しました（{}）: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 画像を本文として POST し、応答の本文を返す
+fn post_image(url: &str, image: &Path) -> Result<String> {
+    let body = fs::read(image).with_context(|| format!("画像を読み込めません: {:?}", image))?;
+    let content_type = match image.extension().and_then(|e| e.to_str()) {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    };
+    http::post(url, content_type, &body).context("代替テキストを生成できません")
+}
+
+// Windows の echo は cmd の組み込みのコマンドで実行できないため、Unix でのみ試す
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    // 単体テスト: コマンドによる代替テキストの生成
+    #[test]
+    fn test_generate_with_command() {
+        let hook = AltTextHook::Command("echo A [bar]\tchart of".to_string());
+        assert_eq!(hook.generate(Path::new("fig-01.png")).unwrap(), "A bar chart of fig-01.png");
+    }
+}
@@ -0,0 +1,107 @@
+use lopdf::{Document, Object, ObjectId};
+
+use crate::destinations::{Destination, Destinations};
+use crate::metadata;
+
+/// ページ上の範囲（ページ座標、y 下向き）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Area {
+    /// 点が範囲内（margin だけ広げた範囲）にあるかどうか
+    pub fn contains(&self, x: f64, y: f64, margin: f64) -> bool {
+        x >= self.x0 - margin && x <= self.x1 + margin && y >= self.y0 - margin && y <= self.y1 + margin
+    }
+}
+
+/// ページに付けられた注釈（ハイライト、コメントなど）
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// 注釈のオブジェクト ID（ページ辞書に直接書かれた注釈は None）
+    pub id: Option<ObjectId>,
+    /// 注釈の種類（Highlight、Text、FreeText など）
+    pub subtype: String,
+    /// 注釈が覆う範囲（QuadPoints があれば四角形ごと、無ければ Rect）
+    pub areas: Vec<Area>,
+    /// 注釈の本文（Contents）
+    pub contents: Option<String>,
+    /// 注釈の作成者（T）
+    pub author: Option<String>,
+    /// 注釈の更新日時（M、ISO 8601 形式）
+    pub modified: Option<String>,
+    /// 返信先の注釈のオブジェクト ID（IRT）
+    pub in_reply_to: Option<ObjectId>,
+    /// リンクの注釈の文書内の移動先（/Dest か GoTo アクション）
+    pub target: Option<Destination>,
+    /// リンクの注釈の外部への URI（URI アクション）
+    pub uri: Option<String>,
+}
+
+/// ページの Annots から注釈を読み込む（座標は page_height で y 下向きに直す）
+pub fn page_annotations(doc: &Document, page_id: ObjectId, page_height: f64, destinations: &Destinations) -> Vec<Annotation> {
+    let Ok(page) = doc.get_dictionary(page_id) else {
+        return Vec::new();
+    };
+    let Some(annots) = page.get(b"Annots").ok().and_then(|a| doc.dereference(a).ok()).and_then(|(_, a)| a.as_array().ok()) else {
+        return Vec::new();
+    };
+
+    annots
+        .iter()
+        .filter_map(|entry| {
+            let id = entry.as_reference().ok();
+            let dict = doc.dereference(entry).ok()?.1.as_dict().ok()?;
+            let subtype = String::from_utf8_lossy(dict.get(b"Subtype").and_then(Object::as_name).ok()?).into_owned();
+            let numbers = |key: &[u8]| -> Vec<f64> {
+                dict.get(key)
+                    .ok()
+                    .and_then(|o| doc.dereference(o).ok())
+                    .and_then(|(_, o)| o.as_array().ok())
+                    .map(|array| array.iter().filter_map(|o| o.as_float().ok().map(f64::from)).collect())
+                    .unwrap_or_default()
+            };
+
+            // QuadPoints は四角形ごとに4つの頂点（x, y の8個の数）
+            let mut areas: Vec<Area> = numbers(b"QuadPoints").chunks_exact(8).map(|quad| bounding_area(quad, page_height)).collect();
+            if areas.is_empty() {
+                let rect = numbers(b"Rect");
+                if rect.len() == 4 {
+                    areas.push(bounding_area(&rect, page_height));
+                }
+            }
+
+            let contents = metadata::info_string(doc, dict, b"Contents").filter(|text| !text.trim().is_empty());
+            let author = metadata::info_string(doc, dict, b"T").filter(|text| !text.trim().is_empty());
+            let modified = metadata::info_string(doc, dict, b"M").and_then(|date| metadata::parse_pdf_date(&date));
+            let in_reply_to = dict.get(b"IRT").and_then(Object::as_reference).ok();
+            let target = if subtype == "Link" { destinations.target(doc, dict) } else { None };
+            let uri = if subtype == "Link" { link_uri(doc, dict) } else { None };
+            Some(Annotation { id, subtype, areas, contents, author, modified, in_reply_to, target, uri })
+        })
+        .collect()
+}
+
+/// リンクの注釈の URI アクションの URI（前後の空白は除く）
+fn link_uri(doc: &Document, dict: &lopdf::Dictionary) -> Option<String> {
+    let action = dict.get(b"A").ok().and_then(|a| doc.dereference(a).ok()).and_then(|(_, a)| a.as_dict().ok())?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"URI" {
+        return None;
+    }
+    let uri = doc.dereference(action.get(b"URI").ok()?).ok()?.1.as_str().ok()?;
+    let uri = String::from_utf8_lossy(uri).trim().to_string();
+    (!uri.is_empty()).then_some(uri)
+}
+
+/// 頂点の座標の並び（x, y, x, y, …）を囲む範囲
+fn bounding_area(points: &[f64], page_height: f64) -> Area {
+    let xs = points.iter().step_by(2);
+    let ys = points.iter().skip(1).step_by(2);
+    let (x0, x1) = xs.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    let (y0, y1) = ys.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &y| (lo.min(y), hi.max(y)));
+    Area { x0, y0: page_height - y1, x1, y1: page_height - y0 }
+}
@@ -0,0 +1,201 @@
+use clap::ValueEnum;
+use regex::Regex;
+use std::sync::LazyLock;
+use serde::Deserialize;
+
+use crate::hyphenation::Dehyphenator;
+use crate::layout::{self, Glyph, PageLayout, Segment};
+use crate::paragraphs;
+
+/// 記事ごとの出力方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleOutput {
+    /// 1つの Markdown に記事ごとの節として出力する
+    Sections,
+    /// 記事ごとに別のファイル（出力ファイル名-NN.md）に出力し、出力ファイルには記事の一覧を書く
+    Files,
+}
+
+/// 紙面から切り出した1本の記事
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    pub page: u32,
+    /// 見出し（見出しより上にある題字などは None）
+    pub headline: Option<String>,
+    /// 署名（By ... / ○○記者）
+    pub byline: Option<String>,
+    /// 本文（段落ごとに空行で区切ったテキスト）
+    pub body: String,
+}
+
+/// 見出しとみなす、本文の文字サイズに対する倍率
+const HEADLINE_SCALE: f64 = 1.4;
+
+/// 署名の行（By ...、○○記者、文：○○）
+static BYLINE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?i:by\s+\S.*)$|^.{1,20}記者$|^文[:：].+$").unwrap());
+
+/// 各ページを見出しと署名で記事に分け、記事ごとに段組みの読み順を整えて本文を組み立てる
+///
+/// 本文の区間は、左右の範囲が重なる見出しのうち最も近い上の見出しの記事に属するものとする。
+pub fn segment_articles(pages: &[PageLayout], dehyphenator: Option<&Dehyphenator>) -> Vec<Article> {
+    let mut articles = Vec::new();
+
+    for page in pages {
+        let segments = layout::split_segments(&page.glyphs);
+        let body_size = body_font_size(&page.glyphs);
+        let headlines = headline_groups(&segments, body_size);
+
+        // 見出しごとの区間（None は最初の見出しより上の区間）
+        let mut members: Vec<Vec<&Segment>> = vec![Vec::new(); headlines.len() + 1];
+        for segment in segments.iter().filter(|s| s.font_size < body_size * HEADLINE_SCALE) {
+            let owner = headlines
+                .iter()
+                .enumerate()
+                .filter(|(_, h)| {
+                    let (x0, x1, y) = extent(h);
+                    y <= segment.y && segment.x0 < x1 && segment.x1 > x0
+                })
+                .max_by(|(_, a), (_, b)| extent(a).2.total_cmp(&extent(b).2))
+                .map_or(0, |(index, _)| index + 1);
+            members[owner].push(segment);
+        }
+
+        for (index, body_segments) in members.iter().enumerate() {
+            let headline = index.checked_sub(1).map(|i| segments_text(&page.glyphs, &headlines[i]));
+            if headline.is_none() && body_segments.is_empty() {
+                continue;
+            }
+
+            let mut body_segments = body_segments.clone();
+            body_segments.sort_by(|a, b| a.y.total_cmp(&b.y));
+            let mut byline = None;
+            if headline.is_some() {
+                if let Some(first) = body_segments.first() {
+                    let text = segments_text(&page.glyphs, &[first]);
+                    if BYLINE_REGEX.is_match(&text) {
+                        byline = Some(text);
+                        body_segments.remove(0);
+                    }
+                }
+            }
+
+            articles.push(Article { page: page.number, headline, byline, body: article_body(page, &body_segments, dehyphenator) });
+        }
+    }
+
+    articles
+}
+
+/// 記事の本文を、段組みの読み順に並べて段落のテキストにする
+fn article_body(page: &PageLayout, segments: &[&Segment], dehyphenator: Option<&Dehyphenator>) -> String {
+    let mut glyphs: Vec<Glyph> = Vec::new();
+    for segment in segments {
+        for (i, glyph) in page.glyphs[segment.start..segment.end].iter().enumerate() {
+            let mut glyph = glyph.clone();
+            glyph.word_start = glyph.word_start || i == 0;
+            glyphs.push(glyph);
+        }
+    }
+
+    // 行の左端の揃う位置の数を段数とする
+    let mut columns: Vec<f64> = Vec::new();
+    for segment in segments {
+        if !columns.iter().any(|&x| (x - segment.x0).abs() <= segment.font_size * 2.0) {
+            columns.push(segment.x0);
+        }
+    }
+
+    let mut article_page = PageLayout { number: page.number, width: page.width, height: page.height, glyphs, ..Default::default() };
+    if columns.len() > 1 {
+        article_page.reorder_columns(columns.len());
+    }
+    paragraphs::pages_to_text(&[article_page], dehyphenator)
+}
+
+/// 本文の文字サイズ（最も多くの文字に使われている大きさ）
+fn body_font_size(glyphs: &[Glyph]) -> f64 {
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    for glyph in glyphs {
+        match counts.iter_mut().find(|(size, _)| (size - glyph.font_size).abs() < 0.5) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((glyph.font_size, 1)),
+        }
+    }
+    counts.into_iter().max_by_key(|&(_, count)| count).map_or(0.0, |(size, _)| size)
+}
+
+/// 本文より十分大きな文字の区間を、縦に続くものごとにまとめて見出しにする
+fn headline_groups(segments: &[Segment], body_size: f64) -> Vec<Vec<&Segment>> {
+    let mut groups: Vec<Vec<&Segment>> = Vec::new();
+    for segment in segments.iter().filter(|s| s.font_size >= body_size * HEADLINE_SCALE) {
+        let joined = groups.iter_mut().find(|group| {
+            let last = group.last().unwrap();
+            (last.font_size - segment.font_size).abs() < 0.5
+                && segment.y > last.y
+                && segment.y - last.y <= segment.font_size * 1.6
+                && segment.x0 < last.x1
+                && segment.x1 > last.x0
+        });
+        match joined {
+            Some(group) => group.push(segment),
+            None => groups.push(vec![segment]),
+        }
+    }
+    groups
+}
+
+/// 区間の集まりの左右の範囲と、先頭の行の y 座標
+fn extent(segments: &[&Segment]) -> (f64, f64, f64) {
+    let x0 = segments.iter().map(|s| s.x0).fold(f64::INFINITY, f64::min);
+    let x1 = segments.iter().map(|s| s.x1).fold(f64::NEG_INFINITY, f64::max);
+    let y = segments.iter().map(|s| s.y).fold(f64::INFINITY, f64::min);
+    (x0, x1, y)
+}
+
+/// 区間の文字を1行のテキストにする
+fn segments_text(glyphs: &[Glyph], segments: &[&Segment]) -> String {
+    let text: Vec<String> = segments.iter().map(|s| layout::glyphs_to_text(&glyphs[s.start..s.end])).collect();
+    text.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 見出しと署名による記事の切り出し
+    #[test]
+    fn test_segment_articles() {
+        let glyph = |text: &str, x: f64, y: f64, font_size: f64| Glyph {
+            text: text.to_string(),
+            x,
+            y,
+            width: text.chars().count() as f64 * font_size * 0.5,
+            font_size,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence: None,
+        };
+        // 左右に並んだ2本の記事。本文は行ごとに左右交互に描かれている
+        let mut glyphs = vec![glyph("The Daily", 50.0, 30.0, 10.0)];
+        glyphs.push(glyph("Storm hits", 50.0, 80.0, 24.0));
+        glyphs.push(glyph("Election", 300.0, 80.0, 24.0));
+        glyphs.push(glyph("By Ann Lee", 50.0, 100.0, 10.0));
+        for (i, y) in [120.0, 132.0, 144.0].into_iter().enumerate() {
+            glyphs.push(glyph(&format!("storm line {}", i + 1), 50.0, y, 10.0));
+            glyphs.push(glyph(&format!("vote line {}", i + 1), 300.0, y, 10.0));
+        }
+        let pages = vec![PageLayout { number: 1, glyphs, ..Default::default() }];
+
+        let articles = segment_articles(&pages, None);
+        let summary: Vec<(Option<&str>, Option<&str>)> =
+            articles.iter().map(|a| (a.headline.as_deref(), a.byline.as_deref())).collect();
+        assert_eq!(summary, vec![(None, None), (Some("Storm hits"), Some("By Ann Lee")), (Some("Election"), None)]);
+        assert_eq!(articles[0].body, "The Daily");
+        assert!(articles[1].body.contains("storm line 1") && !articles[1].body.contains("vote"));
+        assert!(articles[2].body.contains("vote line 3") && !articles[2].body.contains("storm"));
+    }
+}
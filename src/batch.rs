@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 一括変換する PDF（相対パスは出力先のディレクトリ構成に使う）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchInput {
+    pub path: PathBuf,
+    pub relative: PathBuf,
+}
+
+/// 入力がディレクトリかワイルドカード（*、?、**）を含むパターンで、複数の PDF をまとめて変換する指定かどうか（URL の ? は問い合わせとみなす）
+pub fn is_batch(input: &Path) -> bool {
+    !crate::http::is_url(input) && (input.is_dir() || has_wildcard(&input.to_string_lossy()))
+}
+
+/// ディレクトリ以下の PDF か、パターンに一致する PDF を、パスの順に集める
+///
+/// ディレクトリの場合はサブディレクトリも含めた拡張子 .pdf のファイルを、パターンの場合はワイルドカードを含まない
+/// 先頭の部分をディレクトリとして、その下の一致するファイルを集める。相対パスはそのディレクトリからのパスとする。
+/// Windows ではファイル名と同じく、パターンも大文字と小文字を区別しない。UTF-8 ではないファイル名も、パスはそのまま保つ。
+pub fn collect_inputs(input: &Path) -> Result<Vec<BatchInput>> {
+    let (base, pattern) = if input.is_dir() {
+        (input.to_path_buf(), None)
+    } else {
+        let components: Vec<Component> = input.components().collect();
+        let split = components.iter().position(|component| has_wildcard(&component.as_os_str().to_string_lossy())).unwrap_or(components.len());
+        let base: PathBuf = components[..split].iter().collect();
+        let pattern: Vec<String> = components[split..].iter().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect();
+        (if base.as_os_str().is_empty() { PathBuf::from(".") } else { base }, Some(pattern))
+    };
+
+    let mut files = Vec::new();
+    walk(&base, &mut files).with_context(|| format!("入力のディレクトリを読み込めません: {:?}", base))?;
+
+    let mut inputs: Vec<BatchInput> = files
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(&base).ok()?.to_path_buf();
+            let names: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect();
+            let matched = match &pattern {
+                Some(pattern) => matches(pattern, &names, cfg!(windows)),
+                None => path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf")),
+            };
+            matched.then_some(BatchInput { path, relative })
+        })
+        .collect();
+    inputs.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(inputs)
+}
+
+/// 一括変換の出力ファイルのパス（出力先のディレクトリの下に入力の相対パスと同じ構成で、拡張子を .md にする。指定がない場合は入力と同じ場所）
+pub fn output_path(input: &BatchInput, output_dir: Option<&Path>) -> PathBuf {
+    match output_dir {
+        Some(dir) => dir.join(&input.relative).with_extension("md"),
+        None => input.path.with_extension("md"),
+    }
+}
+
+/// ディレクトリ以下のファイルを集める（隠しディレクトリはたどらない）
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                walk(&path, files)?;
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn has_wildcard(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+/// パスの要素がパターンの要素に一致するかどうか（** は0個以上のディレクトリに一致する）
+fn matches(pattern: &[String], names: &[String], ignore_case: bool) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=names.len()).any(|skip| matches(rest, &names[skip..], ignore_case)),
+        Some((first, rest)) => names
+            .split_first()
+            .is_some_and(|(name, names)| matches_name(first, name, ignore_case) && matches(rest, names, ignore_case)),
+    }
+}
+
+/// ファイル名がパターンに一致するかどうか（* は0文字以上、? は1文字に一致する）
+fn matches_name(pattern: &str, name: &str, ignore_case: bool) -> bool {
+    let fold = |text: &str| if ignore_case { text.to_lowercase() } else { text.to_string() };
+    let pattern: Vec<char> = fold(pattern).chars().collect();
+    let name: Vec<char> = fold(name).chars().collect();
+    // 直前の * の位置と、その * に一致させた文字の終わりの位置（先で一致しなければ * に一致させる文字を1つ増やしてやり直す）
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: パターンに一致する PDF の収集
+    #[test]
+    fn test_collect_inputs() {
+        let dir = std::env::temp_dir().join(format!("pdf2md-batch-test-{}", std::process::id()));
+        for file in ["a.pdf", "notes.txt", "reports/2024/q1.pdf", "reports/2024/q2.PDF", "reports/summary.pdf", ".cache/old.pdf"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+        }
+        let relative = |input: &Path| -> Vec<String> {
+            collect_inputs(input).unwrap().iter().map(|input| input.relative.to_string_lossy().replace('\\', "/")).collect()
+        };
+
+        assert_eq!(relative(&dir), vec!["a.pdf", "reports/2024/q1.pdf", "reports/2024/q2.PDF", "reports/summary.pdf"]);
+        assert_eq!(relative(&dir.join("**/*.pdf")), vec!["a.pdf", "reports/2024/q1.pdf", "reports/summary.pdf"]);
+        assert_eq!(relative(&dir.join("reports/*/q?.*")), vec!["2024/q1.pdf", "2024/q2.PDF"]);
+
+        // 出力先では入力のディレクトリ構成を保つ
+        let input = BatchInput { path: dir.join("reports/2024/q1.pdf"), relative: PathBuf::from("2024/q1.pdf") };
+        assert_eq!(output_path(&input, Some(Path::new("out"))), Path::new("out/2024/q1.md"));
+
+        // Windows ではパターンの大文字と小文字を区別しない
+        assert!(matches_name("q?.pdf", "Q2.PDF", true) && !matches_name("q?.pdf", "Q2.PDF", false));
+
+        // UTF-8 ではないファイル名も、元のバイト列のまま出力先に使う
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+            let name = OsStr::from_bytes(b"r\xe9sum\xe9.pdf");
+            fs::write(dir.join("reports").join(name), b"").unwrap();
+            let inputs = collect_inputs(&dir.join("reports/*.pdf")).unwrap();
+            let input = inputs.iter().find(|input| input.relative == Path::new(name)).unwrap();
+            assert_eq!(output_path(input, Some(Path::new("out"))).file_name().unwrap().as_bytes(), b"r\xe9sum\xe9.md");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
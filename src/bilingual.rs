@@ -0,0 +1,285 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::ops::Range;
+
+use crate::layout::{self, Glyph, PageLayout};
+
+/// 左右の段や交互のページに同じ内容を日本語と英語で載せた文書の出力方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BilingualOutput {
+    /// 対応する段落を交互に並べ、言語の見出し（[日本語]、[English]）を付ける
+    Interleave,
+    /// 日本語の部分だけを出力する
+    Ja,
+    /// 英語の部分だけを出力する
+    En,
+    /// 交互に並べたものに加えて、言語ごとのファイル（出力ファイル名.ja.md、.en.md）を書き出す
+    Files,
+}
+
+/// 文書の言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Japanese,
+    English,
+}
+
+impl Language {
+    /// 言語のコード（BCP 47）
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Japanese => "ja",
+            Language::English => "en",
+        }
+    }
+
+    /// 交互に並べるときに、各言語の部分の前に入れる行
+    fn label(self) -> &'static str {
+        match self {
+            Language::Japanese => "**[日本語]**",
+            Language::English => "**[English]**",
+        }
+    }
+
+    /// 出力方法で残す言語（交互に並べる場合は None）
+    fn kept(output: BilingualOutput) -> Option<Language> {
+        match output {
+            BilingualOutput::Ja => Some(Language::Japanese),
+            BilingualOutput::En => Some(Language::English),
+            BilingualOutput::Interleave | BilingualOutput::Files => None,
+        }
+    }
+}
+
+/// テキストの言語（かなや漢字がラテン文字の半分以上あれば日本語。文字が無ければ None）
+pub fn language_of(text: &str) -> Option<Language> {
+    let japanese = text.chars().filter(|&c| matches!(c as u32, 0x3040..=0x30FF | 0x4E00..=0x9FFF)).count();
+    let latin = text.chars().filter(char::is_ascii_alphabetic).count();
+    match (japanese, latin) {
+        (0, 0) => None,
+        _ if japanese * 2 >= latin => Some(Language::Japanese),
+        _ => Some(Language::English),
+    }
+}
+
+/// 段の中の段落（上端の y と行の範囲）
+type Block = (f64, Vec<Range<usize>>);
+
+/// 段の境界の左右で言語が異なるページを、言語ごとの段落に並べ直す（段の順に並べ替えた後に呼ぶ）
+///
+/// 左右の段の段落は上端の位置の順に交互に並べ、段をまたぐ行（全幅の題名など）はその位置に残す。
+/// 左右の言語が同じページはそのままにして false を返す。
+pub fn arrange_parallel_columns(page: &mut PageLayout, boundary: f64, output: BilingualOutput) -> bool {
+    let lines = layout::line_ranges(&page.glyphs);
+    // 行ごとの段（None は段をまたぐ行）
+    let side_of = |range: &Range<usize>| {
+        let glyphs = &page.glyphs[range.clone()];
+        let (x0, last) = (glyphs[0].x, &glyphs[glyphs.len() - 1]);
+        let font_size = glyphs[0].font_size;
+        match (x0 + font_size < boundary, last.x + last.width - font_size > boundary) {
+            (true, true) => None,
+            (true, false) => Some(0),
+            _ => Some(1),
+        }
+    };
+    let sides: Vec<Option<usize>> = lines.iter().map(side_of).collect();
+    let side_text = |side: usize| layout::glyphs_to_text(lines.iter().zip(&sides).filter(|(_, s)| **s == Some(side)).flat_map(|(range, _)| &page.glyphs[range.clone()]));
+    let languages = [language_of(&side_text(0)), language_of(&side_text(1))];
+    let [Some(left), Some(right)] = languages else {
+        return false;
+    };
+    if left == right {
+        return false;
+    }
+
+    // 段ごとの段落を、段をまたぐ行で区切った帯ごとに、上端の順に左右を合わせる
+    let mut arranged: Vec<Glyph> = Vec::with_capacity(page.glyphs.len());
+    let mut band: [Vec<Block>; 2] = [Vec::new(), Vec::new()];
+    let side_languages = [left, right];
+    let flush = |band: &mut [Vec<Block>; 2], arranged: &mut Vec<Glyph>| {
+        let mut last_label = None;
+        let [mut left_blocks, mut right_blocks] = [std::mem::take(&mut band[0]).into_iter().peekable(), std::mem::take(&mut band[1]).into_iter().peekable()];
+        loop {
+            let side = match (left_blocks.peek(), right_blocks.peek()) {
+                (Some((left_y, _)), Some((right_y, ranges))) => usize::from(*left_y > right_y + page.glyphs[ranges[0].start].font_size),
+                (Some(_), None) => 0,
+                (None, Some(_)) => 1,
+                (None, None) => break,
+            };
+            let (_, ranges) = if side == 0 { left_blocks.next() } else { right_blocks.next() }.unwrap();
+            let language = side_languages[side];
+            if Language::kept(output).is_some_and(|kept| kept != language) {
+                continue;
+            }
+            if Language::kept(output).is_none() && last_label != Some(language) {
+                arranged.push(label_glyph(&page.glyphs[ranges[0].start], language));
+                last_label = Some(language);
+            }
+            for range in ranges {
+                arranged.extend_from_slice(&page.glyphs[range]);
+            }
+        }
+    };
+
+    let spacing = [line_spacing(&page.glyphs, &lines, &sides, 0), line_spacing(&page.glyphs, &lines, &sides, 1)];
+    let mut previous_y: [Option<f64>; 2] = [None, None];
+    for (range, side) in lines.iter().zip(&sides) {
+        let y = page.glyphs[range.start].y;
+        match side {
+            None => {
+                flush(&mut band, &mut arranged);
+                previous_y = [None, None];
+                arranged.extend_from_slice(&page.glyphs[range.clone()]);
+            }
+            Some(side) => {
+                // 通常の行間より広く空いた行から、新しい段落にする
+                let new_block = previous_y[*side].is_none_or(|previous| y - previous > spacing[*side] * 1.4);
+                match band[*side].last_mut() {
+                    Some((_, ranges)) if !new_block => ranges.push(range.clone()),
+                    _ => band[*side].push((y, vec![range.clone()])),
+                }
+                previous_y[*side] = Some(y);
+            }
+        }
+    }
+    flush(&mut band, &mut arranged);
+
+    page.glyphs = arranged;
+    true
+}
+
+/// 段の行の通常の間隔（行の間隔の最小値）
+fn line_spacing(glyphs: &[Glyph], lines: &[Range<usize>], sides: &[Option<usize>], side: usize) -> f64 {
+    let baselines: Vec<f64> = lines.iter().zip(sides).filter(|(_, s)| **s == Some(side)).map(|(range, _)| glyphs[range.start].y).collect();
+    baselines.windows(2).map(|pair| pair[1] - pair[0]).filter(|gap| *gap > 0.0).fold(f64::INFINITY, f64::min)
+}
+
+/// ページごとに日本語と英語が交互に現れる文書の、各ページに言語の見出しを付けるか、残さない言語のページを空にする
+///
+/// 文字のあるページの言語が交互に入れ替わり、それぞれの言語のページが2ページ以上ある場合だけ行い、対象にしたページの数を返す。
+pub fn arrange_alternating_pages(pages: &mut [PageLayout], output: BilingualOutput) -> usize {
+    let languages: Vec<Option<Language>> = pages.iter().map(|page| language_of(&layout::glyphs_to_text(&page.glyphs))).collect();
+    let present: Vec<Language> = languages.iter().flatten().copied().collect();
+    let count = |language: Language| present.iter().filter(|&&l| l == language).count();
+    let alternating = present.windows(2).all(|pair| pair[0] != pair[1]);
+    if !alternating || count(Language::Japanese) < 2 || count(Language::English) < 2 {
+        return 0;
+    }
+
+    for (page, language) in pages.iter_mut().zip(&languages) {
+        let Some(language) = *language else {
+            continue;
+        };
+        match Language::kept(output) {
+            Some(kept) if kept != language => page.glyphs.clear(),
+            Some(_) => {}
+            None => {
+                if let Some(first) = page.glyphs.first() {
+                    let label = label_glyph(first, language);
+                    page.glyphs.insert(0, label);
+                }
+            }
+        }
+    }
+    present.len()
+}
+
+/// 言語の見出しの行かどうか
+pub fn is_label(line: &str) -> bool {
+    [Language::Japanese, Language::English].iter().any(|language| line == language.label())
+}
+
+/// 言語の見出しの行を入れる文字（独立した段落にする）
+fn label_glyph(first: &Glyph, language: Language) -> Glyph {
+    Glyph {
+        text: format!("\n\n{}\n\n", language.label()),
+        x: 0.0,
+        width: 0.0,
+        word_start: true,
+        color: None,
+        bold: false,
+        monospace: false,
+        ..first.clone()
+    }
+}
+
+/// 言語の見出しで交互に並べた Markdown を、言語ごとの Markdown（日本語、英語）に分ける
+///
+/// 最初の見出しより前の内容（フロントマターや題名）は両方に入れる。
+pub fn split_languages(markdown: &str) -> (String, String) {
+    let (mut japanese, mut english) = (String::new(), String::new());
+    let mut current = None;
+    for line in markdown.split_inclusive('\n') {
+        match line.trim() {
+            label if label == Language::Japanese.label() => current = Some(Language::Japanese),
+            label if is_label(label) => current = Some(Language::English),
+            _ => match current {
+                Some(Language::Japanese) => japanese.push_str(line),
+                Some(Language::English) => english.push_str(line),
+                None => {
+                    japanese.push_str(line);
+                    english.push_str(line);
+                }
+            },
+        }
+    }
+    (japanese, english)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 左右の段の言語ごとの並べ替えと言語ごとの分割
+    #[test]
+    fn test_arrange_parallel_columns() {
+        let glyph = |text: &str, x: f64, y: f64| Glyph {
+            text: text.to_string(),
+            x,
+            y,
+            width: 10.0 * text.chars().count() as f64,
+            font_size: 10.0,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence: None,
+        };
+        // 左の段に日本語、右の段に英語の条文（段の順に並べ替えた後の順）
+        let page = || PageLayout {
+            width: 600.0,
+            height: 800.0,
+            glyphs: vec![
+                glyph("第1条（目的）", 50.0, 100.0),
+                glyph("本契約は、取引の条件を定める。", 50.0, 112.0),
+                glyph("第2条（期間）", 50.0, 150.0),
+                glyph("Article", 320.0, 100.0),
+                glyph("1", 400.0, 100.0),
+                glyph("This", 320.0, 112.0),
+                glyph("Agreement", 370.0, 112.0),
+                glyph("Article", 320.0, 150.0),
+                glyph("2", 400.0, 150.0),
+            ],
+            ..Default::default()
+        };
+
+        let mut interleaved = page();
+        assert!(arrange_parallel_columns(&mut interleaved, 300.0, BilingualOutput::Interleave));
+        let markdown = layout::glyphs_to_text(&interleaved.glyphs);
+        let texts: Vec<&str> = markdown.split("\n\n").map(str::trim).filter(|text| !text.is_empty()).collect();
+        assert_eq!(texts.len(), 8);
+        assert_eq!(texts[0], "**[日本語]**");
+        assert!(texts[1].starts_with("第1条（目的）") && texts[3].starts_with("Article 1"));
+        assert!(texts[5] == "第2条（期間）" && texts[7] == "Article 2");
+
+        let mut english = page();
+        assert!(arrange_parallel_columns(&mut english, 300.0, BilingualOutput::En));
+        assert_eq!(layout::glyphs_to_text(&english.glyphs).split_whitespace().collect::<Vec<_>>(), ["Article", "1", "This", "Agreement", "Article", "2"]);
+
+        let (japanese, english) = split_languages("# 契約書\n\n**[日本語]**\n\n第1条\n\n**[English]**\n\nArticle 1\n");
+        assert_eq!(japanese, "# 契約書\n\n\n第1条\n\n");
+        assert_eq!(english, "# 契約書\n\n\nArticle 1\n");
+    }
+}
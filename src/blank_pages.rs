@@ -0,0 +1,104 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::layout::{Glyph, PageLayout};
+
+/// 白紙とみなす最大の文字数（ノンブルだけのページを白紙として扱う）
+const MAX_BLANK_CHARS: usize = 4;
+
+/// 「白紙です」の断り書きがあるページを白紙とみなす最大の文字数（柱やノンブルを含めて）
+const MAX_NOTICE_PAGE_CHARS: usize = 120;
+
+/// 白紙とみなす、ベクター図形のパスを構築する命令の最大数（柱の罫線などは許す）
+const MAX_BLANK_PATH_OPS: usize = 20;
+
+/// 「このページは白紙です」の断り書き
+static NOTICE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)this\s+page\s+(?:has\s+been\s+|is\s+|was\s+)?(?:intentionally|deliberately|purposely)\s+(?:left\s+)?blank|白紙ページ|このページは(?:意図的に)?白紙",
+    )
+    .unwrap()
+});
+
+/// 白紙のページ（スキャンした紙の裏面、意図的な白紙など）を取り除き、取り除いたページ数を返す
+///
+/// keep_placeholders が true の場合は、取り除く代わりに [[blank page, p.3]] のような目印に置き換える。
+pub fn remove_blank_pages(pages: &mut Vec<PageLayout>, keep_placeholders: bool) -> usize {
+    let before = pages.len();
+
+    if keep_placeholders {
+        let mut replaced = 0;
+        for page in pages.iter_mut().filter(|page| is_blank(page)) {
+            let font_size = page.glyphs.first().map_or(10.0, |glyph| glyph.font_size);
+            let text = format!("\n\n[[blank page, p.{}]]\n\n", page.number);
+            page.glyphs = vec![Glyph { text, x: 0.0, y: 0.0, width: 0.0, font_size, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None }];
+            replaced += 1;
+        }
+        return replaced;
+    }
+
+    pages.retain(|page| !is_blank(page));
+    before - pages.len()
+}
+
+/// 画像や図形が無く、文字がノンブル程度か「このページは白紙です」の断り書きだけのページかどうか
+fn is_blank(page: &PageLayout) -> bool {
+    if !page.images.is_empty() || page.path_ops > MAX_BLANK_PATH_OPS {
+        return false;
+    }
+
+    let chars: usize = page.glyphs.iter().map(|glyph| glyph.text.chars().filter(|c| !c.is_whitespace()).count()).sum();
+    if chars <= MAX_BLANK_CHARS {
+        return true;
+    }
+    chars <= MAX_NOTICE_PAGE_CHARS && page.lines().iter().any(|line| NOTICE_REGEX.is_match(&line.text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::ImagePlacement;
+
+    fn page(number: u32, lines: &[&str]) -> PageLayout {
+        let glyphs = lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| Glyph {
+                text: text.to_string(),
+                x: 72.0,
+                y: 100.0 + i as f64 * 14.0,
+                width: 200.0,
+                font_size: 10.0,
+                word_start: true,
+                order: i,
+                color: None,
+                bold: false,
+                monospace: false,
+                confidence: None,
+            })
+            .collect();
+        PageLayout { number, width: 600.0, height: 800.0, glyphs, ..Default::default() }
+    }
+
+    // 単体テスト: 白紙のページの除去
+    #[test]
+    fn test_remove_blank_pages() {
+        let photo = ImagePlacement { id: (1, 0), x0: 72.0, y0: 100.0, x1: 300.0, y1: 300.0 };
+        let pages = vec![
+            page(1, &["Chapter 1", "Body text of the first chapter."]),
+            page(2, &[]),
+            page(3, &["This page intentionally left blank.", "iv"]),
+            page(4, &["12"]),
+            PageLayout { images: vec![photo], ..page(5, &[]) },
+        ];
+
+        let mut removed = pages.clone();
+        assert_eq!(remove_blank_pages(&mut removed, false), 3);
+        assert_eq!(removed.iter().map(|page| page.number).collect::<Vec<_>>(), vec![1, 5]);
+
+        let mut kept = pages;
+        assert_eq!(remove_blank_pages(&mut kept, true), 3);
+        assert_eq!(kept.len(), 5);
+        assert_eq!(kept[2].glyphs[0].text, "\n\n[[blank page, p.3]]\n\n");
+    }
+}
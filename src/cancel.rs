@@ -0,0 +1,47 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 変換の中止の要求（複製したものは同じ要求を共有し、別のスレッドから中止できる）
+///
+/// 変換処理はページの合間と処理の段階の合間で中止の要求を確かめ、中止されていれば [`Cancelled`] のエラーを返す。
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 変換の中止を要求する
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 中止が要求されていれば Cancelled のエラーを返す
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// 中止の要求により変換を中止したことを表すエラー（anyhow::Error の downcast_ref で判別できる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "変換が中止されました")
+    }
+}
+
+impl std::error::Error for Cancelled {}
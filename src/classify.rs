@@ -0,0 +1,173 @@
+use anyhow::Result;
+use lopdf::{Document, Object};
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::cancel::CancellationToken;
+use crate::email;
+use crate::layout::{self, PageLayout};
+use crate::patent;
+use crate::probe;
+use crate::resume;
+
+/// 段組みや内容の判定に使う先頭ページ数（全ページは解析しない）
+const SAMPLE_PAGES: u32 = 3;
+
+/// テキストレイヤーありとみなす1ページあたりの最小文字数
+const MIN_TEXT_CHARS: usize = 20;
+
+/// 請求書・領収書らしい語
+static INVOICE_TERMS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\b(invoice|receipt|amount due|bill to)\b|請求書|領収書|御請求").unwrap());
+
+/// 財務書類らしい語
+static FINANCIAL_TERMS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(annual report|balance sheet|income statement|cash flows?|statement of operations)\b|有価証券報告書|決算短信|貸借対照表|損益計算書|キャッシュ・フロー").unwrap()
+});
+
+/// プロファイルの自動選択に使う文書の特徴
+#[derive(Debug, Default)]
+pub struct DocumentTraits {
+    pub pages: usize,
+    /// しおり（アウトライン）があるかどうか
+    pub has_outline: bool,
+    /// テキストレイヤーのあるページの1ページあたりの平均文字数
+    pub chars_per_page: f64,
+    /// テキストレイヤーの無い（スキャン画像の）ページの割合
+    pub scanned_ratio: f64,
+    /// 先頭ページから推定した段数
+    pub columns: usize,
+    /// 先頭ページに請求書・領収書らしい語句があるかどうか
+    pub invoice_terms: bool,
+    /// 先頭ページが横長かどうか
+    pub landscape: bool,
+    /// 先頭ページに差出人と件名のメールのヘッダーがあるかどうか
+    pub email_headers: bool,
+    /// 先頭ページに職歴・学歴などの履歴書の節があるかどうか
+    pub resume_sections: bool,
+    /// 先頭ページに INID コードなどの特許の書誌事項があるかどうか
+    pub patent_fields: bool,
+    /// 先頭ページに決算書・年次報告書らしい語句があるかどうか
+    pub financial_terms: bool,
+}
+
+/// 文書を軽く調べて特徴を求める
+pub fn analyze(doc: &Document) -> Result<DocumentTraits> {
+    let probes = probe::probe_text_layer(doc);
+    let with_text: Vec<usize> = probes.iter().map(|p| p.chars).filter(|&c| c >= MIN_TEXT_CHARS).collect();
+
+    let mut traits = DocumentTraits {
+        pages: probes.len(),
+        has_outline: has_outline(doc),
+        ..Default::default()
+    };
+    if !probes.is_empty() {
+        traits.scanned_ratio = 1.0 - with_text.len() as f64 / probes.len() as f64;
+    }
+    if !with_text.is_empty() {
+        traits.chars_per_page = with_text.iter().sum::<usize>() as f64 / with_text.len() as f64;
+    }
+
+    let pages = layout::extract_layout(doc, |page| page <= SAMPLE_PAGES, &CancellationToken::new())?;
+    traits.columns = pages.iter().map(estimate_columns).max().unwrap_or(1);
+    traits.landscape = pages.first().is_some_and(|page| page.width > page.height);
+
+    traits.invoice_terms = pages
+        .iter()
+        .any(|page| INVOICE_TERMS.is_match(&layout::glyphs_to_text(&page.glyphs)));
+
+    traits.email_headers = pages.first().is_some_and(|page| email::looks_like_email(&page.lines()));
+    traits.resume_sections = pages.first().is_some_and(resume::looks_like_resume);
+    traits.patent_fields = pages.first().is_some_and(|page| patent::looks_like_patent(&page.lines()));
+
+    traits.financial_terms = pages
+        .iter()
+        .any(|page| FINANCIAL_TERMS.is_match(&layout::glyphs_to_text(&page.glyphs)));
+
+    Ok(traits)
+}
+
+/// カタログにしおりがあるかどうか
+fn has_outline(doc: &Document) -> bool {
+    doc.catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|outlines| doc.dereference(outlines).ok())
+        .and_then(|(_, outlines)| outlines.as_dict().ok())
+        .is_some_and(|outlines| outlines.get(b"First").is_ok_and(|first| !matches!(first, Object::Null)))
+}
+
+/// 行の左右の位置から段数（1 または 2）を推定する
+fn estimate_columns(page: &PageLayout) -> usize {
+    let lines = page.lines();
+    if lines.len() < 10 {
+        return 1;
+    }
+
+    let left = lines.iter().map(|l| l.x0).fold(f64::INFINITY, f64::min);
+    let right = lines.iter().map(|l| l.x1).fold(f64::NEG_INFINITY, f64::max);
+    let middle = (left + right) / 2.0;
+
+    // 中央より左で終わる行と、中央より右で始まる行がどちらも多ければ2段組み
+    let left_only = lines.iter().filter(|l| l.x1 < middle).count();
+    let right_only = lines.iter().filter(|l| l.x0 > middle).count();
+    let threshold = lines.len() * 3 / 10;
+    if left_only >= threshold && right_only >= threshold {
+        2
+    } else {
+        1
+    }
+}
+
+/// 特徴から、選ぶべきプロファイル名を推定する
+pub fn suggest_profile(traits: &DocumentTraits) -> Option<&'static str> {
+    if traits.pages > 0 && traits.scanned_ratio >= 0.5 {
+        Some("scanned-book")
+    } else if traits.patent_fields {
+        Some("patent")
+    } else if traits.email_headers {
+        Some("email")
+    } else if traits.resume_sections && traits.pages <= 4 {
+        Some("resume")
+    } else if traits.invoice_terms && traits.pages <= 5 {
+        Some("invoice")
+    } else if traits.financial_terms {
+        Some("financial")
+    } else if traits.landscape && traits.chars_per_page < 600.0 {
+        // 横長で文字の少ないページはスライド
+        Some("slides")
+    } else if traits.columns >= 2 {
+        Some("academic-paper")
+    } else if traits.has_outline && traits.pages >= 20 {
+        Some("book")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 特徴からのプロファイルの推定
+    #[test]
+    fn test_suggest_profile() {
+        let base = || DocumentTraits { pages: 10, columns: 1, chars_per_page: 2000.0, ..Default::default() };
+
+        let test_cases = vec![
+            (DocumentTraits { scanned_ratio: 0.9, ..base() }, Some("scanned-book"), "スキャン文書"),
+            (DocumentTraits { pages: 1, invoice_terms: true, ..base() }, Some("invoice"), "請求書"),
+            (DocumentTraits { email_headers: true, invoice_terms: true, ..base() }, Some("email"), "請求書に触れたメール"),
+            (DocumentTraits { pages: 2, resume_sections: true, ..base() }, Some("resume"), "履歴書"),
+            (DocumentTraits { pages: 30, patent_fields: true, ..base() }, Some("patent"), "特許文献"),
+            (DocumentTraits { pages: 80, financial_terms: true, ..base() }, Some("financial"), "年次報告書"),
+            (DocumentTraits { columns: 2, ..base() }, Some("academic-paper"), "2段組み"),
+            (DocumentTraits { pages: 200, has_outline: true, ..base() }, Some("book"), "しおり付きの長い文書"),
+            (DocumentTraits { landscape: true, chars_per_page: 150.0, ..base() }, Some("slides"), "横長で文字の少ない文書"),
+            (base(), None, "特徴の無い文書"),
+        ];
+
+        for (traits, expected, desc) in test_cases {
+            assert_eq!(suggest_profile(&traits), expected, "Test failed: {}", desc);
+        }
+    }
+}
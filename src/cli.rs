@@ -0,0 +1,1633 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use rayon::prelude::*;
+use std::io::{IsTerminal, Read};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::articles::{self, ArticleOutput};
+use crate::bilingual::{self, BilingualOutput};
+use crate::cancel::CancellationToken;
+use crate::comments::CommentOutput;
+use crate::console;
+use crate::diagnostics::{self, WarningKind};
+use crate::encoding::OutputEncoding;
+use crate::frontmatter::FrontMatter;
+use crate::graphics::{self, GraphicalPages};
+use crate::headings::{self, HeadingDetector};
+use crate::highlights::HighlightStyle;
+use crate::http::{self, HttpHeader};
+use crate::hyphenation::{Dehyphenator, PatternSpec};
+use crate::layout_model::{LayoutModel, ServiceInput};
+use crate::logging::{self, LogFormat, LogOptions};
+use crate::margin_notes::MarginNoteStyle;
+use crate::memory::MemoryBudget;
+use crate::outline::{self, OutlineFormat};
+use crate::oversize::{OutputLimit, OversizePolicy};
+use crate::password::{self, PasswordRequired};
+use crate::progress::{self, ProgressFormat};
+use crate::redact::{PiiKind, Redactor};
+use crate::report::{self, FileReport, FileStatus, ReportSpec};
+use crate::paths::relative_link;
+use crate::selection::{PageRanges, PageSample};
+use crate::split::{self, SplitBy};
+use crate::structure::{self, OutputFormat};
+use crate::transcript::TranscriptStyle;
+use crate::whitespace::{self, LineEnding, TrailingSpaces, WhitespaceOptions};
+use crate::{alt_text, batch, classify, clipboard, config, corpus, destinations, figures, font_styles, isolate, lang_tags, manifest, metadata, ocr, probe, review, server, sniff, toc, watch};
+use crate::{convert_to_markdown, decrypted_copy, extract_pages, extract_pdf_content, layout_pages, load_document, open_document, write_encoded, write_to_file, write_to_stdout, ContentKind, ExtractOptions, MarkdownOptions, NotPdf, PdfInput};
+
+/// PDF を Markdown に変換するCLIツール
+#[derive(Parser, Clone)]
+#[command(author, version, about, long_about = None)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// 入力PDFファイルのパス（ディレクトリか "docs/**/*.pdf" のようなパターンを指定すると、一致するすべての PDF を変換します。
+    /// 「-」の場合は標準入力から PDF を読み込み、--output の指定がなければ標準出力に書き出します。http:// か https:// の URL の場合は
+    /// PDF を一時ファイルに取得して変換し、出力ファイル名は URL のファイル名から決めます。URL の入力には http 機能が必要です）
+    #[arg(short, long, required_unless_present = "stdio_server")]
+    input: Option<PathBuf>,
+
+    /// --input の URL から PDF を取得するときに送る HTTP ヘッダー（例: "Authorization: Bearer xxx"。複数指定可）
+    #[arg(long = "header", value_name = "NAME: VALUE")]
+    headers: Vec<HttpHeader>,
+
+    /// 出力Markdownファイルのパス（指定がない場合は入力ファイル名に .md を付けたものになります。- の場合は標準出力に書き出し、進み具合などの表示は標準エラー出力に出します）
+    #[arg(short, long, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+
+    /// 出力先のディレクトリ（ディレクトリやパターンを入力にした場合は、入力のディレクトリ構成を保って PDF ごとに .md を書き出します）
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// 変換した Markdown をクリップボードに入れる（-o か --output-dir の指定が無い場合はファイルに書き出さない。clipboard 機能が必要）
+    #[arg(long)]
+    to_clipboard: bool,
+
+    /// ディレクトリやパターンを入力にした場合に、同時に変換する PDF の数（指定がない場合は CPU の数）
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+
+    /// 標準入力と標準出力で JSON-RPC 2.0 の要求（1行に1つ）を受け付ける（エディタの拡張機能やデスクトップアプリに組み込む場合に使う）
+    ///
+    /// メソッドは convert（引数は input、output、args。output が無ければ Markdown を結果の markdown で返す）、cancel（引数は中止する要求の id）、shutdown。
+    /// 変換の進み具合は progress の通知で知らせます。
+    #[arg(long, conflicts_with_all = ["input", "output", "output_dir"])]
+    stdio_server: bool,
+
+    /// PDF ごとに子プロセスで変換する（依存するライブラリの内部でパニックやセグメンテーション違反が起きても、一括変換の残りの PDF を変換する）
+    #[arg(long)]
+    isolate: bool,
+
+    /// Markdown の先頭に YAML フロントマターを出力する（PDFの文書情報の Title、Author、Subject を title:、author:、description: に、Keywords を tags:、作成・更新日時を created:/modified: に変換します）
+    #[arg(long)]
+    front_matter: bool,
+
+    /// フロントマターの tags: に追加する固定タグ（複数指定可。指定するとフロントマターを出力します）
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// 出力中の個人情報をマスクする（emails, phones, ssn をカンマ区切りで指定）
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    redact: Vec<PiiKind>,
+
+    /// マスク対象に追加する正規表現（複数指定可）
+    #[arg(long = "redact-pattern", value_name = "REGEX")]
+    redact_patterns: Vec<String>,
+
+    /// 黒塗りの矩形で覆われた（墨消しされた）テキストも出力する
+    #[arg(long)]
+    ignore_redactions: bool,
+
+    /// 権限設定でコピーが禁止されたPDFでもテキストを抽出する
+    #[arg(long)]
+    override_permissions: bool,
+
+    /// 一部のページだけを抜き取って変換する（例: every:10、first:5,last:5）
+    #[arg(long, value_name = "SPEC")]
+    sample: Option<PageSample>,
+
+    /// 指定した範囲のページだけを変換する（例: 1-5,8,12-。12- は12ページ目から最後まで）
+    #[arg(long, value_name = "RANGES")]
+    pages: Option<PageRanges>,
+
+    /// 1ページだけを変換して標準出力に書き出す（ページの内容をすぐに確かめる場合に使う。図の書き出し、OCR、レイアウトのモデルなどの時間のかかる処理は行いません）
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["pages", "sample", "output", "output_dir", "to_clipboard", "stdio_server", "articles", "split_by", "review_html", "manifest"]
+    )]
+    page: Option<u32>,
+
+    /// 段組みの段数を指定して読み順を決める（設定ファイルの文書全体の段数より優先。ページごとの指定はそのまま有効。指定が無いページは文字の配置から段組みを推定し、1 を指定すると推定しません）
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    columns: Option<u16>,
+
+    /// 変換で失われる内容（出力されない図、表として変換できない領域、文字化けなど）の警告をエラーとして扱い、出力せずに終了する（--allow、--warn で指定した種類は除く）
+    #[arg(long)]
+    strict: bool,
+
+    /// 表示しない警告の種類（dropped_figure、unparsed_table、garbled_text、total_mismatch。カンマ区切りで複数指定可）
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    allow: Vec<WarningKind>,
+
+    /// --strict の場合もエラーにせず、警告として表示する警告の種類
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    warn: Vec<WarningKind>,
+
+    /// エラーとして扱い、出力せずに終了する警告の種類
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    deny: Vec<WarningKind>,
+
+    /// 変換できずに失われた内容の位置に [[image omitted, p.2]] や [[unconverted table, p.12]] のような目印を入れる
+    #[arg(long)]
+    placeholders: bool,
+
+    /// 本文の左右の余白にある欄外の注の出力方法（inline: 本文と同じ流れ、footnotes: 脚注、aside: 引用ブロック、appendix: 末尾に一覧）
+    #[arg(long, value_enum, default_value = "inline")]
+    margin_notes: MarginNoteStyle,
+
+    /// 証言録取や議事録の発言者（MR. TANAKA:、Q:、A: など）を検出して整形する（bold: 太字の発言者、definition: 定義リスト）
+    #[arg(long, value_enum, value_name = "STYLE")]
+    transcript: Option<TranscriptStyle>,
+
+    /// 請求書・領収書の項目（番号・日付・金額・明細）を取り出し、フロントマターと JSON（出力ファイル名.invoice.json）に出力する
+    #[arg(long)]
+    invoice: bool,
+
+    /// 楽譜・回路図・地図などの図のページの扱い（image: ページ全体の画像を出力ファイル名_assets に書き出してリンクする、text: テキストとして変換する）
+    #[arg(long, value_enum, default_value = "image")]
+    graphical_pages: GraphicalPages,
+
+    /// 表らしい領域を Markdown の表として出力し（桁区切りや括弧の負数はそのまま）、行・列の合計が合わない箇所を警告する
+    #[arg(long)]
+    financial: bool,
+
+    /// 罫線や空白の位置から判定した表を Markdown の表にせず、本文として出力する（判定を誤る文書向け）
+    #[arg(long, conflicts_with = "financial")]
+    no_tables: bool,
+
+    /// 複数の記事が載った紙面を見出しと署名で記事ごとに分ける（sections: 記事ごとの節、files: 記事ごとのファイル）
+    #[arg(long, value_enum, value_name = "OUTPUT")]
+    articles: Option<ArticleOutput>,
+
+    /// 左右の段や交互のページに日本語と英語で同じ内容を載せた文書（対訳の契約書など）の出力方法（interleave: 対応する段落を言語の見出しを付けて交互に並べる、ja・en: 一方の言語だけを出力する、files: 交互に並べたものに加えて言語ごとのファイル 出力ファイル名.ja.md と .en.md を書き出す）
+    #[arg(long, value_enum, value_name = "OUTPUT", conflicts_with = "articles")]
+    bilingual: Option<BilingualOutput>,
+
+    /// PDF のしおりの項目と同じテキストの行を、しおりの階層のレベルの見出しにする
+    #[arg(long)]
+    bookmark_headings: bool,
+
+    /// 本文との相対的なフォントサイズと太さから見出しを推定せず、行の長さや大文字による推定だけを行う（プロファイルの heading_styles が無い場合）
+    #[arg(long)]
+    no_font_headings: bool,
+
+    /// 柱・ノンブル・図・表・見出しの領域を判定するレイアウト解析の ONNX のモデル（DocLayNet で学習した YOLO 形式のもの。各ページを画像にして判定した領域を変換に使う。onnx 機能が必要）
+    #[arg(long, value_name = "MODEL", conflicts_with = "layout_command")]
+    layout_model: Option<PathBuf>,
+
+    /// 柱・ノンブル・図・表・見出しの領域を判定するレイアウト解析のコマンド（各ページの画像のパスを最後の引数に受け取り、領域の JSON を標準出力に書くもの。判定した領域を変換に使う）
+    #[arg(long, value_name = "COMMAND")]
+    layout_command: Option<String>,
+
+    /// レイアウト解析を外部の HTTP のサービスで行う（ページの内容を POST し、応答の本文に領域の JSON を受け取る）
+    #[arg(long, value_name = "URL", conflicts_with_all = ["layout_model", "layout_command"])]
+    layout_service: Option<String>,
+
+    /// レイアウト解析のサービスに送るページの内容（image: ページ画像の PNG、text: 行ごとの文字と位置の JSON）
+    #[arg(long, value_enum, value_name = "INPUT")]
+    layout_service_input: Option<ServiceInput>,
+
+    /// 出力を最上位の見出し（heading）かしおりの最上位の項目（outline）ごとに「出力ファイル名-NN.md」に分け、出力ファイルには各ファイルへのリンクの一覧を書く
+    #[arg(long, value_enum, value_name = "BOUNDARY", conflicts_with = "articles")]
+    split_by: Option<SplitBy>,
+
+    /// --split-by で分けた各ファイルの末尾に入れる、前後のファイルと一覧へのリンクの行の書式（markdown: Markdown のリンク、hugo: Hugo の ref ショートコード、none: 入れない。既定は markdown）
+    #[arg(long, value_enum, value_name = "FLAVOR")]
+    split_nav: Option<split::Navigation>,
+
+    /// --split-by で分けた各ファイルの先頭に書き足す内容（{title}、{part}、{parts}、{part_title}、{prev}、{next}、{index} などの変数と、改行の \n を使える。設定ファイルの [split] の header より優先）
+    #[arg(long, value_name = "TEMPLATE")]
+    part_header: Option<String>,
+
+    /// --split-by で分けた各ファイルの末尾に書き足す内容（変数は --part-header と同じ。設定ファイルの [split] の footer より優先）
+    #[arg(long, value_name = "TEMPLATE")]
+    part_footer: Option<String>,
+
+    /// 各ページを指定した解像度（dpi）の PNG にして「出力ファイル名_assets/pages」に書き出す（目視での確認や OCR の確認用）
+    #[arg(long, value_name = "DPI", value_parser = clap::value_parser!(u32).range(36..=1200))]
+    page_images: Option<u32>,
+
+    /// --page-images で書き出した画像へのリンクを各ページの先頭に入れる
+    #[arg(long, requires = "page_images")]
+    link_page_images: bool,
+
+    /// 本文の画像（図や写真）を書き出すディレクトリ（省略時は「出力ファイル名_assets」。画像は fig-01.png のような名前で書き出し、画像の位置にリンクを入れる）
+    #[arg(long, value_name = "DIR")]
+    images_dir: Option<PathBuf>,
+
+    /// メモリに置く文書のデータの上限（例: 512M、2G）。超える分は大きい画像から順にデータを一時ファイルに移す（小さなコンテナで大きなスキャン文書を変換する場合向け）
+    #[arg(long, value_name = "SIZE")]
+    memory_budget: Option<MemoryBudget>,
+
+    /// 暗号化された PDF のパスワード（ユーザーパスワードかオーナーパスワード。省略した場合は、パスワードが必要なら端末で入力を求める）
+    #[arg(long, value_name = "PASSWORD")]
+    password: Option<String>,
+
+    /// 変換結果の報告を書き出す（例: junit:report.xml。ファイルごとの成否と警告を JUnit の XML にし、CI のテスト結果の画面で表示できるようにする）
+    #[arg(long, value_name = "FORMAT:PATH")]
+    report: Option<ReportSpec>,
+
+    /// 信頼できない PDF（アップロードされたファイルなど）向けに、オブジェクトの数、入れ子の深さ、ストリームを展開した大きさに上限を設け、超える文書は抽出を始める前にエラーにする
+    #[arg(long)]
+    robust: bool,
+
+    /// 文字のレイヤーの有無にかかわらず、全ページを画像にして tesseract で文字認識する（既定では文字のレイヤーが無いスキャンのページだけを認識する）
+    #[arg(long, conflicts_with = "no_ocr")]
+    ocr: bool,
+
+    /// 文字のレイヤーが無いページを文字認識しない
+    #[arg(long)]
+    no_ocr: bool,
+
+    /// 文字認識に使う tesseract の言語（eng、jpn、eng+jpn など）
+    #[arg(long, value_name = "LANG", default_value = "eng")]
+    ocr_lang: String,
+
+    /// 文字認識の確からしさ（0〜100）がこの値より低い語を {?語?} の形で出力し、誤認識の可能性がある箇所を校正で探せるようにする
+    #[arg(long, value_name = "CONF", conflicts_with = "no_ocr")]
+    ocr_mark_below: Option<f64>,
+
+    /// 文字認識の結果を、単語の辞書と取り違えやすい文字の組（rn→m、0→O など）で補正する（補正した語はすべて --log-file のログに記録する）
+    #[arg(long, conflicts_with = "no_ocr")]
+    ocr_correct: bool,
+
+    /// 文字認識の補正に使う単語の一覧のファイル（1行に1語。複数指定できる。英語の場合、指定が無ければ /usr/share/dict/words を使う）
+    #[arg(long, value_name = "FILE", requires = "ocr_correct")]
+    ocr_dictionary: Vec<PathBuf>,
+
+    /// 前のページと同じかほぼ同じ内容のページ（FAX の送付状や繰り返される免責事項など）を、[[duplicate of p.1, p.3]] のような目印に置き換える
+    #[arg(long)]
+    dedupe_pages: bool,
+
+    /// 白紙のページ（スキャンした紙の裏面、意図的な白紙など）を省略せずに、[[blank page, p.3]] のような目印として残す
+    #[arg(long)]
+    keep_blank_pages: bool,
+
+    /// ページの上下の余白に繰り返し現れる柱（文書の題名や章の名前）とノンブルを取り除かずに残す
+    #[arg(long)]
+    keep_headers: bool,
+
+    /// ハイライト（注釈や文字の背後の蛍光ペンの色）された文字の出力方法（mark: ==文字==、html: <mark>文字</mark>、summary: 末尾に一覧）
+    #[arg(long, value_enum, value_name = "STYLE")]
+    highlights: Option<HighlightStyle>,
+
+    /// 注釈のコメントと返信のスレッド（作成者・日時・本文・注釈を付けた箇所）の出力方法（appendix: 末尾の節、json: 出力ファイル名.comments.json）
+    #[arg(long, value_enum, value_name = "OUTPUT")]
+    comments: Option<CommentOutput>,
+
+    /// 見出しから作ったリンク付きの目次を、題名（文書で唯一の H1）の後に入れる（アンカーは GitHub と同じ形式）
+    #[arg(long)]
+    toc: bool,
+
+    /// 文書の主な言語と異なる言語（日本語か英語）の節の先頭に <!-- lang: en --> のコメントを入れ、--review-html の段落に lang 属性を付ける（読み上げや検索で言語を切り替える場合）
+    #[arg(long)]
+    lang_tags: bool,
+
+    /// 目次に含める見出しの深さ（3 の場合は H3 まで）
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "toc", value_parser = clap::value_parser!(u8).range(1..=6))]
+    toc_depth: u8,
+
+    /// 進み具合の表示の形式（jsonl: ファイルの開始と終了、ページ、警告を1行に1つの JSON のイベントとして標準エラー出力に書く）
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ProgressFormat::Text)]
+    progress_format: ProgressFormat,
+
+    /// 連続する空行の最大数
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    max_blank_lines: usize,
+
+    /// 段落などのブロック内の2行目以降を指定した数の空白で字下げする（指定がない場合は元の字下げのまま）
+    #[arg(long, value_name = "N")]
+    continuation_indent: Option<usize>,
+
+    /// 行末のハイフンで分けた語をつなぐ（分綴のパターンがある言語では、つないだ語のその位置で分綴できる場合だけハイフンを除く）
+    #[arg(long)]
+    dehyphenate: bool,
+
+    /// 行末のハイフンを確かめる言語ごとの分綴のパターンのファイル（例: de=hyph-de-1996.tex。TeX の hyph-xx.tex か LibreOffice の hyph_xx.dic。複数指定でき、設定ファイルの [conversion.hyphenation] より優先。--dehyphenate を含む）
+    #[arg(long, value_name = "LANG=FILE")]
+    hyphenation_patterns: Vec<PatternSpec>,
+
+    /// 段落を指定した文字数で折り返す（空白の位置で折り返し、見出し・表・箇条書き・コードブロックは折り返さない。設定ファイルの wrap より優先）
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    wrap: Option<u16>,
+
+    /// 行末の空白の扱い
+    #[arg(long, value_enum, default_value = "strip")]
+    trailing_spaces: TrailingSpaces,
+
+    /// 書き出す Markdown の改行（native は Windows では CRLF、それ以外では LF）
+    #[arg(long, value_enum, default_value = "lf")]
+    line_ending: LineEnding,
+
+    /// 書き出す Markdown の文字コード（utf8-bom は BOM 付きの UTF-8。shift_jis で表せない文字がある場合はエラーにします）
+    #[arg(long, value_enum, value_name = "ENCODING", default_value = "utf8")]
+    output_encoding: OutputEncoding,
+
+    /// 出力の大きさの上限（バイト。--output-encoding と --line-ending で書き出した大きさで数える。--split-by で分けたファイルなど、付随するファイルには適用しない）
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1..))]
+    max_output_bytes: Option<u64>,
+
+    /// 出力が --max-output-bytes を超えた場合の扱い（error: エラーにする、truncate: 上限に収まるところまでを書き出して末尾に省略の目印を入れる、
+    /// split: 上限に収まる大きさの「出力ファイル名-NN.md」に分けて書き出し、出力ファイルにはその一覧を書く。既定は error）
+    #[arg(long, value_enum, value_name = "POLICY", requires = "max_output_bytes")]
+    oversize: Option<OversizePolicy>,
+
+    /// 変換の詳細（デバッグ用の情報や PDF の読み込みのライブラリのログを含む）を追記するログファイルのパス（画面には従来どおりの要約だけを表示する）
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// 変換の詳細を標準エラー出力にも表示する（-v で読み込んだページや働いた判定、代わりに取った方法、-vv で PDF の読み込みのライブラリを含むすべて）
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// 標準エラー出力に表示するログの形式（json: メッセージと -v の詳細を、1行に1つの JSON のログとして書く）
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// 出力の形式（json: 見出し・段落・リスト・表・コードブロック・画像を、ページ番号とページ上の位置とともに JSON で出力する。出力ファイル名の既定は入力ファイル名.json）
+    #[arg(long, value_enum, default_value = "markdown", conflicts_with_all = ["split_by", "articles", "review_html", "toc", "to_clipboard"])]
+    format: OutputFormat,
+
+    /// ページ画像と変換した Markdown をページごとに左右に並べた確認用の HTML を書き出すファイルのパス
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["articles", "split_by"])]
+    review_html: Option<PathBuf>,
+
+    /// 変換結果の記録（変換率・ページごとの変換率・警告）を JSON で書き出すファイルのパス
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// PDF を解析して見出しや表などの判定まで行い、ページ数・見出し・表・画像の数と出力先のパスを表示する（Markdown や画像などのファイルは書き出さない）
+    #[arg(long, conflicts_with_all = ["report", "manifest", "review_html", "to_clipboard", "isolate"])]
+    dry_run: bool,
+
+    /// 設定ファイルのパス（指定がない場合はカレントディレクトリの pdf2md.toml を読み込みます）
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// 設定ファイルの [profiles.<名前>] に定義した変換プロファイルを使う
+    ///
+    /// 指定がない場合は文書の特徴（段組み・スキャンかどうか・しおりの有無など）から自動で選びます。none で自動選択を無効にします
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+}
+
+/// 変換以外のサブコマンド
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// 検出した見出し（--bookmarks の場合は PDF のしおり）の階層（レベル・テキスト・ページ）を表示する
+    Outline {
+        /// 入力PDFファイルのパス
+        input: PathBuf,
+
+        /// 出力形式
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutlineFormat,
+
+        /// 権限設定でコピーが禁止されたPDFでもテキストを抽出する
+        #[arg(long)]
+        override_permissions: bool,
+
+        /// 見出しを検出する代わりに、PDF のしおり（移動先のページは名前付きの移動先も解決する）を表示する
+        #[arg(long)]
+        bookmarks: bool,
+    },
+
+    /// 抽出した図をキャプションとページ番号付きで一覧にした Markdown を出力する
+    Figures {
+        /// 入力PDFファイルのパス
+        input: PathBuf,
+
+        /// 出力Markdownファイルのパス（指定がない場合は入力ファイル名に .figures.md を付けたものになります）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// 権限設定でコピーが禁止されたPDFでもテキストを抽出する
+        #[arg(long)]
+        override_permissions: bool,
+
+        /// 図の中の文字（軸のラベルなど）を tesseract で文字認識し、代替テキスト（alt）か定義リスト（list）として出力する
+        #[arg(long, value_enum)]
+        figure_text: Option<figures::FigureText>,
+
+        /// 図の文字認識に使う tesseract の言語（eng、jpn、eng+jpn など）
+        #[arg(long, default_value = "eng")]
+        ocr_lang: String,
+
+        /// 図の代替テキストを生成するコマンド（画像のパスを最後の引数として渡し、標準出力を代替テキストにする）
+        #[arg(long, conflicts_with = "alt_text_url")]
+        alt_text_command: Option<String>,
+
+        /// 図の代替テキストを生成する HTTP エンドポイント（画像を本文として POST し、応答の本文を代替テキストにする。http 機能が必要）
+        #[arg(long)]
+        alt_text_url: Option<String>,
+    },
+
+    /// フォントサイズと太字の分布から見出しレベルの対応を推定し、同じ種類の文書の変換に使えるプロファイルの定義を出力する
+    Calibrate {
+        /// 入力PDFファイルのパス
+        input: PathBuf,
+
+        /// プロファイルの定義を書き出すファイルのパス（指定がない場合は標準出力に出力します）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// プロファイル名（指定がない場合は入力ファイル名）
+        #[arg(long)]
+        name: Option<String>,
+
+        /// 分布を調べるページの抜き取り方（例: every:10、first:20）
+        #[arg(long, value_name = "SPEC")]
+        sample: Option<PageSample>,
+
+        /// 権限設定でコピーが禁止されたPDFでもテキストを抽出する
+        #[arg(long)]
+        override_permissions: bool,
+    },
+
+    /// 変換を行わずに、ページごとにテキストレイヤーがあるかを調べる
+    ///
+    /// 終了コード: 0 = 全ページにあり、3 = 一部のページに無い、4 = 全ページに無い
+    HasText {
+        /// 入力PDFファイルのパス
+        input: PathBuf,
+
+        /// テキストレイヤーありとみなす最小の文字数
+        #[arg(long, default_value_t = 20)]
+        min_chars: usize,
+    },
+
+    /// ディレクトリを監視し、PDF が追加・変更されるたびに Markdown に変換し直す（Ctrl-C で終了する）
+    ///
+    /// 監視を始めるときに、Markdown が無いか PDF より古いものを変換する。変換のオプションは -- の後に指定する
+    /// （例: pdf2md watch inbox --output-dir out -- --toc）。
+    Watch {
+        /// 監視するディレクトリ（サブディレクトリも含む）
+        dir: PathBuf,
+
+        /// 出力先のディレクトリ（入力の相対パスと同じ構成で書き出す。指定がない場合は PDF と同じ場所）
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+
+        /// 最後の変更から変換するまでの待ち時間（ミリ秒。コピー中の PDF を途中で変換しないように）
+        #[arg(long, default_value_t = 1000)]
+        debounce_ms: u64,
+
+        /// 変換のオプション（変換するときと同じ引数）
+        #[arg(last = true)]
+        args: Vec<OsString>,
+    },
+
+    /// 開発用: ディレクトリ以下の PDF を変換し、期待する Markdown（foo.pdf に対する foo.expected.md）との差分を表示する
+    ///
+    /// 1つでも異なれば失敗で終える。foo.args があれば、その PDF だけに使う変換のオプション（1行に1つ）として加える。
+    /// すべての PDF に使う変換のオプションは -- の後に指定する。
+    CorpusTest {
+        /// PDF と期待する Markdown を置いたディレクトリ
+        dir: PathBuf,
+
+        /// 差分を表示する代わりに、変換結果で期待する Markdown を書き換える（見出しの判定などを変えた後に使う）
+        #[arg(long)]
+        update: bool,
+
+        /// すべての PDF に使う変換のオプション
+        #[arg(last = true)]
+        args: Vec<OsString>,
+    },
+}
+
+/// コマンドライン引数を解析して、サブコマンドか変換を実行する
+pub fn run() -> Result<()> {
+    // コマンドライン引数の解析
+    let args = Args::parse();
+
+    match args.command {
+        Some(Command::Outline { input, format, override_permissions, bookmarks }) => {
+            let outline = if bookmarks {
+                let doc = open_document(&input, None)?;
+                outline::bookmark_outline(&destinations::bookmarks(&doc, &destinations::Destinations::load(&doc)))
+            } else {
+                let options = ExtractOptions { override_permissions, ..Default::default() };
+                outline::build_outline(&extract_pages(&input, &options)?)
+            };
+            print!("{}", outline::render_outline(&outline, format)?);
+            Ok(())
+        }
+        Some(Command::Figures { input, output, override_permissions, figure_text, ocr_lang, alt_text_command, alt_text_url }) => {
+            let alt_text = alt_text_command.map(alt_text::AltTextHook::Command).or(alt_text_url.map(alt_text::AltTextHook::Url));
+            run_figures(&input, output, override_permissions, figure_text, &ocr_lang, alt_text.as_ref())
+        }
+        Some(Command::Calibrate { input, output, name, sample, override_permissions }) => {
+            run_calibrate(&input, output, name, sample, override_permissions)
+        }
+        Some(Command::HasText { input, min_chars }) => {
+            let doc = open_document(&input, None)?;
+            let probes = probe::probe_text_layer(&doc);
+            for probe in &probes {
+                let status = if probe.chars >= min_chars { "text" } else { "none" };
+                println!("{}\t{}\t{}", probe.page, status, probe.chars);
+            }
+            std::process::exit(probe::exit_code(&probes, min_chars));
+        }
+        Some(Command::Watch { dir, output_dir, debounce_ms, args }) => run_watch(&dir, output_dir, Duration::from_millis(debounce_ms), &args),
+        Some(Command::CorpusTest { dir, update, args }) => run_corpus_test(&dir, update, &args),
+        None => {
+            let result = run_conversion(args);
+            if let Err(e) = &result {
+                tracing::error!("{:#}", e);
+                // PDF ではない入力は、専用の終了コードで終える
+                if e.downcast_ref::<NotPdf>().is_some() {
+                    eprintln!("Error: {:?}", e);
+                    std::process::exit(sniff::EXIT_NOT_PDF);
+                }
+            }
+            result
+        }
+    }
+}
+
+/// ログファイルと設定ファイルを用意して、1つの PDF か、ディレクトリやパターンに一致するすべての PDF を変換する
+///
+/// 設定ファイル（見出しの規則の正規表現など）は一度だけ読み込み、すべての PDF で使い回す。
+fn run_conversion(args: Args) -> Result<()> {
+    progress::init(args.progress_format);
+    logging::init(&LogOptions { file: args.log_file.as_deref(), verbose: args.verbose, format: args.log_format })?;
+    let config = config::load_config(args.config.as_deref())?;
+    if args.stdio_server {
+        return run_server(&config);
+    }
+    let rules = CompiledRules::new(&args);
+    if args.input.as_deref().is_some_and(batch::is_batch) {
+        return run_batch(args, &config, &rules);
+    }
+
+    let input = args.input.clone().unwrap_or_default();
+    let report = args.report.clone();
+    // --isolate の子プロセスでは、ファイルの開始と終了は呼び出し元が知らせる
+    let events = !isolate::is_child();
+    if events {
+        progress::file_started(&input, 1, 1);
+    }
+    let start = Instant::now();
+    let result = run_single(args, &config, &rules);
+    let status = match &result {
+        Ok(_) => FileStatus::Converted,
+        Err(e) => FileStatus::Failed(format!("{:#}", e)),
+    };
+    if events {
+        progress::file_finished(&input, &status, start.elapsed().as_secs_f64());
+    }
+    if let Some(spec) = &report {
+        let warnings = result.as_ref().map(Vec::clone).unwrap_or_default();
+        report::write_report(spec, &[FileReport { input, status, warnings, seconds: start.elapsed().as_secs_f64() }])?;
+    }
+    result.map(drop)
+}
+
+/// 1つの PDF を変換し、表示した警告を返す（--isolate の場合は子プロセスで変換し、警告は返さない）
+fn run_single(args: Args, config: &config::Config, rules: &CompiledRules) -> Result<Vec<String>> {
+    if args.isolate {
+        let input = args.input.as_deref().context("入力PDFファイルのパスが指定されていません")?;
+        if input == Path::new("-") || http::is_url(input) {
+            bail!("--input に - や URL を指定した場合は --isolate を使えません");
+        }
+        let output = output_path_for(input, args.output.as_deref(), args.output_dir.as_deref());
+        ensure_pdf_file(input)?;
+        isolate::convert_in_child(&isolate::child_args(std::env::args_os().skip(1)), input, &output)?;
+        return Ok(Vec::new());
+    }
+    match run_convert(args.clone(), config, rules) {
+        // パスワードが必要で指定されていない場合は、端末で尋ねてやり直す
+        Err(e) if e.downcast_ref::<PasswordRequired>().is_some() && std::io::stdin().is_terminal() => {
+            let input = args.input.clone().unwrap_or_default();
+            let password = password::prompt(&input)?;
+            run_convert(Args { password: Some(password), ..args }, config, rules)
+        }
+        result => result,
+    }
+}
+
+/// 標準入力から PDF を読み込む（PDF ではない入力は NotPdf のエラーにする）
+fn read_stdin_pdf() -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut bytes).context("標準入力から PDF を読み込めません")?;
+    sniff::ensure_pdf(&bytes).context("PDFからのテキスト抽出に失敗しました: 標準入力")?;
+    Ok(bytes)
+}
+
+/// 入力が PDF でなければ NotPdf のエラーにする（子プロセスや一括変換で、変換を始める前に確かめる）
+fn ensure_pdf_file(input: &Path) -> Result<()> {
+    match sniff::sniff_file(input)? {
+        ContentKind::Pdf => Ok(()),
+        kind => Err(NotPdf { kind }).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", input)),
+    }
+}
+
+/// 出力ファイルのパス（指定がない場合は、出力先のディレクトリか入力と同じ場所に、入力のファイル名の拡張子を .md にしたもの）
+fn output_path_for(input: &Path, output: Option<&Path>, output_dir: Option<&Path>) -> PathBuf {
+    match (output, output_dir) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Some(dir)) => dir.join(input.file_name().unwrap_or_default()).with_extension("md"),
+        (None, None) => input.with_extension("md"),
+    }
+}
+
+/// ディレクトリかパターンに一致するすべての PDF を変換する（失敗した PDF があっても残りを変換し、最後にエラーにする）
+fn run_batch(args: Args, config: &config::Config, rules: &CompiledRules) -> Result<()> {
+    let input = args.input.clone().context("入力PDFファイルのパスが指定されていません")?;
+    if args.review_html.is_some() || args.manifest.is_some() || args.to_clipboard || args.page.is_some() {
+        bail!("ディレクトリやパターンを入力にした場合は --review-html、--manifest、--to-clipboard、--page を使えません");
+    }
+    let inputs = batch::collect_inputs(&input)?;
+    if inputs.is_empty() {
+        bail!("変換する PDF が見つかりません: {:?}", input);
+    }
+
+    let jobs = args.jobs.map_or_else(|| std::thread::available_parallelism().map_or(1, usize::from), usize::from);
+    let child_args = args.isolate.then(|| isolate::child_args(std::env::args_os().skip(1)));
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build().context("変換のスレッドを用意できません")?;
+
+    // PDF ごとにエラーとパニックを受け止め、ほかの PDF の変換は続ける
+    let bar = progress::Bar::files(inputs.len());
+    let reports: Vec<FileReport> = pool.install(|| {
+        inputs
+            .par_iter()
+            .enumerate()
+            .map(|(index, file)| {
+                console!(Info, "[{}/{}] {:?}", index + 1, inputs.len(), file.path);
+                progress::file_started(&file.path, index + 1, inputs.len());
+                let start = Instant::now();
+                let (status, warnings) = convert_batch_file(&args, config, rules, file, child_args.as_deref());
+                progress::file_finished(&file.path, &status, start.elapsed().as_secs_f64());
+                bar.inc();
+                if let FileStatus::Failed(error) = &status {
+                    tracing::error!("{:?}: {}", file.path, error);
+                    console!(Error, "{:?} を変換できませんでした: {}", file.path, error);
+                }
+                FileReport { input: file.path.clone(), status, warnings, seconds: start.elapsed().as_secs_f64() }
+            })
+            .collect()
+    });
+    if let Some(spec) = &args.report {
+        report::write_report(spec, &reports)?;
+        console!(Info, "変換結果の報告を書き出しました: {:?}", spec.path);
+    }
+
+    let failed: Vec<&PathBuf> = reports.iter().filter(|report| matches!(report.status, FileStatus::Failed(_))).map(|report| &report.input).collect();
+    let skipped = reports.iter().filter(|report| matches!(report.status, FileStatus::Skipped(_))).count();
+    if !failed.is_empty() {
+        bail!("{} 個中 {} 個の PDF を変換できませんでした: {:?}", inputs.len(), failed.len(), failed);
+    }
+    if skipped > 0 {
+        console!(Info, "{} 個の PDF を変換しました（PDF ではない {} 個のファイルを飛ばしました）", inputs.len() - skipped, skipped);
+    } else {
+        console!(Info, "{} 個の PDF を変換しました", inputs.len());
+    }
+    Ok(())
+}
+
+/// ディレクトリを監視し、追加・変更された PDF を一括変換と同じように変換する（変換に失敗しても監視は続ける）
+fn run_watch(dir: &Path, output_dir: Option<PathBuf>, debounce: Duration, options: &[OsString]) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("監視するディレクトリが見つかりません: {:?}", dir);
+    }
+    let mut argv: Vec<OsString> = vec!["pdf2md".into()];
+    argv.extend(options.iter().cloned());
+    argv.extend(["--input".into(), dir.as_os_str().to_owned()]);
+    let args = Args::try_parse_from(argv).map_err(|e| anyhow::anyhow!("変換のオプションが正しくありません: {}", e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ")))?;
+    if args.output.is_some() || args.output_dir.is_some() {
+        bail!("watch では変換のオプションに --output、--output-dir を指定できません（出力先は watch の --output-dir で指定してください）");
+    }
+    if args.review_html.is_some() || args.manifest.is_some() || args.to_clipboard || args.page.is_some() || args.dry_run {
+        bail!("watch では --review-html、--manifest、--to-clipboard、--page、--dry-run を使えません");
+    }
+    progress::init(args.progress_format);
+    logging::init(&LogOptions { file: args.log_file.as_deref(), verbose: args.verbose, format: args.log_format })?;
+    let config = config::load_config(args.config.as_deref())?;
+    let args = Args { output_dir, ..args };
+    let child_args = args.isolate.then(|| isolate::child_args(options.iter().cloned()));
+    let rules = CompiledRules::new(&args);
+
+    let convert = |file: &batch::BatchInput| {
+        progress::file_started(&file.path, 1, 1);
+        let start = Instant::now();
+        let (status, _) = convert_batch_file(&args, &config, &rules, file, child_args.as_deref());
+        progress::file_finished(&file.path, &status, start.elapsed().as_secs_f64());
+        match &status {
+            FileStatus::Converted => console!(Info, "{:?} を変換しました", file.relative),
+            FileStatus::Failed(error) => console!(Error, "{:?} を変換できませんでした: {}", file.path, error),
+            FileStatus::Skipped(_) => {}
+        }
+    };
+    for file in watch::outdated(dir, args.output_dir.as_deref())? {
+        convert(&file);
+    }
+    console!(Info, "{:?} を監視しています（Ctrl-C で終了します）", dir);
+    watch::watch(dir, debounce, convert)
+}
+
+/// コーパスの PDF を変換し、期待する Markdown と比べる（--update の場合は期待する Markdown を書き換える）
+fn run_corpus_test(dir: &Path, update: bool, options: &[OsString]) -> Result<()> {
+    let fixtures = corpus::collect_fixtures(dir)?;
+    if fixtures.is_empty() {
+        bail!("コーパスに PDF が見つかりません: {:?}", dir);
+    }
+    // コーパスのすべての PDF に共通のオプション（ログや設定ファイル）は、ディレクトリを入力にして解析する
+    let parse = |input: &Path, extra: &[OsString]| {
+        let mut argv: Vec<OsString> = vec!["pdf2md".into()];
+        argv.extend(options.iter().chain(extra).cloned());
+        argv.extend(["--input".into(), input.as_os_str().to_owned(), "--dry-run".into()]);
+        Args::try_parse_from(argv).map_err(|e| anyhow::anyhow!("変換のオプションが正しくありません: {}", e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ")))
+    };
+    let args = parse(dir, &[])?;
+    progress::init(args.progress_format);
+    logging::init(&LogOptions { file: args.log_file.as_deref(), verbose: args.verbose, format: args.log_format })?;
+    let config = config::load_config(args.config.as_deref())?;
+
+    let (mut failed, mut updated) = (Vec::new(), 0);
+    for fixture in &fixtures {
+        let markdown = match parse(&fixture.pdf, &fixture.args).and_then(|args| convert_input(&CompiledRules::new(&args), args, &config, &CancellationToken::new())) {
+            Ok(conversion) => conversion.markdown,
+            Err(e) => {
+                println!("FAIL {}: 変換できませんでした: {:#}", fixture.relative.display(), e);
+                failed.push(&fixture.relative);
+                continue;
+            }
+        };
+        let expected = std::fs::read_to_string(&fixture.expected).ok();
+        if expected.as_deref() == Some(markdown.as_str()) {
+            println!("ok   {}", fixture.relative.display());
+        } else if update {
+            write_to_file(&fixture.expected, &markdown)?;
+            println!("更新 {}", fixture.relative.display());
+            updated += 1;
+        } else {
+            match &expected {
+                Some(expected) => println!("FAIL {}\n{}", fixture.relative.display(), corpus::line_diff(expected, &markdown)),
+                None => println!("FAIL {}: 期待する Markdown {:?} がありません（--update で作成できます）", fixture.relative.display(), fixture.expected),
+            }
+            failed.push(&fixture.relative);
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("{} 個中 {} 個の PDF の変換結果が期待する Markdown と異なります: {:?}", fixtures.len(), failed.len(), failed);
+    }
+    match updated {
+        0 => console!(Info, "{} 個の PDF の変換結果がすべて期待する Markdown と一致しました", fixtures.len()),
+        _ => console!(Info, "{} 個の PDF のうち {} 個の期待する Markdown を更新しました", fixtures.len(), updated),
+    }
+    Ok(())
+}
+
+/// 標準入力と標準出力で変換の要求を受け付ける（要求の args は、コマンドラインと同じように解析する）
+fn run_server(config: &config::Config) -> Result<()> {
+    console!(Info, "標準入力で変換の要求を待っています");
+    server::serve(std::io::stdin().lock(), std::io::stdout(), |params: server::ConvertParams, cancel: &CancellationToken| {
+        if batch::is_batch(&params.input) {
+            bail!(server::InvalidParams(format!("ディレクトリやパターンは入力にできません（PDF ごとに convert を要求してください）: {:?}", params.input)));
+        }
+        // 標準入力は要求の受け付けに使っているため、読もうとすると変換のスレッドが止まったままになる
+        if params.input == Path::new("-") {
+            bail!(server::InvalidParams("標準入力（-）は入力にできません（PDF のパスを指定してください）".to_string()));
+        }
+        let mut argv: Vec<OsString> = vec!["pdf2md".into()];
+        argv.extend(params.args.iter().map(OsString::from));
+        argv.extend(["--input".into(), params.input.clone().into_os_string(), "--output".into()]);
+        argv.push(params.output.clone().map_or_else(|| "-".into(), PathBuf::into_os_string));
+        let args = Args::try_parse_from(argv).map_err(|e| anyhow::anyhow!("変換のオプションが正しくありません: {}", e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ")))?;
+
+        // 要求ごとに --redact などの指定が違うため、規則も要求ごとに構築する
+        let conversion = convert_input(&CompiledRules::new(&args), args, config, cancel)?;
+        let mut result = serde_json::json!({"warnings": conversion.warnings, "coverage": conversion.coverage});
+        if params.output.is_some() {
+            write_encoded(&conversion.output_path, &conversion.markdown, conversion.encoding)?;
+            result["output"] = serde_json::json!(conversion.output_path);
+        } else {
+            result["markdown"] = serde_json::json!(conversion.markdown);
+        }
+        Ok(result)
+    })
+}
+
+/// 一括変換で1つのファイルを変換し、結果と表示した警告を返す（child_args がある場合は子プロセスで変換し、警告は返さない）
+fn convert_batch_file(args: &Args, config: &config::Config, rules: &CompiledRules, file: &batch::BatchInput, child_args: Option<&[OsString]>) -> (FileStatus, Vec<String>) {
+    // PDF ではないファイル（エラーページを .pdf として保存したものなど）は、失敗とせずに飛ばす
+    match sniff::sniff_file(&file.path) {
+        Ok(ContentKind::Pdf) => {}
+        Ok(kind) => {
+            console!(Warn, "{:?} は PDF ではないため飛ばします（内容は{}です）", file.path, kind);
+            return (FileStatus::Skipped(NotPdf { kind }.to_string()), Vec::new());
+        }
+        Err(e) => return (FileStatus::Failed(format!("{:#}", e)), Vec::new()),
+    }
+    let mut output = batch::output_path(file, args.output_dir.as_deref());
+    if args.format == OutputFormat::Json {
+        output.set_extension("json");
+    }
+    if let Some(child_args) = child_args {
+        return match isolate::convert_in_child(child_args, &file.path, &output) {
+            Ok(()) => (FileStatus::Converted, Vec::new()),
+            Err(e) => (FileStatus::Failed(format!("{:#}", e)), Vec::new()),
+        };
+    }
+    let file_args = Args { input: Some(file.path.clone()), output: Some(output), output_dir: None, ..args.clone() };
+    match std::panic::catch_unwind(AssertUnwindSafe(|| run_convert(file_args, config, rules))) {
+        Ok(Ok(warnings)) => (FileStatus::Converted, warnings),
+        Ok(Err(e)) => (FileStatus::Failed(format!("{:#}", e)), Vec::new()),
+        Err(_) => (FileStatus::Failed("変換中に内部エラーが発生しました".to_string()), Vec::new()),
+    }
+}
+
+/// 書式の分布と推定した見出しの対応を表示し、プロファイルの定義を出力する
+fn run_calibrate(input: &Path, output: Option<PathBuf>, name: Option<String>, sample: Option<PageSample>, override_permissions: bool) -> Result<()> {
+    let options = ExtractOptions { override_permissions, sample, ..Default::default() };
+    let pages = extract_pages(input, &options)?;
+    let distribution = font_styles::style_distribution(&pages);
+    let styles = font_styles::propose_heading_styles(&distribution, font_styles::MIN_HEADING_SCALE);
+
+    eprintln!("サイズ\t太字\t行数\t文字数\t見出し\t例");
+    for stats in &distribution {
+        let level = styles
+            .iter()
+            .find(|style| (style.font_size - stats.style.size()).abs() < 0.01 && style.bold.is_none_or(|bold| bold == stats.style.bold))
+            .map(|style| "#".repeat(style.level))
+            .unwrap_or_default();
+        let sample: String = stats.sample.chars().take(40).collect();
+        eprintln!("{}pt\t{}\t{}\t{}\t{}\t{}", stats.style.size(), if stats.style.bold { "○" } else { "" }, stats.lines, stats.chars, level, sample);
+    }
+    if styles.is_empty() {
+        console!(Info, "本文より大きいか太い書式が見つからないため、見出しの対応を推定できませんでした");
+    }
+
+    let source = input.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = name.unwrap_or_else(|| input.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default());
+    let profile = font_styles::render_profile(&name, &source, &distribution, &styles);
+    match output {
+        Some(path) => {
+            write_to_file(&path, &profile)?;
+            console!(Info, "プロファイル {} の定義を書き出しました: {:?}", name, path);
+        }
+        None => print!("{}", profile),
+    }
+    Ok(())
+}
+
+/// 図の一覧を Markdown に書き出す（画像は出力ファイル名_assets ディレクトリに保存）
+fn run_figures(
+    input: &Path,
+    output: Option<PathBuf>,
+    override_permissions: bool,
+    figure_text: Option<figures::FigureText>,
+    ocr_lang: &str,
+    alt_text: Option<&alt_text::AltTextHook>,
+) -> Result<()> {
+    let output_path = output.unwrap_or_else(|| input.with_extension("figures.md"));
+    let (assets_dir, link_dir) = assets_dir_for(&output_path);
+
+    let options = ExtractOptions { override_permissions, ..Default::default() };
+    let doc = load_document(input, &options)?;
+    let pages = layout_pages(&doc, input, &options, &CancellationToken::new())?;
+    let mut figures = figures::extract_figures(&doc, &pages, &assets_dir, &link_dir)?;
+    if figure_text.is_some() {
+        figures::recognize_text(&mut figures, ocr_lang)?;
+    }
+    if let Some(hook) = alt_text {
+        figures::generate_alt_text(&mut figures, hook);
+    }
+
+    let title = input.file_stem().unwrap_or_default().to_string_lossy();
+    write_to_file(&output_path, &figures::render_gallery(&title, &figures, figure_text))?;
+
+    console!(Info, "{} 件の図を書き出しました。出力ファイル: {:?}", figures.len(), output_path);
+    Ok(())
+}
+
+/// 出力ファイルに対応する画像ディレクトリ（出力ファイル名_assets）と、Markdown から見た相対パスを返す
+fn assets_dir_for(output_path: &Path) -> (PathBuf, String) {
+    let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+    let link_dir = format!("{}_assets", stem);
+    (output_path.with_file_name(&link_dir), link_dir)
+}
+
+/// 本文の画像の出力先（--images-dir の指定が無ければ「出力ファイル名_assets」）と、Markdown から見た相対パス
+fn images_dir_for(output_path: &Path, images_dir: Option<&Path>) -> (PathBuf, String) {
+    match images_dir {
+        Some(dir) => (dir.to_path_buf(), relative_link(output_path.parent().unwrap_or(Path::new("")), dir)),
+        None => assets_dir_for(output_path),
+    }
+}
+
+/// PDFを Markdown に変換してファイルに書き込む（config は読み込んだ設定ファイル）
+fn run_convert(args: Args, config: &config::Config, rules: &CompiledRules) -> Result<Vec<String>> {
+    let (to_clipboard, clipboard_only) = (args.to_clipboard, clipboard_only(&args));
+    let conversion = convert_input(rules, args, config, &CancellationToken::new())?;
+    if let Some(summary) = &conversion.dry_run {
+        print!("{}", summary);
+        return Ok(conversion.warnings);
+    }
+
+    // ファイルか標準出力への書き込み（クリップボードにだけ入れる場合は書き出さない）
+    if conversion.to_stdout {
+        write_to_stdout(&conversion.markdown, conversion.encoding)?;
+    } else if !clipboard_only {
+        write_encoded(&conversion.output_path, &conversion.markdown, conversion.encoding)?;
+    }
+    if to_clipboard {
+        clipboard::copy(&conversion.markdown)?;
+    }
+
+    let message = if clipboard_only {
+        format!("変換が完了しました（変換率 {:.1}%）。クリップボードに入れました", conversion.coverage)
+    } else if conversion.to_stdout {
+        format!("変換が完了しました（変換率 {:.1}%）。標準出力に書き出しました", conversion.coverage)
+    } else {
+        format!("変換が完了しました（変換率 {:.1}%）。出力ファイル: {:?}", conversion.coverage, conversion.output_path)
+    };
+    console!(Info, "{}", message);
+    Ok(conversion.warnings)
+}
+
+/// クリップボードにだけ入れ、ファイルには書き出さないかどうか
+fn clipboard_only(args: &Args) -> bool {
+    args.to_clipboard && args.output.is_none() && args.output_dir.is_none()
+}
+
+/// 見出しの規則の判定方法とマスク処理（正規表現をまとめてコンパイルするため、変換の実行ごとに一度だけ構築し、すべての PDF で使い回す）
+///
+/// プロファイルは PDF ごとに選ぶため、プロファイルごとに初めて使うときに構築して覚えておく。
+struct CompiledRules {
+    /// --redact の指定（空の場合はプロファイルの指定を使う）
+    redact: Vec<PiiKind>,
+    /// --redact-pattern の指定（プロファイルの指定に加える）
+    redact_patterns: Vec<String>,
+    /// プロファイルの名前（既定は空文字列）ごとの規則
+    profiles: Mutex<HashMap<String, Arc<ProfileRules>>>,
+}
+
+/// 1つのプロファイルの見出しの判定方法とマスク処理
+struct ProfileRules {
+    headings: Vec<Box<dyn HeadingDetector>>,
+    redactor: Redactor,
+}
+
+impl CompiledRules {
+    fn new(args: &Args) -> Self {
+        CompiledRules { redact: args.redact.clone(), redact_patterns: args.redact_patterns.clone(), profiles: Mutex::new(HashMap::new()) }
+    }
+
+    /// プロファイル（name が None の場合は既定）の規則を返す（初めて使う場合は構築する）
+    fn for_profile(&self, name: Option<&str>, profile: &config::Profile) -> Result<Arc<ProfileRules>> {
+        let mut profiles = self.profiles.lock().unwrap();
+        if let Some(rules) = profiles.get(name.unwrap_or_default()) {
+            return Ok(rules.clone());
+        }
+        let redact_kinds = if self.redact.is_empty() { &profile.redact } else { &self.redact };
+        let redact_patterns: Vec<String> = profile.redact_patterns.iter().chain(&self.redact_patterns).cloned().collect();
+        let rules = Arc::new(ProfileRules {
+            headings: vec![Box::new(headings::RegexDetector::new(profile.headings.clone()))],
+            redactor: Redactor::new(redact_kinds, &redact_patterns)?,
+        });
+        profiles.insert(name.unwrap_or_default().to_string(), rules.clone());
+        Ok(rules)
+    }
+}
+
+/// 1つの PDF の変換の結果（Markdown は書き出す前のもの）
+struct Conversion {
+    markdown: String,
+    output_path: PathBuf,
+    /// 標準出力に書き出す（出力ファイルのパスが -）
+    to_stdout: bool,
+    /// 書き出すときの文字コード
+    encoding: OutputEncoding,
+    /// 表示した警告（色を付けずに描画したもの）
+    warnings: Vec<String>,
+    /// 変換率（%）
+    coverage: f64,
+    /// --dry-run の場合の要約（ファイルは書き出さない）
+    dry_run: Option<DryRunSummary>,
+}
+
+/// --dry-run で表示する、変換した場合に出力する内容の要約
+struct DryRunSummary {
+    input: PathBuf,
+    output_path: PathBuf,
+    pages: usize,
+    counts: structure::BlockCounts,
+}
+
+impl fmt::Display for DryRunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?}（ファイルは書き出していません）", self.input)?;
+        writeln!(f, "  出力先: {:?}", self.output_path)?;
+        writeln!(f, "  ページ: {}", self.pages)?;
+        writeln!(f, "  見出し: {}", self.counts.headings)?;
+        writeln!(f, "  表: {}", self.counts.tables)?;
+        writeln!(f, "  画像: {}", self.counts.images)?;
+        writeln!(f, "  段落: {}、リスト: {}", self.counts.paragraphs, self.counts.lists)
+    }
+}
+
+/// 1つの PDF を Markdown にする（画像や請求書の項目などの付随するファイルは書き出すが、Markdown は書き出さない）
+///
+/// 見出しの規則の判定方法とマスク処理は、rules で構築済みのものを使う。
+fn convert_input(rules: &CompiledRules, args: Args, config: &config::Config, cancel: &CancellationToken) -> Result<Conversion> {
+    let clipboard_only = clipboard_only(&args);
+    let input = args.input.context("入力PDFファイルのパスが指定されていません")?;
+    let _scope = progress::FileScope::enter(&input);
+    // --input - の場合は、標準入力の PDF を読み込んでおく（プロファイルの選択や文書情報の読み取りでも使う）
+    let stdin_pdf = (input == Path::new("-")).then(read_stdin_pdf).transpose()?;
+    // URL の場合は一時ファイルに取得し、変換を終えたら削除する（出力ファイルの名前は URL のファイル名から決める）
+    let download = http::is_url(&input).then(|| http::download(&input.to_string_lossy(), &args.headers)).transpose()?;
+    let named = match &download {
+        Some(_) => http::url_file_name(&input.to_string_lossy()),
+        None => input.clone(),
+    };
+    let pdf = match (&stdin_pdf, &download) {
+        (Some(bytes), _) => PdfInput::Bytes(bytes, Path::new("<stdin>")),
+        (None, Some(download)) => PdfInput::File(&download.path),
+        (None, None) => PdfInput::File(&input),
+    };
+    if stdin_pdf.is_some() && (args.output_dir.is_some() || args.review_html.is_some()) {
+        bail!("--input - では --output-dir と --review-html を使えません（出力ファイルは --output で指定してください）");
+    }
+
+    // 出力ファイルパスの決定（--page と、標準入力から読み込んで出力ファイルの指定がない場合は標準出力）
+    let quick = args.page.is_some();
+    // --page では --ocr を指定しない限り OCR を行わないので、--ocr-mark-below の指定が何もしないことになる
+    if quick && !args.ocr && args.ocr_mark_below.is_some() {
+        bail!("--page で --ocr-mark-below を使うには --ocr も指定してください");
+    }
+    let output =if quick || (stdin_pdf.is_some() && args.output.is_none()) { Some(Path::new("-")) } else { args.output.as_deref() };
+    let structured = args.format == OutputFormat::Json;
+    let output_path = match output_path_for(&named, output, args.output_dir.as_deref()) {
+        path if structured && output.is_none() => path.with_extension("json"),
+        path => path,
+    };
+    let to_stdout = output_path == Path::new("-");
+    let limit = args.max_output_bytes.map(|max_bytes| OutputLimit { max_bytes: max_bytes as usize, policy: args.oversize.unwrap_or_default() });
+    if limit.is_some_and(|limit| limit.policy == OversizePolicy::Split) {
+        if to_stdout || clipboard_only {
+            bail!("--oversize split は、標準出力やクリップボードに出力する場合には使えません（出力ファイルを --output で指定してください）");
+        }
+        if args.split_by.is_some() || args.articles.is_some() {
+            bail!("--oversize split は --split-by と --articles と併用できません");
+        }
+    }
+    if structured && limit.is_some_and(|limit| limit.policy != OversizePolicy::Error) {
+        bail!("--format json では、--oversize は error のみ指定できます");
+    }
+    // 画像や分けた記事などのファイルは、標準出力に書き出す場合は入力と同じ場所に書き出す。
+    // --dry-run では一時的なディレクトリに書き出し、変換を終えたら削除する
+    let dry_run_dir = args.dry_run.then(|| DryRunDir(crate::work_dir("dry-run")));
+    let files_path = match &dry_run_dir {
+        Some(DryRunDir(dir)) => dir.join(output_path.file_name().unwrap_or_else(|| named.file_name().unwrap_or_default())).with_extension("md"),
+        // 標準入力から読み込んだ場合は、カレントディレクトリの stdin.md の場所とする
+        None if stdin_pdf.is_some() && (to_stdout || clipboard_only) => PathBuf::from("stdin.md"),
+        None if to_stdout || clipboard_only => named.with_extension("md"),
+        None => output_path.clone(),
+    };
+    let output_dir = files_path.parent().filter(|_| dry_run_dir.is_some()).or_else(|| output_path.parent());
+    if let Some(dir) = output_dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("出力先のディレクトリを作成できません: {:?}", dir))?;
+    }
+
+    tracing::info!("変換を開始します: {:?} -> {:?}", input, output_path);
+
+    // プロファイルの読み込み（コマンドライン引数 > プロファイル > 設定ファイルの順に優先する）
+    let profile_name = select_profile(config, args.profile.as_deref(), pdf, args.password.as_deref())?;
+    let profile = match &profile_name {
+        Some(name) => {
+            let profile = config.profile(name)?;
+            console!(Info, "プロファイル {} を使用します{}", name, profile.description.as_ref().map(|d| format!("（{}）", d)).unwrap_or_default());
+            profile
+        }
+        None => config::Profile::default(),
+    };
+    let rules = rules.for_profile(profile_name.as_deref(), &profile)?;
+    let redactor = &rules.redactor;
+
+    let mut layout_config = config.layout.clone();
+    layout_config.pages.splice(0..0, profile.pages);
+    if let Some(columns) = args.columns.map(usize::from).or(profile.columns) {
+        layout_config.columns = Some(columns);
+    }
+
+    let front_matter_enabled = args.front_matter || profile.front_matter.unwrap_or(false);
+    let mut tags = profile.tags;
+    tags.extend(args.tags);
+
+    let article_output = args.articles.or(profile.articles);
+    let split_by = args.split_by.or(profile.split_by).filter(|_| article_output.is_none());
+    // 分けた各ファイルの雛形（コマンドライン引数の \n は改行にする）
+    let part_template = split::PartTemplate {
+        header: args.part_header.map(|header| header.replace("\\n", "\n")).or_else(|| config.split.header.clone()),
+        footer: args.part_footer.map(|footer| footer.replace("\\n", "\n")).or_else(|| config.split.footer.clone()),
+        navigation: args.split_nav.or(config.split.navigation).unwrap_or_default(),
+    };
+    part_template.validate()?;
+
+    // 分綴のパターン（コマンドライン引数で指定した言語は設定ファイルより優先する）
+    let mut hyphenation = config.conversion.hyphenation.clone();
+    hyphenation.extend(args.hyphenation_patterns.iter().map(|spec| (spec.language.clone(), spec.path.clone())));
+    let dehyphenate = (args.dehyphenate || config.conversion.dehyphenate || !args.hyphenation_patterns.is_empty())
+        .then(|| Dehyphenator::load(&hyphenation.into_iter().collect::<Vec<_>>()))
+        .transpose()?;
+
+    let layout_model = match (args.layout_model.or(profile.layout_model), args.layout_command.or(profile.layout_command)) {
+        (Some(model), _) => Some(LayoutModel::Onnx(model)),
+        (None, Some(command)) => Some(LayoutModel::Command(command)),
+        (None, None) => args.layout_service.or(profile.layout_service).map(|url| LayoutModel::Service {
+            url,
+            input: args.layout_service_input.or(profile.layout_service_input).unwrap_or_default(),
+        }),
+    };
+
+    // PDF の内容を抽出
+    let extract_options = ExtractOptions {
+        ignore_redactions: args.ignore_redactions,
+        override_permissions: args.override_permissions,
+        sample: args.sample,
+        pages: args.page.map(PageRanges::single).or(args.pages),
+        layout: layout_config,
+        placeholders: args.placeholders,
+        margin_notes: args.margin_notes,
+        mode: profile.mode.unwrap_or_default(),
+        invoice: args.invoice || profile.invoice.unwrap_or(false),
+        articles: article_output.is_some(),
+        bilingual: args.bilingual.or(profile.bilingual),
+        financial: args.financial || profile.financial.unwrap_or(false),
+        ignore_tables: args.no_tables || !config.conversion.tables,
+        graphical_pages: (!quick && args.graphical_pages == GraphicalPages::Image && profile.mode.unwrap_or_default() == config::ConversionMode::Document)
+            .then(|| assets_dir_for(&files_path)),
+        figures: (!quick && profile.mode.unwrap_or_default() == config::ConversionMode::Document).then(|| images_dir_for(&files_path, args.images_dir.as_deref())),
+        page_images: args.page_images.map(|dpi| {
+            let (assets_dir, link_dir) = assets_dir_for(&files_path);
+            graphics::PageImages { dpi, link: args.link_page_images, assets_dir, link_dir }
+        }),
+        dedupe_pages: args.dedupe_pages || profile.dedupe_pages.unwrap_or(false),
+        keep_blank_pages: args.keep_blank_pages,
+        keep_headers: args.keep_headers,
+        colors: profile.colors.clone(),
+        highlights: args.highlights.or(profile.highlights),
+        comments: args.comments.or(profile.comments),
+        split_by_outline: split_by == Some(SplitBy::Outline),
+        heading_styles: profile.heading_styles.clone(),
+        bookmark_headings: args.bookmark_headings || profile.bookmark_headings.unwrap_or(false),
+        ignore_font_sizes: args.no_font_headings || !config.conversion.font_headings,
+        heading_scale: Some(config.conversion.heading_scale),
+        layout_model: layout_model.filter(|_| !quick),
+        page_markers: args.review_html.is_some() || structured,
+        keep_layouts: structured,
+        memory_budget: args.memory_budget,
+        robust: args.robust.then(Default::default),
+        password: args.password.clone(),
+        // --page では、--ocr を指定した場合だけ OCR を行う
+        ocr: (!args.no_ocr && (!quick || args.ocr)).then(|| ocr::OcrOptions {
+            pages: if args.ocr { ocr::OcrPages::All } else { ocr::OcrPages::Missing },
+            lang: args.ocr_lang.clone(),
+            mark_below: args.ocr_mark_below,
+            correct: args.ocr_correct,
+            dictionaries: args.ocr_dictionary.clone(),
+        }),
+        dehyphenate,
+    };
+    let mut extracted = extract_pdf_content(pdf, &extract_options, cancel)?;
+    // 警告の抜粋は本文の文字なので、表示やログ、進み具合の通知に出す前にマスクする
+    if !redactor.is_empty() {
+        for excerpt in extracted.warnings.iter_mut().filter_map(|warning| warning.excerpt.as_mut()) {
+            *excerpt = redactor.redact(excerpt);
+        }
+    }
+
+    // 変換で失われる内容の警告（種類ごとの扱いは プロファイル < --allow < --warn < --deny の順に優先し、エラーがあれば出力しない）
+    let mut severities = diagnostics::SeverityLevels::new(args.strict);
+    for (kind, severity) in &profile.warnings {
+        severities.set(&[*kind], *severity);
+    }
+    severities.set(&args.allow, diagnostics::Severity::Allow);
+    severities.set(&args.warn, diagnostics::Severity::Warn);
+    severities.set(&args.deny, diagnostics::Severity::Deny);
+    let mut denied = 0;
+    let mut shown = Vec::new();
+    let color = diagnostics::use_color();
+    for warning in &extracted.warnings {
+        let severity = severities.severity(warning.kind);
+        // ログファイルには色を付けずに書く
+        let level = match severity {
+            diagnostics::Severity::Allow => {
+                tracing::debug!("表示しない警告: {} [{}]", warning, warning.kind.name());
+                continue;
+            }
+            diagnostics::Severity::Warn => log::Level::Warn,
+            diagnostics::Severity::Deny => {
+                denied += 1;
+                log::Level::Error
+            }
+        };
+        let rendered = diagnostics::render_warning(warning, severity, false);
+        logging::event(level, &rendered);
+        shown.push(rendered);
+        progress::warning(warning, severity);
+        if !progress::is_jsonl() && !logging::is_json() {
+            progress::eprintln_above_bars(&diagnostics::render_warning(warning, severity, color));
+        }
+    }
+    if denied > 0 {
+        bail!("エラーとして扱う警告が {} 件あるため、変換を中止しました（--allow や --warn で種類ごとの扱いを変えられます）", denied);
+    }
+
+    // Markdown への変換
+    let whitespace_options = WhitespaceOptions {
+        max_blank_lines: args.max_blank_lines,
+        continuation_indent: args.continuation_indent,
+        trailing_spaces: args.trailing_spaces,
+        line_ending: args.line_ending,
+        wrap: args.wrap.map(usize::from).or(Some(config.conversion.wrap).filter(|width| *width > 0)),
+    };
+    let markdown_options = MarkdownOptions {
+        headings: &rules.headings,
+        transcript: args.transcript.or(profile.transcript),
+        font_headings: extracted.font_headings,
+        ignore_guessed_headings: !config.conversion.guess_headings,
+        ignore_uppercase: !config.conversion.uppercase_bold,
+    };
+    let mut markdown_content = match (extract_options.mode, article_output) {
+        (config::ConversionMode::Document, Some(ArticleOutput::Sections)) => {
+            let sections = extracted
+                .articles
+                .iter()
+                .map(|article| render_article(article, 2, &markdown_options))
+                .collect::<Result<Vec<_>>>()?;
+            sections.join("\n\n")
+        }
+        (config::ConversionMode::Document, Some(ArticleOutput::Files)) => {
+            write_article_files(&files_path, &extracted.articles, &markdown_options, &whitespace_options, args.output_encoding, redactor)?
+        }
+        (config::ConversionMode::Document, None) => convert_to_markdown(extracted.text, &markdown_options)?,
+        // スライドなどの変換方法では、抽出時に Markdown まで組み立てている
+        _ => extracted.text,
+    };
+    let counts = dry_run_dir.is_some().then(|| structure::count_blocks(&markdown_content));
+    let mut review_pages = Vec::new();
+    if args.review_html.is_some() || structured {
+        (markdown_content, review_pages) = review::split_pages(&markdown_content);
+    }
+    if split_by.is_some() {
+        let mut parts = if extracted.parts.is_empty() {
+            let (preamble, parts) = split::split_markdown(&markdown_content);
+            markdown_content = preamble;
+            parts
+        } else {
+            extracted
+                .parts
+                .into_iter()
+                .map(|part| Ok(split::Part { content: convert_to_markdown(part.content, &markdown_options)?, ..part }))
+                .collect::<Result<Vec<_>>>()?
+        };
+        if parts.is_empty() {
+            console!(Info, "分割する区切りの見出しが見つからないため、1つのファイルに出力します");
+        } else {
+            // 雛形の {title} は PDF の文書情報の題名（無ければファイル名）
+            let title = metadata::read_metadata(pdf, args.password.as_deref())?.title;
+            let title = title.unwrap_or_else(|| named.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default());
+            let index = write_part_files(&files_path, &mut markdown_content, &mut parts, (&part_template, &title), &whitespace_options, args.output_encoding, redactor)?;
+            markdown_content = format!("{}\n\n{}", markdown_content.trim_end(), index);
+        }
+    }
+    if !extracted.trailer.is_empty() {
+        markdown_content.push_str("\n\n");
+        markdown_content.push_str(&extracted.trailer);
+    }
+
+    // 空白と空行の正規化
+    markdown_content = whitespace::normalize(&markdown_content, &whitespace_options);
+
+    // 言語の異なる節の目印
+    let lang_tags = args.lang_tags || profile.lang_tags.unwrap_or(false);
+    if lang_tags {
+        markdown_content = lang_tags::tag_sections(&markdown_content);
+    }
+
+    // 目次の挿入（見出しは正規化した後の Markdown から集める）
+    if args.toc {
+        markdown_content = toc::insert_toc(&markdown_content, usize::from(args.toc_depth));
+    }
+
+    // フロントマターの付与
+    if front_matter_enabled || !tags.is_empty() || extracted.invoice.is_some() || !extracted.bibliography.is_empty() {
+        let pdf_metadata = metadata::read_metadata(pdf, args.password.as_deref())?;
+
+        let mut front_matter = FrontMatter { title: pdf_metadata.title, author: pdf_metadata.author, description: pdf_metadata.subject, ..Default::default() };
+        front_matter.add_tags(pdf_metadata.keywords);
+        front_matter.add_tags(tags);
+        front_matter.created = pdf_metadata.created;
+        front_matter.modified = pdf_metadata.modified;
+        if let Some(invoice) = &extracted.invoice {
+            front_matter.fields = invoice.front_matter_fields();
+        }
+        front_matter.fields.extend(extracted.bibliography.iter().cloned());
+        markdown_content.insert_str(0, &front_matter.render());
+    }
+
+    // 個人情報のマスク
+    if !redactor.is_empty() {
+        markdown_content = redactor.redact(&markdown_content);
+    }
+
+    if let Some(invoice) = &extracted.invoice {
+        let invoice_path = files_path.with_extension("invoice.json");
+        let json = redactor.to_json_pretty(invoice).context("請求書の項目の JSON への変換に失敗しました")?;
+        write_to_file(&invoice_path, &json)?;
+        console!(Info, "請求書の項目を書き出しました: {:?}", invoice_path);
+    }
+
+    if extract_options.comments == Some(CommentOutput::Json) {
+        let comments_path = files_path.with_extension("comments.json");
+        let json = redactor.to_json_pretty(&extracted.comments).context("コメントの JSON への変換に失敗しました")?;
+        write_to_file(&comments_path, &json)?;
+        console!(Info, "{} 件のコメントを書き出しました: {:?}", extracted.comments.len(), comments_path);
+    }
+
+    if args.review_html.is_some() || structured {
+        for (_, content) in review_pages.iter_mut() {
+            *content = whitespace::normalize(content, &whitespace_options);
+            if !redactor.is_empty() {
+                *content = redactor.redact(content);
+            }
+        }
+    }
+    if let Some(html_path) = &args.review_html {
+        // 暗号化された文書は、復号した一時ファイルをページ画像にする
+        let decrypted = decrypted_copy(pdf.path(), args.password.as_deref())?;
+        review::write_review_html(html_path, decrypted.as_ref().map_or(pdf.path(), |copy| copy.path()), &review_pages, lang_tags)?;
+        console!(Info, "確認用の HTML を書き出しました: {:?}", html_path);
+    }
+
+    // 二言語の文書の言語ごとのファイル
+    if extract_options.bilingual == Some(BilingualOutput::Files) {
+        let (japanese, english) = bilingual::split_languages(&markdown_content);
+        for (content, extension) in [(japanese, "ja.md"), (english, "en.md")] {
+            let path = files_path.with_extension(extension);
+            write_encoded(&path, &whitespace_options.line_ending.apply(whitespace::normalize(&content, &whitespace_options)), args.output_encoding)?;
+            console!(Info, "言語ごとのファイルを書き出しました: {:?}", path);
+        }
+    }
+
+    if let Some(manifest_path) = &args.manifest {
+        let manifest = manifest::Manifest::new(&input, &output_path, &extracted.coverage, &extracted.warnings);
+        write_to_file(manifest_path, &manifest.to_json()?)?;
+    }
+
+    // 構造の JSON は、ページごとの Markdown を要素に分けて組み立てる（フロントマターなど、ページに属さない内容は含めない）
+    if structured {
+        let document = structure::build_document(&review_pages, &extracted.layouts);
+        markdown_content = serde_json::to_string_pretty(&document).context("文書の構造の JSON への変換に失敗しました")? + "\n";
+    }
+
+    // 出力の大きさの上限（split の場合は、分けたファイルを書き出して出力をその一覧にする）
+    if let Some(limit) = limit {
+        let size = |text: &str| args.output_encoding.encode(&whitespace_options.line_ending.apply(text.to_string())).map_or(text.len(), |bytes| bytes.len());
+        let mut outputs = limit.apply(&markdown_content, size)?;
+        if limit.policy == OversizePolicy::Split && outputs.len() > 1 {
+            markdown_content = write_oversize_parts(&files_path, &outputs, &whitespace_options, args.output_encoding)?;
+        } else if outputs[0] != markdown_content {
+            console!(Warn, "出力が --max-output-bytes の上限（{} バイト）を超えるため、以降を省略しました", limit.max_bytes);
+            markdown_content = outputs.swap_remove(0);
+        }
+    }
+
+    let coverage = diagnostics::total_coverage(&extracted.coverage) * 100.0;
+    let dry_run = counts.map(|counts| DryRunSummary { input, output_path: output_path.clone(), pages: extracted.coverage.len(), counts });
+    Ok(Conversion {
+        markdown: whitespace_options.line_ending.apply(markdown_content),
+        output_path,
+        to_stdout,
+        encoding: args.output_encoding,
+        warnings: shown,
+        coverage,
+        dry_run,
+    })
+}
+
+/// --dry-run で付随するファイルを書き出す一時的なディレクトリ（破棄すると削除する）
+struct DryRunDir(PathBuf);
+
+impl Drop for DryRunDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 1本の記事を、見出し・署名・本文の Markdown にする（見出しの無い題字などの区間は本文のみ）
+fn render_article(article: &articles::Article, level: usize, markdown_options: &MarkdownOptions) -> Result<String> {
+    let mut blocks = Vec::new();
+    if let Some(headline) = &article.headline {
+        blocks.push(format!("{} {}", "#".repeat(level), headline));
+    }
+    if let Some(byline) = &article.byline {
+        blocks.push(format!("*{}*", byline));
+    }
+    let body = convert_to_markdown(article.body.clone(), markdown_options)?;
+    if !body.trim().is_empty() {
+        blocks.push(body.trim().to_string());
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+/// 記事ごとに「出力ファイル名-NN.md」を書き出し、出力ファイルに書く記事の一覧を返す
+fn write_article_files(
+    output_path: &Path,
+    articles: &[articles::Article],
+    markdown_options: &MarkdownOptions,
+    whitespace_options: &WhitespaceOptions,
+    encoding: OutputEncoding,
+    redactor: &Redactor,
+) -> Result<String> {
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut index = Vec::new();
+
+    for (i, article) in articles.iter().enumerate() {
+        let file_name = format!("{}-{:02}.md", stem, i + 1);
+        let mut content = whitespace::normalize(&render_article(article, 1, markdown_options)?, whitespace_options);
+        if !redactor.is_empty() {
+            content = redactor.redact(&content);
+        }
+        write_encoded(&output_path.with_file_name(&file_name), &whitespace_options.line_ending.apply(content), encoding)?;
+
+        let title = article.headline.clone().unwrap_or_else(|| format!("p.{} の見出しのない記事", article.page));
+        index.push(format!("- [{}]({}) (p.{})", title, file_name, article.page));
+    }
+
+    console!(Info, "{} 本の記事を書き出しました", articles.len());
+    Ok(index.join("\n"))
+}
+
+/// 分割した出力ごとに「出力ファイル名-NN.md」を書き出し、出力ファイルに書くファイルの一覧を返す
+///
+/// 別のファイルに移った見出しへの文書内のリンクは、ファイル名付きのリンクに書き換える。
+/// 各ファイルの先頭と末尾には、(雛形, 文書の題名) から作った内容を書き足す。
+fn write_part_files(
+    output_path: &Path,
+    preamble: &mut String,
+    parts: &mut [split::Part],
+    (template, title): (&split::PartTemplate, &str),
+    whitespace_options: &WhitespaceOptions,
+    encoding: OutputEncoding,
+    redactor: &Redactor,
+) -> Result<String> {
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let file_names: Vec<String> = (1..=parts.len()).map(|i| format!("{}-{:02}.md", stem, i)).collect();
+    split::retarget_links(preamble, parts, &file_names);
+    let index_file = output_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut index = Vec::new();
+
+    for (i, (part, file_name)) in parts.iter().zip(&file_names).enumerate() {
+        let neighbor = |j: usize| parts.get(j).map(|part: &split::Part| (part.title.as_str(), file_names[j].as_str()));
+        let context = split::PartContext {
+            title,
+            part: i + 1,
+            parts: parts.len(),
+            part_title: &part.title,
+            prev: i.checked_sub(1).and_then(neighbor),
+            next: neighbor(i + 1),
+            index: (title, &index_file),
+        };
+        let mut content = whitespace::normalize(&template.apply(&part.content, &context), whitespace_options);
+        if !redactor.is_empty() {
+            content = redactor.redact(&content);
+        }
+        write_encoded(&output_path.with_file_name(file_name), &whitespace_options.line_ending.apply(content), encoding)?;
+
+        let page = part.page.map(|page| format!(" (p.{})", page)).unwrap_or_default();
+        index.push(format!("- [{}]({}){}", part.title, file_name, page));
+    }
+
+    console!(Info, "{} 個のファイルに分けて書き出しました", parts.len());
+    Ok(index.join("\n"))
+}
+
+/// 上限に収まる大きさに分けた出力ごとに「出力ファイル名-NN.md」を書き出し、出力ファイルに書くファイルの一覧を返す
+fn write_oversize_parts(output_path: &Path, parts: &[String], whitespace_options: &WhitespaceOptions, encoding: OutputEncoding) -> Result<String> {
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut index = Vec::new();
+
+    for (i, part) in parts.iter().enumerate() {
+        let file_name = format!("{}-{:02}.md", stem, i + 1);
+        write_encoded(&output_path.with_file_name(&file_name), &whitespace_options.line_ending.apply(part.clone()), encoding)?;
+
+        // 一覧には、分けたファイルの最初の見出しを載せる
+        let heading = part.lines().find_map(|line| line.strip_prefix('#').map(|rest| rest.trim_start_matches('#').trim()).filter(|title| !title.is_empty()));
+        index.push(format!("- [{}]({})", heading.unwrap_or(&file_name), file_name));
+    }
+
+    console!(Info, "出力が --max-output-bytes の上限を超えるため、{} 個のファイルに分けて書き出しました", parts.len());
+    Ok(index.join("\n") + "\n")
+}
+
+/// 使用するプロファイル名を決める（--profile の指定が無ければ、定義済みのプロファイルから自動で選ぶ）
+fn select_profile(config: &config::Config, requested: Option<&str>, input: PdfInput, password: Option<&str>) -> Result<Option<String>> {
+    match requested {
+        Some("none") => return Ok(None),
+        Some(name) => return Ok(Some(name.to_string())),
+        None if config.profiles.is_empty() => return Ok(None),
+        None => {}
+    }
+
+    let doc = input.open(password)?;
+    let traits = classify::analyze(&doc).with_context(|| format!("PDFの特徴の解析に失敗しました: {:?}", input.path()))?;
+    console!(
+        Info,
+        "文書の特徴: {} ページ、{} 段組み、スキャンページの割合 {:.0}%、1ページあたり {:.0} 文字、しおり{}",
+        traits.pages,
+        traits.columns,
+        traits.scanned_ratio * 100.0,
+        traits.chars_per_page,
+        if traits.has_outline { "あり" } else { "なし" }
+    );
+
+    match classify::suggest_profile(&traits) {
+        Some(name) if config.has_profile(name) => {
+            console!(Info, "プロファイル {} を自動で選びました（--profile で変更、--profile none で無効にできます）", name);
+            Ok(Some(name.to_string()))
+        }
+        Some(name) => {
+            console!(Info, "推定したプロファイル {} は設定ファイルに定義されていないため、プロファイルを使用しません", name);
+            Ok(None)
+        }
+        None => {
+            console!(Info, "文書の特徴に合うプロファイルが無いため、プロファイルを使用しません");
+            Ok(None)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pdf::PdfBuilder;
+    use std::ffi::OsStr;
+
+    /// 一時ディレクトリに3ページの PDF を書き出し、そのパスを返す
+    fn sample_input(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pdf2md-cli-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut pdf = PdfBuilder::new();
+        for text in ["First page.", "Second page.", "Third page."] {
+            pdf.page().paragraph(&[text]);
+        }
+        let input = dir.join("sample.pdf");
+        std::fs::write(&input, pdf.build()).unwrap();
+        input
+    }
+
+    /// コマンドライン引数を解析して1つの PDF を変換する
+    fn convert_args(argv: &[&OsStr]) -> Conversion {
+        let args = Args::try_parse_from([OsStr::new("pdf2md")].iter().chain(argv)).unwrap();
+        convert_input(&CompiledRules::new(&args), args, &config::Config::default(), &CancellationToken::new()).unwrap()
+    }
+
+    // 単体テスト: -o - で標準出力に書き出す変換
+    #[test]
+    fn test_convert_to_stdout() {
+        let input = sample_input("stdout");
+        let conversion = convert_args(&["-i".as_ref(), input.as_os_str(), "-o".as_ref(), "-".as_ref()]);
+        assert!(conversion.to_stdout);
+        assert!(conversion.markdown.contains("First page.") && conversion.markdown.contains("Third page."));
+        // Markdown のファイルは作らない
+        assert!(!input.with_extension("md").exists() && !input.with_file_name("-").exists());
+        let _ = std::fs::remove_dir_all(input.parent().unwrap());
+    }
+
+    // 単体テスト: --page で1ページだけを標準出力に書き出す変換
+    #[test]
+    fn test_convert_single_page() {
+        let input = sample_input("page");
+        let conversion = convert_args(&["-i".as_ref(), input.as_os_str(), "--page".as_ref(), "2".as_ref()]);
+        assert!(conversion.to_stdout);
+        assert_eq!(conversion.markdown.trim(), "Second page.");
+        let _ = std::fs::remove_dir_all(input.parent().unwrap());
+    }
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+/// テキストをシステムのクリップボードに入れる
+///
+/// Linux（X11）ではクリップボードの内容をこのプロセスが持ち、終了すると内容が消えるため、
+/// クリップボードマネージャーなどが内容を引き継ぐまで最大 CLIPBOARD_WAIT_SECS 秒待ってから戻る。
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    use anyhow::Context;
+
+    let mut clipboard = arboard::Clipboard::new().context("クリップボードを開けません")?;
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::SetExtLinux;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(CLIPBOARD_WAIT_SECS);
+        clipboard.set().wait_until(deadline).text(text).context("クリップボードに書き込めません")
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        clipboard.set_text(text).context("クリップボードに書き込めません")
+    }
+}
+
+/// Linux でほかのアプリがクリップボードの内容を受け取るのを待つ秒数
+#[cfg(all(feature = "clipboard", target_os = "linux"))]
+const CLIPBOARD_WAIT_SECS: u64 = 2;
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    anyhow::bail!("--to-clipboard を使うには clipboard 機能を有効にして pdf2md をビルドしてください")
+}
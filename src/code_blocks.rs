@@ -0,0 +1,119 @@
+use crate::layout::{self, Glyph, PageLayout};
+
+/// 等幅のフォントの文字がこの割合を超える文書は、本文が等幅（タイプライターの文書や脚本など）とみなしてコードブロックにしない
+const MAX_MONOSPACE_RATIO: f64 = 0.5;
+
+/// コードブロックの中で、通常の行間のこの倍数を超える間隔は空行にする
+const BLANK_LINE_GAP: f64 = 1.5;
+
+/// 各ページの、等幅のフォントの行が続く部分を Markdown のコードブロックに置き換え、置き換えたコードブロックの数を返す
+///
+/// コードブロックの中は、文字の位置から行頭の字下げと語の間の空白を復元し、改行も元の行のとおりにする。
+pub fn convert_code_blocks(pages: &mut [PageLayout]) -> usize {
+    let visible = || pages.iter().flat_map(|page| &page.glyphs).filter(|glyph| !glyph.text.trim().is_empty());
+    let total = visible().count();
+    let monospace = visible().filter(|glyph| glyph.monospace).count();
+    if total == 0 || monospace as f64 > total as f64 * MAX_MONOSPACE_RATIO {
+        return 0;
+    }
+
+    let mut replaced = 0;
+    for page in pages.iter_mut() {
+        let lines = layout::line_ranges(&page.glyphs);
+        let is_code = |range: &std::ops::Range<usize>| page.glyphs[range.clone()].iter().all(|glyph| glyph.monospace || glyph.text.trim().is_empty());
+        // 等幅の行が続く範囲（行の番号で。後ろから置き換えるので逆順にする）
+        let mut blocks: Vec<std::ops::Range<usize>> = Vec::new();
+        for (i, range) in lines.iter().enumerate() {
+            if !is_code(range) {
+                continue;
+            }
+            match blocks.last_mut() {
+                Some(block) if block.end == i => block.end = i + 1,
+                _ => blocks.push(i..i + 1),
+            }
+        }
+
+        for block in blocks.into_iter().rev() {
+            let (start, end) = (lines[block.start].start, lines[block.end - 1].end);
+            let code = render_code(&page.glyphs, &lines[block]);
+            let first = page.glyphs[start].clone();
+            page.glyphs.splice(
+                start..end,
+                [Glyph { text: format!("\n\n```\n{}\n```\n\n", code), x: 0.0, width: 0.0, word_start: true, color: None, bold: false, monospace: false, ..first }],
+            );
+            replaced += 1;
+        }
+    }
+
+    replaced
+}
+
+/// 等幅の行を、文字の位置から桁をそろえたテキストにする
+fn render_code(glyphs: &[Glyph], lines: &[std::ops::Range<usize>]) -> String {
+    let visible: Vec<&Glyph> = lines.iter().flat_map(|range| &glyphs[range.clone()]).filter(|glyph| !glyph.text.trim().is_empty()).collect();
+    let left = visible.iter().map(|glyph| glyph.x).fold(f64::INFINITY, f64::min);
+    // 1桁の幅は、1文字あたりの幅の中央値
+    let mut widths: Vec<f64> = visible.iter().map(|glyph| glyph.width / glyph.text.chars().count() as f64).filter(|width| *width > 0.0).collect();
+    widths.sort_by(f64::total_cmp);
+    let column_width = widths.get(widths.len() / 2).copied().unwrap_or_else(|| visible.first().map_or(1.0, |glyph| glyph.font_size * 0.6));
+    // 通常の行間は、行の間隔の最小値
+    let baselines: Vec<f64> = lines.iter().map(|range| glyphs[range.start].y).collect();
+    let spacing = baselines.windows(2).map(|pair| pair[1] - pair[0]).filter(|gap| *gap > 0.0).fold(f64::INFINITY, f64::min);
+
+    let mut code = String::new();
+    for (i, range) in lines.iter().enumerate() {
+        if i > 0 {
+            code.push('\n');
+            if baselines[i] - baselines[i - 1] > spacing * BLANK_LINE_GAP {
+                code.push('\n');
+            }
+        }
+        let mut column = 0;
+        for glyph in glyphs[range.clone()].iter().filter(|glyph| !glyph.text.trim().is_empty()) {
+            let at = ((glyph.x - left) / column_width).round().max(0.0) as usize;
+            // 語の間は、位置が詰まっていても空白1つは空ける
+            let at = if glyph.word_start && column > 0 { at.max(column + 1) } else { at };
+            code.extend(std::iter::repeat_n(' ', at.saturating_sub(column)));
+            code.push_str(&glyph.text);
+            column = at.max(column) + glyph.text.chars().count();
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 等幅の行のコードブロックへの置き換え
+    #[test]
+    fn test_convert_code_blocks() {
+        let glyph = |text: &str, x: f64, y: f64, monospace: bool| Glyph {
+            text: text.to_string(),
+            x,
+            y,
+            width: 6.0 * text.chars().count() as f64,
+            font_size: 10.0,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace,
+            confidence: None,
+        };
+        let mut glyphs = vec![glyph("Example", 72.0, 100.0, false), glyph("code:", 120.0, 100.0, false)];
+        for (y, words) in [(120.0, vec![(72.0, "fn"), (90.0, "main()"), (132.0, "{")]), (132.0, vec![(96.0, "run();")]), (156.0, vec![(72.0, "}")])] {
+            glyphs.extend(words.into_iter().map(|(x, text)| glyph(text, x, y, true)));
+        }
+        glyphs.extend([glyph("After", 72.0, 180.0, false), glyph("the", 108.0, 180.0, false), glyph("code.", 132.0, 180.0, false)]);
+        let mut pages = vec![PageLayout { number: 1, glyphs, ..Default::default() }];
+
+        assert_eq!(convert_code_blocks(&mut pages), 1);
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        assert_eq!(texts, ["Example", "code:", "\n\n```\nfn main() {\n    run();\n\n}\n```\n\n", "After", "the", "code."]);
+
+        // 本文が等幅の文書はそのまま
+        let mut pages = vec![PageLayout { number: 1, glyphs: vec![glyph("INT.", 72.0, 100.0, true), glyph("HOUSE", 102.0, 100.0, true)], ..Default::default() }];
+        assert_eq!(convert_code_blocks(&mut pages), 0);
+    }
+}
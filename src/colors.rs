@@ -0,0 +1,175 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use serde::Deserialize;
+
+use crate::config::ColorRule;
+use crate::layout::{self, Glyph, PageLayout};
+
+/// 色の差の許容範囲の既定値（RGB の各成分を 0.0〜1.0 としたユークリッド距離）
+pub const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// 注意書きの種類の既定値
+const DEFAULT_ADMONITION: &str = "NOTE";
+
+/// 指定した色の文字の書式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorStyle {
+    /// 太字にする
+    Bold,
+    /// 注意書きの引用ブロック（> [!WARNING] など）にする
+    Admonition,
+    /// URL やメールアドレスに見える文字をリンクにする（注釈の無い青字のリンクなど）
+    Link,
+}
+
+/// URL かメールアドレスだけの語
+static URL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:https?://|www\.)\S+$|^[\w.+-]+@[\w-]+(?:\.[\w-]+)+$").unwrap());
+
+/// 設定した色の文字を Markdown の書式にし、書式を付けた箇所の数を返す
+///
+/// 色の判定は規則を書いた順に行い、最初に一致した規則を使う。
+pub fn apply_color_rules(pages: &mut [PageLayout], rules: &[ColorRule]) -> usize {
+    if rules.is_empty() {
+        return 0;
+    }
+    let mut applied = 0;
+
+    for page in pages.iter_mut() {
+        let mut glyphs: Vec<Glyph> = Vec::with_capacity(page.glyphs.len());
+        let mut run: Vec<Glyph> = Vec::new();
+        let mut run_rule: Option<usize> = None;
+
+        for glyph in std::mem::take(&mut page.glyphs) {
+            let rule = glyph.color.and_then(|color| rules.iter().position(|rule| rule.matches(color)));
+            // 空白は前後の色の続きとして扱う
+            let same_run = run_rule.is_some() && (glyph.text.trim().is_empty() || rule == run_rule);
+            if !same_run {
+                if let Some(index) = run_rule.take() {
+                    applied += format_run(&mut glyphs, std::mem::take(&mut run), &rules[index]);
+                }
+                if rule.is_none() || glyph.text.trim().is_empty() {
+                    glyphs.push(glyph);
+                    continue;
+                }
+                run_rule = rule;
+            }
+            run.push(glyph);
+        }
+        if let Some(index) = run_rule {
+            applied += format_run(&mut glyphs, run, &rules[index]);
+        }
+        page.glyphs = glyphs;
+    }
+
+    applied
+}
+
+/// 同じ色の文字の並びに書式を付けて glyphs に追加し、書式を付けた箇所の数を返す
+fn format_run(glyphs: &mut Vec<Glyph>, mut run: Vec<Glyph>, rule: &ColorRule) -> usize {
+    // 末尾の空白は書式の外に出す
+    let trailing = run.iter().rev().take_while(|glyph| glyph.text.trim().is_empty()).count();
+    let rest = run.split_off(run.len() - trailing);
+
+    let applied = match rule.style {
+        ColorStyle::Admonition => {
+            // 複数行にわたる並びも1つの引用ブロックにまとめる
+            let text = layout::glyphs_to_text(&run).split_whitespace().collect::<Vec<_>>().join(" ");
+            let kind = rule.kind.as_deref().unwrap_or(DEFAULT_ADMONITION).to_uppercase();
+            let first = run[0].clone();
+            glyphs.push(Glyph { text: format!("\n\n> [!{}]\n> {}\n\n", kind, text), width: 0.0, word_start: true, color: None, bold: false, monospace: false, ..first });
+            1
+        }
+        ColorStyle::Bold | ColorStyle::Link => {
+            let mut applied = 0;
+            for line in layout::line_ranges(&run) {
+                let (first, last) = (line.start, line.end - 1);
+                let (prefix, suffix) = if rule.style == ColorStyle::Bold {
+                    ("**", "**")
+                } else {
+                    let text = layout::glyphs_to_text(&run[line.clone()]);
+                    match text.trim() {
+                        text if !URL_REGEX.is_match(text) => continue,
+                        text if text.starts_with("www.") => ("<https://", ">"),
+                        _ => ("<", ">"),
+                    }
+                };
+                run[first].text.insert_str(0, prefix);
+                run[last].text.push_str(suffix);
+                applied += 1;
+            }
+            glyphs.append(&mut run);
+            applied
+        }
+    };
+
+    glyphs.extend(rest);
+    applied
+}
+
+/// 行が注意書きの引用ブロックの1行目（> [!WARNING] など）かどうか
+pub fn is_admonition_marker(line: &str) -> bool {
+    line.strip_prefix("> [!").and_then(|rest| rest.strip_suffix(']')).is_some_and(|kind| kind.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Rgb = (f64, f64, f64);
+
+    const RED: Rgb = (0.8, 0.0, 0.0);
+    const BLUE: Rgb = (0.0, 0.0, 0.9);
+
+    fn words(items: &[(&str, f64, Option<Rgb>)]) -> Vec<Glyph> {
+        let mut x = 72.0;
+        items
+            .iter()
+            .enumerate()
+            .map(|(order, &(text, y, color))| {
+                let glyph = Glyph { text: text.to_string(), x, y, width: text.len() as f64 * 5.0, font_size: 10.0, word_start: true, order, color, bold: false, monospace: false, confidence: None };
+                x += glyph.width + 3.0;
+                glyph
+            })
+            .collect()
+    }
+
+    fn rule(color: Rgb, style: ColorStyle, kind: Option<&str>) -> ColorRule {
+        ColorRule { color, style, kind: kind.map(String::from), tolerance: None }
+    }
+
+    // 単体テスト: 色の規則による書式
+    #[test]
+    fn test_apply_color_rules() {
+        let rules = vec![rule(RED, ColorStyle::Bold, None), rule(BLUE, ColorStyle::Link, None)];
+        let mut pages = vec![PageLayout {
+            glyphs: words(&[
+                ("Do", 100.0, None),
+                ("not", 100.0, Some((0.82, 0.02, 0.0))),
+                ("unplug", 100.0, Some(RED)),
+                ("it.", 100.0, None),
+                ("See", 112.0, None),
+                ("www.example.com", 112.0, Some(BLUE)),
+                ("or", 112.0, None),
+                ("Chapter 2", 112.0, Some(BLUE)),
+            ]),
+            ..Default::default()
+        }];
+
+        assert_eq!(apply_color_rules(&mut pages, &rules), 2);
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        assert_eq!(texts, vec!["Do", "**not", "unplug**", "it.", "See", "<https://www.example.com>", "or", "Chapter 2"]);
+
+        // 注意書きは複数行を1つの引用ブロックにまとめる
+        let rules = vec![rule(RED, ColorStyle::Admonition, Some("warning"))];
+        let mut pages = vec![PageLayout {
+            glyphs: words(&[("Intro", 100.0, None), ("Hot", 112.0, Some(RED)), ("surface", 124.0, Some(RED)), ("Next", 136.0, None)]),
+            ..Default::default()
+        }];
+        assert_eq!(apply_color_rules(&mut pages, &rules), 1);
+        assert_eq!(pages[0].glyphs[1].text, "\n\n> [!WARNING]\n> Hot surface\n\n");
+        assert_eq!(pages[0].glyphs.len(), 3);
+        assert!(is_admonition_marker("> [!WARNING]"));
+        assert!(!is_admonition_marker("> [1] note"));
+    }
+}
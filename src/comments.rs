@@ -0,0 +1,197 @@
+use clap::ValueEnum;
+use lopdf::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::annotations::Annotation;
+use crate::highlights;
+use crate::layout::{self, PageLayout};
+
+/// 返信をたどる深さの上限（循環参照対策）
+const MAX_REPLY_DEPTH: usize = 64;
+
+/// 範囲内の文字を抜粋として示す注釈の種類
+const MARKUP_SUBTYPES: &[&str] = &["Highlight", "Underline", "StrikeOut", "Squiggly"];
+
+/// コメントとして扱わない注釈の種類（リンク、フォームの部品、ポップアップの表示枠）
+const IGNORED_SUBTYPES: &[&str] = &["Link", "Widget", "Popup"];
+
+/// レビューコメントの出力方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentOutput {
+    /// 文書末尾に「レビューコメント」の節として出力する
+    Appendix,
+    /// 出力ファイル名.comments.json に出力する
+    Json,
+}
+
+/// 注釈のコメント1件（返信のコメントも同じ形で、replies に入れ子にする）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comment {
+    pub page: u32,
+    /// 注釈の種類（Text、Highlight、FreeText など）
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    pub content: String,
+    /// 注釈を付けた箇所の文字（ハイライトや下線などの場合）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<Comment>,
+}
+
+/// 全ページの注釈からコメントを集め、返信をスレッドにまとめる
+///
+/// 本文も返信も無い注釈（コメントの無いハイライトなど）は含めない。返信は日時の順に並べる。
+pub fn collect_comments(pages: &[PageLayout]) -> Vec<Comment> {
+    let annotations: Vec<(&PageLayout, &Annotation)> = pages
+        .iter()
+        .flat_map(|page| page.annotations.iter().map(move |annotation| (page, annotation)))
+        .filter(|(_, annotation)| !IGNORED_SUBTYPES.contains(&annotation.subtype.as_str()))
+        .collect();
+    let by_id: HashMap<ObjectId, usize> = annotations.iter().enumerate().filter_map(|(i, (_, a))| a.id.map(|id| (id, i))).collect();
+
+    // 返信をたどって、スレッドの最初の注釈を求める（返信先が見つからない注釈はスレッドの最初とする）
+    let root_of = |mut index: usize| {
+        for _ in 0..MAX_REPLY_DEPTH {
+            match annotations[index].1.in_reply_to.and_then(|id| by_id.get(&id)) {
+                Some(&parent) if parent != index => index = parent,
+                _ => break,
+            }
+        }
+        index
+    };
+
+    let comment = |index: usize| {
+        let (page, annotation) = annotations[index];
+        Comment {
+            page: page.number,
+            kind: annotation.subtype.clone(),
+            author: annotation.author.clone(),
+            date: annotation.modified.clone(),
+            content: annotation.contents.clone().unwrap_or_default(),
+            excerpt: excerpt(page, annotation),
+            replies: Vec::new(),
+        }
+    };
+
+    let roots: Vec<usize> = (0..annotations.len()).map(root_of).collect();
+    let mut threads: Vec<(usize, Comment)> = (0..annotations.len()).filter(|&i| roots[i] == i).map(|i| (i, comment(i))).collect();
+    for (index, &root) in roots.iter().enumerate().filter(|&(index, &root)| root != index) {
+        if let Some((_, thread)) = threads.iter_mut().find(|(i, _)| *i == root) {
+            thread.replies.push(Comment { excerpt: None, ..comment(index) });
+        }
+    }
+
+    threads
+        .into_iter()
+        .map(|(_, mut thread)| {
+            thread.replies.sort_by(|a, b| a.date.cmp(&b.date));
+            thread
+        })
+        .filter(|thread| !thread.content.trim().is_empty() || !thread.replies.is_empty())
+        .collect()
+}
+
+/// ハイライトや下線などの注釈が覆う文字
+fn excerpt(page: &PageLayout, annotation: &Annotation) -> Option<String> {
+    if !MARKUP_SUBTYPES.contains(&annotation.subtype.as_str()) {
+        return None;
+    }
+    let covering: Vec<Option<usize>> = page
+        .glyphs
+        .iter()
+        .map(|glyph| {
+            let (x, y) = (glyph.x + glyph.width / 2.0, glyph.y - glyph.font_size * 0.3);
+            (!glyph.text.trim().is_empty() && annotation.areas.iter().any(|area| area.contains(x, y, 1.0))).then_some(0)
+        })
+        .collect();
+    // ハイライトと同じく、語の途中から引かれた範囲は語の切れ目まで広げる
+    let text = highlights::highlighted_runs(&page.glyphs, &covering)
+        .into_iter()
+        .map(|run| layout::glyphs_to_text(&page.glyphs[run]))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// コメントを「レビューコメント」の節にする（返信は入れ子の箇条書き）
+pub fn render_appendix(comments: &[Comment]) -> String {
+    if comments.is_empty() {
+        return String::new();
+    }
+    let mut markdown = String::from("## レビューコメント\n\n");
+    for comment in comments {
+        markdown.push_str(&format!("- p.{} {}\n", comment.page, comment_line(comment)));
+        if let Some(excerpt) = &comment.excerpt {
+            markdown.push_str(&format!("  > {}\n", excerpt));
+        }
+        for reply in &comment.replies {
+            markdown.push_str(&format!("  - {}\n", comment_line(reply)));
+        }
+    }
+    markdown
+}
+
+/// 作成者・日時・本文の1行（本文の改行は空白にまとめる）
+fn comment_line(comment: &Comment) -> String {
+    let author = comment.author.as_deref().unwrap_or("（作成者不明）");
+    let date = comment.date.as_ref().map(|date| format!(" ({})", date)).unwrap_or_default();
+    let content = comment.content.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("**{}**{}: {}", author, date, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::Area;
+    use crate::layout::Glyph;
+
+    fn annotation(id: u32, subtype: &str, contents: &str, author: &str, date: &str, in_reply_to: Option<u32>) -> Annotation {
+        Annotation {
+            id: Some((id, 0)),
+            subtype: subtype.to_string(),
+            areas: vec![Area { x0: 70.0, y0: 90.0, x1: 200.0, y1: 103.0 }],
+            contents: (!contents.is_empty()).then(|| contents.to_string()),
+            author: Some(author.to_string()),
+            modified: Some(date.to_string()),
+            in_reply_to: in_reply_to.map(|id| (id, 0)),
+            target: None,
+            uri: None,
+        }
+    }
+
+    // 単体テスト: コメントのスレッド
+    #[test]
+    fn test_collect_comments() {
+        let glyph = |text: &str, x: f64, y: f64| Glyph { text: text.to_string(), x, y, width: 30.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let page = PageLayout {
+            number: 4,
+            glyphs: vec![glyph("Revenue", 72.0, 100.0), glyph("grew", 110.0, 100.0), glyph("Next", 72.0, 150.0)],
+            // 返信が返信先より先に並んでいる場合もスレッドにまとめる
+            annotations: vec![
+                annotation(12, "Text", "Fixed in v2.", "Sato", "2024-05-03", Some(11)),
+                annotation(10, "Highlight", "Source?", "Tanaka", "2024-05-01", None),
+                annotation(11, "Text", "Added a citation.", "Sato", "2024-05-02", Some(10)),
+                annotation(13, "Highlight", "", "Tanaka", "2024-05-01", None),
+            ],
+            ..Default::default()
+        };
+
+        let comments = collect_comments(&[page]);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].excerpt.as_deref(), Some("Revenue grew"));
+        let replies: Vec<&str> = comments[0].replies.iter().map(|reply| reply.content.as_str()).collect();
+        assert_eq!(replies, vec!["Added a citation.", "Fixed in v2."]);
+
+        assert_eq!(
+            render_appendix(&comments),
+            "## レビューコメント\n\n- p.4 **Tanaka** (2024-05-01): Source?\n  > Revenue grew\n  - **Sato** (2024-05-02): Added a citation.\n  - **Sato** (2024-05-03): Fixed in v2.\n"
+        );
+    }
+}
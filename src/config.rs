@@ -0,0 +1,504 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::articles::ArticleOutput;
+use crate::bilingual::BilingualOutput;
+use crate::colors::{self, ColorStyle};
+use crate::comments::CommentOutput;
+use crate::diagnostics::{Severity, WarningKind};
+use crate::font_styles;
+use crate::highlights::HighlightStyle;
+use crate::layout_model::ServiceInput;
+use crate::redact::PiiKind;
+use crate::selection::PageRanges;
+use crate::split::{Navigation, SplitBy};
+use crate::transcript::TranscriptStyle;
+
+/// --config の指定が無い場合に探す設定ファイル名（カレントディレクトリ）
+pub const DEFAULT_CONFIG_FILE: &str = "pdf2md.toml";
+
+/// 設定ファイル（pdf2md.toml）の内容
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub layout: LayoutConfig,
+    pub conversion: ConversionConfig,
+    pub split: SplitConfig,
+    /// [profiles.<名前>]: --profile で選ぶ変換プロファイル
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// 文書の種類ごとにまとめた変換オプション（未指定の項目はコマンドライン引数や [layout] の指定に従う）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// プロファイルの説明（表示用）
+    pub description: Option<String>,
+    /// 文書全体の段数
+    pub columns: Option<usize>,
+    /// ページごとの段組みの指定（[layout] の指定より優先）
+    pub pages: Vec<PageLayoutHint>,
+    /// YAML フロントマターを出力するかどうか
+    pub front_matter: Option<bool>,
+    /// フロントマターに追加する固定タグ
+    pub tags: Vec<String>,
+    /// マスクする個人情報の種類
+    pub redact: Vec<PiiKind>,
+    /// マスク対象に追加する正規表現
+    pub redact_patterns: Vec<String>,
+    /// 見出しの判定規則（汎用の判定より先に、書いた順に適用する）
+    pub headings: Vec<HeadingRule>,
+    /// フォントサイズと太字による見出しの判定規則（pdf2md calibrate で生成できる。正規表現の規則より先に適用する）
+    pub heading_styles: Vec<HeadingStyle>,
+    /// しおりの項目と同じテキストの行を見出しにするかどうか
+    pub bookmark_headings: Option<bool>,
+    /// 発言者の書式（"bold" または "definition"）
+    pub transcript: Option<TranscriptStyle>,
+    /// 変換方法（"document"、"slides"、"email"、"resume" または "patent"）
+    pub mode: Option<ConversionMode>,
+    /// 請求書・領収書の項目（日付・金額・明細）をフロントマターと JSON に出力するかどうか
+    pub invoice: Option<bool>,
+    /// 紙面を記事ごとに分けて出力する方法（"sections" または "files"）
+    pub articles: Option<ArticleOutput>,
+    /// 日本語と英語の二言語の文書の出力方法（"interleave"、"ja"、"en" または "files"）
+    pub bilingual: Option<BilingualOutput>,
+    /// 主な言語と異なる言語の節に言語のコメントを入れるかどうか
+    pub lang_tags: Option<bool>,
+    /// 表を Markdown の表として出力し、行・列の合計を検算するかどうか
+    pub financial: Option<bool>,
+    /// 前のページと同じ内容のページを目印に置き換えるかどうか
+    pub dedupe_pages: Option<bool>,
+    /// 文字の色ごとの書式（書いた順に適用する）
+    pub colors: Vec<ColorRule>,
+    /// ハイライトされた文字の出力方法（"mark"、"html" または "summary"）
+    pub highlights: Option<HighlightStyle>,
+    /// 注釈のコメントのスレッドの出力方法（"appendix" または "json"）
+    pub comments: Option<CommentOutput>,
+    /// 出力を分けるファイルの区切り（"heading" または "outline"）
+    pub split_by: Option<SplitBy>,
+    /// 警告の種類ごとの扱い（dropped_figure = "allow" など。"allow"、"warn" または "deny"）
+    pub warnings: BTreeMap<WarningKind, Severity>,
+    /// レイアウト解析の ONNX のモデルのパス（onnx 機能が必要）
+    pub layout_model: Option<PathBuf>,
+    /// レイアウト解析のコマンド（ページ画像のパスを最後の引数に受け取る）
+    pub layout_command: Option<String>,
+    /// レイアウト解析を行う HTTP のサービスの URL
+    pub layout_service: Option<String>,
+    /// レイアウト解析のサービスに送るページの内容（"image" または "text"）
+    pub layout_service_input: Option<ServiceInput>,
+}
+
+/// 文書全体の変換方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversionMode {
+    /// 段落と見出しからなる通常の文書
+    #[default]
+    Document,
+    /// 1ページを1枚のスライドとして変換する
+    Slides,
+    /// 印刷したメール（ヘッダーを表に、引用を入れ子の引用ブロックにする）
+    Email,
+    /// 履歴書・職務経歴書（氏名・連絡先・節・経歴の項目を見出しと箇条書きにする）
+    Resume,
+    /// 特許文献（書誌事項をフロントマターに、段落番号・請求項・図の参照を整える）
+    Patent,
+}
+
+/// 設定ファイルに定義しなくても使える組み込みのプロファイル（同名のプロファイルを定義すると置き換わる）
+const BUILTIN_PROFILES: &[&str] = &["email", "financial", "invoice", "newspaper", "patent", "resume", "slides"];
+
+fn builtin_profile(name: &str) -> Option<Profile> {
+    match name {
+        "email" => Some(Profile {
+            description: Some("印刷したメールのヘッダーを表に、引用を入れ子の引用ブロックに変換".to_string()),
+            mode: Some(ConversionMode::Email),
+            ..Default::default()
+        }),
+        "financial" => Some(Profile {
+            description: Some("決算書・年次報告書の表を Markdown の表にして合計を検算".to_string()),
+            financial: Some(true),
+            ..Default::default()
+        }),
+        "invoice" => Some(Profile {
+            description: Some("請求書・領収書の項目をフロントマターと JSON に出力".to_string()),
+            front_matter: Some(true),
+            invoice: Some(true),
+            ..Default::default()
+        }),
+        "newspaper" => Some(Profile {
+            description: Some("紙面を見出しと署名で記事ごとの節に分けて変換".to_string()),
+            articles: Some(ArticleOutput::Sections),
+            ..Default::default()
+        }),
+        "patent" => Some(Profile {
+            description: Some("特許文献の書誌事項をフロントマターに、請求項を番号付きリストに変換".to_string()),
+            front_matter: Some(true),
+            mode: Some(ConversionMode::Patent),
+            ..Default::default()
+        }),
+        "resume" => Some(Profile {
+            description: Some("履歴書・職務経歴書を氏名・節・経歴の項目の見出しと箇条書きに変換".to_string()),
+            mode: Some(ConversionMode::Resume),
+            ..Default::default()
+        }),
+        "slides" => Some(Profile {
+            description: Some("1ページを1枚のスライドとして変換".to_string()),
+            mode: Some(ConversionMode::Slides),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// [[profiles.<名前>.headings]]: 正規表現に一致する行を指定したレベルの見出しにする
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeadingRule {
+    /// 行（前後の空白を除いたもの）に対する正規表現（例: "^Article \\d+"）
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    /// 見出しレベル（1〜6）
+    #[serde(deserialize_with = "deserialize_heading_level")]
+    pub level: usize,
+}
+
+/// [[profiles.<名前>.heading_styles]]: 指定したフォントサイズ（と太さ）の行を指定したレベルの見出しにする
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeadingStyle {
+    /// フォントサイズ（ポイント。±0.5 ポイントの差は同じとみなす）
+    pub font_size: f64,
+    /// 太字かどうか（指定が無い場合は太さを問わない）
+    #[serde(default)]
+    pub bold: Option<bool>,
+    /// 見出しレベル（1〜6）
+    #[serde(deserialize_with = "deserialize_heading_level")]
+    pub level: usize,
+}
+
+/// [[profiles.<名前>.colors]]: 指定した色の文字を太字・注意書き・リンクにする
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorRule {
+    /// 文字の色（"#cc0000" のような16進表記）
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: (f64, f64, f64),
+    /// 書式（"bold"、"admonition" または "link"）
+    pub style: ColorStyle,
+    /// 注意書きの種類（"NOTE"、"TIP"、"IMPORTANT"、"WARNING"、"CAUTION"。style = "admonition" の場合のみ）
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// 色の差の許容範囲（RGB の各成分を 0.0〜1.0 としたユークリッド距離、既定は 0.1）
+    #[serde(default)]
+    pub tolerance: Option<f64>,
+}
+
+impl ColorRule {
+    /// 文字の色が規則の色に近いかどうか
+    pub fn matches(&self, color: (f64, f64, f64)) -> bool {
+        let (r, g, b) = self.color;
+        let distance = ((color.0 - r).powi(2) + (color.1 - g).powi(2) + (color.2 - b).powi(2)).sqrt();
+        distance <= self.tolerance.unwrap_or(colors::DEFAULT_TOLERANCE)
+    }
+}
+
+fn deserialize_color<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<(f64, f64, f64), D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let hex = text.strip_prefix('#').unwrap_or(&text);
+    let component = |i: usize| hex.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()).map(|v| f64::from(v) / 255.0);
+    match (hex.len(), component(0), component(2), component(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok((r, g, b)),
+        _ => Err(serde::de::Error::custom(format!("色は \"#cc0000\" のような16進表記で指定してください: {}", text))),
+    }
+}
+
+fn deserialize_regex<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(|e| serde::de::Error::custom(format!("見出しの正規表現が不正です: {}: {}", pattern, e)))
+}
+
+fn deserialize_heading_level<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let level = usize::deserialize(deserializer)?;
+    if !(1..=6).contains(&level) {
+        return Err(serde::de::Error::custom(format!("見出しレベルは1〜6で指定してください: {}", level)));
+    }
+    Ok(level)
+}
+
+impl Config {
+    /// 名前を指定してプロファイルを取得する（設定ファイルの定義を組み込みのものより優先する）
+    pub fn profile(&self, name: &str) -> Result<Profile> {
+        match self.profiles.get(name).cloned().or_else(|| builtin_profile(name)) {
+            Some(profile) => Ok(profile),
+            None => {
+                let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                names.extend(BUILTIN_PROFILES.iter().filter(|builtin| !self.profiles.contains_key(**builtin)));
+                bail!("プロファイルが見つかりません: {}（定義済み: {}）", name, names.join(", "))
+            }
+        }
+    }
+
+    /// プロファイルが定義されているか（組み込みのものを含む）
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains_key(name) || BUILTIN_PROFILES.contains(&name)
+    }
+}
+
+/// [layout] セクション: 段組みの指定
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// 文書全体の段数（未指定の場合は文字の配置から段組みを推定する）
+    pub columns: Option<usize>,
+    /// ページごとの段組みの指定（先に書いたものが優先）
+    pub pages: Vec<PageLayoutHint>,
+}
+
+/// [conversion] セクション: 見出し・強調・表の判定と折り返しの既定（コマンドライン引数の指定が優先）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConversionConfig {
+    /// 見出しの規則が無い場合に、本文より大きいフォントサイズの行を見出しにするかどうか（false は --no-font-headings と同じ）
+    pub font_headings: bool,
+    /// フォントサイズから見出しを推定するときの、本文のフォントサイズに対する倍率の下限
+    #[serde(deserialize_with = "deserialize_heading_scale")]
+    pub heading_scale: f64,
+    /// 番号（「1.」など）や行の長さから見出しを推定するかどうか
+    pub guess_headings: bool,
+    /// 全て大文字の語を太字にするかどうか
+    pub uppercase_bold: bool,
+    /// 表を検出して Markdown の表にするかどうか（false は --no-tables と同じ）
+    pub tables: bool,
+    /// 段落を折り返す幅（文字数。0 の場合は折り返さない。--wrap の指定が優先）
+    pub wrap: usize,
+    /// 行末のハイフンで分けた語をつなぐかどうか（--dehyphenate と同じ）
+    pub dehyphenate: bool,
+    /// 言語のコード（de、nl など）ごとの分綴のパターンのファイル（TeX の hyph-xx.tex か LibreOffice の hyph_xx.dic）
+    pub hyphenation: BTreeMap<String, PathBuf>,
+}
+
+impl Default for ConversionConfig {
+    fn default() -> Self {
+        ConversionConfig {
+            font_headings: true,
+            heading_scale: font_styles::MIN_HEADING_SCALE,
+            guess_headings: true,
+            uppercase_bold: true,
+            tables: true,
+            wrap: 0,
+            dehyphenate: false,
+            hyphenation: BTreeMap::new(),
+        }
+    }
+}
+
+fn deserialize_heading_scale<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let scale = f64::deserialize(deserializer)?;
+    if scale < 1.0 {
+        return Err(serde::de::Error::custom(format!("heading_scale は 1.0 以上で指定してください: {}", scale)));
+    }
+    Ok(scale)
+}
+
+/// [split] セクション: --split-by で分けた各ファイルの先頭と末尾に書き足す内容の雛形（--part-header と --part-footer の指定が優先）
+///
+/// {title}（文書の題名）、{part}、{parts}、{part_title}、{prev}、{next}、{index}（前後のファイルと一覧へのリンク）、
+/// {prev_url}、{next_url}、{index_url}（リンク先のファイル名のみ）の変数を使える。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SplitConfig {
+    pub header: Option<String>,
+    pub footer: Option<String>,
+    /// 各ファイルの末尾に入れる前後のファイルと一覧へのリンクの行の書式（"markdown"、"hugo" または "none"。--split-nav の指定が優先）
+    pub navigation: Option<Navigation>,
+}
+
+/// [[layout.pages]]: 特定のページの段組み
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageLayoutHint {
+    /// 対象のページ（例: "1"、"3-5,8"、"10-"）
+    #[serde(deserialize_with = "deserialize_page_ranges")]
+    pub pages: PageRanges,
+    pub columns: usize,
+}
+
+fn deserialize_page_ranges<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<PageRanges, D::Error> {
+    let spec = String::deserialize(deserializer)?;
+    spec.parse().map_err(serde::de::Error::custom)
+}
+
+impl LayoutConfig {
+    /// ページに適用する段数（ページごとの指定が文書全体の指定より優先）
+    pub fn columns_for(&self, page: u32) -> Option<usize> {
+        self.pages
+            .iter()
+            .find(|hint| hint.pages.contains(page))
+            .map(|hint| hint.columns)
+            .or(self.columns)
+    }
+}
+
+/// 設定ファイルを読み込む
+///
+/// path が指定されていればそのファイルを、無ければカレントディレクトリの pdf2md.toml を読む。
+/// 既定のファイルが存在しない場合は既定値を返す。
+pub fn load_config(path: Option<&Path>) -> Result<Config> {
+    let path: PathBuf = match path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+            if !default.exists() {
+                return Ok(Config::default());
+            }
+            default
+        }
+    };
+
+    let text = fs::read_to_string(&path).with_context(|| format!("設定ファイルの読み込みに失敗しました: {:?}", path))?;
+    parse_config(&text).with_context(|| format!("設定ファイルの形式が不正です: {:?}", path))
+}
+
+fn parse_config(text: &str) -> Result<Config> {
+    let config: Config = toml::from_str(text)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 段組みの指定の読み込み
+    #[test]
+    fn test_layout_config() {
+        let config = parse_config(
+            r#"
+            [layout]
+            columns = 2
+
+            [[layout.pages]]
+            pages = "1"
+            columns = 1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.layout.columns_for(1), Some(1));
+        assert_eq!(config.layout.columns_for(2), Some(2));
+        assert_eq!(Config::default().layout.columns_for(1), None);
+    }
+
+    // 単体テスト: 変換の判定の設定の読み込み
+    #[test]
+    fn test_conversion_config() {
+        let config = parse_config(
+            r#"
+            [conversion]
+            heading_scale = 1.4
+            uppercase_bold = false
+            tables = false
+            wrap = 80
+
+            [conversion.hyphenation]
+            de = "hyph-de-1996.tex"
+            "#,
+        )
+        .unwrap();
+
+        let conversion = &config.conversion;
+        assert_eq!((conversion.heading_scale, conversion.wrap), (1.4, 80));
+        assert!(!conversion.uppercase_bold && !conversion.tables);
+        assert_eq!(conversion.hyphenation["de"], Path::new("hyph-de-1996.tex"));
+        // 指定しない項目は既定のまま
+        assert!(conversion.font_headings && conversion.guess_headings);
+        assert_eq!(Config::default().conversion.heading_scale, font_styles::MIN_HEADING_SCALE);
+    }
+
+    // 単体テスト: プロファイルの読み込み
+    #[test]
+    fn test_profiles() {
+        let config = parse_config(
+            r#"
+            [profiles.academic-paper]
+            description = "2段組みの論文"
+            columns = 2
+            front_matter = true
+            tags = ["paper"]
+
+            [profiles.invoice]
+            redact = ["emails", "phones"]
+            "#,
+        )
+        .unwrap();
+
+        let paper = config.profile("academic-paper").unwrap();
+        assert_eq!(paper.columns, Some(2));
+        assert_eq!(paper.tags, vec!["paper"]);
+        assert_eq!(config.profile("invoice").unwrap().redact, vec![PiiKind::Emails, PiiKind::Phones]);
+        assert!(config.profile("scanned-book").is_err());
+
+        // 組み込みのプロファイル
+        assert_eq!(config.profile("slides").unwrap().mode, Some(ConversionMode::Slides));
+        assert!(config.has_profile("slides"));
+        assert_eq!(config.profile("newspaper").unwrap().articles, Some(ArticleOutput::Sections));
+    }
+
+    // 単体テスト: 見出しの判定規則の読み込み
+    #[test]
+    fn test_heading_rules() {
+        let config = parse_config(
+            r#"
+            [[profiles.contract.headings]]
+            pattern = "^Schedule [A-Z]"
+            level = 1
+
+            [[profiles.contract.headings]]
+            pattern = '^Article \d+'
+            level = 2
+            "#,
+        )
+        .unwrap();
+
+        let rules = &config.profile("contract").unwrap().headings;
+        assert_eq!(rules.len(), 2);
+        assert!(rules[1].pattern.is_match("Article 12 Termination"));
+        assert_eq!(rules[1].level, 2);
+
+        assert!(parse_config("[[profiles.x.headings]]\npattern = \"(\"\nlevel = 1").is_err());
+        assert!(parse_config("[[profiles.x.headings]]\npattern = \"a\"\nlevel = 7").is_err());
+    }
+
+    // 単体テスト: 色の規則の読み込み
+    #[test]
+    fn test_color_rules() {
+        let config = parse_config(
+            r##"
+            [[profiles.manual.colors]]
+            color = "#CC0000"
+            style = "admonition"
+            kind = "warning"
+            "##,
+        )
+        .unwrap();
+
+        let rules = &config.profile("manual").unwrap().colors;
+        assert_eq!(rules[0].style, ColorStyle::Admonition);
+        assert!(rules[0].matches((0.8, 0.0, 0.0)));
+        assert!(!rules[0].matches((0.0, 0.0, 0.0)));
+
+        assert!(parse_config("[[profiles.x.colors]]\ncolor = \"red\"\nstyle = \"bold\"").is_err());
+    }
+
+    // 単体テスト: 不正な設定
+    #[test]
+    fn test_invalid_config() {
+        assert!(parse_config("[layout]\nunknown = 1").is_err());
+        assert!(parse_config("[[layout.pages]]\npages = \"5-3\"\ncolumns = 1").is_err());
+        assert!(parse_config("[conversion]\nheading_scale = 0.8").is_err());
+    }
+}
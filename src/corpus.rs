@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+
+/// 差分で、変わった行の前後に表示する行の数
+const CONTEXT_LINES: usize = 2;
+
+/// 変換結果を確かめる PDF と、期待する Markdown（foo.pdf に対する foo.expected.md）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub pdf: PathBuf,
+    /// コーパスのディレクトリからの相対パス（結果の表示用）
+    pub relative: PathBuf,
+    pub expected: PathBuf,
+    /// この PDF だけに使う変換のオプション（foo.args に1行に1つずつ書いたもの）
+    pub args: Vec<OsString>,
+}
+
+/// ディレクトリ以下のすべての PDF を、期待する Markdown と組にして集める
+pub fn collect_fixtures(dir: &Path) -> Result<Vec<Fixture>> {
+    batch::collect_inputs(dir)?
+        .into_iter()
+        .map(|input| {
+            let args_path = input.path.with_extension("args");
+            let args = match args_path.is_file() {
+                true => fs::read_to_string(&args_path)
+                    .with_context(|| format!("変換のオプションのファイルを読み込めません: {:?}", args_path))?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(OsString::from)
+                    .collect(),
+                false => Vec::new(),
+            };
+            Ok(Fixture { expected: input.path.with_extension("expected.md"), pdf: input.path, relative: input.relative, args })
+        })
+        .collect()
+}
+
+/// 期待する Markdown と変換結果の行の差分（期待する行は -、変換結果の行は + を付け、前後の行も表示する）
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let (expected, actual): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+    // 前後の共通の行を除いてから、残りの部分の最長共通部分列を求める
+    let prefix = expected.iter().zip(&actual).take_while(|(a, b)| a == b).count();
+    let suffix = expected[prefix..].iter().rev().zip(actual[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&expected[prefix..expected.len() - suffix], &actual[prefix..actual.len() - suffix]);
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] { lengths[i + 1][j + 1] + 1 } else { lengths[i + 1][j].max(lengths[i][j + 1]) };
+        }
+    }
+
+    // 差分の行（記号、期待する Markdown での行番号、行）
+    let mut lines: Vec<(char, usize, &str)> = expected[..prefix].iter().enumerate().map(|(index, line)| (' ', index + 1, *line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', prefix + i + 1, old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(('-', prefix + i + 1, old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', prefix + i + 1, new[j]));
+            j += 1;
+        }
+    }
+    let start = prefix + old.len();
+    lines.extend(expected[start..].iter().enumerate().map(|(index, line)| (' ', start + index + 1, *line)));
+
+    // 変わった行と、その前後の行だけを表示する
+    let changed: Vec<usize> = lines.iter().enumerate().filter(|(_, (mark, _, _))| *mark != ' ').map(|(index, _)| index).collect();
+    let shown = |index: usize| changed.iter().any(|&c| c.abs_diff(index) <= CONTEXT_LINES);
+    let mut diff = String::new();
+    let mut previous_shown = false;
+    for (index, (mark, number, line)) in lines.iter().enumerate() {
+        if !shown(index) {
+            previous_shown = false;
+            continue;
+        }
+        if !previous_shown {
+            diff.push_str(&format!("@@ {} 行目 @@\n", number));
+        }
+        diff.push_str(&format!("{}{}\n", mark, line));
+        previous_shown = true;
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 期待する Markdown との行の差分
+    #[test]
+    fn test_line_diff() {
+        let expected = "# Title\n\na\nb\nc\nd\ne\nf\n\n## Results\n";
+        let actual = "# Title\n\na\nb\nc\nd\ne\nf\n\n### Results\n\nextra\n";
+        assert_eq!(line_diff(expected, actual), "@@ 8 行目 @@\n f\n \n-## Results\n+### Results\n+\n+extra\n");
+        assert_eq!(line_diff("a\nb\n", "a\nb\n"), "");
+
+        let diff = line_diff("1\n2\n3\n4\n5\n6\n7\n8\n9\n", "1\nX\n3\n4\n5\n6\n7\nY\n9\n");
+        assert_eq!(diff, "@@ 1 行目 @@\n 1\n-2\n+X\n 3\n 4\n@@ 6 行目 @@\n 6\n 7\n-8\n+Y\n 9\n");
+    }
+}
@@ -0,0 +1,230 @@
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{HashMap, HashSet};
+
+use crate::metadata;
+
+/// 名前ツリーの入れ子や、名前から名前への参照をたどる深さの上限（循環参照対策）
+const MAX_DEPTH: usize = 32;
+
+/// リンクやしおりの移動先（ページと、そのページ内の位置）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Destination {
+    /// 移動先のページ番号（1 始まり）
+    pub page: u32,
+    /// 移動先の上端（PDF の座標、y 上向き。/Fit などで位置の指定が無い場合は None）
+    pub top: Option<f64>,
+}
+
+/// 文書の名前付きの移動先（カタログの /Dests と、名前ツリーの /Names /Dests）と、ページのオブジェクト ID
+pub struct Destinations {
+    names: HashMap<Vec<u8>, Object>,
+    pages: HashMap<ObjectId, u32>,
+}
+
+/// しおり（アウトライン）の項目1件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    /// 入れ子の深さ（最上位が 1）
+    pub level: usize,
+    pub title: String,
+    /// 移動先のページ番号（移動先を解決できない場合は None）
+    pub page: Option<u32>,
+}
+
+impl Destinations {
+    /// 文書の名前付きの移動先を読み込む
+    pub fn load(doc: &Document) -> Self {
+        let pages = doc.get_pages().into_iter().map(|(number, id)| (id, number)).collect();
+        let mut names = HashMap::new();
+        if let Ok(catalog) = doc.catalog() {
+            // PDF 1.1 形式: カタログの /Dests は名前から移動先への辞書
+            if let Some(dests) = catalog.get(b"Dests").ok().and_then(|d| doc.dereference(d).ok()).and_then(|(_, d)| d.as_dict().ok()) {
+                for (name, dest) in dests.iter() {
+                    names.insert(name.clone(), dest.clone());
+                }
+            }
+            // PDF 1.2 以降: /Names の /Dests は文字列から移動先への名前ツリー
+            if let Some(tree) = catalog
+                .get(b"Names")
+                .ok()
+                .and_then(|n| doc.dereference(n).ok())
+                .and_then(|(_, n)| n.as_dict().ok())
+                .and_then(|n| n.get(b"Dests").ok())
+                .and_then(|d| doc.dereference(d).ok())
+                .and_then(|(_, d)| d.as_dict().ok())
+            {
+                collect_name_tree(doc, tree, 0, &mut names);
+            }
+        }
+        Destinations { names, pages }
+    }
+
+    /// 移動先（[ページ /XYZ 左 上 倍率] などの配列、名前、文字列、/D を持つ辞書）をページと位置にする
+    pub fn resolve(&self, doc: &Document, dest: &Object) -> Option<Destination> {
+        self.resolve_at(doc, dest, 0)
+    }
+
+    fn resolve_at(&self, doc: &Document, dest: &Object, depth: usize) -> Option<Destination> {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+        match doc.dereference(dest).ok()?.1 {
+            Object::Array(array) => {
+                let page = match array.first()? {
+                    Object::Reference(id) => *self.pages.get(id)?,
+                    // 別の文書への移動（GoToR）ではページの番号（0 始まり）を直接書く
+                    Object::Integer(index) => u32::try_from(*index).ok()? + 1,
+                    _ => return None,
+                };
+                let number = |index: usize| array.get(index).and_then(|o| o.as_float().ok()).map(f64::from);
+                let top = match array.get(1).and_then(|o| o.as_name().ok()) {
+                    Some(b"XYZ") => number(3),
+                    Some(b"FitH") | Some(b"FitBH") => number(2),
+                    Some(b"FitR") => number(5),
+                    _ => None,
+                };
+                Some(Destination { page, top })
+            }
+            Object::Name(name) | Object::String(name, _) => self.resolve_at(doc, self.names.get(name)?, depth + 1),
+            Object::Dictionary(dict) => self.resolve_at(doc, dict.get(b"D").ok()?, depth + 1),
+            _ => None,
+        }
+    }
+
+    /// リンクの注釈やしおりの項目の移動先（/Dest か、/A の GoTo アクションの /D）
+    pub fn target(&self, doc: &Document, dict: &Dictionary) -> Option<Destination> {
+        if let Ok(dest) = dict.get(b"Dest") {
+            return self.resolve(doc, dest);
+        }
+        let action = dict.get(b"A").ok().and_then(|a| doc.dereference(a).ok()).and_then(|(_, a)| a.as_dict().ok())?;
+        if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+            return None;
+        }
+        self.resolve(doc, action.get(b"D").ok()?)
+    }
+}
+
+/// 名前ツリーの葉（Names）の [名前, 移動先] を集める
+fn collect_name_tree(doc: &Document, node: &Dictionary, depth: usize, names: &mut HashMap<Vec<u8>, Object>) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    if let Ok(pairs) = node.get(b"Names").and_then(Object::as_array) {
+        for pair in pairs.chunks(2) {
+            let (Some(Ok((_, Object::String(name, _)))), Some(dest)) = (pair.first().map(|n| doc.dereference(n)), pair.get(1)) else {
+                continue;
+            };
+            names.insert(name.clone(), dest.clone());
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                collect_name_tree(doc, kid, depth + 1, names);
+            }
+        }
+    }
+}
+
+/// しおりの項目を、文書での順（親の後に子）に並べて返す
+pub fn bookmarks(doc: &Document, destinations: &Destinations) -> Vec<Bookmark> {
+    let mut items = Vec::new();
+    let first = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|outlines| outlines.get(b"First").and_then(Object::as_reference).ok());
+    if let Some(first) = first {
+        collect_bookmarks(doc, destinations, first, 1, &mut HashSet::new(), &mut items);
+    }
+    items
+}
+
+/// 兄弟の項目を /Next でたどり、子の項目（/First）を再帰的に集める
+fn collect_bookmarks(
+    doc: &Document,
+    destinations: &Destinations,
+    first: ObjectId,
+    level: usize,
+    visited: &mut HashSet<ObjectId>,
+    items: &mut Vec<Bookmark>,
+) {
+    if level > MAX_DEPTH {
+        return;
+    }
+    let mut next = Some(first);
+    while let Some(id) = next.filter(|id| visited.insert(*id)) {
+        let Ok(item) = doc.get_dictionary(id) else {
+            break;
+        };
+        let title = metadata::info_string(doc, item, b"Title").unwrap_or_default();
+        let page = destinations.target(doc, item).map(|dest| dest.page);
+        items.push(Bookmark { level, title: title.trim().to_string(), page });
+        if let Ok(child) = item.get(b"First").and_then(Object::as_reference) {
+            collect_bookmarks(doc, destinations, child, level + 1, visited, items);
+        }
+        next = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+}
+
+/// 見出しのテキストから GitHub と同じ形式のアンカー（小文字にし、記号を除き、空白をハイフンにしたもの）を作る
+pub fn slug(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+
+    // 単体テスト: 名前付きの移動先の解決
+    #[test]
+    fn test_resolve_destinations() {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let page_ids: Vec<ObjectId> = (0..3).map(|_| doc.add_object(dictionary! {"Type" => "Page", "Parent" => pages_id})).collect();
+        let kids: Vec<Object> = page_ids.iter().map(|&id| id.into()).collect();
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {"Type" => "Pages", "Kids" => kids, "Count" => 3}));
+        let leaf = doc.add_object(dictionary! {
+            "Names" => vec![
+                Object::String(b"sec.2".to_vec(), StringFormat::Literal),
+                dictionary! {"D" => vec![page_ids[1].into(), "XYZ".into(), 0.into(), 700.into(), Object::Null]}.into(),
+            ],
+        });
+        let tree = doc.add_object(dictionary! {"Kids" => vec![leaf.into()]});
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Names" => dictionary! {"Dests" => tree},
+            "Dests" => dictionary! {"appendix" => vec![page_ids[2].into(), "Fit".into()], "loop" => "loop"},
+        });
+        doc.trailer.set("Root", catalog);
+
+        let destinations = Destinations::load(&doc);
+        let test_cases = vec![
+            (Object::String(b"sec.2".to_vec(), StringFormat::Literal), Some(Destination { page: 2, top: Some(700.0) }), "名前ツリーの文字列"),
+            ("appendix".into(), Some(Destination { page: 3, top: None }), "カタログの /Dests の名前"),
+            (vec![page_ids[0].into(), "FitH".into(), 500.into()].into(), Some(Destination { page: 1, top: Some(500.0) }), "配列"),
+            ("loop".into(), None, "循環する名前"),
+            ("missing".into(), None, "存在しない名前"),
+        ];
+        for (dest, expected, desc) in test_cases {
+            assert_eq!(destinations.resolve(&doc, &dest), expected, "Test failed: {}", desc);
+        }
+
+        let link = dictionary! {"A" => dictionary! {"S" => "GoTo", "D" => Object::String(b"sec.2".to_vec(), StringFormat::Literal)}};
+        assert_eq!(destinations.target(&doc, &link).map(|dest| dest.page), Some(2));
+        assert_eq!(slug("2.1 Results & Discussion"), "21-results--discussion");
+    }
+}
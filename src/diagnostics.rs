@@ -0,0 +1,389 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::LazyLock;
+
+use crate::layout::{Glyph, PageLayout};
+
+/// 警告に添える抜粋の文字数の上限
+const MAX_EXCERPT_CHARS: usize = 60;
+
+/// pdf2md が本文に挿入する目印（--placeholders の目印、画像にできなかった図のページ、重複したページ、--review-html のページの目印）
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[\[(?:(?:image omitted|unconverted table|graphical page), p\.[^\[\]]+|duplicate of p\.[^\[\],]+, p\.[^\[\],]+|pdf2md-page \d+)\]\]$").unwrap()
+});
+
+/// 変換で忠実に表現できなかった内容の種類（--allow、--warn、--deny で種類ごとの扱いを指定する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// 画像（図）は Markdown に出力されない
+    DroppedFigure,
+    /// 表らしい領域をテキストの羅列として出力した
+    UnparsedTable,
+    /// 文字コードを解釈できなかった文字（文字化け）がある
+    GarbledText,
+    /// 表の行・列の合計が、各行・各列の数値の和と一致しない
+    TotalMismatch,
+}
+
+/// 変換時の警告
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub page: u32,
+    /// 該当箇所の上端の y 座標（ページ左上が原点）。位置を特定できない場合は None
+    pub y: Option<f64>,
+    pub kind: WarningKind,
+    pub message: String,
+    /// 該当箇所の文字の抜粋（図の場合はキャプションなど、近くの行）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+}
+
+impl Warning {
+    /// --placeholders で本文中に挿入する目印（挿入しない種類の場合は None）
+    pub fn placeholder(&self) -> Option<String> {
+        match self.kind {
+            WarningKind::DroppedFigure => Some(format!("[[image omitted, p.{}]]", self.page)),
+            WarningKind::UnparsedTable => Some(format!("[[unconverted table, p.{}]]", self.page)),
+            WarningKind::GarbledText | WarningKind::TotalMismatch => None,
+        }
+    }
+}
+
+/// 警告の種類ごとの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// 表示しない
+    Allow,
+    /// 警告として表示する
+    Warn,
+    /// エラーとして表示し、出力せずに終了する
+    Deny,
+}
+
+/// 警告の種類ごとの扱いの設定（指定の無い種類は既定の扱いにする）
+#[derive(Debug, Clone)]
+pub struct SeverityLevels {
+    default: Severity,
+    levels: HashMap<WarningKind, Severity>,
+}
+
+impl SeverityLevels {
+    /// strict が真の場合は、既定の扱いをエラーにする
+    pub fn new(strict: bool) -> Self {
+        SeverityLevels { default: if strict { Severity::Deny } else { Severity::Warn }, levels: HashMap::new() }
+    }
+
+    /// 種類ごとの扱いを指定する（先に指定した扱いを上書きする）
+    pub fn set(&mut self, kinds: &[WarningKind], severity: Severity) {
+        for kind in kinds {
+            self.levels.insert(*kind, severity);
+        }
+    }
+
+    pub fn severity(&self, kind: WarningKind) -> Severity {
+        self.levels.get(&kind).copied().unwrap_or(self.default)
+    }
+}
+
+impl WarningKind {
+    /// --allow などで指定する種類の名前
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::DroppedFigure => "dropped_figure",
+            WarningKind::UnparsedTable => "unparsed_table",
+            WarningKind::GarbledText => "garbled_text",
+            WarningKind::TotalMismatch => "total_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ページ {}: {}", self.page, self.message)
+    }
+}
+
+/// 端末に色を付けて表示するかどうか（NO_COLOR が空でない値で設定されている場合と、標準エラー出力が端末でない場合は付けない）
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stderr().is_terminal()
+}
+
+/// 警告を、コンパイラの診断のように種類・ページ・抜粋を添えた複数行の表示にする
+///
+/// 例:
+/// ```text
+/// 警告[dropped_figure]: 画像（200x100pt）は出力されません
+///   --> p.3（上端から 120pt）
+///    | Figure 2: Revenue by quarter
+/// ```
+pub fn render_warning(warning: &Warning, severity: Severity, color: bool) -> String {
+    let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_string() };
+    let (label, code) = match severity {
+        Severity::Deny => ("エラー", "1;31"),
+        _ => ("警告", "1;33"),
+    };
+    let location = match warning.y {
+        Some(y) => format!("p.{}（上端から {:.0}pt）", warning.page, y),
+        None => format!("p.{}", warning.page),
+    };
+
+    let mut lines = vec![
+        format!("{}{}", paint(code, &format!("{}[{}]", label, warning.kind.name())), paint("1", &format!(": {}", warning.message))),
+        format!("  {} {}", paint("1;34", "-->"), location),
+    ];
+    if let Some(excerpt) = &warning.excerpt {
+        lines.push(format!("   {} {}", paint("1;34", "|"), excerpt));
+    }
+    lines.join("\n")
+}
+
+/// ページごとのレイアウト情報から、変換で失われる内容を警告として集める
+pub fn collect_warnings(pages: &[PageLayout]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for page in pages {
+        let lines = page.lines();
+        for image in &page.images {
+            // 図のすぐ下の行（キャプション）か、無ければすぐ上の行
+            let caption = lines
+                .iter()
+                .find(|line| line.y > image.y1 && line.y <= image.y1 + line.font_size * 3.0)
+                .or_else(|| lines.iter().rev().find(|line| line.y < image.y0 && line.y >= image.y0 - line.font_size * 3.0));
+            warnings.push(Warning {
+                page: page.number,
+                y: Some(image.y0),
+                kind: WarningKind::DroppedFigure,
+                message: format!("画像（{:.0}x{:.0}pt）は出力されません", image.x1 - image.x0, image.y1 - image.y0),
+                excerpt: caption.and_then(|line| excerpt(&line.text)),
+            });
+        }
+
+        for table in page.table_regions() {
+            let first_row = lines.iter().find(|line| (line.y - table.y0).abs() <= line.font_size * 0.5);
+            warnings.push(Warning {
+                page: page.number,
+                y: Some(table.y0),
+                kind: WarningKind::UnparsedTable,
+                message: format!("表らしい領域（{} 行）を表として変換できませんでした", table.rows),
+                excerpt: first_row.and_then(|line| excerpt(&line.text)),
+            });
+        }
+
+        let garbled = page.glyphs.iter().filter(|glyph| is_garbled(glyph)).count();
+        if garbled > 0 {
+            let line = lines.iter().find(|line| line.text.chars().any(is_garbled_char));
+            warnings.push(Warning {
+                page: page.number,
+                y: None,
+                kind: WarningKind::GarbledText,
+                message: format!("解釈できない文字が {} 個あります", garbled),
+                excerpt: line.and_then(|line| excerpt(&line.text)),
+            });
+        }
+    }
+
+    warnings
+}
+
+/// 1ページ分の変換率（文字の領域の面積のうち、Markdown に表現できた割合）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageCoverage {
+    pub page: u32,
+    /// 空白以外の文字の領域（文字送り幅 x フォントサイズ）の合計
+    pub text_area: f64,
+    /// そのうち表や文字化けで崩れずに出力できた文字の領域の合計
+    pub converted_area: f64,
+}
+
+impl PageCoverage {
+    /// 変換率（文字が1つも無いページは 1.0）
+    pub fn ratio(&self) -> f64 {
+        if self.text_area > 0.0 {
+            self.converted_area / self.text_area
+        } else {
+            1.0
+        }
+    }
+}
+
+/// ページごとの変換率を求める（墨消しで意図的に除いた文字は含めない）
+///
+/// tables_converted が真の場合は、表らしい領域も表として変換できたものとして数える。
+pub fn measure_coverage(pages: &[PageLayout], tables_converted: bool) -> Vec<PageCoverage> {
+    pages
+        .iter()
+        .map(|page| {
+            let tables = if tables_converted { Vec::new() } else { page.table_regions() };
+            let mut coverage = PageCoverage { page: page.number, text_area: 0.0, converted_area: 0.0 };
+
+            for glyph in page.glyphs.iter().filter(|glyph| !glyph.text.trim().is_empty()) {
+                let area = glyph.width.abs() * glyph.font_size;
+                coverage.text_area += area;
+
+                let in_table = tables
+                    .iter()
+                    .any(|table| glyph.y >= table.y0 - glyph.font_size && glyph.y <= table.y1 + glyph.font_size * 0.5);
+                if !in_table && !is_garbled(glyph) {
+                    coverage.converted_area += area;
+                }
+            }
+            coverage
+        })
+        .collect()
+}
+
+/// 文書全体の変換率（ページごとの面積の合計から求める）
+pub fn total_coverage(pages: &[PageCoverage]) -> f64 {
+    let text_area: f64 = pages.iter().map(|page| page.text_area).sum();
+    let converted_area: f64 = pages.iter().map(|page| page.converted_area).sum();
+    PageCoverage { page: 0, text_area, converted_area }.ratio()
+}
+
+/// 警告の位置に目印の文字列を挿入する（位置は描画順で、その y 座標より下にある最初の文字の直前）
+///
+/// 目印は前後に空行を付けた1文字分として挿入し、段落の区切りになるようにする。
+pub fn insert_placeholders(pages: &mut [PageLayout], warnings: &[Warning]) {
+    for warning in warnings {
+        let (Some(marker), Some(y)) = (warning.placeholder(), warning.y) else {
+            continue;
+        };
+        let Some(page) = pages.iter_mut().find(|page| page.number == warning.page) else {
+            continue;
+        };
+
+        let index = page.glyphs.iter().position(|glyph| glyph.y > y).unwrap_or(page.glyphs.len());
+        let font_size = page.glyphs.get(index).or(page.glyphs.last()).map_or(10.0, |glyph| glyph.font_size);
+        let order = page.glyphs.get(index).map_or(0, |glyph| glyph.order);
+        page.glyphs.insert(
+            index,
+            Glyph { text: format!("\n\n{}\n\n", marker), x: 0.0, y, width: 0.0, font_size, word_start: true, order, color: None, bold: false, monospace: false, confidence: None },
+        );
+    }
+}
+
+/// 行が --placeholders などで pdf2md が挿入した目印かどうか（本文の [[...]] のような行は目印としない）
+pub fn is_placeholder(line: &str) -> bool {
+    PLACEHOLDER_REGEX.is_match(line)
+}
+
+/// 置換文字・制御文字・私用領域の文字は、フォントの文字コードを Unicode に変換できなかったものとみなす
+fn is_garbled(glyph: &Glyph) -> bool {
+    glyph.text.chars().any(is_garbled_char)
+}
+
+fn is_garbled_char(c: char) -> bool {
+    c == '\u{FFFD}' || (c.is_control() && !c.is_whitespace()) || ('\u{E000}'..='\u{F8FF}').contains(&c)
+}
+
+/// 警告に添える抜粋（空白をまとめ、長い場合は先頭だけにする）
+fn excerpt(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((index, _)) => Some(format!("{}…", &text[..index])),
+        None => Some(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::ImagePlacement;
+
+    // 単体テスト: 警告の収集
+    #[test]
+    fn test_collect_warnings() {
+        let glyph = |text: &str| Glyph { text: text.to_string(), x: 10.0, y: 100.0, width: 6.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let pages = vec![
+            PageLayout { number: 1, glyphs: vec![glyph("a"), glyph("\u{FFFD}"), glyph("\u{E001}")], ..Default::default() },
+            PageLayout {
+                number: 2,
+                glyphs: vec![glyph("b")],
+                images: vec![ImagePlacement { id: (5, 0), x0: 0.0, y0: 0.0, x1: 10.0, y1: 10.0 }],
+                ..Default::default()
+            },
+        ];
+
+        let warnings = collect_warnings(&pages);
+        let kinds: Vec<(u32, WarningKind)> = warnings.iter().map(|w| (w.page, w.kind)).collect();
+        assert_eq!(kinds, vec![(1, WarningKind::GarbledText), (2, WarningKind::DroppedFigure)]);
+        assert_eq!(warnings[0].to_string(), "ページ 1: 解釈できない文字が 2 個あります");
+        assert_eq!(warnings[0].excerpt.as_deref(), Some("a\u{FFFD}\u{E001}"));
+
+        let rendered = render_warning(&warnings[0], Severity::Deny, false);
+        assert_eq!(rendered, "エラー[garbled_text]: 解釈できない文字が 2 個あります\n  --> p.1\n   | a\u{FFFD}\u{E001}");
+        assert!(render_warning(&warnings[0], Severity::Warn, true).starts_with("\x1b[1;33m警告[garbled_text]\x1b[0m"));
+    }
+
+    // 単体テスト: 変換率の計算
+    #[test]
+    fn test_measure_coverage() {
+        let glyph = |text: &str| Glyph { text: text.to_string(), x: 10.0, y: 100.0, width: 5.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let pages = vec![
+            PageLayout { number: 1, glyphs: vec![glyph("a"), glyph(" "), glyph("b"), glyph("\u{FFFD}")], ..Default::default() },
+            PageLayout { number: 2, ..Default::default() },
+        ];
+
+        let coverage = measure_coverage(&pages, false);
+        assert_eq!((coverage[0].text_area, coverage[0].converted_area), (150.0, 100.0));
+        assert_eq!(coverage[1].ratio(), 1.0);
+        assert!((total_coverage(&coverage) - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    // 単体テスト: 目印の挿入
+    #[test]
+    fn test_insert_placeholders() {
+        let glyph = |text: &str, y: f64| Glyph { text: text.to_string(), x: 10.0, y, width: 6.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let mut pages = vec![PageLayout { number: 3, glyphs: vec![glyph("above", 100.0), glyph("below", 300.0)], ..Default::default() }];
+        let warnings = vec![
+            Warning { page: 3, y: Some(150.0), kind: WarningKind::DroppedFigure, message: String::new(), excerpt: None },
+            Warning { page: 3, y: None, kind: WarningKind::GarbledText, message: String::new(), excerpt: None },
+        ];
+
+        insert_placeholders(&mut pages, &warnings);
+        let text = crate::layout::glyphs_to_text(&pages[0].glyphs);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        assert_eq!(lines, vec!["above", "[[image omitted, p.3]]", "below"]);
+        assert!(is_placeholder(lines[1]));
+        for (line, expected) in [
+            ("[[unconverted table, p.12]]", true),
+            ("[[graphical page, p.iv]]", true),
+            ("[[duplicate of p.1, p.3]]", true),
+            ("[[pdf2md-page 2]]", true),
+            ("[[Wiki link]]", false),
+            ("[[image omitted, p.3]] and more", false),
+            ("[[profiles.x.headings]]", false),
+        ] {
+            assert_eq!(is_placeholder(line), expected, "{}", line);
+        }
+    }
+
+    // 単体テスト: 警告の種類ごとの扱い
+    #[test]
+    fn test_severity_levels() {
+        let profile: crate::config::Profile = toml::from_str("[warnings]\ndropped_figure = \"allow\"\ngarbled_text = \"deny\"\n").unwrap();
+        let mut levels = SeverityLevels::new(true);
+        for (kind, severity) in &profile.warnings {
+            levels.set(&[*kind], *severity);
+        }
+        levels.set(&[WarningKind::GarbledText], Severity::Warn);
+
+        let test_cases = vec![
+            (WarningKind::DroppedFigure, Severity::Allow, "プロファイルの指定"),
+            (WarningKind::GarbledText, Severity::Warn, "プロファイルより後の指定"),
+            (WarningKind::UnparsedTable, Severity::Deny, "指定の無い種類は --strict の既定"),
+        ];
+        for (kind, expected, desc) in test_cases {
+            assert_eq!(levels.severity(kind), expected, "Test failed: {}", desc);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use crate::layout::{Glyph, PageLayout};
+
+/// 重複の判定の対象にする最小の語数（語数の少ないページは、たまたま同じになりやすいため対象外にする）
+const MIN_WORDS: usize = 20;
+
+/// 同じ内容とみなす語の並びの一致率（Jaccard 係数）
+const MIN_SIMILARITY: f64 = 0.9;
+
+/// 前のページと同じかほぼ同じ内容のページ（FAX の送付状、結合した PDF で繰り返される免責事項など）を、
+/// 最初のページを示す [[duplicate of p.1, p.3]] のような目印に置き換え、置き換えたページ数を返す
+///
+/// 数字はページ番号や日付の違いを無視するため同じものとして比べる。
+pub fn collapse_duplicate_pages(pages: &mut [PageLayout]) -> usize {
+    let signatures: Vec<Option<HashSet<(String, String)>>> = pages.iter().map(page_signature).collect();
+    let mut collapsed = 0;
+
+    for index in 1..pages.len() {
+        let Some(signature) = &signatures[index] else {
+            continue;
+        };
+        // 最初に現れたページを元のページとする（置き換えたページより前に必ず元のページがある）
+        let original = (0..index).find(|&earlier| signatures[earlier].as_ref().is_some_and(|other| similarity(signature, other) >= MIN_SIMILARITY));
+        let Some(original) = original else {
+            continue;
+        };
+
+        let note = format!("[[duplicate of p.{}, p.{}]]", pages[original].number, pages[index].number);
+        let page = &mut pages[index];
+        let font_size = page.glyphs.first().map_or(10.0, |glyph| glyph.font_size);
+        page.glyphs = vec![Glyph { text: format!("\n\n{}\n\n", note), x: 0.0, y: 0.0, width: 0.0, font_size, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None }];
+        page.images.clear();
+        collapsed += 1;
+    }
+
+    collapsed
+}
+
+/// ページの語の2つ組の集合（語数が少ないページは None）
+fn page_signature(page: &PageLayout) -> Option<HashSet<(String, String)>> {
+    let words: Vec<String> = page
+        .lines()
+        .iter()
+        .flat_map(|line| line.text.split_whitespace().map(normalize_word).collect::<Vec<_>>())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    Some(words.windows(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect())
+}
+
+/// 語を小文字にし、数字を # にそろえ、前後の記号を除く
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .chars()
+        .map(|c| if c.is_numeric() { '#' } else { c })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn similarity(a: &HashSet<(String, String)>, b: &HashSet<(String, String)>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(number: u32, text: &str) -> PageLayout {
+        let glyphs = text
+            .split_whitespace()
+            .enumerate()
+            .map(|(i, word)| Glyph {
+                text: word.to_string(),
+                x: 72.0 + (i % 8) as f64 * 50.0,
+                y: 100.0 + (i / 8) as f64 * 14.0,
+                width: 40.0,
+                font_size: 10.0,
+                word_start: true,
+                order: i,
+                color: None,
+                bold: false,
+                monospace: false,
+                confidence: None,
+            })
+            .collect();
+        PageLayout { number, width: 600.0, height: 800.0, glyphs, ..Default::default() }
+    }
+
+    // 単体テスト: 重複したページの置き換え
+    #[test]
+    fn test_collapse_duplicate_pages() {
+        let disclaimer = "This message is confidential and intended only for the named recipient. If you received it in error, \
+                          notify the sender immediately and delete all copies. Page";
+        let body = "The quarterly results show steady growth across all regions, with particular strength in the \
+                    northern markets where new products were launched earlier this year";
+        let mut pages = vec![
+            page(1, &format!("{} 1", disclaimer)),
+            page(2, body),
+            page(3, &format!("{} 3", disclaimer)),
+            page(4, "Short note"),
+            page(5, "Short note"),
+        ];
+
+        assert_eq!(collapse_duplicate_pages(&mut pages), 1);
+        assert_eq!(pages[2].glyphs[0].text, "\n\n[[duplicate of p.1, p.3]]\n\n");
+        assert_eq!(pages[1].glyphs.len(), 24);
+        assert_eq!(pages[4].glyphs.len(), 2);
+    }
+}
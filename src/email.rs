@@ -0,0 +1,179 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::layout::{PageLayout, TextLine};
+use crate::paragraphs;
+
+/// メールのヘッダー行（From: / To: / 件名： など）の正規表現
+static HEADER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?i)(From|To|Cc|Bcc|Subject|Date|Sent|Reply-To|差出人|送信者|宛先|件名|日付|送信日時|CC)\s*[:：]\s*(.*)$").unwrap());
+
+/// 返信・転送の元のメッセージの区切り行
+static SEPARATOR_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?i)-{2,}\s*(Original Message|Forwarded message|元のメッセージ|転送メッセージ)\s*-{2,}$").unwrap());
+
+/// 引用の前の「... wrote:」の行
+static ATTRIBUTION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(wrote|writes|書きました)\s*[:：]$").unwrap());
+
+/// 行の並びが印刷したメールらしいかどうか（差出人と件名のヘッダー行がある）
+pub fn looks_like_email(lines: &[TextLine]) -> bool {
+    let names: Vec<String> = lines
+        .iter()
+        .filter_map(|line| HEADER_REGEX.captures(strip_quotes(line.text.trim()).1).map(|caps| caps[1].to_lowercase()))
+        .collect();
+    let has = |candidates: &[&str]| names.iter().any(|name| candidates.contains(&name.as_str()));
+    has(&["from", "差出人", "送信者"]) && has(&["subject", "件名"])
+}
+
+/// 印刷したメールを Markdown にする
+///
+/// ヘッダー行のまとまりを表に、行頭の > の数に応じて引用を入れ子の引用ブロックにし、
+/// 元のメッセージの区切り行を水平線にしてスレッドの構造を残す。
+pub fn render_email(pages: &[PageLayout]) -> String {
+    let lines: Vec<TextLine> = pages.iter().flat_map(|page| page.lines()).collect();
+    render_lines(&lines)
+}
+
+fn render_lines(lines: &[TextLine]) -> String {
+    let breaks = paragraphs::paragraph_breaks(lines);
+    let mut blocks: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut paragraph_depth = 0;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let (depth, text) = strip_quotes(lines[i].text.trim());
+
+        // 同じ引用の深さで2行以上続くヘッダー行は、ヘッダーの表にする
+        let header_end = lines[i..]
+            .iter()
+            .position(|line| {
+                let (d, t) = strip_quotes(line.text.trim());
+                d != depth || !HEADER_REGEX.is_match(t)
+            })
+            .map_or(lines.len(), |n| i + n);
+        if header_end - i >= 2 {
+            flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+            let rows: Vec<(String, String)> = lines[i..header_end]
+                .iter()
+                .map(|line| {
+                    let caps = HEADER_REGEX.captures(strip_quotes(line.text.trim()).1).unwrap();
+                    (caps[1].to_string(), caps[2].trim().to_string())
+                })
+                .collect();
+            blocks.push(quote(&header_table(&rows), depth));
+            i = header_end;
+            continue;
+        }
+
+        if SEPARATOR_REGEX.is_match(text) {
+            flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+            blocks.push(quote("---", depth));
+        } else if text.is_empty() {
+            flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+        } else if ATTRIBUTION_REGEX.is_match(text) {
+            flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+            blocks.push(quote(text, depth));
+        } else {
+            if depth != paragraph_depth || breaks[i] {
+                flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+            }
+            paragraph_depth = depth;
+            paragraph.push(text);
+        }
+        i += 1;
+    }
+    flush_paragraph(&mut blocks, &mut paragraph, paragraph_depth);
+
+    blocks.join("\n\n")
+}
+
+/// 組み立て中の段落を、引用の深さに応じてブロックに追加する
+fn flush_paragraph(blocks: &mut Vec<String>, paragraph: &mut Vec<&str>, depth: usize) {
+    if !paragraph.is_empty() {
+        blocks.push(quote(&paragraph.join(" "), depth));
+        paragraph.clear();
+    }
+}
+
+/// 行頭の > を取り除き、引用の深さと本文を返す（「> > 本文」のような空白を挟む書き方にも対応）
+fn strip_quotes(text: &str) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = text;
+    while let Some(stripped) = rest.strip_prefix('>') {
+        depth += 1;
+        rest = stripped.trim_start();
+    }
+    (depth, rest)
+}
+
+/// ブロックの各行を引用の深さの分だけ > で始める
+fn quote(block: &str, depth: usize) -> String {
+    if depth == 0 {
+        return block.to_string();
+    }
+    let prefix = "> ".repeat(depth);
+    block
+        .lines()
+        .map(|line| format!("{}{}", prefix, line).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ヘッダーの項目と値を Markdown の表にする
+fn header_table(rows: &[(String, String)]) -> String {
+    let mut table = String::from("| ヘッダー | 値 |\n| --- | --- |");
+    for (name, value) in rows {
+        table.push_str(&format!("\n| {} | {} |", name, value.replace('|', "\\|")));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, y: f64) -> TextLine {
+        TextLine { text: text.to_string(), x0: 50.0, x1: 400.0, y, font_size: 10.0, confidence: None }
+    }
+
+    // 単体テスト: ヘッダーの表と入れ子の引用
+    #[test]
+    fn test_render_lines() {
+        let texts = [
+            "From: Alice <alice@example.com>",
+            "To: Bob <bob@example.com>",
+            "Subject: Re: Lunch",
+            "Sounds good to me.",
+            "On Monday, Bob wrote:",
+            "> Shall we meet at noon?",
+            "> > Are you free this week?",
+            "> > Let me know.",
+        ];
+        let lines: Vec<TextLine> = texts.iter().enumerate().map(|(i, text)| line(text, 100.0 + i as f64 * 12.0)).collect();
+
+        assert!(looks_like_email(&lines));
+        assert_eq!(
+            render_lines(&lines),
+            "| ヘッダー | 値 |\n| --- | --- |\n| From | Alice <alice@example.com> |\n| To | Bob <bob@example.com> |\n| Subject | Re: Lunch |\n\n\
+             Sounds good to me.\n\nOn Monday, Bob wrote:\n\n\
+             > Shall we meet at noon?\n\n\
+             > > Are you free this week? Let me know."
+        );
+    }
+
+    // 単体テスト: 引用の深さの判定
+    #[test]
+    fn test_strip_quotes() {
+        let test_cases = vec![
+            ("plain", (0, "plain"), "引用なし"),
+            ("> quoted", (1, "quoted"), "1段の引用"),
+            (">> deep", (2, "deep"), "空白なしの2段の引用"),
+            ("> > deep", (2, "deep"), "空白ありの2段の引用"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(strip_quotes(input), expected, "Test failed: {}", desc);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::borrow::Cow;
+
+/// UTF-8 の BOM
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 書き出す Markdown の文字コード
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputEncoding {
+    /// UTF-8（BOM なし）
+    #[default]
+    #[value(name = "utf8")]
+    Utf8,
+    /// BOM 付きの UTF-8（Excel など、BOM で文字コードを判別するツール向け）
+    #[value(name = "utf8-bom")]
+    Utf8Bom,
+    /// Shift_JIS（Windows の CP932 の拡張文字を含む）
+    #[value(name = "shift_jis", alias = "cp932")]
+    ShiftJis,
+}
+
+impl OutputEncoding {
+    /// テキストを文字コードに変換する（Shift_JIS で表せない文字がある場合は、最初の文字の位置を示してエラーにする）
+    pub fn encode(self, text: &str) -> Result<Cow<'_, [u8]>> {
+        match self {
+            OutputEncoding::Utf8 => Ok(Cow::Borrowed(text.as_bytes())),
+            OutputEncoding::Utf8Bom => Ok(Cow::Owned([UTF8_BOM, text.as_bytes()].concat())),
+            OutputEncoding::ShiftJis => {
+                let (bytes, _, unmappable) = encoding_rs::SHIFT_JIS.encode(text);
+                if unmappable {
+                    let found = unmappable_chars(text);
+                    let (line, c) = found.first().copied().unwrap_or((0, '\u{FFFD}'));
+                    let others = if found.len() > 1 { format!("ほか {} 文字", found.len() - 1) } else { String::new() };
+                    bail!(
+                        "{} 行目の文字「{}」（U+{:04X}）{}は Shift_JIS で表せません（--output-encoding utf8 か utf8-bom を指定してください）",
+                        line,
+                        c,
+                        u32::from(c),
+                        others
+                    );
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+/// Shift_JIS で表せない文字と、その行番号（1 始まり）
+fn unmappable_chars(text: &str) -> Vec<(usize, char)> {
+    let mut buffer = [0; 4];
+    text.lines()
+        .enumerate()
+        .flat_map(|(index, line)| line.chars().map(move |c| (index + 1, c)))
+        .filter(|&(_, c)| encoding_rs::SHIFT_JIS.encode(c.encode_utf8(&mut buffer)).2)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 文字コードの変換と表せない文字のエラー
+    #[test]
+    fn test_encode() {
+        assert_eq!(&*OutputEncoding::Utf8.encode("表").unwrap(), "表".as_bytes());
+        assert_eq!(&*OutputEncoding::Utf8Bom.encode("# A").unwrap(), b"\xEF\xBB\xBF# A");
+        // CP932 の拡張文字（丸数字）も変換できる
+        assert_eq!(&*OutputEncoding::ShiftJis.encode("# 表①\n").unwrap(), b"# \x95\x5C\x87\x40\n");
+
+        let error = OutputEncoding::ShiftJis.encode("# 報告\n\n絵文字 😀 と 🎉\n").unwrap_err();
+        assert_eq!(error.to_string(), "3 行目の文字「😀」（U+1F600）ほか 1 文字は Shift_JIS で表せません（--output-encoding utf8 か utf8-bom を指定してください）");
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use lopdf::{Document, ObjectId};
+use regex::Regex;
+use std::sync::LazyLock;
+use std::path::{Path, PathBuf};
+
+use crate::alt_text::AltTextHook;
+use crate::console;
+use crate::images;
+use crate::ocr;
+use crate::layout::{Glyph, ImagePlacement, PageLayout, TextLine};
+use crate::memory::SpilledImages;
+
+/// ページから取り出した図1件
+#[derive(Debug)]
+pub struct Figure {
+    pub page: u32,
+    /// 図の近くで見つかったキャプション（「Figure 1: …」「図1 …」など）
+    pub caption: Option<String>,
+    /// Markdown から参照する画像のパス
+    pub link: String,
+    /// 書き出した画像ファイルのパス
+    pub path: PathBuf,
+    /// 図の中の文字（軸のラベルや図中の箱の文字など）を文字認識したもの
+    pub text: Vec<String>,
+    /// 外部の仕組みで生成した代替テキスト（無ければキャプションを代替テキストにする）
+    pub alt: Option<String>,
+}
+
+/// 図の中の文字を文字認識した結果の出力方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FigureText {
+    /// 画像の代替テキストに含める
+    Alt,
+    /// 画像の後ろに定義リストとして出力する
+    List,
+}
+
+/// キャプションとみなす行の正規表現
+static CAPTION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?i:fig(?:ure)?\.?|図|chart|plate)\s*\d+").unwrap());
+
+/// 全ページの画像を assets_dir に書き出し、キャプションと対応付ける
+///
+/// 同じ画像（ロゴなど）が複数回描かれている場合は最初の1回だけを扱う。
+/// link_dir は Markdown から見た assets_dir の相対パス。
+pub fn extract_figures(doc: &Document, pages: &[PageLayout], assets_dir: &Path, link_dir: &str) -> Result<Vec<Figure>> {
+    let mut seen: Vec<ObjectId> = Vec::new();
+    let mut figures = Vec::new();
+
+    for page in pages {
+        let lines = page.lines();
+        let mut used_captions: Vec<usize> = Vec::new();
+
+        for placement in &page.images {
+            if seen.contains(&placement.id) {
+                continue;
+            }
+            seen.push(placement.id);
+
+            // 対応していない形式の画像は警告を出して読み飛ばす
+            let image = match images::decode_image(doc, placement.id, None) {
+                Ok(image) => image,
+                Err(e) => {
+                    console!(Warn, "ページ {} の画像を書き出せませんでした: {:#}", page.number, e);
+                    continue;
+                }
+            };
+            let path = images::save_image(&image, assets_dir, figures.len() + 1)?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            let caption = find_caption(&lines, placement, &used_captions).map(|index| {
+                used_captions.push(index);
+                lines[index].text.trim().to_string()
+            });
+
+            let link = format!("{}/{}", link_dir, file_name);
+            figures.push(Figure { page: page.number, caption, link, path, text: Vec::new(), alt: None });
+        }
+    }
+
+    Ok(figures)
+}
+
+/// 本文の画像を assets_dir に書き出し、画像の位置に画像へのリンクを独立した段落として入れて、書き出した画像の数を返す
+///
+/// 代替テキストは近くのキャプションにし、無ければ figure とする。同じ画像が複数回描かれている場合は同じファイルにリンクする。
+/// 書き出せなかった画像はページに残す（出力されない図の警告の対象になる）。spilled は一時ファイルに移した画像。
+pub fn embed_figures(doc: &Document, spilled: Option<&SpilledImages>, pages: &mut [PageLayout], assets_dir: &Path, link_dir: &str) -> Result<usize> {
+    let mut saved: Vec<(ObjectId, String)> = Vec::new();
+
+    for page in pages.iter_mut() {
+        let lines = page.lines();
+        let mut used_captions: Vec<usize> = Vec::new();
+        let mut kept = Vec::new();
+
+        for placement in std::mem::take(&mut page.images) {
+            let file_name = match saved.iter().find(|(id, _)| *id == placement.id) {
+                Some((_, file_name)) => file_name.clone(),
+                None => match images::decode_image(doc, placement.id, spilled) {
+                    Ok(image) => {
+                        let path = images::save_image(&image, assets_dir, saved.len() + 1)?;
+                        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        saved.push((placement.id, file_name.clone()));
+                        file_name
+                    }
+                    Err(e) => {
+                        console!(Warn, "ページ {} の画像を書き出せませんでした: {:#}", page.number, e);
+                        kept.push(placement);
+                        continue;
+                    }
+                },
+            };
+            let caption = find_caption(&lines, &placement, &used_captions).map(|index| {
+                used_captions.push(index);
+                lines[index].text.split_whitespace().collect::<Vec<_>>().join(" ").replace(['[', ']'], "")
+            });
+            let text = format!("\n\n![{}]({}/{})\n\n", caption.as_deref().unwrap_or("figure"), link_dir, file_name);
+
+            // 画像の縦の中央より下にある最初の文字の前に入れる
+            let middle = (placement.y0 + placement.y1) / 2.0;
+            let index = page.glyphs.iter().position(|glyph| glyph.y > middle).unwrap_or(page.glyphs.len());
+            let neighbor = page.glyphs.get(index).or(page.glyphs.last());
+            let (font_size, order) = neighbor.map_or((10.0, 0), |glyph| (glyph.font_size, glyph.order));
+            let glyph = Glyph { text, x: placement.x0, y: middle, width: 0.0, font_size, word_start: true, order, color: None, bold: false, monospace: false, confidence: None };
+            page.glyphs.insert(index, glyph);
+        }
+        page.images = kept;
+    }
+
+    Ok(saved.len())
+}
+
+/// 各図の画像を tesseract で文字認識し、認識できた行を図に付ける（認識できなかった図は読み飛ばす）
+pub fn recognize_text(figures: &mut [Figure], lang: &str) -> Result<()> {
+    for figure in figures.iter_mut() {
+        match ocr::recognize_image(&figure.path, lang) {
+            Ok(text) => figure.text = ocr::clean_lines(&text),
+            // tesseract が無い場合は、以降の図でも失敗するため中断する
+            Err(e) if e.root_cause().downcast_ref::<std::io::Error>().is_some() => return Err(e),
+            Err(e) => console!(Warn, "ページ {} の図の文字を認識できませんでした: {:#}", figure.page, e),
+        }
+    }
+    Ok(())
+}
+
+/// 各図の代替テキストを外部のコマンドか HTTP エンドポイントで生成する（生成できなかった図はキャプションのままにする）
+pub fn generate_alt_text(figures: &mut [Figure], hook: &AltTextHook) {
+    for figure in figures.iter_mut() {
+        match hook.generate(&figure.path) {
+            Ok(alt) if !alt.is_empty() => figure.alt = Some(alt),
+            Ok(_) => {}
+            Err(e) => console!(Warn, "ページ {} の図の代替テキストを生成できませんでした: {:#}", figure.page, e),
+        }
+    }
+}
+
+/// 画像の直下（なければ直上）にある未使用のキャプション行を探す
+fn find_caption(lines: &[TextLine], image: &ImagePlacement, used: &[usize]) -> Option<usize> {
+    // 画像からこの距離（ポイント）以内の行のみをキャプション候補とする
+    const MAX_DISTANCE: f64 = 72.0;
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(index, line)| !used.contains(index) && CAPTION_REGEX.is_match(line.text.trim()))
+        // 横方向に画像と重なっていること
+        .filter(|(_, line)| line.x1 >= image.x0 && line.x0 <= image.x1)
+        .filter_map(|(index, line)| {
+            let below = line.y - image.y1;
+            let above = image.y0 - (line.y - line.font_size);
+            // 下にあるキャプションを優先するため、上にあるものは距離を割り増す
+            let distance = if below >= 0.0 { below } else if above >= 0.0 { above * 1.5 } else { return None };
+            (distance <= MAX_DISTANCE).then_some((index, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// 図の一覧を Markdown のギャラリーにする
+///
+/// text_style を指定すると、図の中の文字を代替テキストか画像の後ろの定義リストとして出力する。
+pub fn render_gallery(title: &str, figures: &[Figure], text_style: Option<FigureText>) -> String {
+    let mut markdown = format!("# {} の図一覧\n\n", title);
+
+    if figures.is_empty() {
+        markdown.push_str("図は見つかりませんでした。\n");
+        return markdown;
+    }
+
+    for (index, figure) in figures.iter().enumerate() {
+        let caption = figure.caption.clone().unwrap_or_else(|| format!("図 {}", index + 1));
+        // キャプション中の角括弧は画像の代替テキストを壊すため除く
+        let mut alt = figure.alt.clone().unwrap_or_else(|| caption.replace(['[', ']'], ""));
+        if text_style == Some(FigureText::Alt) && !figure.text.is_empty() {
+            alt = format!("{}: {}", alt, figure.text.join("; ").replace(['[', ']'], ""));
+        }
+        markdown.push_str(&format!("## {}\n\n![{}]({})\n\n", caption, alt, figure.link));
+        if text_style == Some(FigureText::List) && !figure.text.is_empty() {
+            markdown.push_str("図中の文字\n");
+            for line in &figure.text {
+                markdown.push_str(&format!(":   {}\n", line));
+            }
+            markdown.push('\n');
+        }
+        markdown.push_str(&format!("*p.{}*\n\n", figure.page));
+    }
+
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, y: f64) -> TextLine {
+        TextLine { text: text.to_string(), x0: 72.0, x1: 300.0, y, font_size: 10.0, confidence: None }
+    }
+
+    // 単体テスト: キャプションの対応付け
+    #[test]
+    fn test_find_caption() {
+        let image = ImagePlacement { id: (1, 0), x0: 72.0, y0: 100.0, x1: 300.0, y1: 200.0 };
+        let lines = vec![
+            line("Figure 1: above", 95.0),
+            line("Body text", 205.0),
+            line("Figure 2: below", 215.0),
+            line("Figure 9: far away", 500.0),
+        ];
+
+        assert_eq!(find_caption(&lines, &image, &[]), Some(2));
+        assert_eq!(find_caption(&lines, &image, &[2]), Some(0));
+        assert_eq!(find_caption(&lines, &image, &[0, 2]), None);
+    }
+
+    // 単体テスト: 本文への画像の埋め込み
+    #[test]
+    fn test_embed_figures() {
+        use lopdf::{dictionary, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let dict = dictionary! {"Type" => "XObject", "Subtype" => "Image", "Width" => 1, "Height" => 1, "ColorSpace" => "DeviceGray", "BitsPerComponent" => 8};
+        let id = doc.add_object(Stream::new(dict, vec![0]));
+        let glyph = |text: &str, y: f64, order: usize| Glyph { text: text.to_string(), x: 72.0, y, width: 100.0, font_size: 10.0, word_start: true, order, color: None, bold: false, monospace: false, confidence: None };
+        let placement = |y0: f64| ImagePlacement { id, x0: 72.0, y0, x1: 300.0, y1: y0 + 100.0 };
+        let mut pages = vec![PageLayout {
+            number: 1,
+            glyphs: vec![glyph("Intro", 80.0, 0), glyph("Figure 1: Sales", 215.0, 1), glyph("After", 400.0, 2)],
+            images: vec![placement(100.0), placement(420.0)],
+            ..Default::default()
+        }];
+        let dir = std::env::temp_dir().join(format!("pdf2md-figures-{}", std::process::id()));
+
+        // 同じ画像は1回だけ書き出し、2回目はキャプションが無いため figure とする
+        assert_eq!(embed_figures(&doc, None, &mut pages, &dir, "a_assets").unwrap(), 1);
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.trim()).collect();
+        assert_eq!(texts, vec!["Intro", "![Figure 1: Sales](a_assets/fig-01.png)", "Figure 1: Sales", "After", "![figure](a_assets/fig-01.png)"]);
+        assert!(pages[0].images.is_empty());
+        assert!(dir.join("fig-01.png").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // 単体テスト: ギャラリーの生成
+    #[test]
+    fn test_render_gallery() {
+        let figure = |text: Vec<&str>| Figure {
+            page: 3,
+            caption: Some("Figure 1: [draft] chart".to_string()),
+            link: "a_assets/fig-01.png".to_string(),
+            path: PathBuf::from("a_assets/fig-01.png"),
+            text: text.into_iter().map(String::from).collect(),
+            alt: None,
+        };
+
+        let test_cases = vec![
+            (
+                None,
+                "# report の図一覧\n\n## Figure 1: [draft] chart\n\n![Figure 1: draft chart](a_assets/fig-01.png)\n\n*p.3*\n\n",
+                "文字認識なし",
+            ),
+            (
+                Some(FigureText::Alt),
+                "# report の図一覧\n\n## Figure 1: [draft] chart\n\n![Figure 1: draft chart: Revenue; Q1 Q2](a_assets/fig-01.png)\n\n*p.3*\n\n",
+                "代替テキスト",
+            ),
+            (
+                Some(FigureText::List),
+                "# report の図一覧\n\n## Figure 1: [draft] chart\n\n![Figure 1: draft chart](a_assets/fig-01.png)\n\n図中の文字\n:   Revenue\n:   Q1 Q2\n\n*p.3*\n\n",
+                "定義リスト",
+            ),
+        ];
+
+        for (style, expected, desc) in test_cases {
+            assert_eq!(render_gallery("report", &[figure(vec!["Revenue", "Q1 Q2"])], style), expected, "Test failed: {}", desc);
+        }
+
+        // 生成した代替テキストはキャプションの代わりに使う
+        let generated = Figure { alt: Some("Bar chart of revenue".to_string()), ..figure(vec![]) };
+        assert!(render_gallery("report", &[generated], None).contains("![Bar chart of revenue](a_assets/fig-01.png)"));
+    }
+}
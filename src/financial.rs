@@ -0,0 +1,203 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::diagnostics::{Warning, WarningKind};
+use crate::layout::PageLayout;
+use crate::tables;
+
+/// 合計の検算で許す誤差（表示単位への丸めによるずれ）
+const TOLERANCE: f64 = 1.0;
+
+/// 各ページの表を Markdown の表に置き換え、行・列の合計を検算する
+///
+/// セルの文字は桁区切りの , や括弧の負数表記（(1,234)）を含めてそのまま出力する。
+/// 合計が一致しない箇所は警告として返す。
+pub fn convert_tables(pages: &mut [PageLayout]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    tables::replace_tables(pages, |page, region, rows| {
+        warnings.extend(verify_totals(rows).into_iter().map(|message| Warning {
+            page: page.number,
+            y: Some(region.y0),
+            kind: WarningKind::TotalMismatch,
+            message,
+            excerpt: None,
+        }));
+    });
+    warnings
+}
+
+/// 金額のセルを数値にする（桁区切り、通貨記号、括弧や △ の負数表記に対応し、単独の - は 0 とする）
+pub fn parse_amount(cell: &str) -> Option<f64> {
+    let text = cell.trim();
+    if text.is_empty() || text.contains('%') {
+        return None;
+    }
+    if matches!(text, "-" | "—" | "–" | "―") {
+        return Some(0.0);
+    }
+
+    let negative = (text.starts_with('(') && text.ends_with(')')) || text.starts_with(['△', '▲', '-', '−', '–']);
+    let digits: String = text
+        .chars()
+        .filter(|c| !matches!(c, '(' | ')' | '△' | '▲' | '-' | '−' | '–' | ',' | '¥' | '￥' | '$' | '€' | '£' | '円' | ' '))
+        .collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    // 極端に長い数字の並びは f64 で無限大になるため、金額とみなさない
+    let value: f64 = digits.parse().ok().filter(|value: &f64| value.is_finite())?;
+    Some(if negative { -value } else { value })
+}
+
+/// 合計・小計の行や列の見出し
+static TOTAL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^(?:grand\s+)?(?:total|sub-?total|net)\b|^(?:合計|小計|総計|計)").unwrap());
+
+/// 行・列の合計を検算し、一致しない箇所の説明を返す
+///
+/// 列の合計は「合計」「Total」などで始まる行について、前の合計の行からの各行の和か、
+/// それまでの小計の行の和と比べる。行の合計は、見出しの最後の列が合計の場合に各行の数値の和と比べる。
+fn verify_totals(rows: &[Vec<String>]) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    let Some(header) = rows.first() else {
+        return mismatches;
+    };
+    let columns = header.len();
+
+    // 列の合計
+    let mut section_start = 1;
+    let mut total_rows: Vec<usize> = Vec::new();
+    for (index, row) in rows.iter().enumerate().skip(1) {
+        if !TOTAL_REGEX.is_match(row[0].trim()) {
+            continue;
+        }
+        for column in 1..columns {
+            let Some(stated) = parse_amount(&row[column]) else {
+                continue;
+            };
+            let items: Vec<f64> = rows[section_start..index].iter().filter_map(|r| parse_amount(&r[column])).collect();
+            let subtotals: Vec<f64> = total_rows.iter().filter_map(|&i| parse_amount(&rows[i][column])).collect();
+            let candidates: Vec<f64> = [items, subtotals].iter().filter(|v| !v.is_empty()).map(|v| v.iter().sum()).collect();
+            // 和が f64 の範囲を超えた場合は検算しない
+            if let Some(&computed) = candidates.first().filter(|computed| computed.is_finite()) {
+                if candidates.iter().all(|sum| (sum - stated).abs() > TOLERANCE) {
+                    mismatches.push(format!(
+                        "表の合計が一致しません（行「{}」・列「{}」: 記載 {}、計算 {}）",
+                        row[0].trim(),
+                        header[column].trim(),
+                        row[column].trim(),
+                        format_amount(computed)
+                    ));
+                }
+            }
+        }
+        total_rows.push(index);
+        section_start = index + 1;
+    }
+
+    // 行の合計
+    if columns >= 3 && TOTAL_REGEX.is_match(header[columns - 1].trim()) {
+        for row in &rows[1..] {
+            let Some(stated) = parse_amount(&row[columns - 1]) else {
+                continue;
+            };
+            let values: Vec<f64> = row[1..columns - 1].iter().filter_map(|cell| parse_amount(cell)).collect();
+            let computed: f64 = values.iter().sum();
+            if values.len() >= 2 && computed.is_finite() && (computed - stated).abs() > TOLERANCE {
+                mismatches.push(format!(
+                    "表の合計が一致しません（行「{}」の横の合計: 記載 {}、計算 {}）",
+                    row[0].trim(),
+                    row[columns - 1].trim(),
+                    format_amount(computed)
+                ));
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// 検算した値を桁区切り付きで表示する（負数は括弧で囲む）
+fn format_amount(value: f64) -> String {
+    let rounded = format!("{:.2}", value.abs());
+    let (integer, fraction) = rounded.split_once('.').unwrap_or((&rounded, "00"));
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if fraction != "00" {
+        grouped = format!("{}.{}", grouped, fraction);
+    }
+    if value < 0.0 {
+        format!("({})", grouped)
+    } else {
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+    }
+
+    // 単体テスト: 金額の解釈
+    #[test]
+    fn test_parse_amount() {
+        let long_digits = "9".repeat(400);
+        let test_cases = vec![
+            ("1,234", Some(1234.0), "桁区切り"),
+            ("(567)", Some(-567.0), "括弧の負数"),
+            ("△1,000", Some(-1000.0), "△の負数"),
+            ("$12.50", Some(12.5), "通貨記号"),
+            ("-", Some(0.0), "ゼロの -"),
+            ("12%", None, "百分率"),
+            ("Revenue", None, "文字列"),
+            (&long_digits, None, "無限大になる長い数字"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(parse_amount(input), expected, "Test failed: {}", desc);
+        }
+
+        assert_eq!(format_amount(f64::INFINITY), "inf");
+    }
+
+    // 単体テスト: 行・列の合計の検算
+    #[test]
+    fn test_verify_totals() {
+        let consistent = rows(&[
+            &["Item", "2023", "2024", "Total"],
+            &["Sales", "1,000", "1,200", "2,200"],
+            &["Costs", "(400)", "(500)", "(900)"],
+            &["Total", "600", "700", "1,300"],
+        ]);
+        assert!(verify_totals(&consistent).is_empty());
+
+        let inconsistent = rows(&[
+            &["Item", "2023", "2024"],
+            &["Sales", "1,000", "1,200"],
+            &["Costs", "(400)", "(500)"],
+            &["Total", "600", "750"],
+        ]);
+        assert_eq!(
+            verify_totals(&inconsistent),
+            vec!["表の合計が一致しません（行「Total」・列「2024」: 記載 750、計算 700）"]
+        );
+
+        // 小計の行の和と比べる総計
+        let subtotals = rows(&[
+            &["Item", "Amount"],
+            &["A", "10"],
+            &["Subtotal", "10"],
+            &["B", "5"],
+            &["Subtotal", "5"],
+            &["Grand total", "15"],
+        ]);
+        assert!(verify_totals(&subtotals).is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::config::HeadingStyle;
+use crate::headings::{HeadingDetector, HeadingLine};
+use crate::layout::{self, Glyph, PageLayout};
+
+/// 同じフォントサイズとみなす差（ポイント）
+const SIZE_TOLERANCE: f64 = 0.5;
+
+/// 見出しとみなす、本文より大きいフォントサイズの倍率（設定ファイルの heading_scale の既定値）
+pub const MIN_HEADING_SCALE: f64 = 1.15;
+
+/// 見出しの候補とする、1行あたりの平均文字数の上限
+const MAX_HEADING_LINE_CHARS: f64 = 80.0;
+
+/// 見出しの候補とする、本文の文字数に対する文字数の割合の上限（本文と同じくらい使われる書式は見出しではない）
+const MAX_HEADING_CHAR_RATIO: f64 = 0.3;
+
+/// 提案する見出しレベルの数の上限
+const MAX_LEVELS: usize = 6;
+
+/// 規則を指定せずに推定した見出しに使うレベルの上限（それより小さい書式もこのレベルにする）
+const MAX_AUTOMATIC_LEVEL: usize = 3;
+
+/// 行の書式（フォントサイズを 0.5 ポイント単位に丸めたものと、太字かどうか）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontStyle {
+    half_points: u32,
+    pub bold: bool,
+}
+
+impl FontStyle {
+    fn of(glyph: &Glyph) -> Self {
+        FontStyle { half_points: (glyph.font_size * 2.0).round().max(0.0) as u32, bold: glyph.bold }
+    }
+
+    pub fn size(&self) -> f64 {
+        f64::from(self.half_points) / 2.0
+    }
+}
+
+/// 書式ごとの使われ方
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleStats {
+    pub style: FontStyle,
+    pub lines: usize,
+    pub chars: usize,
+    /// 最初に現れた行のテキスト
+    pub sample: String,
+}
+
+/// 行の主な書式（空白を除いて最も多くの文字に使われている書式）
+fn line_style(glyphs: &[Glyph]) -> Option<(FontStyle, usize)> {
+    let mut counts: HashMap<FontStyle, usize> = HashMap::new();
+    for glyph in glyphs {
+        *counts.entry(FontStyle::of(glyph)).or_default() += glyph.text.chars().filter(|c| !c.is_whitespace()).count();
+    }
+    let chars = counts.values().sum();
+    counts.into_iter().filter(|(_, count)| *count > 0).max_by_key(|(style, count)| (*count, style.half_points, style.bold)).map(|(style, _)| (style, chars))
+}
+
+/// ページの行ごとの (文字の範囲, 主な書式, 文字数)
+pub fn styled_lines(page: &PageLayout) -> Vec<(Range<usize>, FontStyle, usize)> {
+    layout::line_ranges(&page.glyphs)
+        .into_iter()
+        .filter_map(|range| {
+            let (style, chars) = line_style(&page.glyphs[range.clone()])?;
+            Some((range, style, chars))
+        })
+        .collect()
+}
+
+/// 全ページの行の書式の分布（文字数の多い順）
+pub fn style_distribution(pages: &[PageLayout]) -> Vec<StyleStats> {
+    let mut stats: Vec<StyleStats> = Vec::new();
+    for page in pages {
+        for (range, style, chars) in styled_lines(page) {
+            match stats.iter_mut().find(|stats| stats.style == style) {
+                Some(stats) => {
+                    stats.lines += 1;
+                    stats.chars += chars;
+                }
+                None => {
+                    let sample = layout::glyphs_to_text(&page.glyphs[range]).trim().to_string();
+                    stats.push(StyleStats { style, lines: 1, chars, sample });
+                }
+            }
+        }
+    }
+    stats.sort_by_key(|stats| std::cmp::Reverse(stats.chars));
+    stats
+}
+
+/// 書式の分布から見出しレベルの対応を推定する（最も文字数の多い書式を本文とし、それより大きいか、同じ大きさで太字の書式を見出しとする）
+///
+/// 大きい書式ほど上位のレベルにし、同じ大きさでは太字を上位にする。min_scale は見出しとみなす本文に対するフォントサイズの倍率の下限。
+pub fn propose_heading_styles(distribution: &[StyleStats], min_scale: f64) -> Vec<HeadingStyle> {
+    let Some(body) = distribution.first() else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<&StyleStats> = distribution[1..]
+        .iter()
+        .filter(|stats| {
+            let larger = stats.style.size() >= body.style.size() * min_scale;
+            let bolder = stats.style.bold && !body.style.bold && stats.style.size() >= body.style.size() - SIZE_TOLERANCE;
+            (larger || bolder)
+                && (stats.chars as f64 / stats.lines as f64) <= MAX_HEADING_LINE_CHARS
+                && (stats.chars as f64) <= body.chars as f64 * MAX_HEADING_CHAR_RATIO
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.style.half_points.cmp(&a.style.half_points).then(b.style.bold.cmp(&a.style.bold)));
+
+    // 同じ大きさの書式が太字とそうでないものに分かれている場合だけ、太さを規則に含める
+    candidates
+        .iter()
+        .take(MAX_LEVELS)
+        .enumerate()
+        .map(|(index, stats)| {
+            let same_size = distribution.iter().filter(|other| other.style.half_points == stats.style.half_points).count();
+            HeadingStyle { font_size: stats.style.size(), bold: (same_size > 1).then_some(stats.style.bold), level: index + 1 }
+        })
+        .collect()
+}
+
+/// 規則が指定されていない文書の、本文との相対的なフォントサイズと太さによる見出しの対応（#、##、### の3段階）
+pub fn automatic_heading_styles(pages: &[PageLayout], min_scale: f64) -> Vec<HeadingStyle> {
+    propose_heading_styles(&style_distribution(pages), min_scale)
+        .into_iter()
+        .map(|style| HeadingStyle { level: style.level.min(MAX_AUTOMATIC_LEVEL), ..style })
+        .collect()
+}
+
+/// フォントサイズと太字の規則（[[profiles.<名前>.heading_styles]]）による見出しの判定
+pub struct FontStyleDetector {
+    rules: Vec<HeadingStyle>,
+}
+
+impl FontStyleDetector {
+    pub fn new(rules: Vec<HeadingStyle>) -> Self {
+        FontStyleDetector { rules }
+    }
+}
+
+impl HeadingDetector for FontStyleDetector {
+    fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)> {
+        let (size, bold) = line.style?;
+        self.rules
+            .iter()
+            .find(|rule| (rule.font_size - size).abs() <= SIZE_TOLERANCE && rule.bold.is_none_or(|rule_bold| rule_bold == bold))
+            .map(|rule| (rule.level, line.text))
+    }
+}
+
+/// 推定した見出しの対応を、設定ファイルに追記できるプロファイルの定義にする
+pub fn render_profile(name: &str, source: &str, distribution: &[StyleStats], styles: &[HeadingStyle]) -> String {
+    let key = if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        name.to_string()
+    } else {
+        toml::Value::String(name.to_string()).to_string()
+    };
+    let body = distribution.first().map(|stats| format!("{}pt{}", stats.style.size(), if stats.style.bold { " 太字" } else { "" }));
+    let mut toml = format!(
+        "# pdf2md calibrate で {} から推定した見出しの対応（本文: {}）\n# pdf2md.toml に追記するか --config で指定し、--profile {} で使う\n\n[profiles.{}]\ndescription = {}\n",
+        source,
+        body.unwrap_or_else(|| "不明".to_string()),
+        name,
+        key,
+        toml::Value::String(format!("{} から推定した見出しの対応", source))
+    );
+    for style in styles {
+        if let Some(stats) = distribution.iter().find(|stats| (stats.style.size() - style.font_size).abs() < 0.01 && style.bold.is_none_or(|bold| bold == stats.style.bold)) {
+            toml.push_str(&format!("\n# {} 行（例: {}）\n", stats.lines, stats.sample.chars().take(40).collect::<String>()));
+        }
+        toml.push_str(&format!("[[profiles.{}.heading_styles]]\nfont_size = {:.1}\n", key, style.font_size));
+        if let Some(bold) = style.bold {
+            toml.push_str(&format!("bold = {}\n", bold));
+        }
+        toml.push_str(&format!("level = {}\n", style.level));
+    }
+    toml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn line(text: &str, y: f64, font_size: f64, bold: bool) -> Vec<Glyph> {
+        let mut x = 72.0;
+        text.split(' ')
+            .map(|word| {
+                let glyph = Glyph { text: word.to_string(), x, y, width: word.len() as f64 * font_size * 0.5, font_size, word_start: true, order: 0, color: None, bold, monospace: false, confidence: None };
+                x += glyph.width + font_size * 0.3;
+                glyph
+            })
+            .collect()
+    }
+
+    fn page() -> PageLayout {
+        let mut glyphs = Vec::new();
+        glyphs.extend(line("Chapter 1 Basics", 80.0, 20.0, true));
+        glyphs.extend(line("of Conversion", 104.0, 20.0, true));
+        let mut y = 140.0;
+        for i in 0..8 {
+            glyphs.extend(line("Body text that runs along the page for a while.", y, 10.0, false));
+            y += 14.0;
+            if i == 3 {
+                glyphs.extend(line("1.1 Scope", y, 12.0, true));
+                y += 16.0;
+                glyphs.extend(line("Caption", y, 12.0, false));
+                y += 16.0;
+            }
+        }
+        PageLayout { number: 1, glyphs, ..Default::default() }
+    }
+
+    // 単体テスト: 見出しの対応の推定
+    #[test]
+    fn test_propose_heading_styles() {
+        let distribution = style_distribution(&[page()]);
+        assert_eq!((distribution[0].style.size(), distribution[0].lines), (10.0, 8));
+
+        let styles = propose_heading_styles(&distribution, MIN_HEADING_SCALE);
+        let proposed: Vec<(f64, Option<bool>, usize)> = styles.iter().map(|style| (style.font_size, style.bold, style.level)).collect();
+        assert_eq!(proposed, vec![(20.0, None, 1), (12.0, Some(true), 2), (12.0, Some(false), 3)]);
+
+        // 出力した定義は設定ファイルとして読み込める
+        let toml = render_profile("manual", "manual.pdf", &distribution, &styles);
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(config.profiles["manual"].heading_styles.len(), 3);
+
+        // 規則を指定しない場合も同じ対応を使い、4段階目以降は ### にする
+        let mut document = page();
+        document.glyphs.extend(line("Note", 400.0, 11.0, true));
+        let levels: Vec<usize> = automatic_heading_styles(&[document], MIN_HEADING_SCALE).iter().map(|style| style.level).collect();
+        assert_eq!(levels, vec![1, 2, 3, 3]);
+    }
+
+    // 単体テスト: フォントサイズによる見出し
+    #[test]
+    fn test_font_style_detector() {
+        let detector = FontStyleDetector::new(vec![
+            HeadingStyle { font_size: 20.0, bold: None, level: 1 },
+            HeadingStyle { font_size: 12.0, bold: Some(true), level: 2 },
+        ]);
+        let detectors: Vec<Box<dyn HeadingDetector>> = vec![Box::new(detector)];
+        let mut pages = vec![page()];
+        assert_eq!(crate::headings::apply_detectors(&mut pages, &detectors), 2);
+        let headings: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).filter(|text| text.contains('#')).collect();
+        assert_eq!(headings, vec!["\n\n# Chapter 1 Basics of Conversion\n\n", "\n\n## 1.1 Scope\n\n"]);
+    }
+}
@@ -0,0 +1,126 @@
+/// Markdown の先頭に付与する YAML フロントマター
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+    /// title: に出力する題名
+    pub title: Option<String>,
+    /// author: に出力する作成者
+    pub author: Option<String>,
+    /// description: に出力する文書の主題（静的サイトジェネレーターで概要として使われる名前にする）
+    pub description: Option<String>,
+    /// tags: に出力するタグ一覧
+    pub tags: Vec<String>,
+    /// created: に出力する作成日時（ISO 8601）
+    pub created: Option<String>,
+    /// modified: に出力する更新日時（ISO 8601）
+    pub modified: Option<String>,
+    /// その他のフィールド（名前と値。modified: の後、tags: の前に出力する）
+    pub fields: Vec<(String, String)>,
+}
+
+impl FrontMatter {
+    /// タグを重複なしで追加する
+    pub fn add_tags<I: IntoIterator<Item = String>>(&mut self, tags: I) {
+        for tag in tags {
+            let tag = tag.trim().to_string();
+            if !tag.is_empty() && !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
+    /// YAML フロントマターの文字列を生成する
+    pub fn render(&self) -> String {
+        let mut yaml = String::from("---\n");
+
+        let scalars = [("title", &self.title), ("author", &self.author), ("description", &self.description)];
+        for (name, value) in scalars.iter().filter_map(|(name, value)| Some((name, value.as_ref()?))) {
+            yaml.push_str(&format!("{}: {}\n", name, yaml_string(value)));
+        }
+        if let Some(created) = &self.created {
+            yaml.push_str(&format!("created: {}\n", yaml_string(created)));
+        }
+        if let Some(modified) = &self.modified {
+            yaml.push_str(&format!("modified: {}\n", yaml_string(modified)));
+        }
+        for (name, value) in &self.fields {
+            yaml.push_str(&format!("{}: {}\n", name, yaml_string(value)));
+        }
+
+        if !self.tags.is_empty() {
+            yaml.push_str("tags:\n");
+            for tag in &self.tags {
+                yaml.push_str(&format!("  - {}\n", yaml_string(tag)));
+            }
+        }
+
+        yaml.push_str("---\n\n");
+        yaml
+    }
+}
+
+/// YAML のスカラー値として安全な形に変換する（常にダブルクォートで囲む）
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            // その他の制御文字はエスケープして YAML の破損を防ぐ
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: タグ付きフロントマターの生成
+    #[test]
+    fn test_render_tags() {
+        let mut front_matter = FrontMatter::default();
+        front_matter.add_tags(vec!["rust".to_string(), "say \"hi\"".to_string(), "rust".to_string()]);
+
+        assert_eq!(
+            front_matter.render(),
+            "---\ntags:\n  - \"rust\"\n  - \"say \\\"hi\\\"\"\n---\n\n"
+        );
+    }
+
+    // 単体テスト: 日付フィールドの出力
+    #[test]
+    fn test_render_dates() {
+        let front_matter = FrontMatter {
+            created: Some("2024-01-15T12:30:00+09:00".to_string()),
+            modified: Some("2024-02-01".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            front_matter.render(),
+            "---\ncreated: \"2024-01-15T12:30:00+09:00\"\nmodified: \"2024-02-01\"\n---\n\n"
+        );
+    }
+
+    // 単体テスト: 文書情報の題名・作成者・主題の出力
+    #[test]
+    fn test_render_document_info() {
+        let front_matter = FrontMatter {
+            title: Some("Annual Report: 2024".to_string()),
+            author: Some("Takao H.".to_string()),
+            description: Some("Results by region".to_string()),
+            created: Some("2024-01-15".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            front_matter.render(),
+            "---\ntitle: \"Annual Report: 2024\"\nauthor: \"Takao H.\"\ndescription: \"Results by region\"\ncreated: \"2024-01-15\"\n---\n\n"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Converter;
+
+/// 任意のバイト列を変換する、ファジングの入口（fuzz/fuzz_targets/convert_bytes.rs から呼ぶ）
+///
+/// 信頼できない PDF と同じ上限で変換し、エラーは無視する。どんな入力でもパニックせず、上限に見合う時間とメモリで戻ることを確かめる。
+pub fn fuzz_convert(data: &[u8]) {
+    let _ = Converter::robust().convert_bytes(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pdf::PdfBuilder;
+    use proptest::prelude::*;
+    use std::time::{Duration, Instant};
+
+    /// 1つの入力の変換にかかる時間の上限（遅い環境でも超えない程度に余裕を持たせる）
+    const MAX_ELAPSED: Duration = Duration::from_secs(10);
+
+    fn sample_pdf() -> Vec<u8> {
+        PdfBuilder::new()
+            .heading(1, "Report")
+            .paragraph(&["The first paragraph of the report, long enough to be", "treated as the body text of the document."])
+            .table(&[&["Item", "Qty"], &["Paper", "10"]])
+            .code(&["fn main() {}"])
+            .build()
+    }
+
+    fn assert_converts(data: &[u8]) {
+        let start = Instant::now();
+        fuzz_convert(data);
+        assert!(start.elapsed() < MAX_ELAPSED, "変換に {:?} かかりました", start.elapsed());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig { cases: 256, failure_persistence: None, ..ProptestConfig::default() })]
+
+        // 単体テスト: 任意のバイト列と、PDF のヘッダーで始まる任意のバイト列の変換
+        #[test]
+        fn test_fuzz_arbitrary(data in proptest::collection::vec(any::<u8>(), 0..2048)) {
+            assert_converts(&data);
+            assert_converts(&[b"%PDF-1.5\n".as_slice(), &data].concat());
+        }
+
+        // 単体テスト: 一部を書き換えたり、途中で切ったりした PDF の変換
+        #[test]
+        fn test_fuzz_mutated(edits in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..16), cut in any::<prop::sample::Index>()) {
+            let pdf = sample_pdf();
+            let mut mutated = pdf.clone();
+            for (index, byte) in edits {
+                mutated[index.index(pdf.len())] = byte;
+            }
+            assert_converts(&mutated);
+            assert_converts(&pdf[..cut.index(pdf.len())]);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use lopdf::Document;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::console;
+use crate::images;
+use crate::layout::{Glyph, PageLayout};
+use crate::memory::SpilledImages;
+use crate::metadata;
+
+/// 図のページとみなす最大の文字数（これより多い場合は文章のページとして扱う）
+const MAX_TEXT_CHARS: usize = 40;
+
+/// 図のページとみなす、ページに占める画像の面積の割合
+const MIN_IMAGE_RATIO: f64 = 0.5;
+
+/// 図のページとみなす、ベクター図形のパスを構築する命令の数
+const MIN_PATH_OPS: usize = 300;
+
+/// ページ画像を書き出すときの解像度（dpi）
+const PAGE_IMAGE_DPI: u32 = 150;
+
+/// 図のページ（楽譜・回路図・地図など）の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GraphicalPages {
+    /// ページ全体の画像を書き出し、ページラベル付きの画像リンクにする
+    #[default]
+    Image,
+    /// 他のページと同じくテキストとして変換する
+    Text,
+}
+
+/// 文字がわずかで、画像かベクター図形がページの大半を占めるページかどうか
+pub fn is_graphical(page: &PageLayout) -> bool {
+    let chars: usize = page.glyphs.iter().map(|glyph| glyph.text.chars().filter(|c| !c.is_whitespace()).count()).sum();
+    if chars > MAX_TEXT_CHARS {
+        return false;
+    }
+
+    let page_area = page.width * page.height;
+    let image_area: f64 = page.images.iter().map(|image| (image.x1 - image.x0) * (image.y1 - image.y0)).sum();
+    (page_area > 0.0 && image_area / page_area >= MIN_IMAGE_RATIO) || page.path_ops >= MIN_PATH_OPS
+}
+
+/// 図のページの文字（拾えたわずかな文字）を、ページ全体の画像へのリンクに置き換え、置き換えたページ数を返す
+///
+/// ページの大半を占める画像があればその画像を、無ければ pdftoppm でページを画像にしたものを
+/// assets_dir に page-001.png のような名前で書き出す。画像を用意できなかったページには目印を入れる。spilled は一時ファイルに移した画像。
+pub fn replace_graphical_pages(
+    doc: &Document,
+    spilled: Option<&SpilledImages>,
+    pdf_path: &Path,
+    pages: &mut [PageLayout],
+    assets_dir: &Path,
+    link_dir: &str,
+) -> Result<usize> {
+    let labels = metadata::page_labels(doc);
+    let mut replaced = 0;
+
+    for page in pages.iter_mut().filter(|page| is_graphical(page)) {
+        let label = labels.get(&page.number).cloned().unwrap_or_else(|| page.number.to_string());
+        let text = match export_page_image(doc, spilled, pdf_path, page, assets_dir) {
+            Ok(path) => {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                format!("![Page {}]({}/{})", label.replace(['[', ']'], ""), link_dir, file_name)
+            }
+            Err(e) => {
+                console!(Warn, "ページ {} の画像を書き出せませんでした: {:#}", page.number, e);
+                format!("[[graphical page, p.{}]]", label)
+            }
+        };
+
+        let font_size = page.glyphs.first().map_or(10.0, |glyph| glyph.font_size);
+        page.glyphs = vec![Glyph { text: format!("\n\n{}\n\n", text), x: 0.0, y: 0.0, width: 0.0, font_size, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None }];
+        // 書き出した画像は出力されない図の警告の対象にしない
+        page.images.clear();
+        replaced += 1;
+    }
+
+    Ok(replaced)
+}
+
+/// 全ページの画像の書き出し方（--page-images）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageImages {
+    /// 画像の解像度（dpi）
+    pub dpi: u32,
+    /// 各ページの先頭に画像へのリンクを入れるかどうか
+    pub link: bool,
+    /// 画像を書き出すディレクトリと、Markdown から見た相対パス
+    pub assets_dir: PathBuf,
+    pub link_dir: String,
+}
+
+/// 各ページを assets_dir/pages に page-001.png のような名前の画像にし、書き出したページ数を返す
+///
+/// link が真の場合は各ページの先頭に画像へのリンクを入れる（図のページとして画像に置き換えたページには入れない）。
+pub fn export_page_images(doc: &Document, pdf_path: &Path, pages: &mut [PageLayout], options: &PageImages) -> Result<usize> {
+    let labels = metadata::page_labels(doc);
+    let dir = options.assets_dir.join("pages");
+
+    for page in pages.iter_mut() {
+        let path = rasterize_page(pdf_path, page.number, options.dpi, &dir.join(format!("page-{:03}.png", page.number)))?;
+        let replaced = matches!(page.glyphs.as_slice(), [glyph] if is_image_line(glyph.text.trim()));
+        if !options.link || replaced {
+            continue;
+        }
+        let label = labels.get(&page.number).cloned().unwrap_or_else(|| page.number.to_string());
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let text = format!("\n\n![Page {}]({}/pages/{})\n\n", label.replace(['[', ']'], ""), options.link_dir, file_name);
+        let (font_size, order) = page.glyphs.first().map_or((10.0, 0), |glyph| (glyph.font_size, glyph.order));
+        page.glyphs.insert(0, Glyph { text, x: 0.0, y: 0.0, width: 0.0, font_size, word_start: true, order, color: None, bold: false, monospace: false, confidence: None });
+    }
+
+    Ok(pages.len())
+}
+
+/// 図のページの画像を書き出す（ページの大半を占める画像が書き出せなければページ全体を画像にする）
+fn export_page_image(doc: &Document, spilled: Option<&SpilledImages>, pdf_path: &Path, page: &PageLayout, assets_dir: &Path) -> Result<PathBuf> {
+    let stem = format!("page-{:03}", page.number);
+    let page_area = page.width * page.height;
+    let dominant = page
+        .images
+        .iter()
+        .find(|image| page_area > 0.0 && (image.x1 - image.x0) * (image.y1 - image.y0) / page_area >= MIN_IMAGE_RATIO);
+
+    if let Some(image) = dominant {
+        // JBIG2 などデコードできない形式はページ全体の画像にする
+        if let Ok(decoded) = images::decode_image(doc, image.id, spilled) {
+            return images::save_image_as(&decoded, assets_dir, &stem);
+        }
+    }
+    rasterize_page(pdf_path, page.number, PAGE_IMAGE_DPI, &assets_dir.join(format!("{}.png", stem)))
+}
+
+/// pdftoppm（poppler-utils）でページを PNG にする
+pub fn rasterize_page(pdf_path: &Path, page: u32, dpi: u32, output: &Path) -> Result<PathBuf> {
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("画像の出力先ディレクトリを作成できません: {:?}", dir))?;
+    }
+    // -singlefile を付けると、出力のファイル名は「接頭辞.png」になる
+    let prefix = output.with_extension("");
+    let status = Command::new("pdftoppm")
+        .args(["-png", "-singlefile", "-r", &dpi.to_string(), "-f", &page.to_string(), "-l", &page.to_string()])
+        .arg(pdf_path)
+        .arg(&prefix)
+        .status()
+        .context("pdftoppm を実行できません（poppler-utils をインストールしてください）")?;
+    if !status.success() {
+        bail!("pdftoppm がページ {} の画像化に失敗しました（{}）", page, status);
+    }
+    Ok(prefix.with_extension("png"))
+}
+
+/// 8 ビットの画素に展開したページ画像
+#[cfg(any(feature = "ocr", feature = "onnx"))]
+pub struct Raster {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// 画素の色の種類（Grayscale、Rgb、Rgba など。1画素のバイト数は samples() で分かる）
+    pub color: png::ColorType,
+}
+
+/// rasterize_page で書き出した PNG の画像を、8 ビットの画素に展開して読み込む
+#[cfg(any(feature = "ocr", feature = "onnx"))]
+pub fn read_png(path: &Path) -> Result<Raster> {
+    let mut decoder = png::Decoder::new(fs::File::open(path).with_context(|| format!("ページ画像を読み込めません: {:?}", path))?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().with_context(|| format!("ページ画像を読み込めません: {:?}", path))?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).with_context(|| format!("ページ画像を読み込めません: {:?}", path))?;
+    data.truncate(info.buffer_size());
+    Ok(Raster { data, width: info.width, height: info.height, color: reader.output_color_type().0 })
+}
+
+/// 行が図のページの画像リンクかどうか
+pub fn is_image_line(line: &str) -> bool {
+    line.starts_with("![") && line.ends_with(')')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::ImagePlacement;
+
+    // 単体テスト: 図のページの判定
+    #[test]
+    fn test_is_graphical() {
+        let glyph = |text: &str| Glyph { text: text.to_string(), x: 50.0, y: 50.0, width: 30.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let page = |glyphs: Vec<Glyph>, images: Vec<ImagePlacement>, path_ops: usize| PageLayout {
+            number: 1,
+            width: 600.0,
+            height: 800.0,
+            glyphs,
+            images,
+            path_ops,
+            ..Default::default()
+        };
+        let full_page = ImagePlacement { id: (1, 0), x0: 0.0, y0: 0.0, x1: 600.0, y1: 700.0 };
+        let prose = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod";
+
+        let test_cases = vec![
+            (page(vec![glyph("Allegro")], vec![], 2000), true, "ベクターの楽譜"),
+            (page(vec![glyph("N")], vec![full_page.clone()], 0), true, "ページ全体の地図の画像"),
+            (page(vec![glyph(prose)], vec![full_page.clone()], 2000), false, "文章のあるページ"),
+            (page(vec![glyph("Note")], vec![], 10), false, "図の無いページ"),
+        ];
+
+        for (page, expected, desc) in test_cases {
+            assert_eq!(is_graphical(&page), expected, "Test failed: {}", desc);
+        }
+    }
+}
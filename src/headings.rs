@@ -0,0 +1,175 @@
+use regex::RegexSet;
+use std::ops::Range;
+
+use crate::config::HeadingRule;
+use crate::destinations::Bookmark;
+use crate::diagnostics;
+use crate::font_styles;
+use crate::graphics;
+use crate::layout::{self, Glyph, PageLayout};
+
+/// 見出しとして扱う行の文字数の上限
+const MAX_HEADING_CHARS: usize = 200;
+
+/// 見出しかどうかを判定する行
+#[derive(Debug, Clone, Copy)]
+pub struct HeadingLine<'a> {
+    /// 前後の空白を除いた行のテキスト
+    pub text: &'a str,
+    /// 行のページ番号（Markdown に変換する段階の行では None）
+    pub page: Option<u32>,
+    /// 行の主なフォントサイズと、太字かどうか（Markdown に変換する段階の行では None）
+    pub style: Option<(f64, bool)>,
+}
+
+/// 見出しの判定方法
+///
+/// 抽出した行（ページ番号とフォントの情報がある）と、Markdown に変換する段階の行（テキストのみ）の両方に使う。
+/// 判定方法は順に試し、最初に見出しと判定したものを使う。いずれも見出しと判定しない行には汎用の判定を行う。
+/// 変換処理（Converter）をスレッド間で共有できるように、判定方法も Send + Sync とする。
+pub trait HeadingDetector: Send + Sync {
+    /// 見出しであれば、見出しレベル（1〜6）と見出しのテキストを返す
+    fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)>;
+}
+
+/// 正規表現の規則（[[profiles.<名前>.headings]]）による判定
+///
+/// 規則の正規表現は設定ファイルの読み込み時にコンパイルし、判定方法の作成時にまとめて1つの RegexSet にして、
+/// 行ごとにすべての規則を一度に照合する。
+pub struct RegexDetector {
+    rules: Vec<HeadingRule>,
+    /// すべての規則の正規表現（規則が多すぎてまとめられない場合は None として、規則を順に照合する）
+    set: Option<RegexSet>,
+}
+
+impl RegexDetector {
+    pub fn new(rules: Vec<HeadingRule>) -> Self {
+        let set = RegexSet::new(rules.iter().map(|rule| rule.pattern.as_str())).ok();
+        RegexDetector { rules, set }
+    }
+}
+
+impl HeadingDetector for RegexDetector {
+    fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)> {
+        // 複数の規則に一致する場合は、先に書いた規則を使う
+        let rule = match &self.set {
+            Some(set) => set.matches(line.text).iter().next().map(|index| &self.rules[index]),
+            None => self.rules.iter().find(|rule| rule.pattern.is_match(line.text)),
+        };
+        rule.map(|rule| (rule.level, line.text))
+    }
+}
+
+/// PDF のしおりによる判定（しおりの項目の題名と同じ行を、しおりの階層のレベルの見出しにする）
+pub struct OutlineDetector {
+    /// (正規化した題名, レベル, ページ)
+    titles: Vec<(String, usize, Option<u32>)>,
+}
+
+impl OutlineDetector {
+    pub fn new(bookmarks: &[Bookmark]) -> Self {
+        let titles = bookmarks
+            .iter()
+            .map(|bookmark| (normalize(&bookmark.title), bookmark.level.min(6), bookmark.page))
+            .filter(|(title, _, _)| !title.is_empty())
+            .collect();
+        OutlineDetector { titles }
+    }
+}
+
+impl HeadingDetector for OutlineDetector {
+    fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)> {
+        let text = normalize(line.text);
+        // ページが分かる場合は、しおりの移動先のページの行に限る
+        self.titles
+            .iter()
+            .find(|(title, _, page)| *title == text && (line.page.is_none() || page.is_none() || *page == line.page))
+            .map(|(_, level, _)| (*level, line.text))
+    }
+}
+
+/// 比較のために、先頭の節番号（「1.」「2.3」など）を除き、空白をまとめて小文字にする
+fn normalize(text: &str) -> String {
+    let text = text.trim_start();
+    let numbered = text.split_once(char::is_whitespace).filter(|(number, _)| number.chars().all(|c| c.is_ascii_digit() || c == '.'));
+    let text = numbered.map_or(text, |(_, rest)| rest);
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// 抽出した各ページの行に判定方法を適用し、見出しと判定した行を Markdown の見出しにして、見出しにした数を返す
+///
+/// 同じレベルと判定した行が行間を空けずに続く場合は、複数行にわたる1つの見出しとしてまとめる。
+pub fn apply_detectors(pages: &mut [PageLayout], detectors: &[Box<dyn HeadingDetector>]) -> usize {
+    if detectors.is_empty() {
+        return 0;
+    }
+    let mut applied = 0;
+
+    for page in pages.iter_mut() {
+        // 見出しにする (文字の範囲, レベル, テキスト)
+        let mut headings: Vec<(Range<usize>, usize, String)> = Vec::new();
+        let mut last_y = f64::NEG_INFINITY;
+        for (range, style, chars) in font_styles::styled_lines(page) {
+            let text = layout::glyphs_to_text(&page.glyphs[range.clone()]);
+            let line = HeadingLine { text: text.trim(), page: Some(page.number), style: Some((style.size(), style.bold)) };
+            // 画像へのリンクや目印を入れた行は見出しにしない
+            let passthrough = graphics::is_image_line(line.text) || diagnostics::is_placeholder(line.text);
+            let detected = detectors.iter().find_map(|detector| detector.detect(&line)).filter(|_| chars <= MAX_HEADING_CHARS && !passthrough);
+            let y = page.glyphs[range.start].y;
+            match (detected, headings.last_mut()) {
+                (Some((level, text)), Some((last, last_level, last_text)))
+                    if *last_level == level
+                        && page.glyphs[last.end..range.start].iter().all(|glyph| glyph.text.trim().is_empty())
+                        && y - last_y <= style.size() * 1.6 =>
+                {
+                    last.end = range.end;
+                    last_text.push(' ');
+                    last_text.push_str(text);
+                }
+                (Some((level, text)), _) => headings.push((range, level, text.to_string())),
+                _ => {}
+            }
+            last_y = y;
+        }
+
+        for (range, level, text) in headings.into_iter().rev() {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let first = page.glyphs[range.start].clone();
+            tracing::trace!(page = page.number, level, text = %text, "見出しにしました");
+            let heading = Glyph { text: format!("\n\n{} {}\n\n", "#".repeat(level), text), width: 0.0, word_start: true, color: None, bold: false, monospace: false, ..first };
+            page.glyphs.splice(range, [heading]);
+            applied += 1;
+        }
+    }
+
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    // 単体テスト: 見出しの判定方法
+    #[test]
+    fn test_detectors() {
+        let bookmark = |level: usize, title: &str, page: Option<u32>| Bookmark { level, title: title.to_string(), page };
+        let outline = OutlineDetector::new(&[bookmark(1, "Getting  Started", Some(1)), bookmark(2, "Install", Some(2))]);
+        let regex = RegexDetector::new(vec![HeadingRule { pattern: Regex::new(r"^Article \d+").unwrap(), level: 2 }]);
+        let line = |text: &'static str, page: Option<u32>| HeadingLine { text, page, style: None };
+
+        let test_cases = vec![
+            (line("getting started", Some(1)), Some(1), "しおりの題名（大文字小文字と空白の違いは無視）"),
+            (line("2.1 Install", Some(2)), Some(2), "節番号の有無の違いは無視"),
+            (line("Install", Some(5)), None, "しおりの移動先と違うページ"),
+            (line("Install", None), Some(2), "ページの分からない行"),
+            (line("Article 3 Payment", None), Some(2), "正規表現の規則"),
+            (line("Body text.", Some(1)), None, "見出しではない行"),
+        ];
+        let detectors: Vec<Box<dyn HeadingDetector>> = vec![Box::new(outline), Box::new(regex)];
+        for (line, expected, desc) in test_cases {
+            let level = detectors.iter().find_map(|detector| detector.detect(&line)).map(|(level, _)| level);
+            assert_eq!(level, expected, "Test failed: {}", desc);
+        }
+    }
+}
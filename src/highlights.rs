@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::annotations::Area;
+use crate::layout::{self, FilledRect, PageLayout};
+
+/// ハイライトとみなす塗りつぶしの最大の高さ（覆う文字のフォントサイズに対する倍率）
+const MAX_HEIGHT_RATIO: f64 = 2.5;
+
+/// 文字がハイライトの範囲内かどうかを判定するときの余裕（ポイント）
+const MARGIN: f64 = 1.0;
+
+/// ハイライトされた文字の出力方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// ==文字== で囲む
+    Mark,
+    /// <mark>文字</mark> で囲む
+    Html,
+    /// 本文はそのままにし、文書末尾にハイライトの一覧を出力する
+    Summary,
+}
+
+/// ハイライトの範囲（注釈のハイライトには、付けられたメモがある場合がある）
+struct Marker {
+    area: Area,
+    note: Option<String>,
+    /// 塗りつぶしによるハイライトの描画順（この後に描かれた文字のみを覆う）
+    order: Option<usize>,
+}
+
+/// ハイライト注釈か、文字の背後の蛍光ペンの色の塗りつぶしで覆われた文字に書式を付け、文書末尾に追加する Markdown を返す
+///
+/// 末尾に追加するのは HighlightStyle::Summary の場合のハイライトの一覧のみ。
+pub fn apply_highlights(pages: &mut [PageLayout], style: HighlightStyle) -> String {
+    let mut summary = Vec::new();
+
+    for page in pages.iter_mut() {
+        let markers = page_markers(page);
+        if markers.is_empty() {
+            continue;
+        }
+
+        // 文字ごとに覆っているハイライト
+        let covering: Vec<Option<usize>> = page
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                if glyph.text.trim().is_empty() {
+                    return None;
+                }
+                let (x, y) = (glyph.x + glyph.width / 2.0, glyph.y - glyph.font_size * 0.3);
+                markers.iter().position(|marker| {
+                    marker.area.contains(x, y, MARGIN)
+                        && marker.order.is_none_or(|order| {
+                            order < glyph.order && marker.area.y1 - marker.area.y0 <= glyph.font_size * MAX_HEIGHT_RATIO
+                        })
+                })
+            })
+            .collect();
+
+        for run in highlighted_runs(&page.glyphs, &covering) {
+            let glyphs = &mut page.glyphs[run.clone()];
+            match style {
+                HighlightStyle::Mark | HighlightStyle::Html => {
+                    let (prefix, suffix) = if style == HighlightStyle::Mark { ("==", "==") } else { ("<mark>", "</mark>") };
+                    for line in layout::line_ranges(glyphs) {
+                        glyphs[line.start].text.insert_str(0, prefix);
+                        glyphs[line.end - 1].text.push_str(suffix);
+                    }
+                }
+                HighlightStyle::Summary => {
+                    let text = layout::glyphs_to_text(&*glyphs).split_whitespace().collect::<Vec<_>>().join(" ");
+                    let mut notes: Vec<&str> = Vec::new();
+                    for note in covering[run].iter().flatten().filter_map(|&index| markers[index].note.as_deref()) {
+                        if !notes.contains(&note) {
+                            notes.push(note);
+                        }
+                    }
+                    let note = if notes.is_empty() { String::new() } else { format!("（メモ: {}）", notes.join(" / ")) };
+                    summary.push(format!("- p.{}: {}{}", page.number, text, note));
+                }
+            }
+        }
+    }
+
+    if summary.is_empty() {
+        return String::new();
+    }
+    format!("## ハイライト\n\n{}\n", summary.join("\n"))
+}
+
+/// ページのハイライト注釈と、蛍光ペンの色の塗りつぶし
+fn page_markers(page: &PageLayout) -> Vec<Marker> {
+    let annotations = page
+        .annotations
+        .iter()
+        .filter(|annotation| annotation.subtype == "Highlight")
+        .flat_map(|annotation| annotation.areas.iter().map(|&area| Marker { area, note: annotation.contents.clone(), order: None }));
+    let fills = page.fills.iter().filter(|fill| is_highlighter(fill)).map(|fill| Marker {
+        area: Area { x0: fill.x0, y0: fill.y0, x1: fill.x1, y1: fill.y1 },
+        note: None,
+        order: Some(fill.order),
+    });
+    annotations.chain(fills).collect()
+}
+
+/// 明るく鮮やかな色（黄・緑・桃・水色など）の塗りつぶしかどうか（表の地の薄い灰色などは除く）
+fn is_highlighter(fill: &FilledRect) -> bool {
+    fill.color.is_some_and(|(r, g, b)| {
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        max - min >= 0.3 && max >= 0.7 && 0.2126 * r + 0.7152 * g + 0.0722 * b >= 0.5
+    })
+}
+
+/// ハイライトで覆われた文字の連続する範囲（間の空白を含み、前後の空白は含めない）
+///
+/// 蛍光ペンは語の途中から引かれることが多いため、範囲は語の切れ目まで広げる。
+pub fn highlighted_runs(glyphs: &[layout::Glyph], covering: &[Option<usize>]) -> Vec<std::ops::Range<usize>> {
+    let mut runs = Vec::new();
+    let mut current: Option<std::ops::Range<usize>> = None;
+
+    for (index, glyph) in glyphs.iter().enumerate() {
+        if covering[index].is_some() {
+            current = Some(current.map_or(index..index + 1, |run| run.start..index + 1));
+        } else if !glyph.text.trim().is_empty() {
+            runs.extend(current.take());
+        }
+    }
+    runs.extend(current);
+
+    // 隣の文字と空白を挟まずに続いている（同じ語の）場合
+    let joined = |a: &layout::Glyph, b: &layout::Glyph| {
+        !a.text.trim().is_empty()
+            && !b.text.trim().is_empty()
+            && (a.y - b.y).abs() <= b.font_size * 0.5
+            && b.x <= a.x + a.width + b.font_size * 0.1
+    };
+    let mut snapped: Vec<std::ops::Range<usize>> = Vec::new();
+    for mut run in runs {
+        while run.start > 0 && joined(&glyphs[run.start - 1], &glyphs[run.start]) {
+            run.start -= 1;
+        }
+        while run.end < glyphs.len() && joined(&glyphs[run.end - 1], &glyphs[run.end]) {
+            run.end += 1;
+        }
+        // 広げた結果つながった範囲はまとめる
+        match snapped.last_mut() {
+            Some(last) if last.end >= run.start => last.end = last.end.max(run.end),
+            _ => snapped.push(run),
+        }
+    }
+    snapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::Annotation;
+    use crate::layout::Glyph;
+
+    fn word(text: &str, x: f64, y: f64, order: usize) -> Glyph {
+        Glyph { text: text.to_string(), x, y, width: text.len() as f64 * 5.0, font_size: 10.0, word_start: true, order, color: None, bold: false, monospace: false, confidence: None }
+    }
+
+    fn page() -> PageLayout {
+        let yellow = FilledRect { x0: 70.0, y0: 90.0, x1: 160.0, y1: 103.0, color: Some((1.0, 1.0, 0.0)), order: 0 };
+        let gray = FilledRect { x0: 0.0, y0: 110.0, x1: 600.0, y1: 125.0, color: Some((0.9, 0.9, 0.9)), order: 0 };
+        let note = Annotation {
+            id: None,
+            subtype: "Highlight".to_string(),
+            areas: vec![Area { x0: 100.0, y0: 110.0, x1: 140.0, y1: 123.0 }],
+            contents: Some("check this".to_string()),
+            author: None,
+            modified: None,
+            in_reply_to: None,
+            target: None,
+            uri: None,
+        };
+        PageLayout {
+            number: 2,
+            glyphs: vec![
+                word("Key", 72.0, 100.0, 1),
+                word("finding", 100.0, 100.0, 2),
+                word("here.", 170.0, 100.0, 3),
+                word("Table", 72.0, 120.0, 4),
+                word("result", 105.0, 120.0, 5),
+            ],
+            fills: vec![yellow, gray],
+            annotations: vec![note],
+            ..Default::default()
+        }
+    }
+
+    // 単体テスト: ハイライトの書式
+    #[test]
+    fn test_apply_highlights() {
+        let mut pages = vec![page()];
+        assert_eq!(apply_highlights(&mut pages, HighlightStyle::Mark), "");
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        assert_eq!(texts, vec!["==Key", "finding==", "here.", "Table", "==result=="]);
+
+        let mut pages = vec![page()];
+        assert_eq!(
+            apply_highlights(&mut pages, HighlightStyle::Summary),
+            "## ハイライト\n\n- p.2: Key finding\n- p.2: result（メモ: check this）\n"
+        );
+    }
+}
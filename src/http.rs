@@ -0,0 +1,157 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+/// HTTP の接続・読み書きのタイムアウト
+#[cfg(feature = "http")]
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// POST の応答の本文の上限（代替テキストや領域の一覧には十分な大きさ）
+#[cfg(feature = "http")]
+const MAX_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// URL から取得する PDF の大きさの上限（応答が終わらない場合などに一時ディレクトリを使い切らないようにする）
+#[cfg(feature = "http")]
+const MAX_DOWNLOAD_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// body を POST し、応答の本文を返す
+#[cfg(feature = "http")]
+pub fn post(url: &str, content_type: &str, body: &[u8]) -> Result<String> {
+    use std::io::Read;
+
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let response = agent
+        .post(url)
+        .set("Content-Type", content_type)
+        .send_bytes(body)
+        .with_context(|| format!("URL に POST できません: {}", url))?;
+    let mut text = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_BYTES + 1)
+        .read_to_end(&mut text)
+        .with_context(|| format!("URL から応答を読み込めません: {}", url))?;
+    if text.len() as u64 > MAX_RESPONSE_BYTES {
+        bail!("URL の応答が大きすぎます（上限 {} バイト）: {}", MAX_RESPONSE_BYTES, url);
+    }
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+#[cfg(not(feature = "http"))]
+pub fn post(url: &str, _content_type: &str, _body: &[u8]) -> Result<String> {
+    bail!("HTTP エンドポイントを使うには http 機能を有効にして pdf2md をビルドしてください: {}", url)
+}
+
+/// URL の最後のパスの部分に名前が無い場合の、取得した PDF のファイル名
+const DEFAULT_FILE_NAME: &str = "download.pdf";
+
+/// --header で指定された、URL から PDF を取得するときに送るヘッダー（例: "Authorization: Bearer xxx"）
+#[derive(Clone, PartialEq, Eq)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+// 認証のトークンをログやエラーに書かないよう、値は表示しない
+impl std::fmt::Debug for HttpHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: ***", self.name)
+    }
+}
+
+impl FromStr for HttpHeader {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        match spec.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() && !name.trim().contains(char::is_whitespace) => {
+                Ok(HttpHeader { name: name.trim().to_string(), value: value.trim().to_string() })
+            }
+            _ => Err(format!("ヘッダーは 名前: 値 の形式で指定してください（例: \"Authorization: Bearer xxx\"）: {}", spec)),
+        }
+    }
+}
+
+/// 入力が http:// か https:// の URL かどうか
+pub fn is_url(input: &Path) -> bool {
+    input.to_str().is_some_and(|input| input.starts_with("http://") || input.starts_with("https://"))
+}
+
+/// URL の最後のパスの部分（問い合わせと断片を除く）を、取得した PDF のファイル名にする（出力ファイルの名前にも使う）
+pub fn url_file_name(url: &str) -> PathBuf {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    // Windows ではパスの区切りになる \ も区切りとして扱い、一時ディレクトリの外を指す名前にしない
+    let name = path.split_once("://").map_or(path, |(_, rest)| rest).split_once('/').map_or("", |(_, path)| path).rsplit(['/', '\\']).next().unwrap_or_default();
+    match name {
+        "" | "." | ".." => PathBuf::from(DEFAULT_FILE_NAME),
+        name => PathBuf::from(name),
+    }
+}
+
+/// URL から取得して一時的なディレクトリに保存した PDF（破棄すると削除する）
+pub struct Download {
+    dir: PathBuf,
+    pub path: PathBuf,
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// URL から PDF を取得して一時ファイルに保存する（ヘッダーは認証などに使う）
+pub fn download(url: &str, headers: &[HttpHeader]) -> Result<Download> {
+    let dir = crate::work_dir("download");
+    std::fs::create_dir_all(&dir).with_context(|| format!("一時ディレクトリを作成できません: {:?}", dir))?;
+    let download = Download { path: dir.join(url_file_name(url)), dir };
+    fetch(url, headers, &download.path)?;
+    Ok(download)
+}
+
+#[cfg(feature = "http")]
+fn fetch(url: &str, headers: &[HttpHeader], path: &Path) -> Result<()> {
+    use std::io::Read;
+
+    let agent = ureq::AgentBuilder::new().timeout_connect(HTTP_TIMEOUT).timeout_read(HTTP_TIMEOUT).build();
+    let mut request = agent.get(url);
+    for header in headers {
+        request = request.set(&header.name, &header.value);
+    }
+    let response = request.call().with_context(|| format!("URL から PDF を取得できません: {}", url))?;
+    let mut file = std::fs::File::create(path).with_context(|| format!("取得した PDF を保存できません: {:?}", path))?;
+    let copied = std::io::copy(&mut response.into_reader().take(MAX_DOWNLOAD_BYTES + 1), &mut file)
+        .with_context(|| format!("URL から PDF を取得できません: {}", url))?;
+    if copied > MAX_DOWNLOAD_BYTES {
+        bail!("URL から取得する PDF が大きすぎます（上限 {} バイト）: {}", MAX_DOWNLOAD_BYTES, url);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &str, _headers: &[HttpHeader], _path: &Path) -> Result<()> {
+    bail!("--input に URL を指定するには http 機能を有効にして pdf2md をビルドしてください: {}", url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 取得する PDF のファイル名とヘッダーの指定
+    #[test]
+    fn test_url_file_name() {
+        assert_eq!(url_file_name("https://example.com/reports/2024/annual.pdf?token=abc#page=2"), Path::new("annual.pdf"));
+        assert_eq!(url_file_name("https://example.com/"), Path::new("download.pdf"));
+        assert_eq!(url_file_name("https://example.com"), Path::new("download.pdf"));
+        assert_eq!(url_file_name("https://example.com/files/..\\..\\evil.pdf"), Path::new("evil.pdf"));
+        assert_eq!(url_file_name("https://example.com/files/a\\.."), Path::new("download.pdf"));
+        assert!(is_url(Path::new("https://example.com/a.pdf")) && !is_url(Path::new("docs/a.pdf")));
+
+        let header: HttpHeader = "Authorization: Bearer secret".parse().unwrap();
+        assert_eq!((header.name.as_str(), header.value.as_str()), ("Authorization", "Bearer secret"));
+        assert_eq!(format!("{:?}", header), "Authorization: ***");
+        assert!("Bearer secret".parse::<HttpHeader>().is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 語の先頭と末尾で分綴しない文字数の既定（TeX の \lefthyphenmin と \righthyphenmin）
+const DEFAULT_HYPHEN_MIN: usize = 2;
+
+/// 言語を判定する、ありふれた語の一覧（言語のコード、語）
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "mit", "sich", "des", "ein", "eine", "den", "von", "für", "auf", "dem", "wird"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "dat", "op", "te", "zijn", "voor", "met", "niet", "wordt", "bij", "ook"]),
+    ("en", &["the", "and", "of", "to", "is", "that", "for", "with", "on", "are", "this", "be", "by", "which", "from"]),
+];
+
+/// 言語を判定するのに必要な、ありふれた語の数の下限
+const MIN_STOPWORDS: usize = 3;
+
+/// 行末のハイフンの後に続くと、語の後半を省略したハイフン（Nord- und Südamerika など）とみなす語
+const CONJUNCTIONS: &[&str] = &["und", "oder", "bis", "bzw", "sowie", "en", "of", "tot", "and", "or", "to"];
+
+/// 1つの言語の分綴のパターン（TeX の \patterns と \hyphenation の例外、または LibreOffice の hyph_xx.dic）
+#[derive(Debug, Default)]
+pub struct Patterns {
+    /// パターンの文字列と、文字の間の値（文字数 + 1 個）
+    patterns: HashMap<String, Vec<u8>>,
+    /// 分綴の位置を明示した例外の語と、分綴できる位置（先頭からの文字数）
+    exceptions: HashMap<String, Vec<usize>>,
+    max_length: usize,
+    left_min: usize,
+    right_min: usize,
+}
+
+impl Patterns {
+    /// パターンのファイルの内容を読み取る（\patterns{ があれば TeX の形式、無ければ1行に1つのパターンの形式とみなす）
+    pub fn parse(text: &str) -> Self {
+        let mut patterns = Patterns { left_min: DEFAULT_HYPHEN_MIN, right_min: DEFAULT_HYPHEN_MIN, ..Default::default() };
+        let text: String = text.lines().map(|line| line.split('%').next().unwrap_or_default()).collect::<Vec<_>>().join("\n");
+        match tex_group(&text, "\\patterns") {
+            Some(group) => {
+                group.split_whitespace().for_each(|pattern| patterns.add_pattern(pattern));
+                if let Some(group) = tex_group(&text, "\\hyphenation") {
+                    group.split_whitespace().for_each(|word| patterns.add_exception(word));
+                }
+            }
+            None => {
+                for line in text.lines().map(str::trim) {
+                    let minimum = |key: &str| line.strip_prefix(key).and_then(|value| value.trim().parse().ok());
+                    if let Some(value) = minimum("LEFTHYPHENMIN") {
+                        patterns.left_min = value;
+                    } else if let Some(value) = minimum("RIGHTHYPHENMIN") {
+                        patterns.right_min = value;
+                    } else if !line.is_empty() && !line.starts_with(char::is_uppercase) && !line.contains('/') {
+                        // 大文字で始まる行（1行目の文字コードや COMPOUNDLEFTHYPHENMIN などの指定）と、置き換えを伴うパターンは使わない
+                        patterns.add_pattern(line);
+                    }
+                }
+            }
+        }
+        patterns
+    }
+
+    /// パターンのファイルを読み込む
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("分綴のパターンのファイル {:?} を読み込めません", path))?;
+        let patterns = Patterns::parse(&text);
+        if patterns.patterns.is_empty() {
+            bail!("分綴のパターンのファイル {:?} にパターンがありません", path);
+        }
+        Ok(patterns)
+    }
+
+    fn add_pattern(&mut self, pattern: &str) {
+        let mut letters = String::new();
+        let mut values = vec![0];
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(digit) => *values.last_mut().unwrap() = digit as u8,
+                None => {
+                    letters.extend(c.to_lowercase());
+                    values.push(0);
+                }
+            }
+        }
+        self.max_length = self.max_length.max(letters.chars().count());
+        self.patterns.insert(letters, values);
+    }
+
+    fn add_exception(&mut self, word: &str) {
+        let mut points = Vec::new();
+        let mut length = 0;
+        for c in word.chars() {
+            match c {
+                '-' => points.push(length),
+                _ => length += 1,
+            }
+        }
+        self.exceptions.insert(word.replace('-', "").to_lowercase(), points);
+    }
+
+    /// 語を分綴できる位置（先頭からの文字数）
+    pub fn hyphenation_points(&self, word: &str) -> Vec<usize> {
+        let word = word.to_lowercase();
+        if let Some(points) = self.exceptions.get(&word) {
+            return points.clone();
+        }
+        let chars: Vec<char> = format!(".{}.", word).chars().collect();
+        let mut values = vec![0u8; chars.len() + 1];
+        for start in 0..chars.len() {
+            for end in start + 1..=chars.len().min(start + self.max_length) {
+                let key: String = chars[start..end].iter().collect();
+                if let Some(pattern) = self.patterns.get(&key) {
+                    for (offset, &value) in pattern.iter().enumerate() {
+                        values[start + offset] = values[start + offset].max(value);
+                    }
+                }
+            }
+        }
+        // 語の n 文字目の後の位置は、先頭の「.」の分だけずれる
+        let length = chars.len() - 2;
+        (self.left_min.max(1)..=length.saturating_sub(self.right_min.max(1))).filter(|&point| values[point + 1] % 2 == 1).collect()
+    }
+}
+
+/// TeX のコマンドの {} の中身
+fn tex_group<'a>(text: &'a str, command: &str) -> Option<&'a str> {
+    let start = text.find(&format!("{}{{", command))? + command.len() + 1;
+    let end = text[start..].find('}')? + start;
+    Some(&text[start..end])
+}
+
+/// --hyphenation-patterns で指定された言語とパターンのファイル（例: de=hyph-de-1996.tex）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternSpec {
+    pub language: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for PatternSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        match spec.split_once('=') {
+            Some((language, path)) if !language.trim().is_empty() && !path.is_empty() => Ok(PatternSpec { language: language.trim().to_string(), path: PathBuf::from(path) }),
+            _ => Err(format!("分綴のパターンは 言語=パス の形式で指定してください（例: de=hyph-de-1996.tex）: {}", spec)),
+        }
+    }
+}
+
+/// 行末のハイフンで分けた語をつなぐ処理（言語ごとの分綴のパターンで、つないだ語の分綴の位置かを確かめる）
+#[derive(Debug, Default)]
+pub struct Dehyphenator {
+    /// 言語のコード（de、nl など）と、その言語のパターン
+    languages: Vec<(String, Patterns)>,
+}
+
+/// 行末のハイフンの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Join {
+    /// ハイフンを除いて、空白を入れずにつなぐ
+    Word,
+    /// ハイフンを残して、空白を入れずにつなぐ（E-Mail など、もともとハイフンのある語）
+    Hyphenated,
+    /// ハイフンを残して、空白を入れる（語の後半を省略したハイフン）
+    Separate,
+}
+
+impl Dehyphenator {
+    /// 言語のコードとパターンのファイルの組から読み込む
+    pub fn load(patterns: &[(String, PathBuf)]) -> Result<Self> {
+        let languages = patterns.iter().map(|(language, path)| Ok((language.clone(), Patterns::load(path)?))).collect::<Result<_>>()?;
+        Ok(Dehyphenator { languages })
+    }
+
+    /// ページのテキストの言語（パターンを読み込んだ言語に限らない。判定できなければ None）
+    pub fn detect_language(text: &str) -> Option<&'static str> {
+        let words: Vec<String> = text.split(|c: char| !c.is_alphabetic()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect();
+        STOPWORDS
+            .iter()
+            .map(|(language, stopwords)| (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count()))
+            .filter(|&(_, count)| count >= MIN_STOPWORDS)
+            .max_by_key(|&(_, count)| count)
+            .map(|(language, _)| language)
+    }
+
+    /// 行末が「fragment-」で、次の行が「rest」で始まる場合の扱い（ハイフンで終わらない行や、次の行が小文字で始まらない場合は None）
+    ///
+    /// 言語のパターンがあれば、つないだ語のその位置で分綴できる場合だけハイフンを除く。パターンが無ければ常に除く。
+    pub fn join(&self, line_end: &str, next_line: &str, language: Option<&str>) -> Option<Join> {
+        let fragment = line_end.strip_suffix('-')?.rsplit(|c: char| !c.is_alphabetic()).next()?;
+        let rest: String = next_line.trim_start().chars().take_while(|c| c.is_alphabetic()).collect();
+        if fragment.is_empty() || !rest.starts_with(char::is_lowercase) {
+            return None;
+        }
+        if CONJUNCTIONS.contains(&rest.as_str()) {
+            return Some(Join::Separate);
+        }
+        let patterns = language.and_then(|language| self.languages.iter().find(|(code, _)| code.split(['-', '_']).next() == Some(language)));
+        match patterns {
+            Some((_, patterns)) if !patterns.hyphenation_points(&format!("{}{}", fragment, rest)).contains(&fragment.chars().count()) => Some(Join::Hyphenated),
+            _ => Some(Join::Word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 分綴のパターンによる、行末のハイフンの確認
+    #[test]
+    fn test_dehyphenate() {
+        // ドイツ語のパターンの一部（TeX の形式）
+        let tex = "% hyph-de\n\\patterns{\n.ar1b 1beit n1a 1ge 1n2g 1ta\n}\n\\hyphenation{ta-ge}\n";
+        let german = Patterns::parse(tex);
+        assert_eq!(german.hyphenation_points("Arbeit"), vec![2]);
+        assert_eq!(german.hyphenation_points("tage"), vec![2]);
+        // LibreOffice の形式
+        let dic = Patterns::parse("UTF-8\nLEFTHYPHENMIN 2\nRIGHTHYPHENMIN 3\n1beit\n");
+        assert_eq!(dic.hyphenation_points("arbeit"), vec![2]);
+
+        let dehyphenator = Dehyphenator { languages: vec![("de-1996".to_string(), german)] };
+        assert_eq!(dehyphenator.join("die Ar-", "beit ist", Some("de")), Some(Join::Word));
+        // パターンで分綴できない位置のハイフンは残す
+        assert_eq!(dehyphenator.join("die Arb-", "eit", Some("de")), Some(Join::Hyphenated));
+        assert_eq!(dehyphenator.join("Nord-", "und Südamerika", Some("de")), Some(Join::Separate));
+        // パターンの無い言語は、小文字で続く場合につなぐ
+        assert_eq!(dehyphenator.join("hyphen-", "ation", Some("en")), Some(Join::Word));
+        assert_eq!(dehyphenator.join("Max-", "Planck", Some("de")), None);
+
+        assert_eq!(Dehyphenator::detect_language("Die Arbeit ist nicht mit dem Vertrag"), Some("de"));
+        assert_eq!(Dehyphenator::detect_language("Het werk is niet voor de klant en ook"), Some("nl"));
+    }
+}
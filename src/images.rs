@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use lopdf::{Document, Object, ObjectId, Stream};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::memory::SpilledImages;
+
+/// 書き出した画像の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// ファイルとして書き出せる形にデコードした画像
+#[derive(Debug)]
+pub struct DecodedImage {
+    pub format: ImageFormat,
+    pub data: Vec<u8>,
+}
+
+/// 画像 XObject を JPEG または PNG のバイト列にデコードする（spilled は --memory-budget で一時ファイルに移した画像）
+pub fn decode_image(doc: &Document, id: ObjectId, spilled: Option<&SpilledImages>) -> Result<DecodedImage> {
+    let stream = doc
+        .get_object(id)
+        .and_then(Object::as_stream)
+        .with_context(|| format!("画像オブジェクトを読み込めません: {:?}", id))?;
+    // --memory-budget で一時ファイルに移した画像のデータは読み戻す
+    let stream = match spilled {
+        Some(spilled) => spilled.restore(id, stream)?,
+        None => std::borrow::Cow::Borrowed(stream),
+    };
+    let stream = stream.as_ref();
+
+    let filters = stream.filters().unwrap_or_default();
+
+    // JPEG はそのまま書き出せる
+    if filters.iter().map(String::as_str).eq(["DCTDecode"]) {
+        return Ok(DecodedImage { format: ImageFormat::Jpeg, data: stream.content.clone() });
+    }
+    if let Some(filter) = filters
+        .iter()
+        .find(|f| matches!(f.as_str(), "DCTDecode" | "JPXDecode" | "CCITTFaxDecode" | "JBIG2Decode"))
+    {
+        bail!("未対応の画像形式です: {}", filter);
+    }
+
+    let samples = if filters.is_empty() {
+        stream.content.clone()
+    } else {
+        stream
+            .decompressed_content()
+            .with_context(|| format!("画像データの展開に失敗しました: {:?}", filters))?
+    };
+
+    let data = encode_png(doc, stream, &samples)?;
+    Ok(DecodedImage { format: ImageFormat::Png, data })
+}
+
+/// 画像の色空間
+enum ImageColor {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// パレット（RGB の並び）を参照する
+    Indexed(Vec<u8>),
+}
+
+/// 画像辞書と展開済みのサンプルから PNG を生成する
+fn encode_png(doc: &Document, stream: &Stream, samples: &[u8]) -> Result<Vec<u8>> {
+    let dict = &stream.dict;
+    let width = dict.get(b"Width").and_then(Object::as_i64).context("画像の幅がありません")? as u32;
+    let height = dict.get(b"Height").and_then(Object::as_i64).context("画像の高さがありません")? as u32;
+    let image_mask = dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false);
+    let bits = if image_mask { 1 } else { dict.get(b"BitsPerComponent").and_then(Object::as_i64).unwrap_or(8) };
+
+    let color = if image_mask {
+        ImageColor::Gray
+    } else {
+        let color_space = dict.get(b"ColorSpace").context("画像の色空間がありません")?;
+        image_color(doc, color_space)?
+    };
+
+    if width == 0 || height == 0 || width > 20000 || height > 20000 {
+        bail!("画像のサイズが不正です: {}x{}", width, height);
+    }
+
+    let (color_type, channels, pixels) = match (&color, bits) {
+        (ImageColor::Gray, 8) => (png::ColorType::Grayscale, 1, samples.to_vec()),
+        (ImageColor::Gray, 1) => (png::ColorType::Grayscale, 1, samples.to_vec()),
+        (ImageColor::Rgb, 8) => (png::ColorType::Rgb, 3, samples.to_vec()),
+        (ImageColor::Cmyk, 8) => (png::ColorType::Rgb, 3, cmyk_to_rgb(samples)),
+        (ImageColor::Indexed(_), 8) => (png::ColorType::Indexed, 1, samples.to_vec()),
+        _ => bail!("未対応のビット深度です: {}", bits),
+    };
+
+    // 1行あたりのバイト数は1ビット画像では切り上げになる
+    let row_bytes = (width as usize * channels * bits as usize).div_ceil(8);
+    let expected = row_bytes * height as usize;
+    if pixels.len() < expected {
+        bail!("画像データが不足しています（{} / {} バイト）", pixels.len(), expected);
+    }
+
+    let mut data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut data, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(if bits == 1 { png::BitDepth::One } else { png::BitDepth::Eight });
+        if let ImageColor::Indexed(palette) = &color {
+            encoder.set_palette(palette.clone());
+        }
+        let mut writer = encoder.write_header().context("PNG の書き出しに失敗しました")?;
+        let mut rows = pixels[..expected].to_vec();
+        // ImageMask は 1 が透明（描画しない）部分なので白黒を反転して見た目を合わせる
+        if image_mask {
+            rows.iter_mut().for_each(|b| *b = !*b);
+        }
+        writer.write_image_data(&rows).context("PNG の書き出しに失敗しました")?;
+    }
+    Ok(data)
+}
+
+/// 色空間オブジェクトを解釈する
+fn image_color(doc: &Document, color_space: &Object) -> Result<ImageColor> {
+    let (_, color_space) = doc.dereference(color_space)?;
+    match color_space {
+        Object::Name(name) => device_color(name),
+        Object::Array(array) => {
+            let family = array.first().and_then(|o| o.as_name().ok()).unwrap_or_default();
+            match family {
+                b"ICCBased" => {
+                    // ICC プロファイルは成分数（N）だけを見てデバイス色空間として扱う
+                    let (_, profile) = doc.dereference(array.get(1).context("ICC プロファイルがありません")?)?;
+                    match profile.as_stream().ok().and_then(|s| s.dict.get(b"N").and_then(Object::as_i64).ok()) {
+                        Some(1) => Ok(ImageColor::Gray),
+                        Some(3) => Ok(ImageColor::Rgb),
+                        Some(4) => Ok(ImageColor::Cmyk),
+                        n => bail!("未対応の ICC プロファイルです（成分数 {:?}）", n),
+                    }
+                }
+                b"Indexed" => {
+                    let base = image_color(doc, array.get(1).context("パレットの基本色空間がありません")?)?;
+                    let (_, lookup) = doc.dereference(array.get(3).context("パレットがありません")?)?;
+                    let lookup = match lookup {
+                        Object::String(bytes, _) => bytes.clone(),
+                        Object::Stream(stream) => stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()),
+                        _ => bail!("パレットの形式が不正です"),
+                    };
+                    let palette = match base {
+                        ImageColor::Rgb => lookup,
+                        ImageColor::Gray => lookup.iter().flat_map(|&g| [g, g, g]).collect(),
+                        ImageColor::Cmyk => cmyk_to_rgb(&lookup),
+                        ImageColor::Indexed(_) => bail!("入れ子のパレットには対応していません"),
+                    };
+                    Ok(ImageColor::Indexed(palette))
+                }
+                _ => device_color(family),
+            }
+        }
+        _ => bail!("画像の色空間の形式が不正です"),
+    }
+}
+
+fn device_color(name: &[u8]) -> Result<ImageColor> {
+    match name {
+        b"DeviceGray" | b"CalGray" | b"G" => Ok(ImageColor::Gray),
+        b"DeviceRGB" | b"CalRGB" | b"RGB" => Ok(ImageColor::Rgb),
+        b"DeviceCMYK" | b"CMYK" => Ok(ImageColor::Cmyk),
+        other => bail!("未対応の色空間です: {}", String::from_utf8_lossy(other)),
+    }
+}
+
+/// CMYK のサンプル列を RGB に変換する
+fn cmyk_to_rgb(samples: &[u8]) -> Vec<u8> {
+    samples
+        .chunks_exact(4)
+        .flat_map(|p| {
+            let k = 255 - p[3] as u32;
+            [0, 1, 2].map(|i| ((255 - p[i] as u32) * k / 255) as u8)
+        })
+        .collect()
+}
+
+/// 画像を出力先のディレクトリに fig-01.png のような名前で書き出し、書き出したパスを返す
+pub fn save_image(image: &DecodedImage, dir: &Path, number: usize) -> Result<PathBuf> {
+    save_image_as(image, dir, &format!("fig-{:02}", number))
+}
+
+/// 画像を出力先のディレクトリに「stem.拡張子」の名前で書き出し、書き出したパスを返す
+pub fn save_image_as(image: &DecodedImage, dir: &Path, stem: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("画像の出力先ディレクトリを作成できません: {:?}", dir))?;
+    let path = dir.join(format!("{}.{}", stem, image.format.extension()));
+    fs::write(&path, &image.data).with_context(|| format!("画像の書き出しに失敗しました: {:?}", path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    // 単体テスト: CMYK から RGB への変換
+    #[test]
+    fn test_cmyk_to_rgb() {
+        assert_eq!(cmyk_to_rgb(&[0, 0, 0, 0, 0, 0, 0, 255, 255, 0, 0, 0]), vec![255, 255, 255, 0, 0, 0, 0, 255, 255]);
+    }
+
+    // 単体テスト: 非圧縮のRGB画像を PNG に変換
+    #[test]
+    fn test_decode_raw_rgb() {
+        let mut doc = Document::with_version("1.5");
+        let dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        };
+        let id = doc.add_object(Stream::new(dict, vec![255, 0, 0, 0, 0, 255]));
+
+        let image = decode_image(&doc, id, None).unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+        assert!(image.data.starts_with(b"\x89PNG"));
+    }
+}
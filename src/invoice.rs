@@ -0,0 +1,223 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use crate::layout::PageLayout;
+use crate::metadata::days_in_month;
+
+/// 請求書・領収書から取り出した項目
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct InvoiceData {
+    pub invoice_number: Option<String>,
+    /// 発行日（解釈できた場合は YYYY-MM-DD）
+    pub invoice_date: Option<String>,
+    /// 支払期限（解釈できた場合は YYYY-MM-DD）
+    pub due_date: Option<String>,
+    /// 通貨記号（¥、$ など）
+    pub currency: Option<String>,
+    /// 金額は区切りの , を除いた数値の文字列
+    pub subtotal: Option<String>,
+    pub tax: Option<String>,
+    pub total: Option<String>,
+    /// 明細（最初の表らしい領域の各行）
+    pub line_items: Vec<LineItem>,
+}
+
+/// 明細の1行
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LineItem {
+    pub description: String,
+    pub quantity: Option<String>,
+    pub unit_price: Option<String>,
+    pub amount: Option<String>,
+}
+
+impl InvoiceData {
+    /// フロントマターに出力する項目（明細は JSON のみに出力する）
+    pub fn front_matter_fields(&self) -> Vec<(String, String)> {
+        let fields = [
+            ("invoice_number", &self.invoice_number),
+            ("invoice_date", &self.invoice_date),
+            ("due_date", &self.due_date),
+            ("currency", &self.currency),
+            ("subtotal", &self.subtotal),
+            ("tax", &self.tax),
+            ("total", &self.total),
+        ];
+        fields
+            .into_iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name.to_string(), value.clone())))
+            .collect()
+    }
+}
+
+/// 金額（通貨記号は任意）の正規表現の文字列
+const AMOUNT: &str = r"([¥￥$€£])?\s*(\d{1,3}(?:,\d{3})+(?:\.\d+)?|\d+(?:\.\d+)?)\s*(?:円)?";
+
+/// 項目名に続く値の正規表現
+fn label_value(labels: &str, value: &str) -> Regex {
+    Regex::new(&format!(r"(?i)(?:{})\s*[:：#]?\s*{}", labels, value)).unwrap()
+}
+
+static NUMBER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| label_value(r"invoice\s*(?:no\.?|number|#)|receipt\s*(?:no\.?|number|#)|請求書番号|領収書番号", r"([A-Za-z0-9][A-Za-z0-9-]*)"));
+static DUE_REGEX: LazyLock<Regex> = LazyLock::new(|| label_value(r"due\s*date|payment\s*due|支払期限|お支払期限", r"(.+)"));
+static DATE_REGEX: LazyLock<Regex> = LazyLock::new(|| label_value(r"invoice\s*date|date\s*of\s*issue|\bdate|発行日|請求日", r"(.+)"));
+static SUBTOTAL_REGEX: LazyLock<Regex> = LazyLock::new(|| label_value(r"sub\s*-?total|小計", AMOUNT));
+static TAX_REGEX: LazyLock<Regex> = LazyLock::new(|| label_value(r"\b(?:sales\s*)?tax|\bvat|消費税", AMOUNT));
+static TOTAL_REGEX: LazyLock<Regex> = LazyLock::new(|| label_value(r"\btotal(?:\s*due)?|amount\s*due|合計|ご請求金額", AMOUNT));
+
+/// 表のセル全体の金額
+static AMOUNT_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!("^{}$", AMOUNT)).unwrap());
+
+/// 年が先頭の日付と「年月日」の日付
+static NUMERIC_DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d{4})\s*[-/.年]\s*(\d{1,2})\s*[-/.月]\s*(\d{1,2})").unwrap());
+
+/// 英語の月名を使った日付（Jan. 5, 2024 など）
+static ENGLISH_DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\b([a-z]{3})[a-z]*\.?\s+(\d{1,2}),?\s+(\d{4})").unwrap());
+
+/// ページの行と表から請求書の項目を取り出す
+pub fn extract_invoice(pages: &[PageLayout]) -> InvoiceData {
+
+    let mut data = InvoiceData::default();
+    for page in pages {
+        for line in page.lines() {
+            let text = line.text.trim();
+            let capture = |regex: &Regex, group: usize| regex.captures(text).and_then(|caps| caps.get(group)).map(|m| m.as_str().trim().to_string());
+
+            if data.invoice_number.is_none() {
+                data.invoice_number = capture(&NUMBER_REGEX, 1);
+            }
+            if DUE_REGEX.is_match(text) {
+                if data.due_date.is_none() {
+                    data.due_date = capture(&DUE_REGEX, 1).and_then(|value| parse_date(&value));
+                }
+            } else if data.invoice_date.is_none() {
+                data.invoice_date = capture(&DATE_REGEX, 1).and_then(|value| parse_date(&value));
+            }
+
+            if let Some(caps) = SUBTOTAL_REGEX.captures(text) {
+                data.subtotal.get_or_insert_with(|| normalize_amount(&caps[2]));
+            } else if let Some(caps) = TAX_REGEX.captures(text) {
+                data.tax.get_or_insert_with(|| normalize_amount(&caps[2]));
+            } else if let Some(caps) = TOTAL_REGEX.captures(text) {
+                // 合計は最後に現れたもの（小計の後の総額）を使う
+                data.total = Some(normalize_amount(&caps[2]));
+                if let Some(symbol) = caps.get(1) {
+                    data.currency.get_or_insert_with(|| symbol.as_str().replace('￥', "¥"));
+                }
+            }
+        }
+
+        if data.line_items.is_empty() {
+            if let Some(region) = page.table_regions().first() {
+                data.line_items = line_items(&page.table_cells(region));
+            }
+        }
+    }
+
+    data
+}
+
+/// 表の行から明細を作る（右端の数値を金額、その左の数値を単価・数量とみなし、見出し行は除く）
+fn line_items(rows: &[Vec<String>]) -> Vec<LineItem> {
+    let amount = |cell: &String| AMOUNT_REGEX.captures(cell.trim()).map(|caps| normalize_amount(&caps[2]));
+
+    rows.iter()
+        .filter(|row| row.len() >= 2)
+        .filter_map(|row| {
+            let numbers: Vec<Option<String>> = row[1..].iter().map(amount).collect();
+            let total = numbers.last()?.clone()?;
+            let count = numbers.iter().filter(|n| n.is_some()).count();
+            Some(LineItem {
+                description: row[0].clone(),
+                quantity: if count >= 3 { numbers[numbers.len() - 3].clone() } else if count == 2 { numbers[numbers.len() - 2].clone() } else { None },
+                unit_price: if count >= 3 { numbers[numbers.len() - 2].clone() } else { None },
+                amount: Some(total),
+            })
+        })
+        .collect()
+}
+
+/// 金額の区切りの , を取り除く
+fn normalize_amount(amount: &str) -> String {
+    amount.replace(',', "")
+}
+
+/// 日付を YYYY-MM-DD に変換する（年が先頭の形式と「年月日」、英語の月名に対応）
+pub fn parse_date(value: &str) -> Option<String> {
+    let months = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+    let (year, month, day): (u32, u32, u32) = if let Some(caps) = NUMERIC_DATE.captures(value) {
+        (caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?)
+    } else {
+        let caps = ENGLISH_DATE.captures(value)?;
+        let month = months.iter().position(|m| caps[1].eq_ignore_ascii_case(m))? as u32 + 1;
+        (caps[3].parse().ok()?, month, caps[2].parse().ok()?)
+    };
+
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    // 単体テスト: 日付の解釈
+    #[test]
+    fn test_parse_date() {
+        let test_cases = vec![
+            ("2024/3/5", Some("2024-03-05"), "スラッシュ区切り"),
+            ("2024年12月01日", Some("2024-12-01"), "年月日"),
+            ("March 7, 2024", Some("2024-03-07"), "英語の月名"),
+            ("2024-13-01", None, "不正な月"),
+            ("2024/2/30", None, "月に無い日"),
+            ("2024/2/29", Some("2024-02-29"), "うるう年の2月29日"),
+            ("2023年2月29日", None, "うるう年でない年の2月29日"),
+            ("next week", None, "日付でない"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(parse_date(input).as_deref(), expected, "Test failed: {}", desc);
+        }
+    }
+
+    // 単体テスト: 請求書の項目の取り出し
+    #[test]
+    fn test_extract_invoice() {
+        let mut glyphs = Vec::new();
+        let mut push = |text: &str, x: f64, y: f64| {
+            glyphs.push(Glyph { text: text.to_string(), x, y, width: text.chars().count() as f64 * 5.0, font_size: 10.0, word_start: true, order: glyphs.len(), color: None, bold: false, monospace: false, confidence: None });
+        };
+        push("Invoice No: INV-2024-001", 50.0, 50.0);
+        push("Invoice Date: 2024-03-01", 50.0, 70.0);
+        push("Due Date: March 31, 2024", 50.0, 90.0);
+        for (i, row) in [["Item", "Qty", "Price", "Amount"], ["Widget", "2", "1,500", "3,000"], ["Gadget", "1", "500", "500"]].iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                push(cell, 50.0 + j as f64 * 100.0, 130.0 + i as f64 * 12.0);
+            }
+        }
+        push("Subtotal: $3,500", 50.0, 200.0);
+        push("Tax: $350", 50.0, 220.0);
+        push("Total: $3,850.00", 50.0, 240.0);
+        let pages = vec![PageLayout { number: 1, glyphs, ..Default::default() }];
+
+        let data = extract_invoice(&pages);
+        assert_eq!(data.invoice_number.as_deref(), Some("INV-2024-001"));
+        assert_eq!(data.invoice_date.as_deref(), Some("2024-03-01"));
+        assert_eq!(data.due_date.as_deref(), Some("2024-03-31"));
+        assert_eq!((data.subtotal.as_deref(), data.tax.as_deref(), data.total.as_deref()), (Some("3500"), Some("350"), Some("3850.00")));
+        assert_eq!(data.currency.as_deref(), Some("$"));
+        assert_eq!(
+            data.line_items,
+            vec![
+                LineItem { description: "Widget".into(), quantity: Some("2".into()), unit_price: Some("1500".into()), amount: Some("3000".into()) },
+                LineItem { description: "Gadget".into(), quantity: Some("1".into()), unit_price: Some("500".into()), amount: Some("500".into()) },
+            ]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::Command;
+
+/// 子プロセスに渡さない、値を取るオプション（子プロセスには PDF ごとの入力と出力を指定し、報告は呼び出し元でまとめて書き出す）
+const VALUE_OPTIONS: &[&str] = &["-i", "--input", "-o", "--output", "--output-dir", "--jobs", "--report"];
+
+/// 子プロセスであることを知らせる環境変数（ファイルの開始と終了は呼び出し元が知らせる）
+const CHILD_ENV: &str = "PDF2MD_ISOLATED_CHILD";
+
+/// 子プロセスに渡さない、値を取らないオプション
+const FLAG_OPTIONS: &[&str] = &["--isolate"];
+
+/// 子プロセスに渡す引数（元の引数から、入力と出力の指定と、並列化・分離の指定を除いたもの）
+///
+/// args にはプログラム名を除いた引数を渡す。-ia.pdf のように値を続けて書いた短いオプションは取り除かないため、
+/// 子プロセスで入力の指定が重なってエラーになる。
+pub fn child_args<I: IntoIterator<Item = OsString>>(args: I) -> Vec<OsString> {
+    let mut child = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        let name = text.split_once('=').map_or(&*text, |(name, _)| name);
+        if VALUE_OPTIONS.contains(&name) {
+            // --input=a.pdf のように値を続けて書いた場合を除き、次の引数が値
+            if !text.contains('=') {
+                args.next();
+            }
+        } else if !FLAG_OPTIONS.contains(&name) {
+            child.push(arg);
+        }
+    }
+    child
+}
+
+/// 1つの PDF の変換を、同じプログラムの子プロセスで行う
+///
+/// 依存するライブラリの内部でパニックやセグメンテーション違反が起きても、呼び出し元のプロセスは続けられる。
+/// 子プロセスの画面への出力はそのまま表示する。
+pub fn convert_in_child(base_args: &[OsString], input: &Path, output: &Path) -> Result<()> {
+    let program = std::env::current_exe().context("実行中のプログラムのパスを取得できません")?;
+    let status = Command::new(&program)
+        .args(base_args)
+        .args([OsStr::new("--input"), input.as_os_str(), OsStr::new("--output"), output.as_os_str()])
+        .env(CHILD_ENV, "1")
+        .status()
+        .with_context(|| format!("変換の子プロセスを起動できません: {:?}", program))?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => bail!("変換の子プロセスが終了コード {} で終了しました", code),
+        None => bail!("変換の子プロセスが異常終了しました（{}）", status),
+    }
+}
+
+/// --isolate で起動された子プロセスかどうか
+pub fn is_child() -> bool {
+    std::env::var_os(CHILD_ENV).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 子プロセスに渡す引数
+    #[test]
+    fn test_child_args() {
+        let args = ["-i", "docs", "--isolate", "--front-matter", "--output-dir=out", "--jobs", "4", "--tag", "-internal", "--profile", "manual"];
+        let child: Vec<String> = child_args(args.iter().map(OsString::from)).iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        assert_eq!(child, vec!["--front-matter", "--tag", "-internal", "--profile", "manual"]);
+    }
+}
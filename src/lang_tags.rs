@@ -0,0 +1,77 @@
+use crate::bilingual::{self, Language};
+
+/// 言語を判定する節の、文字数（空白と記号を除き、かなや漢字は2文字と数える）の下限（短い節は誤判定しやすいため）
+const MIN_SECTION_CHARS: usize = 20;
+
+/// 文書の主な言語と異なる言語の節に、言語のコメント（<!-- lang: en -->）を入れる
+///
+/// 節は見出しの行から次の見出しの前まで（最初の見出しより前の部分も1つの節とする）で、コメントは節の先頭に入れる。
+/// コードブロックの中は言語の判定に含めない。言語は日本語と英語（ラテン文字の文）を区別する。
+pub fn tag_sections(markdown: &str) -> String {
+    let sections = split_sections(markdown);
+    let languages: Vec<Option<(Language, usize)>> = sections.iter().map(|section| section_language(section)).collect();
+    // 文書の主な言語は、判定した節の文字数が多いほうの言語
+    let total = |language: Language| languages.iter().flatten().filter(|(l, _)| *l == language).map(|(_, chars)| chars).sum::<usize>();
+    let main = if total(Language::English) > total(Language::Japanese) { Language::English } else { Language::Japanese };
+
+    let mut tagged = String::with_capacity(markdown.len());
+    for (section, language) in sections.iter().zip(&languages) {
+        if let Some((language, _)) = language.filter(|(language, _)| *language != main) {
+            tagged.push_str(&format!("<!-- lang: {} -->\n\n", language.code()));
+        }
+        tagged.push_str(section);
+    }
+    tagged
+}
+
+/// Markdown を見出しの行ごとの節に分ける（コードブロックの中の # の行では分けない）
+fn split_sections(markdown: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let (mut start, mut offset, mut in_code) = (0, 0, false);
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code && line.starts_with('#') && offset > start {
+            sections.push(&markdown[start..offset]);
+            start = offset;
+        }
+        offset += line.len();
+    }
+    sections.push(&markdown[start..]);
+    sections
+}
+
+/// 節の言語と、判定に使った文字数（コードブロックを除き、かなや漢字は2文字と数える。文字数が少ない節は None）
+fn section_language(section: &str) -> Option<(Language, usize)> {
+    let mut text = String::new();
+    let mut in_code = false;
+    for line in section.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    // かなや漢字1文字は、ラテン文字のおよそ2文字分の内容とみなす
+    let chars: usize = text.chars().filter(|c| c.is_alphanumeric()).map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+    (chars >= MIN_SECTION_CHARS).then(|| bilingual::language_of(&text).map(|language| (language, chars))).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 言語の異なる節へのコメントの挿入
+    #[test]
+    fn test_tag_sections() {
+        let markdown = "# 概要\n\n本書は製品の使い方を説明する利用者向けの手引きです。\n\n## Appendix: License\n\nPermission is hereby granted, free of charge, to any person.\n\n```\n# 設定ファイルの例\n```\n\n## 付録\n\n問い合わせ先は巻末に記載しています。よろしくお願いします。\n";
+        assert_eq!(
+            tag_sections(markdown),
+            "# 概要\n\n本書は製品の使い方を説明する利用者向けの手引きです。\n\n<!-- lang: en -->\n\n## Appendix: License\n\nPermission is hereby granted, free of charge, to any person.\n\n```\n# 設定ファイルの例\n```\n\n## 付録\n\n問い合わせ先は巻末に記載しています。よろしくお願いします。\n"
+        );
+
+        // 1つの言語だけの文書はそのまま
+        assert_eq!(tag_sections("# Intro\n\nThis manual explains how to use the product.\n"), "# Intro\n\nThis manual explains how to use the product.\n");
+    }
+}
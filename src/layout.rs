@@ -0,0 +1,1416 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::panic::AssertUnwindSafe;
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use crate::annotations::{self, Annotation};
+use crate::cancel::CancellationToken;
+use crate::destinations::Destinations;
+use crate::vertical;
+use pdf_extract::{ColorSpace, MediaBox, OutputDev, OutputError, Path, PathOp, Transform};
+
+/// ページ上に配置された1文字分の情報（座標はページ左上を原点とし、y は下向き）
+#[derive(Debug, Clone)]
+pub struct Glyph {
+    pub text: String,
+    /// ベースライン左端の x 座標
+    pub x: f64,
+    /// ベースラインの y 座標
+    pub y: f64,
+    /// 文字送り幅
+    pub width: f64,
+    /// 変換後のフォントサイズ
+    pub font_size: f64,
+    /// テキスト表示命令の先頭の文字かどうか（空白・改行の判定に使う）
+    pub word_start: bool,
+    /// ページ内での描画順
+    pub order: usize,
+    /// 文字の塗りつぶし色（RGB、0.0〜1.0）。判定できない場合や、挿入したブロックの場合は None
+    pub color: Option<(f64, f64, f64)>,
+    /// 太字のフォント（フォント名に Bold などを含むもの）かどうか。判定できない場合は false
+    pub bold: bool,
+    /// 等幅のフォント（Courier、Consolas など）かどうか。判定できない場合は false
+    pub monospace: bool,
+    /// 文字認識の確からしさ（0〜100）。文字認識した語の場合のみ
+    pub confidence: Option<f64>,
+}
+
+impl Glyph {
+    /// 文字の中心付近の座標
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y - self.font_size * 0.3)
+    }
+}
+
+/// 塗りつぶされた矩形領域
+#[derive(Debug, Clone)]
+pub struct FilledRect {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+    /// 塗りつぶし色（RGB、0.0〜1.0）。判定できない場合は None
+    pub color: Option<(f64, f64, f64)>,
+    /// ページ内での描画順
+    pub order: usize,
+}
+
+impl FilledRect {
+    /// 座標が矩形の内側にあるかどうか
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        self.x0 <= x && x <= self.x1 && self.y0 <= y && y <= self.y1
+    }
+
+    /// 黒塗り（墨消し）とみなせる暗い色かどうか
+    fn is_dark(&self) -> bool {
+        self.color
+            .map(|(r, g, b)| 0.2126 * r + 0.7152 * g + 0.0722 * b < 0.2)
+            .unwrap_or(false)
+    }
+}
+
+/// ページ上に配置された画像（画像 XObject）
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    /// 画像 XObject のオブジェクト ID
+    pub id: ObjectId,
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl ImagePlacement {
+    /// 画像は単位正方形を CTM で変換した位置に描かれる
+    fn from_ctm(id: ObjectId, ctm: &Matrix) -> Self {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| (x * ctm[0] + y * ctm[2] + ctm[4], x * ctm[1] + y * ctm[3] + ctm[5]));
+        ImagePlacement {
+            id,
+            x0: corners.iter().map(|p| p.0).fold(f64::INFINITY, f64::min),
+            y0: corners.iter().map(|p| p.1).fold(f64::INFINITY, f64::min),
+            x1: corners.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max),
+            y1: corners.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    /// y 座標を反転してページ座標（y 下向き）に変換する
+    fn flipped(mut self, height: f64) -> Self {
+        (self.y0, self.y1) = (height - self.y1, height - self.y0);
+        self
+    }
+}
+
+/// テキストの1行分（glyphs_to_text と同じ規則で区切ったもの）
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    pub x0: f64,
+    pub x1: f64,
+    /// ベースラインの y 座標
+    pub y: f64,
+    /// 行内の最大フォントサイズ
+    pub font_size: f64,
+    /// 行内の文字認識した語の確からしさの最小値（文字認識した語が無い場合は None）
+    pub confidence: Option<f64>,
+}
+
+/// 線を引く命令で描かれた水平または垂直の線分（表の罫線の判定に使う）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuledLine {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+/// 表らしい領域（大きな空白で3つ以上に区切られた行が3行以上続く範囲）
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRegion {
+    /// 先頭行と最終行のベースラインの y 座標
+    pub y0: f64,
+    pub y1: f64,
+    pub rows: usize,
+}
+
+/// 本文の左右の余白に置かれた注記（欄外の注）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginNote {
+    /// 先頭行のベースラインの y 座標
+    pub y: f64,
+    pub text: String,
+}
+
+/// 1ページ分のレイアウト情報
+#[derive(Debug, Clone, Default)]
+pub struct PageLayout {
+    pub number: u32,
+    /// ページの幅と高さ（MediaBox）
+    pub width: f64,
+    pub height: f64,
+    pub glyphs: Vec<Glyph>,
+    pub fills: Vec<FilledRect>,
+    /// 内容ストリームを走査して集めた、すべての塗りつぶし命令（f・F・f*・B・B*・b・b*）の領域と色
+    ///
+    /// pdf-extract は f と F の塗りつぶししか fills に渡さないため、墨消しの判定にはこちらを使う。走査できなかった場合は None。
+    pub painted: Option<Vec<FilledRect>>,
+    pub ruled_lines: Vec<RuledLine>,
+    pub images: Vec<ImagePlacement>,
+    /// take_margin_notes で本文から取り出した欄外の注
+    pub margin_notes: Vec<MarginNote>,
+    /// ベクター図形のパスを構築する命令の数（楽譜や回路図などの図のページの判定に使う）
+    pub path_ops: usize,
+    /// ページに付けられた注釈（ハイライトやコメントなど）
+    pub annotations: Vec<Annotation>,
+}
+
+impl PageLayout {
+    /// ページを時計回りに degrees 度（90 の倍数）回転した向きの座標に直す
+    pub fn rotate(&mut self, degrees: u32) {
+        let (width, height) = (self.width, self.height);
+        let point = |x: f64, y: f64| match degrees {
+            90 => (height - y, x),
+            180 => (width - x, height - y),
+            270 => (y, width - x),
+            _ => (x, y),
+        };
+
+        for glyph in &mut self.glyphs {
+            (glyph.x, glyph.y) = point(glyph.x, glyph.y);
+        }
+        let rotate_rect = |x0: &mut f64, y0: &mut f64, x1: &mut f64, y1: &mut f64| {
+            let (ax, ay) = point(*x0, *y0);
+            let (bx, by) = point(*x1, *y1);
+            (*x0, *y0, *x1, *y1) = (ax.min(bx), ay.min(by), ax.max(bx), ay.max(by));
+        };
+        for fill in self.fills.iter_mut().chain(self.painted.iter_mut().flatten()) {
+            rotate_rect(&mut fill.x0, &mut fill.y0, &mut fill.x1, &mut fill.y1);
+        }
+        for line in &mut self.ruled_lines {
+            rotate_rect(&mut line.x0, &mut line.y0, &mut line.x1, &mut line.y1);
+        }
+        for image in &mut self.images {
+            rotate_rect(&mut image.x0, &mut image.y0, &mut image.x1, &mut image.y1);
+        }
+        for area in self.annotations.iter_mut().flat_map(|annotation| annotation.areas.iter_mut()) {
+            rotate_rect(&mut area.x0, &mut area.y0, &mut area.x1, &mut area.y1);
+        }
+        if degrees == 90 || degrees == 270 {
+            (self.width, self.height) = (height, width);
+        }
+    }
+
+    /// 後から黒塗りの矩形で覆われた文字を取り除き、取り除いた文字数を返す
+    pub fn remove_redacted(&mut self) -> usize {
+        let fills = self.painted.as_ref().unwrap_or(&self.fills);
+        let redactions: Vec<&FilledRect> = fills.iter().filter(|f| f.is_dark()).collect();
+        let before = self.glyphs.len();
+
+        // 文字の上に描かれた矩形のみを対象にし、黒背景に白文字を載せたものは残す
+        self.glyphs.retain(|glyph| {
+            !redactions
+                .iter()
+                .any(|rect| rect.order > glyph.order && rect.contains(glyph.center()))
+        });
+
+        before - self.glyphs.len()
+    }
+
+    /// 段数を指定して読み順を並べ替える（左の段から順に、各段は上から下へ）
+    ///
+    /// 段の境界をまたぐ行（全幅の見出しなど）はその位置で段組みを区切り、前後の段組みとは別に扱う。
+    pub fn reorder_columns(&mut self, columns: usize) {
+        if !self.glyphs.is_empty() {
+            self.reorder_at(&self.column_boundaries(columns));
+        }
+    }
+
+    /// 文字のある範囲を段数で等分した、段の境界の x 座標（左から順）
+    pub fn column_boundaries(&self, columns: usize) -> Vec<f64> {
+        let columns = columns.max(1);
+        let segments = split_segments(&self.glyphs);
+        if segments.is_empty() {
+            return Vec::new();
+        }
+
+        let left = segments.iter().map(|s| s.x0).fold(f64::INFINITY, f64::min);
+        let right = segments.iter().map(|s| s.x1).fold(f64::NEG_INFINITY, f64::max);
+        let column_width = (right - left) / columns as f64;
+        (1..columns).map(|i| left + column_width * i as f64).collect()
+    }
+
+    /// 段の境界の x 座標（左から順）を指定して読み順を並べ替える（左の段から順に、各段は上から下へ）
+    pub fn reorder_at(&mut self, boundaries: &[f64]) {
+        let segments = split_segments(&self.glyphs);
+        if segments.is_empty() {
+            return;
+        }
+        let column_of = |x: f64| boundaries.iter().filter(|&&boundary| x >= boundary).count();
+
+        // 段の境界を文字サイズ分以上またぐ区間は全幅とみなす
+        let spans_columns = |segment: &Segment| column_of(segment.x0 + segment.font_size) != column_of(segment.x1 - segment.font_size);
+
+        // 上から順に見て、全幅の区間ごとに帯を区切る
+        let mut order: Vec<&Segment> = segments.iter().collect();
+        order.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x0.total_cmp(&b.x0)));
+
+        let mut reordered: Vec<Glyph> = Vec::with_capacity(self.glyphs.len());
+        let mut band: Vec<&Segment> = Vec::new();
+        let flush = |band: &mut Vec<&Segment>, reordered: &mut Vec<Glyph>| {
+            // 帯の中は段ごとに上から下へ並べる（sort_by は安定なので段内の上下順は保たれる）
+            band.sort_by_key(|segment| column_of(segment.x0));
+            for segment in band.drain(..) {
+                for (i, glyph) in self.glyphs[segment.start..segment.end].iter().enumerate() {
+                    let mut glyph = glyph.clone();
+                    glyph.word_start = glyph.word_start || i == 0;
+                    reordered.push(glyph);
+                }
+            }
+        };
+
+        for segment in order {
+            if spans_columns(segment) {
+                flush(&mut band, &mut reordered);
+                band.push(segment);
+                flush(&mut band, &mut reordered);
+            } else {
+                band.push(segment);
+            }
+        }
+        flush(&mut band, &mut reordered);
+
+        self.glyphs = reordered;
+    }
+
+    /// 文字の横方向の分布から、段組みの段の境界の x 座標（左から順）を推定する（段組みでなければ空）
+    ///
+    /// 全幅の区間（見出しなど）を除いた区間がほとんど重ならない縦の余白を段の間とする。余白で分けたどの段にも
+    /// 本文の行が MIN_COLUMN_LINES 行以上あり、段の幅が本文の幅の 1/4 以上で、行の長さの中央値が段の幅の半分以上ある
+    /// 場合だけ段組みとみなす（短いセルが並ぶ表の列や、項目名と値が並ぶ欄は段組みとしない）。
+    pub fn detect_columns(&self) -> Vec<f64> {
+        let segments: Vec<Segment> = split_segments(&self.glyphs)
+            .into_iter()
+            .filter(|segment| self.glyphs[segment.start..segment.end].iter().any(|glyph| !glyph.text.trim().is_empty()))
+            .collect();
+        let left = segments.iter().map(|s| s.x0).fold(f64::INFINITY, f64::min);
+        let right = segments.iter().map(|s| s.x1).fold(f64::NEG_INFINITY, f64::max);
+        if segments.len() < MIN_COLUMN_LINES * 2 || right - left <= 0.0 {
+            return Vec::new();
+        }
+        let narrow: Vec<&Segment> = segments.iter().filter(|segment| segment.x1 - segment.x0 < (right - left) * 0.6).collect();
+        let mut sizes: Vec<f64> = narrow.iter().map(|segment| segment.font_size).collect();
+        sizes.sort_by(f64::total_cmp);
+        let Some(&font_size) = sizes.get(sizes.len() / 2) else {
+            return Vec::new();
+        };
+
+        // 1pt ごとの、区間が重なる数（ページ番号などの少数の区間は余白の中にあってもよい）
+        let bins = (right - left).ceil() as usize;
+        let mut counts = vec![0usize; bins];
+        for segment in &narrow {
+            let (start, end) = ((segment.x0 - left).floor() as usize, ((segment.x1 - left).ceil() as usize).min(bins));
+            counts[start.min(bins)..end].iter_mut().for_each(|count| *count += 1);
+        }
+        let stray = (narrow.len() / 50).max(1);
+        let mut gaps: Vec<(usize, usize)> = Vec::new();
+        let mut start = None;
+        for (i, &count) in counts.iter().enumerate() {
+            match (count <= stray, start) {
+                (true, None) => start = Some(i),
+                (false, Some(from)) => {
+                    if (i - from) as f64 >= font_size && from > 0 {
+                        gaps.push((from, i));
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        let boundaries: Vec<f64> = gaps.iter().map(|&(from, to)| left + (from + to) as f64 / 2.0).collect();
+        if boundaries.is_empty() {
+            return Vec::new();
+        }
+
+        // 各段の行が本文らしいかを確かめる
+        let column_of = |x: f64| boundaries.iter().filter(|&&boundary| x >= boundary).count();
+        for column in 0..=boundaries.len() {
+            let lines: Vec<&&Segment> = narrow.iter().filter(|segment| column_of((segment.x0 + segment.x1) / 2.0) == column).collect();
+            let x0 = lines.iter().map(|s| s.x0).fold(f64::INFINITY, f64::min);
+            let x1 = lines.iter().map(|s| s.x1).fold(f64::NEG_INFINITY, f64::max);
+            let mut widths: Vec<f64> = lines.iter().map(|s| s.x1 - s.x0).collect();
+            widths.sort_by(f64::total_cmp);
+            if lines.len() < MIN_COLUMN_LINES || x1 - x0 < (right - left) * 0.25 || widths[widths.len() / 2] < (x1 - x0) * 0.5 {
+                return Vec::new();
+            }
+        }
+        boundaries
+    }
+
+    /// 本文の左右の余白にある幅の狭い段を欄外の注として取り出し、margin_notes に入れる
+    ///
+    /// 本文の左端は最も多くの文字が揃う位置とし、本文の幅の半分より広い段（2段組みの段）は対象にしない。
+    pub fn take_margin_notes(&mut self) {
+        let segments = split_segments(&self.glyphs);
+        if segments.is_empty() {
+            return;
+        }
+
+        // 本文の左端（2pt 単位で、左端の揃う文字数が最も多い位置）と右端
+        let mut left_counts: BTreeMap<i64, usize> = BTreeMap::new();
+        for segment in &segments {
+            *left_counts.entry((segment.x0 / 2.0).round() as i64).or_default() += segment.end - segment.start;
+        }
+        let body_left = left_counts.iter().max_by_key(|&(_, count)| *count).map(|(&bucket, _)| bucket as f64 * 2.0).unwrap();
+        let body_right = segments
+            .iter()
+            .filter(|s| (s.x0 - body_left).abs() <= s.font_size * 2.0)
+            .map(|s| s.x1)
+            .fold(body_left, f64::max);
+        let body_width = body_right - body_left;
+
+        let mut notes: Vec<(f64, Vec<&Segment>)> = Vec::new();
+        for outside_left in [true, false] {
+            let mut side: Vec<&Segment> = segments
+                .iter()
+                .filter(|s| {
+                    if outside_left {
+                        s.x1 <= body_left - s.font_size * 0.5
+                    } else {
+                        s.x0 >= body_right + s.font_size * 0.5
+                    }
+                })
+                .collect();
+            let x0 = side.iter().map(|s| s.x0).fold(f64::INFINITY, f64::min);
+            let x1 = side.iter().map(|s| s.x1).fold(f64::NEG_INFINITY, f64::max);
+            if side.is_empty() || x1 - x0 > body_width * 0.5 {
+                continue;
+            }
+
+            // 縦に近い区間を1つの注にまとめる
+            side.sort_by(|a, b| a.y.total_cmp(&b.y));
+            let mut last_y = f64::NEG_INFINITY;
+            for segment in side {
+                match notes.last_mut() {
+                    Some((_, group)) if segment.y - last_y <= segment.font_size * 2.5 => group.push(segment),
+                    _ => notes.push((segment.y, vec![segment])),
+                }
+                last_y = segment.y;
+            }
+        }
+
+        let mut removed = vec![false; self.glyphs.len()];
+        for (y, group) in notes {
+            let mut glyphs: Vec<Glyph> = Vec::new();
+            for segment in group {
+                for (i, glyph) in self.glyphs[segment.start..segment.end].iter().enumerate() {
+                    let mut glyph = glyph.clone();
+                    glyph.word_start = glyph.word_start || i == 0;
+                    glyphs.push(glyph);
+                }
+                removed[segment.start..segment.end].fill(true);
+            }
+            let text = glyphs_to_text(&glyphs).split_whitespace().collect::<Vec<_>>().join(" ");
+            self.margin_notes.push(MarginNote { y, text });
+        }
+
+        let mut index = 0;
+        self.glyphs.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+        self.margin_notes.sort_by(|a, b| a.y.total_cmp(&b.y));
+    }
+
+    /// 表らしい領域を探す（2段組みの本文と区別するため、1行が3つ以上に区切られたものを対象にする）
+    pub fn table_regions(&self) -> Vec<TableRegion> {
+        const MIN_CELLS: usize = 3;
+        const MIN_ROWS: usize = 3;
+
+        // 同じベースラインの区間を1行にまとめ、行ごとの区間数を数える
+        let mut rows: Vec<(f64, usize, f64)> = Vec::new();
+        let mut segments = split_segments(&self.glyphs);
+        segments.sort_by(|a, b| a.y.total_cmp(&b.y));
+        for segment in &segments {
+            match rows.last_mut() {
+                Some((y, cells, _)) if (segment.y - *y).abs() <= segment.font_size * 0.5 => *cells += 1,
+                _ => rows.push((segment.y, 1, segment.font_size)),
+            }
+        }
+
+        let mut regions = Vec::new();
+        let mut run: Vec<(f64, usize, f64)> = Vec::new();
+        for row in rows.into_iter().chain([(f64::INFINITY, 0, 0.0)]) {
+            // 行間が大きく空いた場合も表の終わりとみなす
+            let continues = run.last().is_none_or(|last| row.0 - last.0 <= last.2 * 3.0);
+            if row.1 >= MIN_CELLS && continues {
+                run.push(row);
+                continue;
+            }
+            if run.len() >= MIN_ROWS {
+                regions.push(TableRegion { y0: run[0].0, y1: run[run.len() - 1].0, rows: run.len() });
+            }
+            run.clear();
+            if row.1 >= MIN_CELLS {
+                run.push(row);
+            }
+        }
+
+        regions
+    }
+
+    /// 表らしい領域の文字を、行ごと・セルごと（左から順）のテキストにする
+    pub fn table_cells(&self, region: &TableRegion) -> Vec<Vec<String>> {
+        let mut segments: Vec<Segment> = split_segments(&self.glyphs)
+            .into_iter()
+            .filter(|s| s.y >= region.y0 - s.font_size * 0.5 && s.y <= region.y1 + s.font_size * 0.5)
+            .collect();
+        segments.sort_by(|a, b| a.y.total_cmp(&b.y));
+
+        let mut rows: Vec<(f64, Vec<&Segment>)> = Vec::new();
+        for segment in &segments {
+            match rows.last_mut() {
+                Some((y, cells)) if (segment.y - *y).abs() <= segment.font_size * 0.5 => cells.push(segment),
+                _ => rows.push((segment.y, vec![segment])),
+            }
+        }
+
+        rows.into_iter()
+            .map(|(_, mut cells)| {
+                cells.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+                cells
+                    .iter()
+                    .map(|cell| glyphs_to_text(&self.glyphs[cell.start..cell.end]).trim().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// ページ内の文字を行に分けて返す
+    pub fn lines(&self) -> Vec<TextLine> {
+        let mut lines: Vec<TextLine> = Vec::new();
+        let mut last_end = 100000.0;
+        let mut last_y = 0.0;
+
+        for glyph in &self.glyphs {
+            let starts_line = lines.is_empty() || (glyph.word_start && line_breaks(glyph, last_end, last_y) > 0);
+            if starts_line {
+                lines.push(TextLine { text: String::new(), x0: glyph.x, x1: glyph.x, y: glyph.y, font_size: 0.0, confidence: None });
+            }
+
+            let line = lines.last_mut().unwrap();
+            if !starts_line && glyph.word_start && glyph.x > last_end + glyph.font_size * 0.1 {
+                line.text.push(' ');
+            }
+            line.text.push_str(&glyph.text);
+            line.x0 = line.x0.min(glyph.x);
+            line.x1 = line.x1.max(glyph.x + glyph.width);
+            line.font_size = line.font_size.max(glyph.font_size);
+            line.confidence = match (line.confidence, glyph.confidence) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+
+            last_y = glyph.y;
+            last_end = glyph.x + glyph.width;
+        }
+
+        lines
+    }
+}
+
+/// 同じベースライン上で連続する文字の区間（段の間の大きな空白で区切る）
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// glyphs の範囲（start..end）
+    pub start: usize,
+    pub end: usize,
+    pub x0: f64,
+    pub x1: f64,
+    pub y: f64,
+    pub font_size: f64,
+}
+
+/// 段組みとみなす、各段の行の数の下限
+const MIN_COLUMN_LINES: usize = 6;
+
+/// 描画順の文字列を、行の変わり目と大きな横方向の空白で区間に分ける
+pub fn split_segments(glyphs: &[Glyph]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for (index, glyph) in glyphs.iter().enumerate() {
+        let continues = segments.last().is_some_and(|segment| {
+            (glyph.y - segment.y).abs() <= glyph.font_size * 0.5
+                && glyph.x >= segment.x1 - glyph.font_size * 0.1
+                // 1文字分以上の空白は段の間の余白とみなす
+                && glyph.x <= segment.x1 + glyph.font_size
+        });
+
+        if continues {
+            let segment = segments.last_mut().unwrap();
+            segment.end = index + 1;
+            segment.x1 = segment.x1.max(glyph.x + glyph.width);
+            segment.font_size = segment.font_size.max(glyph.font_size);
+        } else {
+            segments.push(Segment {
+                start: index,
+                end: index + 1,
+                x0: glyph.x,
+                x1: glyph.x + glyph.width,
+                y: glyph.y,
+                font_size: glyph.font_size,
+            });
+        }
+    }
+
+    segments
+}
+
+/// PDF文書のページのうち、include が真を返すページのレイアウト情報を抽出する
+///
+/// 中止が要求された場合は、それまでに抽出したページを返す（中止されたかどうかは呼び出し側で確かめる）。
+pub fn extract_layout<F: Fn(u32) -> bool>(doc: &Document, include: F, cancel: &CancellationToken) -> Result<Vec<PageLayout>> {
+    let mut collector = LayoutCollector::default();
+    let destinations = Destinations::load(doc);
+    let pages = doc.get_pages();
+    let total = pages.len();
+    let bar = crate::progress::Bar::pages(pages.keys().filter(|&&page| include(page)).count());
+
+    for (page_num, page_id) in pages {
+        if cancel.is_cancelled() {
+            break;
+        }
+        if !include(page_num) {
+            continue;
+        }
+        let scan = scan_page(doc, page_id);
+        collector.word_orders.clear();
+        collector.fill_colors = scan.as_ref().map(|scan| scan.fill_colors.clone());
+        collector.text_colors = scan.as_ref().map(|scan| scan.text_colors.clone());
+        collector.text_bold = scan.as_ref().map(|scan| scan.text_bold.clone());
+        collector.text_monospace = scan.as_ref().map(|scan| scan.text_monospace.clone());
+        collector.text_vertical = scan.as_ref().map(|scan| scan.text_vertical.clone());
+        // pdf-extract は壊れた PDF の辞書の欠けなどでパニックすることがあるので、そのページのエラーにする
+        match std::panic::catch_unwind(AssertUnwindSafe(|| pdf_extract::output_doc_page(doc, &mut collector, page_num))) {
+            Ok(result) => result.with_context(|| format!("ページ {} のテキスト抽出に失敗しました", page_num))?,
+            Err(_) => bail!("ページ {} のテキスト抽出に失敗しました（PDF の構造が壊れています）", page_num),
+        }
+
+        let height = collector.flip_height;
+        let directions = collector.directions;
+        let last_order = collector.next_order();
+        let word_orders = std::mem::take(&mut collector.word_orders);
+        if let Some(page) = collector.pages.last_mut() {
+            if let Some(scan) = scan {
+                page.images = scan.images.into_iter().map(|image| image.flipped(height)).collect();
+                page.path_ops = scan.path_ops;
+                // 塗りつぶしは、その後の最初のテキスト表示命令の直前に描かれたものとする（命令の数が合わない場合は対応が分からないので使わない）
+                if word_orders.len() == scan.text_colors.len() {
+                    let order = |fill: &ScannedFill| word_orders.get(fill.words).copied().unwrap_or(last_order);
+                    page.painted = Some(scan.fills.iter().map(|fill| fill.placed(height, order(fill))).collect());
+                }
+            }
+            page.annotations = annotations::page_annotations(doc, page_id, height, &destinations);
+            // 横倒しや逆さまに書かれた文字（回転したページやスキャンの文字レイヤー）が左から右に読める向きにする。
+            // 文字が無いページは /Rotate の指定どおりに回す
+            let rotation = dominant_direction(&directions).unwrap_or_else(|| page_rotation(doc, page_id));
+            if rotation != 0 {
+                tracing::debug!(page = page_num, rotation, "文字の向きに合わせてページを回しました");
+                page.rotate(rotation);
+            }
+            if vertical::apply_vertical_writing(page) {
+                tracing::debug!(page = page_num, "縦書きのページとして読み順を並べ替えました");
+            }
+        }
+        crate::progress::page_done(page_num, total);
+        bar.inc();
+    }
+
+    Ok(collector.pages)
+}
+
+/// 最も多い文字の向き（ベースラインの向きを、左から右を 0 として反時計回りに 90 度単位で表したもの）
+fn dominant_direction(directions: &[usize; 4]) -> Option<u32> {
+    let (index, &count) = directions.iter().enumerate().max_by_key(|(_, &count)| count)?;
+    (count > 0).then_some(index as u32 * 90)
+}
+
+/// ページ辞書または親のページツリーの /Rotate（時計回りの角度、0・90・180・270 のいずれか）
+fn page_rotation(doc: &Document, page_id: ObjectId) -> u32 {
+    let mut node = doc.get_dictionary(page_id).ok();
+    while let Some(dict) = node {
+        if let Ok(rotate) = dict.get(b"Rotate").and_then(Object::as_i64) {
+            return (rotate.rem_euclid(360) / 90 * 90) as u32;
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|id| doc.get_dictionary(id)).ok();
+    }
+    0
+}
+
+/// 文字の並びを行ごとの範囲に分ける（行頭・行末の空白は範囲に含めない）
+///
+/// 文字に Markdown の書式の記号を付けるときに、記号が行をまたがないようにするために使う。
+pub fn line_ranges(glyphs: &[Glyph]) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for i in 1..glyphs.len() {
+        if (glyphs[i].y - glyphs[i - 1].y).abs() > glyphs[i].font_size * 0.5 {
+            ranges.push(start..i);
+            start = i;
+        }
+    }
+    if start < glyphs.len() {
+        ranges.push(start..glyphs.len());
+    }
+    ranges
+        .into_iter()
+        .filter_map(|range| {
+            let first = range.clone().find(|&i| !glyphs[i].text.trim().is_empty())?;
+            let last = range.clone().rev().find(|&i| !glyphs[i].text.trim().is_empty())?;
+            Some(first..last + 1)
+        })
+        .collect()
+}
+
+/// 抽出した文字列をテキストに組み立てる（空白・改行の判定は pdf-extract の PlainTextOutput に準じる）
+pub fn glyphs_to_text<'a, I: IntoIterator<Item = &'a Glyph>>(glyphs: I) -> String {
+    let mut text = String::new();
+    let mut last_end = 100000.0;
+    let mut last_y = 0.0;
+
+    for glyph in glyphs {
+        if glyph.word_start {
+            let breaks = line_breaks(glyph, last_end, last_y);
+            (0..breaks).for_each(|_| text.push('\n'));
+            if breaks == 0 && glyph.x > last_end + glyph.font_size * 0.1 {
+                text.push(' ');
+            }
+        }
+        text.push_str(&glyph.text);
+        last_y = glyph.y;
+        last_end = glyph.x + glyph.width;
+    }
+
+    text
+}
+
+/// 単語の先頭の文字の前に入れる改行の数
+fn line_breaks(glyph: &Glyph, last_end: f64, last_y: f64) -> usize {
+    let dy = glyph.y - last_y;
+    let mut breaks = 0;
+    if dy.abs() > glyph.font_size * 1.5 {
+        breaks += 1;
+    }
+    // 左下に移動した場合、または1行分以上上に戻った場合（段組みの次の段など）は改行
+    if (glyph.x < last_end && dy.abs() > glyph.font_size * 0.5) || dy < -glyph.font_size {
+        breaks += 1;
+    }
+    breaks.min(2)
+}
+
+/// pdf-extract から文字と塗りつぶしを受け取る OutputDev
+#[derive(Default)]
+struct LayoutCollector {
+    pages: Vec<PageLayout>,
+    /// ページの y 座標の反転に使う高さ
+    flip_height: f64,
+    first_char: bool,
+    order: usize,
+    /// 塗りつぶし命令の順に並んだ色（pdf-extract は色を追跡しないため別途取得する）
+    fill_colors: Option<Vec<Option<(f64, f64, f64)>>>,
+    /// ページ内の文字の向きごとの文字数（0・90・180・270 度の順）
+    directions: [usize; 4],
+    /// テキスト表示命令（Tj と TJ の文字列）の順に並んだ文字の色
+    text_colors: Option<Vec<Option<(f64, f64, f64)>>>,
+    /// テキスト表示命令の順に並んだ、太字のフォントかどうか
+    text_bold: Option<Vec<bool>>,
+    /// テキスト表示命令の順に並んだ、等幅のフォントかどうか
+    text_monospace: Option<Vec<bool>>,
+    /// テキスト表示命令の順に並んだ、縦書きのフォントかどうか
+    text_vertical: Option<Vec<bool>>,
+    /// 縦書きの文字の並びの (始まりの x, 始まりの y, pdf-extract が横に送った終わりの x)
+    vertical_run: Option<(f64, f64, f64)>,
+    /// ページ内のテキスト表示命令の数（begin_word の呼び出し回数）
+    words: usize,
+    /// テキスト表示命令ごとに、その文字より前に取っておいた描画順（走査した塗りつぶしの描画順に使う）
+    word_orders: Vec<usize>,
+}
+
+impl LayoutCollector {
+    fn current_page(&mut self) -> &mut PageLayout {
+        self.pages.last_mut().expect("begin_page の前に描画命令が呼ばれました")
+    }
+
+    fn next_order(&mut self) -> usize {
+        self.order += 1;
+        self.order
+    }
+}
+
+impl OutputDev for LayoutCollector {
+    fn begin_page(&mut self, page_num: u32, media_box: &MediaBox, _: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.flip_height = media_box.ury - media_box.lly;
+        self.order = 0;
+        self.directions = [0; 4];
+        self.words = 0;
+        self.vertical_run = None;
+        self.pages.push(PageLayout {
+            number: page_num,
+            width: media_box.urx - media_box.llx,
+            height: media_box.ury - media_box.lly,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        // 塗りつぶし命令の数が一致しない場合は対応がずれているため、色を不明として扱う
+        let expected = self.fill_colors.as_ref().map(Vec::len);
+        let expected_words = self.text_colors.as_ref().map(Vec::len);
+        let words = self.words;
+        let page = self.current_page();
+        if expected != Some(page.fills.len()) {
+            page.fills.iter_mut().for_each(|fill| fill.color = None);
+        }
+        // 文字の色も、テキスト表示命令の数が一致しない場合は不明として扱う
+        if expected_words != Some(words) {
+            page.glyphs.iter_mut().for_each(|glyph| {
+                glyph.color = None;
+                glyph.bold = false;
+                glyph.monospace = false;
+            });
+        }
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        // フォントサイズは縦横の拡大率を面積で平均したものを使う
+        let size_x = font_size * (trm.m11 + trm.m21);
+        let size_y = font_size * (trm.m12 + trm.m22);
+        let transformed_font_size = (size_x * size_y).abs().sqrt();
+
+        // 縦書きのフォントの文字は pdf-extract が横に送るため、送った量だけ下に置き直す（文字の左端は送りの中心線から半文字分左）
+        let (mut x, mut y) = (trm.m31, self.flip_height - trm.m32);
+        if self.text_vertical.as_ref().and_then(|vertical| vertical.get(self.words.wrapping_sub(1)).copied()).unwrap_or(false) {
+            let (start_x, start_y, _) = self
+                .vertical_run
+                .filter(|&(_, start_y, end_x)| (x - end_x).abs() < transformed_font_size * 2.0 && (y - start_y).abs() < transformed_font_size * 0.1)
+                .unwrap_or((x, y, x));
+            self.vertical_run = Some((start_x, start_y, x + width * transformed_font_size));
+            (x, y) = (start_x - transformed_font_size / 2.0, start_y + (x - start_x) + transformed_font_size);
+        } else {
+            self.vertical_run = None;
+        }
+        let glyph = Glyph {
+            text: char.to_string(),
+            x,
+            y,
+            width: width * transformed_font_size,
+            font_size: transformed_font_size,
+            word_start: self.first_char,
+            order: self.next_order(),
+            color: self.text_colors.as_ref().and_then(|colors| colors.get(self.words.wrapping_sub(1)).copied().flatten()),
+            bold: self.text_bold.as_ref().and_then(|bold| bold.get(self.words.wrapping_sub(1)).copied()).unwrap_or(false),
+            monospace: self.text_monospace.as_ref().and_then(|monospace| monospace.get(self.words.wrapping_sub(1)).copied()).unwrap_or(false),
+            confidence: None,
+        };
+        if !char.trim().is_empty() {
+            // ベースラインの向き（文字空間の x 軸を変換した向き）を 90 度単位に丸める
+            let angle = trm.m12.atan2(trm.m11).to_degrees();
+            self.directions[((angle / 90.0).round() as i64).rem_euclid(4) as usize] += 1;
+        }
+        self.first_char = false;
+        self.current_page().glyphs.push(glyph);
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        self.first_char = true;
+        self.words += 1;
+        let order = self.next_order();
+        self.word_orders.push(order);
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn stroke(&mut self, ctm: &Transform, _colorspace: &ColorSpace, _color: &[f64], path: &Path) -> Result<(), OutputError> {
+        // 直線の線分をページ座標（y 下向き）にし、水平か垂直のものだけを罫線の候補として残す
+        let flip_height = self.flip_height;
+        let point = |(x, y): (f64, f64)| (x * ctm.m11 + y * ctm.m21 + ctm.m31, flip_height - (x * ctm.m12 + y * ctm.m22 + ctm.m32));
+        let mut segments = Vec::new();
+        let (mut start, mut current) = ((0.0, 0.0), (0.0, 0.0));
+        for op in &path.ops {
+            match *op {
+                PathOp::MoveTo(x, y) => (start, current) = ((x, y), (x, y)),
+                PathOp::LineTo(x, y) => {
+                    segments.push((current, (x, y)));
+                    current = (x, y);
+                }
+                PathOp::CurveTo(_, _, _, _, x, y) => current = (x, y),
+                PathOp::Rect(x, y, w, h) => {
+                    let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+                    segments.extend((0..4).map(|i| (corners[i], corners[(i + 1) % 4])));
+                    (start, current) = ((x, y), (x, y));
+                }
+                PathOp::Close => {
+                    segments.push((current, start));
+                    current = start;
+                }
+            }
+        }
+
+        let lines: Vec<RuledLine> = segments
+            .into_iter()
+            .map(|(a, b)| (point(a), point(b)))
+            .filter(|((ax, ay), (bx, by))| ((ax - bx).abs() < 1.0) != ((ay - by).abs() < 1.0))
+            .map(|((ax, ay), (bx, by))| RuledLine { x0: ax.min(bx), y0: ay.min(by), x1: ax.max(bx), y1: ay.max(by) })
+            .collect();
+        self.current_page().ruled_lines.extend(lines);
+        Ok(())
+    }
+
+    fn fill(&mut self, ctm: &Transform, _colorspace: &ColorSpace, _color: &[f64], path: &Path) -> Result<(), OutputError> {
+        let index = self.current_page().fills.len();
+        let color = match &self.fill_colors {
+            Some(colors) => colors.get(index).copied().flatten(),
+            None => None,
+        };
+
+        let mut points = Vec::new();
+        for op in &path.ops {
+            match *op {
+                PathOp::MoveTo(x, y) | PathOp::LineTo(x, y) => points.push((x, y)),
+                PathOp::CurveTo(_, _, _, _, x, y) => points.push((x, y)),
+                PathOp::Rect(x, y, w, h) => {
+                    points.push((x, y));
+                    points.push((x + w, y + h));
+                }
+                PathOp::Close => {}
+            }
+        }
+
+        // ユーザー空間の座標をページ座標（y 下向き）に変換して外接矩形を求める
+        let flip_height = self.flip_height;
+        let transformed: Vec<(f64, f64)> = points
+            .iter()
+            .map(|&(x, y)| {
+                let px = x * ctm.m11 + y * ctm.m21 + ctm.m31;
+                let py = x * ctm.m12 + y * ctm.m22 + ctm.m32;
+                (px, flip_height - py)
+            })
+            .collect();
+        if transformed.is_empty() {
+            return Ok(());
+        }
+
+        let rect = FilledRect {
+            x0: transformed.iter().map(|p| p.0).fold(f64::INFINITY, f64::min),
+            y0: transformed.iter().map(|p| p.1).fold(f64::INFINITY, f64::min),
+            x1: transformed.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max),
+            y1: transformed.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max),
+            color,
+            order: self.next_order(),
+        };
+        self.current_page().fills.push(rect);
+        Ok(())
+    }
+}
+
+/// Form XObject の入れ子の上限（循環参照対策）
+const MAX_FORM_DEPTH: usize = 16;
+
+/// 変換行列 [a b c d e f]（点 (x, y) を (ax + cy + e, bx + dy + f) に移す）
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// m を適用した後に n を適用する行列
+fn multiply(m: &Matrix, n: &Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+fn matrix_from(operands: &[f64]) -> Option<Matrix> {
+    match *operands {
+        [a, b, c, d, e, f] => Some([a, b, c, d, e, f]),
+        _ => None,
+    }
+}
+
+/// 内容ストリームを走査して得た塗りつぶしの領域（座標は PDF のページの座標のまま）
+struct ScannedFill {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+    color: Option<(f64, f64, f64)>,
+    /// この塗りつぶしより前のテキスト表示命令の数
+    words: usize,
+}
+
+impl ScannedFill {
+    /// ページ座標（y 下向き）の矩形にする
+    fn placed(&self, height: f64, order: usize) -> FilledRect {
+        FilledRect { x0: self.x0, y0: height - self.y1, x1: self.x1, y1: height - self.y0, color: self.color, order }
+    }
+}
+
+/// 内容ストリームを独自に走査して得た、pdf-extract が提供しない描画情報
+#[derive(Default)]
+struct PageScan {
+    /// 塗りつぶし命令（f / F）ごとの塗りつぶし色（描画順）
+    fill_colors: Vec<Option<(f64, f64, f64)>>,
+    /// すべての塗りつぶし命令（f・F・f*・B・B*・b・b*）の領域と色（描画順）
+    fills: Vec<ScannedFill>,
+    /// 画像の配置（座標は PDF のユーザー空間のまま）
+    images: Vec<ImagePlacement>,
+    /// パスを構築する命令（m / l / c / re など）の数
+    path_ops: usize,
+    /// テキスト表示命令（Tj と、TJ の配列中の文字列）ごとの文字の色（描画順）
+    text_colors: Vec<Option<(f64, f64, f64)>>,
+    /// テキスト表示命令ごとの、太字のフォントかどうか（描画順）
+    text_bold: Vec<bool>,
+    /// テキスト表示命令ごとの、等幅のフォントかどうか（描画順）
+    text_monospace: Vec<bool>,
+    /// テキスト表示命令ごとの、縦書きのフォントかどうか（描画順）
+    text_vertical: Vec<bool>,
+}
+
+/// 走査中のグラフィックス状態
+#[derive(Clone, Copy)]
+struct ScanState {
+    ctm: Matrix,
+    fill_color: Option<(f64, f64, f64)>,
+    /// Tf で選んだフォントが太字かどうか
+    bold: bool,
+    /// Tf で選んだフォントが等幅かどうか
+    monospace: bool,
+    /// Tf で選んだフォントが縦書きかどうか
+    vertical: bool,
+}
+
+/// ページの内容ストリームを走査する
+///
+/// 内容ストリームを解釈できない場合は None を返し、色は不明として扱う。
+fn scan_page(doc: &Document, page_id: ObjectId) -> Option<PageScan> {
+    let content = doc.get_page_content(page_id).ok()?;
+    let resources = inherited_resources(doc, page_id);
+    let mut scan = PageScan::default();
+    // 初期状態の塗りつぶし色は黒
+    let state = ScanState { ctm: IDENTITY, fill_color: Some((0.0, 0.0, 0.0)), bold: false, monospace: false, vertical: false };
+    scan_content(doc, &content, resources, state, 0, &mut scan).then_some(scan)
+}
+
+/// ページ辞書または親のページツリーから Resources を取得する
+pub fn inherited_resources(doc: &Document, page_id: ObjectId) -> Option<&Dictionary> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    loop {
+        if let Ok(resources) = node.get(b"Resources") {
+            return doc.dereference(resources).ok()?.1.as_dict().ok();
+        }
+        node = doc.get_dictionary(node.get(b"Parent").ok()?.as_reference().ok()?).ok()?;
+    }
+}
+
+/// 内容ストリームを走査して塗りつぶし色と画像の配置を集める（解釈に失敗した場合は false）
+fn scan_content(
+    doc: &Document,
+    content: &[u8],
+    resources: Option<&Dictionary>,
+    mut state: ScanState,
+    depth: usize,
+    scan: &mut PageScan,
+) -> bool {
+    let Ok(content) = Content::decode(content) else {
+        return false;
+    };
+
+    let mut stack = Vec::new();
+    // 構築中のパスの点（ページの座標）
+    let mut path: Vec<(f64, f64)> = Vec::new();
+
+    for operation in &content.operations {
+        let operands: Vec<f64> = operation
+            .operands
+            .iter()
+            .filter_map(|o| o.as_float().ok().map(f64::from))
+            .collect();
+
+        match operation.operator.as_str() {
+            "q" => stack.push(state),
+            "Q" => state = stack.pop().unwrap_or(state),
+            "cm" => {
+                if let Some(matrix) = matrix_from(&operands) {
+                    state.ctm = multiply(&matrix, &state.ctm);
+                }
+            }
+            "g" | "rg" | "k" | "sc" | "scn" => state.fill_color = operands_to_rgb(&operands),
+            "cs" => state.fill_color = Some((0.0, 0.0, 0.0)),
+            "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" => {
+                if matches!(operation.operator.as_str(), "f" | "F") {
+                    scan.fill_colors.push(state.fill_color);
+                }
+                if !path.is_empty() {
+                    let (xs, ys): (Vec<f64>, Vec<f64>) = path.drain(..).unzip();
+                    let (x0, x1) = (xs.iter().copied().fold(f64::INFINITY, f64::min), xs.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+                    let (y0, y1) = (ys.iter().copied().fold(f64::INFINITY, f64::min), ys.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+                    scan.fills.push(ScannedFill { x0, y0, x1, y1, color: state.fill_color, words: scan.text_colors.len() });
+                }
+            }
+            "S" | "s" | "n" => path.clear(),
+            // pdf-extract は TJ の配列中の文字列ごとに begin_word を呼ぶため、それに合わせて数える
+            "Tj" => {
+                scan.text_colors.push(state.fill_color);
+                scan.text_bold.push(state.bold);
+                scan.text_monospace.push(state.monospace);
+                scan.text_vertical.push(state.vertical);
+            }
+            "TJ" => {
+                let strings = operation.operands.first().and_then(|o| o.as_array().ok()).map_or(0, |array| {
+                    array.iter().filter(|o| matches!(o, Object::String(..))).count()
+                });
+                scan.text_colors.extend(std::iter::repeat_n(state.fill_color, strings));
+                scan.text_bold.extend(std::iter::repeat_n(state.bold, strings));
+                scan.text_monospace.extend(std::iter::repeat_n(state.monospace, strings));
+                scan.text_vertical.extend(std::iter::repeat_n(state.vertical, strings));
+            }
+            "Tf" => {
+                let font = selected_font(doc, resources, operation.operands.first());
+                state.bold = font.is_some_and(is_bold_font);
+                state.monospace = font.is_some_and(|font| is_monospace_font(doc, font));
+                state.vertical = font.is_some_and(|font| vertical::is_vertical_font(doc, font));
+            }
+            "m" | "l" | "c" | "v" | "y" | "re" => {
+                scan.path_ops += 1;
+                let point = |x: f64, y: f64| (state.ctm[0] * x + state.ctm[2] * y + state.ctm[4], state.ctm[1] * x + state.ctm[3] * y + state.ctm[5]);
+                match (operation.operator.as_str(), operands.as_slice()) {
+                    ("re", &[x, y, w, h]) => path.extend([point(x, y), point(x + w, y), point(x, y + h), point(x + w, y + h)]),
+                    ("re", _) => {}
+                    _ => path.extend(operands.chunks_exact(2).map(|pair| point(pair[0], pair[1]))),
+                }
+            }
+            "Do" => {
+                let Some((id, xobject)) = xobject(doc, resources, operation.operands.first()) else {
+                    continue;
+                };
+                match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                    Ok(b"Image") => scan.images.push(ImagePlacement::from_ctm(id, &state.ctm)),
+                    Ok(b"Form") => {
+                        if depth >= MAX_FORM_DEPTH {
+                            return false;
+                        }
+                        let form_content = xobject.decompressed_content().unwrap_or_else(|_| xobject.content.clone());
+                        let form_resources = form_resources(doc, xobject, resources);
+                        let mut form_state = state;
+                        if let Some(matrix) = xobject
+                            .dict
+                            .get(b"Matrix")
+                            .and_then(Object::as_array)
+                            .ok()
+                            .and_then(|m| matrix_from(&m.iter().filter_map(|o| o.as_float().ok().map(f64::from)).collect::<Vec<_>>()))
+                        {
+                            form_state.ctm = multiply(&matrix, &state.ctm);
+                        }
+                        if !scan_content(doc, &form_content, form_resources, form_state, depth + 1, scan) {
+                            return false;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Form XObject の Resources（無ければ呼び出し元の Resources）を取得する
+pub fn form_resources<'a>(doc: &'a Document, form: &'a lopdf::Stream, parent: Option<&'a Dictionary>) -> Option<&'a Dictionary> {
+    form.dict
+        .get(b"Resources")
+        .ok()
+        .and_then(|r| doc.dereference(r).ok())
+        .and_then(|(_, r)| r.as_dict().ok())
+        .or(parent)
+}
+
+/// Do 命令の対象の XObject を取得する
+pub fn xobject<'a>(doc: &'a Document, resources: Option<&'a Dictionary>, name: Option<&Object>) -> Option<(ObjectId, &'a lopdf::Stream)> {
+    let xobjects = doc.dereference(resources?.get(b"XObject").ok()?).ok()?.1.as_dict().ok()?;
+    let (id, object) = doc.dereference(xobjects.get(name?.as_name().ok()?).ok()?).ok()?;
+    Some((id?, object.as_stream().ok()?))
+}
+
+/// Tf で選んだフォントの辞書
+fn selected_font<'a>(doc: &'a Document, resources: Option<&'a Dictionary>, name: Option<&Object>) -> Option<&'a Dictionary> {
+    let fonts = doc.dereference(resources?.get(b"Font").ok()?).ok()?.1.as_dict().ok()?;
+    doc.dereference(fonts.get(name?.as_name().ok()?).ok()?).ok()?.1.as_dict().ok()
+}
+
+/// フォントの名前（BaseFont）を小文字にしたもの
+fn font_name(font: &Dictionary) -> Option<String> {
+    let base_font = String::from_utf8_lossy(font.get(b"BaseFont").and_then(Object::as_name).ok()?).to_lowercase();
+    // サブセットのフォントは ABCDEF+ の接頭辞が付く
+    Some(base_font.split_once('+').map_or(base_font.as_str(), |(_, name)| name).to_string())
+}
+
+/// フォントの名前が太字を表すかどうか（Arial-BoldMT、HiraginoSans-W6 など）
+fn is_bold_font(font: &Dictionary) -> bool {
+    font_name(font).is_some_and(|name| ["bold", "black", "heavy", "semibold", "demi", "-w6", "-w7", "-w8", "-w9"].iter().any(|weight| name.contains(weight)))
+}
+
+/// フォントが等幅かどうか（フォント記述子の FixedPitch のフラグか、Courier、Consolas などの名前で判定する）
+fn is_monospace_font(doc: &Document, font: &Dictionary) -> bool {
+    const FIXED_PITCH: i64 = 1;
+    let flags = |font: &Dictionary| -> Option<i64> {
+        let descriptor = doc.dereference(font.get(b"FontDescriptor").ok()?).ok()?.1.as_dict().ok()?;
+        descriptor.get(b"Flags").and_then(Object::as_i64).ok()
+    };
+    // Type0 のフォントは、子孫のフォントにフォント記述子がある
+    let descendant = font
+        .get(b"DescendantFonts")
+        .ok()
+        .and_then(|fonts| doc.dereference(fonts).ok())
+        .and_then(|(_, fonts)| doc.dereference(fonts.as_array().ok()?.first()?).ok())
+        .and_then(|(_, font)| font.as_dict().ok());
+    if flags(font).or_else(|| descendant.and_then(flags)).is_some_and(|flags| flags & FIXED_PITCH != 0) {
+        return true;
+    }
+    font_name(font).is_some_and(|name| ["courier", "consolas", "mono", "menlo", "monaco", "inconsolata", "lucidaconsole", "sourcecode", "firacode"].iter().any(|family| name.contains(family)))
+}
+
+/// 色の成分数（グレー・RGB・CMYK）から RGB に変換する
+fn operands_to_rgb(operands: &[f64]) -> Option<(f64, f64, f64)> {
+    match *operands {
+        [gray] => Some((gray, gray, gray)),
+        [r, g, b] => Some((r, g, b)),
+        [c, m, y, k] => Some(((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k))),
+        // パターンなどは判定しない
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(text: &str, x: f64, y: f64, word_start: bool, order: usize) -> Glyph {
+        Glyph { text: text.to_string(), x, y, width: 6.0, font_size: 10.0, word_start, order, color: None, bold: false, monospace: false, confidence: None }
+    }
+
+    // 単体テスト: 文字列の組み立て
+    #[test]
+    fn test_glyphs_to_text() {
+        let glyphs = vec![
+            glyph("a", 10.0, 100.0, true, 1),
+            glyph("b", 16.0, 100.0, false, 2),
+            glyph("c", 40.0, 100.0, true, 3),
+            glyph("d", 10.0, 112.0, true, 4),
+            glyph("e", 10.0, 140.0, true, 5),
+        ];
+
+        // 先頭の改行は pdf-extract の PlainTextOutput と同じ挙動
+        assert_eq!(glyphs_to_text(&glyphs), "\n\nab c\nd\n\ne");
+    }
+
+    // 単体テスト: 行への分割
+    #[test]
+    fn test_lines() {
+        let page = PageLayout {
+            glyphs: vec![
+                glyph("a", 10.0, 100.0, true, 1),
+                glyph("b", 40.0, 100.0, true, 2),
+                glyph("c", 10.0, 112.0, true, 3),
+            ],
+            ..Default::default()
+        };
+
+        let lines = page.lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "a b");
+        assert_eq!((lines[0].x0, lines[0].x1), (10.0, 46.0));
+        assert_eq!(lines[1].text, "c");
+    }
+
+    // 単体テスト: 段組みの読み順の並べ替え
+    #[test]
+    fn test_reorder_columns() {
+        // 左右の段が行ごとに交互に描かれ、先頭に全幅の見出しがあるページ
+        let row = |left: &str, right: &str, y: f64, order: usize| {
+            vec![glyph(left, 10.0, y, true, order), glyph(right, 200.0, y, true, order + 1)]
+        };
+        let mut glyphs = vec![];
+        for (i, c) in "Title of paper across columns".chars().enumerate() {
+            glyphs.push(glyph(&c.to_string(), 10.0 + i as f64 * 6.0, 50.0, i == 0, i));
+        }
+        glyphs.extend(row("L1", "R1", 100.0, 100));
+        glyphs.extend(row("L2", "R2", 112.0, 102));
+        let mut page = PageLayout { glyphs, ..Default::default() };
+
+        page.reorder_columns(2);
+        let text = glyphs_to_text(&page.glyphs);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines, vec!["Title of paper across columns", "L1", "L2", "R1", "R2"]);
+    }
+
+    // 単体テスト: 段組みの推定
+    #[test]
+    fn test_detect_columns() {
+        // 左右の段の行（幅 150pt と 160pt）が行ごとに交互に描かれ、先頭に全幅の見出し、段の間にページ番号があるページ
+        let line = |x: f64, y: f64, chars: usize| -> Vec<Glyph> { (0..chars).map(|i| glyph("x", x + i as f64 * 6.0, y, i == 0, 0)).collect() };
+        let mut glyphs = line(10.0, 50.0, 60);
+        for i in 0..8 {
+            let y = 100.0 + i as f64 * 12.0;
+            glyphs.extend(line(10.0, y, if i == 7 { 10 } else { 25 }));
+            glyphs.extend(line(200.0, y, 26));
+        }
+        glyphs.extend(line(180.0, 400.0, 2));
+        let page = PageLayout { glyphs: glyphs.clone(), ..Default::default() };
+        let boundaries = page.detect_columns();
+        assert_eq!(boundaries.len(), 1);
+        assert!(boundaries[0] > 160.0 && boundaries[0] < 200.0, "{:?}", boundaries);
+
+        // 短いセルが並ぶ表は段組みとしない
+        let mut table = Vec::new();
+        for i in 0..8 {
+            let y = 100.0 + i as f64 * 12.0;
+            (0..3).for_each(|column| table.extend(line(10.0 + column as f64 * 190.0, y, 8)));
+        }
+        assert!(PageLayout { glyphs: table, ..Default::default() }.detect_columns().is_empty());
+        // 1段の本文
+        let single: Vec<Glyph> = (0..12).flat_map(|i| line(10.0, 100.0 + i as f64 * 12.0, 60)).collect();
+        assert!(PageLayout { glyphs: single, ..Default::default() }.detect_columns().is_empty());
+    }
+
+    // 単体テスト: 表らしい領域の検出
+    #[test]
+    fn test_table_regions() {
+        let row = |cells: &[&str], y: f64, order: usize| -> Vec<Glyph> {
+            cells.iter().enumerate().map(|(i, cell)| glyph(cell, 10.0 + i as f64 * 100.0, y, true, order + i)).collect()
+        };
+        let mut glyphs = row(&["L", "R"], 50.0, 0);
+        glyphs.extend(row(&["Item", "Qty", "Price"], 100.0, 10));
+        glyphs.extend(row(&["Apple", "1", "100"], 112.0, 20));
+        glyphs.extend(row(&["Pear", "2", "250"], 124.0, 30));
+        glyphs.extend(row(&["a", "b", "c"], 300.0, 40));
+        let page = PageLayout { glyphs, ..Default::default() };
+
+        // 2段組みの行や、離れた位置の1行だけの区切りは表とみなさない
+        let regions = page.table_regions();
+        assert_eq!(regions, vec![TableRegion { y0: 100.0, y1: 124.0, rows: 3 }]);
+        assert_eq!(page.table_cells(&regions[0])[1], vec!["Apple", "1", "100"]);
+    }
+
+    // 単体テスト: 欄外の注の取り出し
+    #[test]
+    fn test_take_margin_notes() {
+        let word = |text: &str, x: f64, y: f64, order: usize| Glyph { width: text.len() as f64 * 6.0, ..glyph(text, x, y, true, order) };
+        let mut glyphs = vec![];
+        for (i, y) in [100.0, 112.0, 124.0, 136.0].into_iter().enumerate() {
+            glyphs.push(word("body text of the main column", 100.0, y, i * 2));
+            glyphs.push(word("note", 20.0, y, i * 2 + 1));
+        }
+        glyphs[7].y = 300.0;
+        let mut page = PageLayout { glyphs, ..Default::default() };
+
+        page.take_margin_notes();
+        assert_eq!(page.glyphs.len(), 4);
+        let notes: Vec<(f64, &str)> = page.margin_notes.iter().map(|n| (n.y, n.text.as_str())).collect();
+        assert_eq!(notes, vec![(100.0, "note note note"), (300.0, "note")]);
+
+        // 本文と同じくらいの幅の段（2段組み）は欄外の注とみなさない
+        let mut page = PageLayout {
+            glyphs: vec![word("left column text", 20.0, 100.0, 0), word("right column text", 300.0, 100.0, 1)],
+            ..Default::default()
+        };
+        page.take_margin_notes();
+        assert!(page.margin_notes.is_empty());
+    }
+
+    // 単体テスト: ページの回転
+    #[test]
+    fn test_rotate() {
+        // 下から上に向かって書かれた2行（右の行が後の行）
+        let upward = |text: &str, x: f64, y: f64, order: usize| Glyph { width: 12.0, ..glyph(text, x, y, true, order) };
+        let mut page = PageLayout {
+            width: 600.0,
+            height: 800.0,
+            glyphs: vec![upward("first", 100.0, 500.0, 1), upward("line", 100.0, 480.0, 2), upward("second", 114.0, 500.0, 3)],
+            images: vec![ImagePlacement { id: (1, 0), x0: 0.0, y0: 0.0, x1: 100.0, y1: 50.0 }],
+            ..Default::default()
+        };
+
+        page.rotate(90);
+        assert_eq!((page.width, page.height), (800.0, 600.0));
+        let lines: Vec<String> = page.lines().into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, vec!["first line", "second"]);
+        let image = &page.images[0];
+        assert_eq!((image.x0, image.y0, image.x1, image.y1), (750.0, 0.0, 800.0, 100.0));
+
+        assert_eq!(dominant_direction(&[3, 0, 10, 1]), Some(180));
+        assert_eq!(dominant_direction(&[0; 4]), None);
+    }
+
+    // 単体テスト: 画像の配置の計算
+    #[test]
+    fn test_image_placement() {
+        // 200x100 に拡大して (50, 600) に配置した画像
+        let ctm = multiply(&[200.0, 0.0, 0.0, 100.0, 50.0, 600.0], &IDENTITY);
+        let image = ImagePlacement::from_ctm((1, 0), &ctm).flipped(842.0);
+
+        assert_eq!((image.x0, image.x1), (50.0, 250.0));
+        assert_eq!((image.y0, image.y1), (142.0, 242.0));
+    }
+
+    /// 2行の文字を描き、1行目の上に operator で黒い矩形を塗ったページ
+    fn redacted_page(operator: &str) -> PageLayout {
+        use crate::test_pdf::{Font, PdfBuilder};
+        let mut builder = PdfBuilder::new();
+        builder.text(72.0, 700.0, 12.0, Font::Regular, "Secret").text(72.0, 680.0, 12.0, Font::Regular, "Public").fill(70.0, 695.0, 60.0, 16.0, operator);
+        let doc = Document::load_mem(&builder.build()).unwrap();
+        let mut pages = extract_layout(&doc, |_| true, &CancellationToken::new()).unwrap();
+        pages.remove(0)
+    }
+
+    // 単体テスト: 黒塗り矩形で覆われた文字の除去
+    #[test]
+    fn test_remove_redacted() {
+        let black = |order| FilledRect { x0: 0.0, y0: 90.0, x1: 30.0, y1: 105.0, color: Some((0.0, 0.0, 0.0)), order };
+        let mut page = PageLayout {
+            glyphs: vec![glyph("a", 10.0, 100.0, true, 2), glyph("b", 50.0, 100.0, true, 3)],
+            fills: vec![black(1), black(4)],
+            ..Default::default()
+        };
+
+        assert_eq!(page.remove_redacted(), 1);
+        assert_eq!(page.glyphs[0].text, "b");
+
+        // 文字より前に描かれた黒背景や、色の不明な矩形では取り除かない
+        let mut page = PageLayout {
+            glyphs: vec![glyph("a", 10.0, 100.0, true, 2)],
+            fills: vec![black(1), FilledRect { color: None, ..black(3) }],
+            ..Default::default()
+        };
+        assert_eq!(page.remove_redacted(), 0);
+
+        // pdf-extract が fills に渡さない、偶奇規則の塗りつぶしや、塗りつぶしと線の描画で塗った矩形
+        for operator in ["f", "f*", "B", "b*"] {
+            let mut page = redacted_page(operator);
+            assert_eq!(page.remove_redacted(), 6, "{}", operator);
+            assert_eq!(glyphs_to_text(&page.glyphs).trim(), "Public", "{}", operator);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::console;
+use crate::graphics;
+use crate::headings::{HeadingDetector, HeadingLine};
+use crate::http;
+use crate::layout::{self, Glyph, PageLayout, TableRegion};
+use crate::onnx::OnnxModel;
+use crate::tables;
+
+/// レイアウト解析のモデルに渡すページ画像の解像度（dpi）
+const PAGE_IMAGE_DPI: u32 = 150;
+
+/// 採用する領域の確信度の下限
+const MIN_SCORE: f64 = 0.5;
+
+/// レイアウト解析のモデルが判定した領域の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionLabel {
+    /// 文書の題名（# 見出しにする）
+    Title,
+    /// 節の見出し（## 見出しにする）
+    SectionHeader,
+    /// 柱・ノンブルなどのページの上下の余白の内容（出力しない）
+    PageHeader,
+    PageFooter,
+    /// 図（図の中のラベルや目盛りの文字は出力しない）
+    Figure,
+    /// 表（Markdown の表にする）
+    Table,
+    /// 本文・キャプション・リストなど（そのまま出力する）
+    Text,
+}
+
+impl RegionLabel {
+    /// モデルの出力するラベル（DocLayNet、PubLayNet などの名前の揺れを含む）を種類にする
+    fn parse(label: &str) -> Option<Self> {
+        match label.trim().to_lowercase().replace([' ', '_'], "-").as_str() {
+            "title" | "doc-title" => Some(RegionLabel::Title),
+            "section-header" | "heading" | "header-section" | "subtitle" => Some(RegionLabel::SectionHeader),
+            "page-header" | "header" => Some(RegionLabel::PageHeader),
+            "page-footer" | "footer" | "page-number" | "footnote" => Some(RegionLabel::PageFooter),
+            "figure" | "picture" | "image" | "chart" => Some(RegionLabel::Figure),
+            "table" => Some(RegionLabel::Table),
+            "text" | "plain-text" | "caption" | "list" | "list-item" | "formula" | "reference" => Some(RegionLabel::Text),
+            _ => None,
+        }
+    }
+}
+
+/// ページ内の領域（ページの座標、左上が原点）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub label: RegionLabel,
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl Region {
+    /// 文字の中心が領域内にあるかどうか
+    fn contains(&self, glyph: &Glyph) -> bool {
+        let (x, y) = (glyph.x + glyph.width / 2.0, glyph.y - glyph.font_size * 0.3);
+        x >= self.x0 && x <= self.x1 && y >= self.y0 && y <= self.y1
+    }
+}
+
+/// レイアウト解析のサービスに送るページの内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceInput {
+    /// ページ画像（PNG）を送る（返す領域の座標は画像のピクセル）
+    #[default]
+    Image,
+    /// ページの大きさと行ごとの文字と位置の JSON を送る（返す領域の座標はページのポイント）
+    Text,
+}
+
+/// レイアウト解析のモデル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutModel {
+    /// DocLayNet で学習した ONNX のモデル（YOLO 形式の領域の判定）をページ画像に対して実行する（onnx 機能が必要）
+    Onnx(PathBuf),
+    /// 外部のコマンド（学習済みのモデルを実行するスクリプトなど）で領域を判定する
+    ///
+    /// コマンドの文字列は空白で引数に分け、ページ画像のパスを最後の引数として渡し、標準出力に領域の JSON を受け取る。
+    /// モデルのファイルなどはコマンドの文字列に含めて指定する。
+    Command(String),
+    /// 外部の HTTP のサービス（組織内の文書解析の仕組みなど）にページの内容を POST し、応答の本文に領域の JSON を受け取る（http 機能が必要）
+    Service { url: String, input: ServiceInput },
+}
+
+impl LayoutModel {
+    /// 領域の判定にページ画像を使うかどうか
+    fn uses_image(&self) -> bool {
+        !matches!(self, LayoutModel::Service { input: ServiceInput::Text, .. })
+    }
+
+    /// ページの領域を判定する（image は uses_image の場合のページ画像、onnx は Onnx の場合に読み込んだモデル）
+    fn detect(&self, page: &PageLayout, image: Option<&Path>, onnx: Option<&OnnxModel>) -> Result<Vec<Region>> {
+        match (self, image) {
+            (LayoutModel::Onnx(path), Some(image)) => {
+                let model = onnx.with_context(|| format!("ONNX のモデルが読み込まれていません: {:?}", path))?;
+                parse_regions(&model.detect(image)?, PAGE_IMAGE_DPI)
+            }
+            (LayoutModel::Command(command), Some(image)) => {
+                let mut words = command.split_whitespace();
+                let program = words.next().context("レイアウト解析のコマンドが空です")?;
+                let output = Command::new(program)
+                    .args(words)
+                    .arg(image)
+                    .output()
+                    .with_context(|| format!("レイアウト解析のコマンドを実行できません: {}", program))?;
+                if !output.status.success() {
+                    bail!("レイアウト解析のコマンドが失敗しました（{}）: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+                }
+                parse_regions(&String::from_utf8_lossy(&output.stdout), PAGE_IMAGE_DPI)
+            }
+            (LayoutModel::Service { url, .. }, Some(image)) => {
+                let body = fs::read(image).with_context(|| format!("ページ画像を読み込めません: {:?}", image))?;
+                parse_regions(&http::post(url, "image/png", &body)?, PAGE_IMAGE_DPI)
+            }
+            (LayoutModel::Service { url, .. }, None) => parse_regions(&http::post(url, "application/json", text_boxes(page).to_string().as_bytes())?, 72),
+            (LayoutModel::Onnx(_) | LayoutModel::Command(_), None) => bail!("レイアウト解析のモデルにはページ画像が必要です"),
+        }
+    }
+}
+
+/// レイアウト解析のサービスに送る、ページの行ごとの文字と位置（bbox は [左, 上, 右, 下] のポイント、左上が原点）
+fn text_boxes(page: &PageLayout) -> serde_json::Value {
+    let lines: Vec<serde_json::Value> = page
+        .lines()
+        .into_iter()
+        .filter(|line| !line.text.trim().is_empty())
+        .map(|line| serde_json::json!({"text": line.text.trim(), "bbox": [line.x0, line.y - line.font_size, line.x1, line.y], "font_size": line.font_size}))
+        .collect();
+    serde_json::json!({"page": page.number, "width": page.width, "height": page.height, "lines": lines})
+}
+
+/// モデルの出力する領域1件（bbox は [左, 上, 右, 下] のピクセル座標）
+#[derive(Deserialize)]
+struct RawRegion {
+    label: String,
+    bbox: [f64; 4],
+    score: Option<f64>,
+}
+
+/// モデルの出力（領域の配列か、"regions" に配列を持つオブジェクト）
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawOutput {
+    Regions(Vec<RawRegion>),
+    Object { regions: Vec<RawRegion> },
+}
+
+/// モデルの出力した JSON を、ページの座標の領域にする（知らない種類や確信度の低い領域は除く）
+pub fn parse_regions(json: &str, dpi: u32) -> Result<Vec<Region>> {
+    let output: RawOutput = serde_json::from_str(json).context("レイアウト解析の結果を JSON として読み込めません")?;
+    let (RawOutput::Regions(regions) | RawOutput::Object { regions }) = output;
+    let scale = 72.0 / f64::from(dpi);
+    Ok(regions
+        .into_iter()
+        .filter(|region| region.score.is_none_or(|score| score >= MIN_SCORE))
+        .filter_map(|region| {
+            let [x0, y0, x1, y1] = region.bbox.map(|v| v * scale);
+            Some(Region { label: RegionLabel::parse(&region.label)?, x0: x0.min(x1), y0: y0.min(y1), x1: x0.max(x1), y1: y0.max(y1) })
+        })
+        .collect())
+}
+
+/// 各ページの領域をモデルで判定し、判定した領域を適用する
+///
+/// ページ画像を用意できない場合はエラーにし、モデルが領域の判定に失敗したページは警告を出してそのまま変換する。
+pub fn analyze_pages(model: &LayoutModel, pdf_path: &Path, pages: &mut [PageLayout]) -> Result<RegionDetector> {
+    let work_dir = crate::work_dir("layout");
+    let mut detector = RegionDetector { headings: Vec::new() };
+    // ONNX のモデルの読み込みと最適化には時間がかかるため、文書ごとに一度だけ行う
+    let onnx = match model {
+        LayoutModel::Onnx(path) => Some(OnnxModel::load(path)?),
+        _ => None,
+    };
+
+    let result = pages.iter_mut().try_for_each(|page| {
+        let image = if model.uses_image() {
+            Some(graphics::rasterize_page(pdf_path, page.number, PAGE_IMAGE_DPI, &work_dir.join(format!("page-{:03}.png", page.number)))?)
+        } else {
+            None
+        };
+        match model.detect(page, image.as_deref(), onnx.as_ref()) {
+            Ok(regions) => detector.headings.extend(apply_regions(page, &regions).into_iter().map(|(text, level)| (page.number, text, level))),
+            Err(e) => console!(Warn, "警告: ページ {} のレイアウト解析に失敗しました: {:#}", page.number, e),
+        }
+        anyhow::Ok(())
+    });
+
+    let _ = fs::remove_dir_all(&work_dir);
+    result.map(|_| detector)
+}
+
+/// ページに領域を適用し、見出しの領域にある行の (テキスト, レベル) を返す
+///
+/// 柱・ノンブルと図の中の文字を取り除き、表の領域は Markdown の表に置き換える。
+pub fn apply_regions(page: &mut PageLayout, regions: &[Region]) -> Vec<(String, usize)> {
+    for region in regions.iter().filter(|region| region.label == RegionLabel::Table) {
+        let baselines: Vec<f64> = page.glyphs.iter().filter(|glyph| region.contains(glyph)).map(|glyph| glyph.y).collect();
+        let (Some(y0), Some(y1)) = (baselines.iter().copied().reduce(f64::min), baselines.iter().copied().reduce(f64::max)) else {
+            continue;
+        };
+        let table = tables::table_markdown(page, &TableRegion { y0, y1, rows: 0 });
+        let Some(index) = page.glyphs.iter().position(|glyph| region.contains(glyph)) else {
+            continue;
+        };
+        let first = page.glyphs[index].clone();
+        page.glyphs.retain(|glyph| !region.contains(glyph));
+        page.glyphs.insert(index, Glyph { text: format!("\n\n{}\n\n", table), x: 0.0, width: 0.0, word_start: true, color: None, bold: false, monospace: false, ..first });
+    }
+
+    let removed = |glyph: &Glyph| {
+        regions
+            .iter()
+            .any(|region| matches!(region.label, RegionLabel::PageHeader | RegionLabel::PageFooter | RegionLabel::Figure) && region.contains(glyph))
+    };
+    page.glyphs.retain(|glyph| !removed(glyph));
+
+    let mut headings = Vec::new();
+    for range in layout::line_ranges(&page.glyphs) {
+        let glyphs = &page.glyphs[range];
+        let level = regions.iter().find_map(|region| match region.label {
+            RegionLabel::Title if glyphs.iter().any(|glyph| region.contains(glyph)) => Some(1),
+            RegionLabel::SectionHeader if glyphs.iter().any(|glyph| region.contains(glyph)) => Some(2),
+            _ => None,
+        });
+        let text = layout::glyphs_to_text(glyphs).trim().to_string();
+        if let Some(level) = level.filter(|_| !text.is_empty()) {
+            headings.push((text, level));
+        }
+    }
+    headings
+}
+
+/// レイアウト解析のモデルが題名・節の見出しと判定した行による見出しの判定
+pub struct RegionDetector {
+    /// (ページ, 行のテキスト, レベル)
+    headings: Vec<(u32, String, usize)>,
+}
+
+impl HeadingDetector for RegionDetector {
+    fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)> {
+        let page = line.page?;
+        self.headings.iter().find(|(number, text, _)| *number == page && text == line.text).map(|(_, _, level)| (*level, line.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, y: f64, font_size: f64) -> Vec<Glyph> {
+        let mut x = 72.0;
+        text.split(' ')
+            .map(|word| {
+                let glyph = Glyph { text: word.to_string(), x, y, width: word.len() as f64 * font_size * 0.5, font_size, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+                x += glyph.width + font_size * 0.3;
+                glyph
+            })
+            .collect()
+    }
+
+    // 単体テスト: レイアウト解析の領域の適用
+    #[test]
+    fn test_apply_regions() {
+        let json = r#"{"regions": [
+            {"label": "Title", "bbox": [140, 140, 1000, 190], "score": 0.98},
+            {"label": "Picture", "bbox": [140, 300, 1000, 420], "score": 0.91},
+            {"label": "Page-footer", "bbox": [140, 1550, 1000, 1600]},
+            {"label": "Table", "bbox": [140, 900, 1000, 1100], "score": 0.3},
+            {"label": "Logo", "bbox": [0, 0, 10, 10]}
+        ]}"#;
+        let regions = parse_regions(json, 144).unwrap();
+        let labels: Vec<RegionLabel> = regions.iter().map(|region| region.label).collect();
+        assert_eq!(labels, vec![RegionLabel::Title, RegionLabel::Figure, RegionLabel::PageFooter], "確信度の低い領域と知らない種類は除く");
+        assert_eq!((regions[0].x0, regions[0].y1), (70.0, 95.0));
+
+        let mut glyphs = Vec::new();
+        glyphs.extend(line("Annual Report", 90.0, 18.0));
+        glyphs.extend(line("Body text of the report.", 120.0, 10.0));
+        glyphs.extend(line("Q1 Q2 Q3", 190.0, 8.0));
+        glyphs.extend(line("Page 3", 790.0, 8.0));
+        let mut page = PageLayout { number: 3, glyphs, ..Default::default() };
+
+        let headings = apply_regions(&mut page, &regions);
+        assert_eq!(headings, vec![("Annual Report".to_string(), 1)]);
+        assert_eq!(layout::glyphs_to_text(&page.glyphs).trim(), "Annual Report\n\nBody text of the report.");
+
+        let detector = RegionDetector { headings: headings.into_iter().map(|(text, level)| (3, text, level)).collect() };
+        let detectors: Vec<Box<dyn HeadingDetector>> = vec![Box::new(detector)];
+        assert_eq!(crate::headings::apply_detectors(&mut [page], &detectors), 1);
+    }
+
+    // 単体テスト: HTTP のサービスによる領域の判定
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_service_text_input() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/layout", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // ヘッダーと本文は別々に届くことがあるため、本文の JSON の終わりまで読む
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buffer).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..n]);
+            }
+            stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n[{\"label\": \"footer\", \"bbox\": [0, 780, 600, 800]}]").unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        let page = PageLayout { number: 2, width: 600.0, height: 800.0, glyphs: line("Page 2", 790.0, 8.0), ..Default::default() };
+        let model = LayoutModel::Service { url, input: ServiceInput::Text };
+        assert!(!model.uses_image());
+        let regions = model.detect(&page, None, None).unwrap();
+        assert_eq!(regions, vec![Region { label: RegionLabel::PageFooter, x0: 0.0, y0: 780.0, x1: 600.0, y1: 800.0 }]);
+        let request = server.join().unwrap();
+        assert!(request.contains("Content-Type: application/json") && request.contains(r#""text":"Page 2""#), "{}", request);
+    }
+}
@@ -0,0 +1,1250 @@
+//! PDF を Markdown に変換するライブラリ（CLI の pdf2md はこのライブラリの薄いラッパー）
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+
+mod alt_text;
+mod annotations;
+mod articles;
+mod batch;
+mod bilingual;
+mod blank_pages;
+mod cancel;
+mod classify;
+mod clipboard;
+pub mod cli;
+mod code_blocks;
+mod colors;
+mod comments;
+mod config;
+mod corpus;
+mod destinations;
+mod diagnostics;
+mod duplicates;
+mod email;
+mod encoding;
+mod financial;
+mod font_styles;
+mod figures;
+mod frontmatter;
+mod fuzz;
+mod graphics;
+mod headings;
+mod highlights;
+mod hyphenation;
+mod http;
+mod images;
+mod invoice;
+mod isolate;
+mod lang_tags;
+mod layout;
+mod layout_model;
+mod links;
+mod lists;
+mod logging;
+mod manifest;
+mod margin_notes;
+mod memory;
+mod metadata;
+mod ocr;
+mod outline;
+mod onnx;
+mod oversize;
+mod paragraphs;
+mod password;
+mod patent;
+mod paths;
+mod probe;
+mod progress;
+mod redact;
+mod resume;
+mod report;
+mod review;
+mod robust;
+mod running_headers;
+mod selection;
+mod server;
+mod slides;
+#[cfg(feature = "ocr")]
+mod spelling;
+mod sniff;
+mod split;
+mod structure;
+mod stream;
+mod tables;
+#[cfg(test)]
+mod test_pdf;
+mod toc;
+mod transcript;
+mod vertical;
+mod watch;
+mod whitespace;
+
+use bilingual::BilingualOutput;
+use comments::CommentOutput;
+use encoding::OutputEncoding;
+use highlights::HighlightStyle;
+use layout_model::LayoutModel;
+use margin_notes::MarginNoteStyle;
+use selection::{PageRanges, PageSample};
+use transcript::TranscriptStyle;
+use whitespace::WhitespaceOptions;
+
+pub use cancel::{CancellationToken, Cancelled};
+#[doc(hidden)]
+pub use fuzz::fuzz_convert;
+pub use headings::{HeadingDetector, HeadingLine};
+pub use sniff::{ContentKind, NotPdf};
+pub use stream::{FlushPolicy, MarkdownWriter};
+
+/// PDF ファイルを既定の設定で Markdown に変換する
+///
+/// 図のページの画像の書き出しなど、ファイルを書き出す処理は行わない。
+pub fn convert_file(path: impl AsRef<Path>) -> Result<String> {
+    Converter::new().convert_file(path)
+}
+
+/// メモリ上の PDF を既定の設定で Markdown に変換する
+pub fn convert_bytes(bytes: &[u8]) -> Result<String> {
+    Converter::new().convert_bytes(bytes)
+}
+
+/// 繰り返し使う PDF の変換処理
+///
+/// 複製しても中身は共有するため安価で、スレッドをまたいで共有できる。見出しの判定方法などの設定は、
+/// 複製したものを含めて、この変換処理で変換するすべての文書で使い回す。
+#[derive(Clone, Default)]
+pub struct Converter {
+    shared: Arc<Shared>,
+}
+
+/// 変換処理の間で共有する設定
+struct Shared {
+    options: ExtractOptions,
+    /// ページごとに書き出す場合の設定（各ページの先頭にページの目印を入れる）
+    paged_options: ExtractOptions,
+    headings: Vec<Box<dyn HeadingDetector>>,
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Shared { options: ExtractOptions::default(), paged_options: ExtractOptions { page_markers: true, ..Default::default() }, headings: Vec::new() }
+    }
+}
+
+impl Converter {
+    /// 既定の設定の変換処理
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 信頼できない PDF 向けの変換処理（--robust と同じく、抽出を始める前に文書の大きさや入れ子の深さが上限に収まるかを確かめる）
+    pub fn robust() -> Self {
+        let limits = Some(robust::RobustLimits::default());
+        let shared = Shared::default();
+        let options = ExtractOptions { robust: limits, ..shared.options };
+        let paged_options = ExtractOptions { robust: limits, ..shared.paged_options };
+        Converter { shared: Arc::new(Shared { options, paged_options, ..shared }) }
+    }
+
+    /// 汎用の判定より優先する見出しの判定方法を指定した変換処理
+    pub fn with_heading_detectors(headings: Vec<Box<dyn HeadingDetector>>) -> Self {
+        Converter { shared: Arc::new(Shared { headings, ..Default::default() }) }
+    }
+
+    /// PDF ファイルを Markdown に変換する
+    pub fn convert_file(&self, path: impl AsRef<Path>) -> Result<String> {
+        self.convert_file_cancellable(path, &CancellationToken::new())
+    }
+
+    /// メモリ上の PDF を Markdown に変換する
+    pub fn convert_bytes(&self, bytes: &[u8]) -> Result<String> {
+        self.convert_bytes_cancellable(bytes, &CancellationToken::new())
+    }
+
+    /// PDF ファイルを Markdown に変換する（中止が要求されると、ページの合間か処理の段階の合間で Cancelled のエラーを返す）
+    pub fn convert_file_cancellable(&self, path: impl AsRef<Path>, cancel: &CancellationToken) -> Result<String> {
+        let path = path.as_ref();
+        cancel.check()?;
+        let mut doc = load_document(path, &self.shared.options)?;
+        let spilled = spill_images(&mut doc, &self.shared.options)?;
+        self.convert_document(&doc, spilled.as_ref(), path, cancel)
+    }
+
+    /// メモリ上の PDF を Markdown に変換する（中止が要求されると、ページの合間か処理の段階の合間で Cancelled のエラーを返す）
+    pub fn convert_bytes_cancellable(&self, bytes: &[u8], cancel: &CancellationToken) -> Result<String> {
+        let source = Path::new("<bytes>");
+        cancel.check()?;
+        let mut doc = self.load_bytes(bytes, source)?;
+        let spilled = spill_images(&mut doc, &self.shared.options)?;
+        self.convert_document(&doc, spilled.as_ref(), source, cancel)
+    }
+
+    /// PDF ファイルを Markdown に変換し、ページごとに writer へ書き出す（段落や表はページの境目で分ける）
+    pub fn write_file<W: Write>(&self, path: impl AsRef<Path>, writer: &mut MarkdownWriter<W>, cancel: &CancellationToken) -> Result<()> {
+        let path = path.as_ref();
+        cancel.check()?;
+        let mut doc = load_document(path, &self.shared.paged_options)?;
+        let spilled = spill_images(&mut doc, &self.shared.paged_options)?;
+        self.write_document(&doc, spilled.as_ref(), path, writer, cancel)
+    }
+
+    /// メモリ上の PDF を Markdown に変換し、ページごとに writer へ書き出す（段落や表はページの境目で分ける）
+    pub fn write_bytes<W: Write>(&self, bytes: &[u8], writer: &mut MarkdownWriter<W>, cancel: &CancellationToken) -> Result<()> {
+        let source = Path::new("<bytes>");
+        cancel.check()?;
+        let mut doc = self.load_bytes(bytes, source)?;
+        let spilled = spill_images(&mut doc, &self.shared.paged_options)?;
+        self.write_document(&doc, spilled.as_ref(), source, writer, cancel)
+    }
+
+    fn load_bytes(&self, bytes: &[u8], source: &Path) -> Result<lopdf::Document> {
+        sniff::ensure_pdf(bytes).context("PDFからのテキスト抽出に失敗しました")?;
+        let doc = lopdf::Document::load_mem(bytes).context("PDFからのテキスト抽出に失敗しました: PDF として読み込めません")?;
+        prepare_document(doc, source, &self.shared.options)
+    }
+
+    /// 読み込んだ文書を Markdown にする
+    fn convert_document(&self, doc: &lopdf::Document, spilled: Option<&memory::SpilledImages>, source: &Path, cancel: &CancellationToken) -> Result<String> {
+        let markdown = self.render_document(doc, spilled, source, &self.shared.options, cancel)?;
+        Ok(whitespace::normalize(&markdown, &WhitespaceOptions::default()))
+    }
+
+    /// 読み込んだ文書を Markdown にし、ページの目印で分けてページごとに書き出す（末尾の注などは最後のページの後に書き出す）
+    fn write_document<W: Write>(
+        &self,
+        doc: &lopdf::Document,
+        spilled: Option<&memory::SpilledImages>,
+        source: &Path,
+        writer: &mut MarkdownWriter<W>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let markdown = self.render_document(doc, spilled, source, &self.shared.paged_options, cancel)?;
+        let (_, pages) = review::split_pages(&markdown);
+        for (_, content) in pages {
+            cancel.check()?;
+            writer.write_page(&whitespace::normalize(&content, &WhitespaceOptions::default()))?;
+        }
+        Ok(())
+    }
+
+    /// 読み込んだ文書から、空白を正規化する前の Markdown を組み立てる
+    fn render_document(&self, doc: &lopdf::Document, spilled: Option<&memory::SpilledImages>, source: &Path, options: &ExtractOptions, cancel: &CancellationToken) -> Result<String> {
+        let shared = &*self.shared;
+        let extracted = extract_content(doc, spilled, source, options, cancel)?;
+        cancel.check()?;
+        let markdown = match options.mode {
+            config::ConversionMode::Document => {
+                let options = MarkdownOptions { headings: &shared.headings, font_headings: extracted.font_headings, ..Default::default() };
+                convert_to_markdown(extracted.text, &options)?
+            }
+            _ => extracted.text,
+        };
+        Ok(if extracted.trailer.is_empty() { markdown } else { format!("{}\n\n{}", markdown, extracted.trailer) })
+    }
+}
+
+/// テキスト抽出時のオプション
+#[derive(Default)]
+struct ExtractOptions {
+    /// 墨消し箇所のテキストも出力する
+    ignore_redactions: bool,
+    /// コピー禁止の権限設定を無視する
+    override_permissions: bool,
+    /// 抜き取って変換するページ（None の場合は全ページ）
+    sample: Option<PageSample>,
+    /// 変換するページの範囲（None の場合は全ページ。抜き取りと併せて指定した場合は両方に含まれるページ）
+    pages: Option<PageRanges>,
+    /// 段組みの指定
+    layout: config::LayoutConfig,
+    /// 失われた内容の位置に目印を入れる
+    placeholders: bool,
+    /// 欄外の注の出力方法
+    margin_notes: MarginNoteStyle,
+    /// 変換方法（スライドの場合は抽出時に Markdown まで組み立てる）
+    mode: config::ConversionMode,
+    /// 請求書の項目を取り出す
+    invoice: bool,
+    /// 紙面を記事ごとに分ける
+    articles: bool,
+    /// 日本語と英語の二言語の文書の出力方法（None の場合は言語を区別しない）
+    bilingual: Option<BilingualOutput>,
+    /// 表を Markdown の表にして合計を検算する
+    financial: bool,
+    /// 表を Markdown の表にしない（--financial を指定しない場合）
+    ignore_tables: bool,
+    /// 図のページの画像を書き出すディレクトリと、Markdown から見た相対パス（None の場合は図のページを判定しない）
+    graphical_pages: Option<(PathBuf, String)>,
+    /// 本文の画像を書き出すディレクトリと、Markdown から見た相対パス（None の場合は画像を出力しない）
+    figures: Option<(PathBuf, String)>,
+    /// 全ページの画像の書き出し方（None の場合は書き出さない）
+    page_images: Option<graphics::PageImages>,
+    /// 重複したページを目印に置き換える
+    dedupe_pages: bool,
+    /// 白紙のページを省略せずに目印として残す
+    keep_blank_pages: bool,
+    /// ページの上下に繰り返し現れる柱とノンブルを取り除かずに残す
+    keep_headers: bool,
+    /// 文字の色ごとの書式
+    colors: Vec<config::ColorRule>,
+    /// ハイライトされた文字の出力方法（None の場合はハイライトを扱わない）
+    highlights: Option<HighlightStyle>,
+    /// 注釈のコメントの出力方法（None の場合はコメントを扱わない）
+    comments: Option<CommentOutput>,
+    /// しおりの最上位の項目ごとに本文を分ける
+    split_by_outline: bool,
+    /// フォントサイズと太字による見出しの規則
+    heading_styles: Vec<config::HeadingStyle>,
+    /// しおりの項目と同じ行を見出しにする
+    bookmark_headings: bool,
+    /// 見出しの規則が無い場合に、フォントサイズから見出しを推定しない
+    ignore_font_sizes: bool,
+    /// フォントサイズから見出しを推定するときの、本文に対する倍率の下限（None の場合は既定の倍率）
+    heading_scale: Option<f64>,
+    /// 領域を判定するレイアウト解析のモデル
+    layout_model: Option<LayoutModel>,
+    /// 確認用の HTML のために、各ページの先頭にページの目印を入れる
+    page_markers: bool,
+    /// 構造の JSON の位置を求めるために、表などを置き換える前のページの文字の配置を残す
+    keep_layouts: bool,
+    /// メモリに置く文書のデータの上限（超える分の画像のデータは一時ファイルに移す）
+    memory_budget: Option<memory::MemoryBudget>,
+    /// ページの文字認識（None の場合は文字認識を行わない）
+    ocr: Option<ocr::OcrOptions>,
+    /// 行末のハイフンで分けた語をつなぐ（None の場合はつながない）
+    dehyphenate: Option<hyphenation::Dehyphenator>,
+    /// 信頼できない PDF 向けの上限（None の場合は確かめない）
+    robust: Option<robust::RobustLimits>,
+    /// 暗号化された PDF のパスワード（None の場合は空のユーザーパスワードで復号する）
+    password: Option<String>,
+}
+
+/// 抽出したテキストと、変換時の警告
+struct ExtractedContent {
+    /// 抽出したテキスト（スライドの場合は変換済みの Markdown）
+    text: String,
+    warnings: Vec<diagnostics::Warning>,
+    coverage: Vec<diagnostics::PageCoverage>,
+    /// 文書末尾に追加する Markdown（欄外の注の脚注や一覧）
+    trailer: String,
+    /// 請求書の項目（--invoice の場合のみ）
+    invoice: Option<invoice::InvoiceData>,
+    /// 記事ごとに分けた紙面（--articles の場合のみ）
+    articles: Vec<articles::Article>,
+    /// 特許文献の表紙の書誌事項（フロントマターに出力する）
+    bibliography: Vec<(String, String)>,
+    /// 注釈のコメント（--comments json の場合のみ。appendix の場合は trailer に含める）
+    comments: Vec<comments::Comment>,
+    /// しおりで分けた本文（--split-by outline の場合のみ。text には最初の項目より前のページを入れる）
+    parts: Vec<split::Part>,
+    /// 表などを置き換える前のページの文字の配置（keep_layouts の場合のみ）
+    layouts: Vec<layout::PageLayout>,
+    /// フォントサイズで見出しを判定した（Markdown への変換で行の長さや大文字による推定を行わない）
+    font_headings: bool,
+}
+
+/// 変換する PDF（ファイルのパスか、標準入力などから読み込んだバイト列）
+#[derive(Debug, Clone, Copy)]
+enum PdfInput<'a> {
+    File(&'a Path),
+    /// PDF のバイト列と、メッセージに使う名前
+    Bytes(&'a [u8], &'a Path),
+}
+
+impl PdfInput<'_> {
+    /// メッセージに使うパス
+    fn path(&self) -> &Path {
+        match self {
+            PdfInput::File(path) | PdfInput::Bytes(_, path) => path,
+        }
+    }
+
+    /// 読み込んで、権限の確認と復号を行う
+    fn load(&self, options: &ExtractOptions) -> Result<lopdf::Document> {
+        match self {
+            PdfInput::File(path) => load_document(path, options),
+            PdfInput::Bytes(bytes, source) => prepare_document(read_document_bytes(bytes, source)?, source, options),
+        }
+    }
+
+    /// 権限を確認せずに読み込んで復号する（テキストを出力しない処理用）
+    fn open(&self, password: Option<&str>) -> Result<lopdf::Document> {
+        match self {
+            PdfInput::File(path) => open_document(path, password),
+            PdfInput::Bytes(bytes, source) => {
+                let mut doc = read_document_bytes(bytes, source)?;
+                decrypt_document(&mut doc, source, password)?;
+                Ok(doc)
+            }
+        }
+    }
+}
+
+/// PDF からテキスト内容を抽出する（中止が要求された場合は Cancelled のエラーを返す）
+fn extract_pdf_content(input: PdfInput, options: &ExtractOptions, cancel: &CancellationToken) -> Result<ExtractedContent> {
+    let mut doc = input.load(options)?;
+    cancel.check()?;
+    // 文字認識やページの画像化では pdftoppm にファイルを渡すので、バイト列は一時ファイルに書き出して渡す。
+    // 暗号化された文書は pdftoppm では開けないため、復号した文書を（画像を一時ファイルに移す前に）書き出す
+    let rasterized = options.ocr.is_some() || options.graphical_pages.is_some() || options.page_images.is_some() || options.layout_model.is_some();
+    let copy = match input {
+        _ if rasterized && doc.is_encrypted() => Some(TempPdf::write_decrypted(&doc)?),
+        PdfInput::Bytes(bytes, _) if rasterized => Some(TempPdf::write(bytes)?),
+        _ => None,
+    };
+    let spilled = spill_images(&mut doc, options)?;
+    extract_content(&doc, spilled.as_ref(), copy.as_ref().map_or(input.path(), TempPdf::path), options, cancel)
+}
+
+/// 暗号化された PDF を pdftoppm に渡すための、復号した一時ファイル（暗号化されていなければ None）
+fn decrypted_copy(pdf_path: &Path, password: Option<&str>) -> Result<Option<TempPdf>> {
+    let doc = open_document(pdf_path, password)?;
+    doc.is_encrypted().then(|| TempPdf::write_decrypted(&doc)).transpose()
+}
+
+/// 外部のコマンドに渡すために書き出した、バイト列の PDF の一時ファイル（破棄すると削除する）
+struct TempPdf {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl TempPdf {
+    fn write(bytes: &[u8]) -> Result<Self> {
+        let dir = work_dir("input");
+        std::fs::create_dir_all(&dir).with_context(|| format!("一時ディレクトリを作成できません: {:?}", dir))?;
+        let path = dir.join("input.pdf");
+        std::fs::write(&path, bytes).with_context(|| format!("PDF の一時ファイルを書き出せません: {:?}", path))?;
+        Ok(TempPdf { dir, path })
+    }
+
+    /// 復号した文書を、暗号化の指定を除いて書き出す
+    fn write_decrypted(doc: &lopdf::Document) -> Result<Self> {
+        let mut copy = doc.clone();
+        if let Ok(id) = copy.trailer.get(b"Encrypt").and_then(lopdf::Object::as_reference) {
+            copy.objects.remove(&id);
+        }
+        copy.trailer.remove(b"Encrypt");
+        let mut bytes = Vec::new();
+        copy.save_to(&mut bytes).context("復号した PDF を書き出せません")?;
+        Self::write(&bytes)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPdf {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// メモリの上限が指定されていれば、上限を超える分の画像のデータを一時ファイルに移す（戻り値を破棄すると一時ファイルを削除する）
+fn spill_images(doc: &mut lopdf::Document, options: &ExtractOptions) -> Result<Option<memory::SpilledImages>> {
+    let Some(budget) = options.memory_budget else {
+        return Ok(None);
+    };
+    let spilled = memory::spill_images(doc, budget)?;
+    if spilled.count > 0 {
+        console!(Info, "メモリの上限を超えるため、画像 {} 個（{} バイト）のデータを一時ファイルに移しました", spilled.count, spilled.bytes);
+    }
+    Ok(Some(spilled))
+}
+
+/// 読み込んだ文書からテキスト内容を抽出する（pdf_path はページを画像にする処理とメッセージに使う。spilled は一時ファイルに移した画像）
+///
+/// 中止が要求された場合は、ページの合間か処理の段階の合間で Cancelled のエラーを返す。
+fn extract_content(doc: &lopdf::Document, spilled: Option<&memory::SpilledImages>, pdf_path: &Path, options: &ExtractOptions, cancel: &CancellationToken) -> Result<ExtractedContent> {
+    let mut pages = layout_pages(doc, pdf_path, options, cancel)?;
+    if let Some(ocr) = &options.ocr {
+        recognize_pages(pdf_path, &mut pages, ocr)?;
+    }
+    tracing::debug!("{:?}: PDF {}、全 {} ページ中 {} ページを変換します", pdf_path, doc.version, doc.get_pages().len(), pages.len());
+    for page in &pages {
+        tracing::debug!(
+            page = page.number,
+            width = page.width.round(),
+            height = page.height.round(),
+            glyphs = page.glyphs.len(),
+            images = page.images.len(),
+            fills = page.fills.len(),
+            annotations = page.annotations.len(),
+            "ページを読み込みました"
+        );
+    }
+    // コメントの抜粋は、白紙の除去や書式の記号を付ける前の文字から取る
+    let mut comments = if options.comments.is_some() { comments::collect_comments(&pages) } else { Vec::new() };
+    // 柱とノンブルは、白紙のページを判定する前に取り除く（柱だけのページも白紙として扱う）
+    if !options.keep_headers {
+        let removed = running_headers::remove_running_headers(&mut pages);
+        if removed > 0 {
+            console!(Info, "ページの上下に繰り返し現れる柱とノンブル {} 行を取り除きました（--keep-headers で残せます）", removed);
+        }
+    }
+    let blank = blank_pages::remove_blank_pages(&mut pages, options.keep_blank_pages);
+    if blank > 0 && !options.keep_blank_pages {
+        console!(Info, "白紙の {} ページを省略しました", blank);
+    }
+    if options.dedupe_pages {
+        let collapsed = duplicates::collapse_duplicate_pages(&mut pages);
+        if collapsed > 0 {
+            console!(Info, "前のページと同じ内容の {} ページを省略しました", collapsed);
+        }
+    }
+    cancel.check()?;
+    // レイアウト解析のモデルで判定した柱・図・表の領域は、ほかの判定より先に適用する
+    let regions = match &options.layout_model {
+        Some(model) => Some(layout_model::analyze_pages(model, pdf_path, &mut pages)?),
+        None => None,
+    };
+    if let Some((assets_dir, link_dir)) = &options.graphical_pages {
+        let replaced = graphics::replace_graphical_pages(doc, spilled, pdf_path, &mut pages, assets_dir, link_dir)?;
+        if replaced > 0 {
+            console!(Info, "図のページ {} ページのテキストを、ページの画像への参照に置き換えました", replaced);
+        }
+    }
+    if let Some(page_images) = &options.page_images {
+        let exported = graphics::export_page_images(doc, pdf_path, &mut pages, page_images)?;
+        console!(Info, "{} ページの画像を {:?} に書き出しました", exported, page_images.assets_dir.join("pages"));
+    }
+    cancel.check()?;
+    if let Some((assets_dir, link_dir)) = &options.figures {
+        let embedded = figures::embed_figures(doc, spilled, &mut pages, assets_dir, link_dir)?;
+        if embedded > 0 {
+            console!(Info, "画像 {} 個を {:?} に書き出しました", embedded, assets_dir);
+        }
+    }
+    // 規則が無い場合は、本文との相対的なフォントサイズと太さから見出しを推定する（表を置き換える前の文字の書式から求める）
+    let heading_styles = match options.heading_styles.is_empty() && !options.ignore_font_sizes {
+        true => {
+            tracing::debug!("見出しの規則が無いため、本文との相対的なフォントサイズと太さから見出しを推定します");
+            font_styles::automatic_heading_styles(&pages, options.heading_scale.unwrap_or(font_styles::MIN_HEADING_SCALE))
+        }
+        false => options.heading_styles.clone(),
+    };
+    let mut warnings = diagnostics::collect_warnings(&pages);
+    // 表は --financial では検算して、そうでなければ本文の変換のときだけ Markdown の表にする
+    let convert_tables = options.financial || (!options.ignore_tables && options.mode == config::ConversionMode::Document);
+    let coverage = diagnostics::measure_coverage(&pages, convert_tables);
+    // 請求書の明細は、表を置き換える前の文字から取り出す
+    let invoice = options.invoice.then(|| invoice::extract_invoice(&pages));
+    if convert_tables {
+        warnings.retain(|warning| warning.kind != diagnostics::WarningKind::UnparsedTable);
+    }
+    let layouts = if options.keep_layouts { pages.clone() } else { Vec::new() };
+    // 等幅のフォントの行は、表と判定されないよう先にコードブロックにする
+    if options.mode == config::ConversionMode::Document {
+        let converted = code_blocks::convert_code_blocks(&mut pages);
+        tracing::debug!("コードブロック {} 個を作りました", converted);
+    }
+    if options.financial {
+        warnings.extend(financial::convert_tables(&mut pages));
+    } else if convert_tables {
+        let converted = tables::convert_tables(&mut pages);
+        tracing::debug!("表 {} 個を Markdown の表にしました", converted);
+    }
+    if options.placeholders {
+        diagnostics::insert_placeholders(&mut pages, &warnings);
+    }
+    let mut trailer = margin_notes::place_margin_notes(&mut pages, options.margin_notes);
+    cancel.check()?;
+    // フォントサイズやしおりによる見出しは、抽出した行の段階で Markdown の見出しにする
+    let mut detectors: Vec<Box<dyn HeadingDetector>> = Vec::new();
+    if let Some(regions) = regions {
+        detectors.push(Box::new(regions));
+    }
+    if options.bookmark_headings {
+        let bookmarks = destinations::bookmarks(doc, &destinations::Destinations::load(doc));
+        detectors.push(Box::new(headings::OutlineDetector::new(&bookmarks)));
+    }
+    let font_headings = !heading_styles.is_empty();
+    if font_headings {
+        tracing::debug!("フォントサイズによる見出しの対応: {:?}", heading_styles);
+        detectors.push(Box::new(font_styles::FontStyleDetector::new(heading_styles)));
+    }
+    let headings = headings::apply_detectors(&mut pages, &detectors);
+    tracing::debug!("見出しの判定方法 {} 個で {} 行を見出しにしました", detectors.len(), headings);
+    // 文書内へのリンクは、色やハイライトの書式を付ける前の見出しのテキストからアンカーを作る
+    let linked = links::apply_links(&mut pages);
+    tracing::debug!("リンクを {} 個作りました", linked);
+    colors::apply_color_rules(&mut pages, &options.colors);
+    if let Some(style) = options.highlights {
+        let summary = highlights::apply_highlights(&mut pages, style);
+        if !summary.is_empty() {
+            if !trailer.is_empty() {
+                trailer.push('\n');
+            }
+            trailer.push_str(&summary);
+        }
+    }
+    if options.comments == Some(CommentOutput::Appendix) {
+        let appendix = comments::render_appendix(&std::mem::take(&mut comments));
+        if !appendix.is_empty() {
+            if !trailer.is_empty() {
+                trailer.push('\n');
+            }
+            trailer.push_str(&appendix);
+        }
+    }
+    let articles = if options.articles { articles::segment_articles(&pages, options.dehyphenate.as_ref()) } else { Vec::new() };
+    if options.page_markers && options.mode == config::ConversionMode::Document {
+        review::insert_page_markers(&mut pages);
+    }
+    let mut bibliography = Vec::new();
+    let mut parts = Vec::new();
+    let text = match options.mode {
+        config::ConversionMode::Slides => slides::render_slides(doc, &pages),
+        config::ConversionMode::Email => email::render_email(&pages),
+        config::ConversionMode::Resume => resume::render_resume(&pages),
+        config::ConversionMode::Patent => {
+            let patent = patent::render_patent(&pages);
+            bibliography = patent.bibliography;
+            patent.markdown
+        }
+        config::ConversionMode::Document if options.split_by_outline => {
+            let bookmarks = destinations::bookmarks(doc, &destinations::Destinations::load(doc));
+            let ranges = split::outline_ranges(&bookmarks, &pages);
+            if ranges.is_empty() {
+                console!(Info, "移動先のあるしおりが無いため、見出しで分割します");
+            }
+            let first = ranges.first().map_or(pages.len(), |(_, _, range)| range.start);
+            parts = ranges
+                .into_iter()
+                .map(|(title, page, range)| split::Part { title, page: Some(page), content: paragraphs::pages_to_text(&pages[range], options.dehyphenate.as_ref()) })
+                .collect();
+            paragraphs::pages_to_text(&pages[..first], options.dehyphenate.as_ref())
+        }
+        config::ConversionMode::Document => paragraphs::pages_to_text(&pages, options.dehyphenate.as_ref()),
+    };
+
+    // 抜き取り変換では、全体の規模を見積もるための統計を表示する
+    if options.sample.is_some() {
+        let total = doc.get_pages().len();
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        let estimated = if pages.is_empty() { 0 } else { chars * total / pages.len() };
+        let numbers: Vec<String> = pages.iter().map(|page| page.number.to_string()).collect();
+        console!(Info, "抜き取り変換: 全 {} ページ中 {} ページ（{}）", total, pages.len(), numbers.join(", "));
+        console!(Info, "抜き取ったページの文字数: {}（全体の推定: {}）", chars, estimated);
+    }
+
+    Ok(ExtractedContent { text, warnings, coverage, trailer, invoice, articles, bibliography, comments, parts, font_headings, layouts })
+}
+
+/// PDFファイルからページごとのレイアウト情報を抽出する
+fn extract_pages(pdf_path: &Path, options: &ExtractOptions) -> Result<Vec<layout::PageLayout>> {
+    let doc = load_document(pdf_path, options)?;
+    layout_pages(&doc, pdf_path, options, &CancellationToken::new())
+}
+
+/// PDFファイルを読み込み、権限の確認と復号を行う
+fn load_document(pdf_path: &Path, options: &ExtractOptions) -> Result<lopdf::Document> {
+    prepare_document(read_document(pdf_path)?, pdf_path, options)
+}
+
+/// 読み込んだ文書の権限の確認と復号を行う
+fn prepare_document(mut doc: lopdf::Document, pdf_path: &Path, options: &ExtractOptions) -> Result<lopdf::Document> {
+    // 権限設定は復号すると文書から消えるため、先に確かめておく
+    let allows_copying = metadata::allows_copying(&doc);
+    let access = decrypt_document(&mut doc, pdf_path, options.password.as_deref())?;
+    let vertical_fonts = vertical::prepare_vertical_fonts(&mut doc);
+    if vertical_fonts > 0 {
+        tracing::debug!("縦書きのフォント {} 個を読み込めるようにしました", vertical_fonts);
+    }
+
+    // 権限設定でコピーが禁止されている場合は、明示的な指定かオーナーパスワードがない限り抽出しない
+    if !allows_copying && access != password::Access::Owner {
+        if !options.override_permissions {
+            bail!(
+                "PDFの権限設定でテキストのコピーが禁止されているため、変換を中止しました（--override-permissions で上書きできます）: {:?}",
+                pdf_path
+            );
+        }
+        console!(Info, "PDFの権限設定でコピーが禁止されていますが、--override-permissions の指定により抽出を続行します: {:?}", pdf_path);
+    }
+
+    if let Some(limits) = &options.robust {
+        robust::check_document(&doc, limits).with_context(|| format!("PDFが安全に変換できる上限を超えているため、変換を中止しました: {:?}", pdf_path))?;
+    }
+    Ok(doc)
+}
+
+/// 権限を確認せずにPDFファイルを読み込んで復号する（テキストを出力しない処理用）
+fn open_document(pdf_path: &Path, password: Option<&str>) -> Result<lopdf::Document> {
+    let mut doc = read_document(pdf_path)?;
+    decrypt_document(&mut doc, pdf_path, password)?;
+    Ok(doc)
+}
+
+/// PDFファイルを読み込む（PDF ではないファイルは読み込む前に NotPdf のエラーにする）
+fn read_document(pdf_path: &Path) -> Result<lopdf::Document> {
+    let kind = sniff::sniff_file(pdf_path)?;
+    if kind != ContentKind::Pdf {
+        return Err(NotPdf { kind }).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", pdf_path));
+    }
+    lopdf::Document::load(pdf_path).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", pdf_path))
+}
+
+/// PDF のバイト列を読み込む（PDF ではないバイト列は NotPdf のエラーにする。source はメッセージに使う）
+fn read_document_bytes(bytes: &[u8], source: &Path) -> Result<lopdf::Document> {
+    sniff::ensure_pdf(bytes).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", source))?;
+    lopdf::Document::load_mem(bytes).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", source))
+}
+
+/// 暗号化されたPDFを復号する（パスワードの指定が無ければ空のユーザーパスワードで復号する）
+fn decrypt_document(doc: &mut lopdf::Document, pdf_path: &Path, password: Option<&str>) -> Result<password::Access> {
+    password::decrypt(doc, password).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", pdf_path))
+}
+
+/// 文字のレイヤーが無いページ（--ocr の場合は全ページ）を文字認識し、ページの文字を認識結果に置き換える
+#[cfg(feature = "ocr")]
+fn recognize_pages(pdf_path: &Path, pages: &mut [layout::PageLayout], options: &ocr::OcrOptions) -> Result<()> {
+    let recognized = ocr::recognize_pages(pdf_path, pages, options)?;
+    if recognized.pages > 0 {
+        console!(Info, "{} ページを文字認識しました（言語: {}）", recognized.pages, options.lang);
+    }
+    if recognized.marked_words > 0 {
+        console!(Info, "確からしさが低い {} 語に {{?語?}} の目印を付けました", recognized.marked_words);
+    }
+    if recognized.corrected_words > 0 {
+        console!(Info, "文字認識の結果の {} 語を辞書で補正しました（補正した語は --log-file のログに記録します）", recognized.corrected_words);
+    }
+    Ok(())
+}
+
+/// ocr 機能を外してビルドした場合は、文字のレイヤーが無いページをそのまま変換する（--ocr の場合はエラー）
+#[cfg(not(feature = "ocr"))]
+fn recognize_pages(_pdf_path: &Path, _pages: &mut [layout::PageLayout], options: &ocr::OcrOptions) -> Result<()> {
+    if options.pages == ocr::OcrPages::All {
+        bail!("--ocr を使うには ocr 機能を有効にして pdf2md をビルドしてください");
+    }
+    Ok(())
+}
+
+/// 読み込んだ文書からページごとのレイアウト情報を抽出する
+fn layout_pages(doc: &lopdf::Document, pdf_path: &Path, options: &ExtractOptions, cancel: &CancellationToken) -> Result<Vec<layout::PageLayout>> {
+    // 抜き取り変換とページ範囲の指定では、対象外のページのレイアウト解析を行わない
+    let total = doc.get_pages().len();
+    let selected = options.sample.as_ref().map(|sample| sample.select(total as u32));
+    let included = |page: u32| selected.as_ref().is_none_or(|s| s.contains(&page)) && options.pages.as_ref().is_none_or(|ranges| ranges.contains(page));
+    let mut pages = layout::extract_layout(doc, included, cancel).with_context(|| format!("PDFからのテキスト抽出に失敗しました: {:?}", pdf_path))?;
+    cancel.check()?;
+    if options.pages.is_some() && pages.is_empty() {
+        bail!("指定されたページ範囲に変換するページがありません（全 {} ページ）: {:?}", total, pdf_path);
+    }
+
+    // 墨消しの矩形で覆われた文字は、PDF内に残っていても出力しない
+    if !options.ignore_redactions {
+        for page in &mut pages {
+            let removed = page.remove_redacted();
+            if removed > 0 {
+                console!(Info, "ページ {} の墨消し箇所の文字 {} 個を除外しました", page.number, removed);
+            }
+        }
+    }
+
+    // 欄外の注は段組みの判定より先に本文から取り出す
+    if options.margin_notes != MarginNoteStyle::Inline {
+        pages.iter_mut().for_each(layout::PageLayout::take_margin_notes);
+    }
+
+    // 段組みのページは読み順を並べ替える（段数の指定が無いページは文字の配置から推定する。記事に分ける場合は記事ごとに並べ替える）
+    let mut bilingual_pages = 0;
+    for page in &mut pages {
+        let boundaries = match options.layout.columns_for(page.number) {
+            Some(columns) => {
+                let boundaries = page.column_boundaries(columns);
+                page.reorder_at(&boundaries);
+                boundaries
+            }
+            None if !options.articles => {
+                let boundaries = page.detect_columns();
+                if !boundaries.is_empty() {
+                    tracing::debug!("ページ {} を {} 段組みとして並べ替えます", page.number, boundaries.len() + 1);
+                    page.reorder_at(&boundaries);
+                }
+                boundaries
+            }
+            None => Vec::new(),
+        };
+        // 二言語の文書では、左右の段の言語が異なる2段組みのページを言語ごとの段落に並べ直す
+        if let (Some(output), [boundary]) = (options.bilingual, boundaries.as_slice()) {
+            if bilingual::arrange_parallel_columns(page, *boundary, output) {
+                bilingual_pages += 1;
+            }
+        }
+    }
+    if let Some(output) = options.bilingual {
+        if bilingual_pages == 0 {
+            bilingual_pages = bilingual::arrange_alternating_pages(&mut pages, output);
+        }
+        match bilingual_pages {
+            0 => console!(Info, "左右の段や交互のページで言語が異なる箇所が見つからないため、通常の文書として変換します"),
+            pages => console!(Info, "{} ページを日本語と英語の二言語の文書として変換します", pages),
+        }
+    }
+
+    Ok(pages)
+}
+
+/// Markdown への変換のオプション
+#[derive(Default)]
+struct MarkdownOptions<'a> {
+    /// 汎用の判定より優先する見出しの判定方法
+    headings: &'a [Box<dyn HeadingDetector>],
+    /// 発言者の書式（None の場合は発言者を検出しない）
+    transcript: Option<TranscriptStyle>,
+    /// 見出しを抽出時にフォントサイズで判定済み（既に見出しにした行以外は、行の長さや大文字から見出しと推定しない）
+    font_headings: bool,
+    /// 番号や行の長さから見出しを推定しない（既に # の付いた行と、判定方法に一致した行だけを見出しにする）
+    ignore_guessed_headings: bool,
+    /// 全て大文字の語を太字にしない
+    ignore_uppercase: bool,
+}
+
+/// 抽出したPDFコンテンツをMarkdownに変換する
+fn convert_to_markdown(content: String, options: &MarkdownOptions) -> Result<String> {
+    // 発言録では、段落の途中から始まる発言を別の段落に分けておく
+    let content = match options.transcript {
+        Some(_) => transcript::split_turns(&content),
+        None => content,
+    };
+
+    // PDFから抽出したテキストを解析して構造を把握（出力は行の部分文字列を1つのバッファに書き足して組み立て、語や行ごとの文字列を作らない）
+    let mut markdown = String::with_capacity(content.len() + content.len() / 8);
+    let lines = content.lines();
+
+    // 前の行のフォントサイズや太さなどを格納する変数（実際のPDF解析では必要になる可能性があります）
+    let mut current_block_type = "p"; // デフォルトは段落
+
+    let mut in_table = false;
+    // 箇条書きの項目は空行を挟まずに続ける
+    let mut list = lists::ListWriter::default();
+    let mut in_list = false;
+    let mut in_code = false;
+    let push_text: fn(&mut String, &str) = if options.ignore_uppercase { push_words } else { push_formatted };
+
+    for line in lines {
+        let trimmed = line.trim();
+        // コードブロックの中は、字下げも含めてそのまま出力する
+        let fence = trimmed == "```";
+        if in_code || fence {
+            if !in_code {
+                if in_table || in_list {
+                    markdown.push('\n');
+                }
+                list.clear();
+                (in_table, in_list) = (false, false);
+                if !markdown.is_empty() && !markdown.ends_with("\n\n") {
+                    markdown.push_str("\n\n");
+                }
+            }
+            markdown.push_str(if fence { trimmed } else { line });
+            markdown.push('\n');
+            if in_code && fence {
+                markdown.push('\n');
+                current_block_type = "h";
+            }
+            in_code ^= fence;
+            continue;
+        }
+        if in_table && !tables::is_table_row(trimmed) {
+            markdown.push('\n');
+            in_table = false;
+        }
+        if in_list && !trimmed.is_empty() && lists::list_item(trimmed).is_none() {
+            markdown.push('\n');
+            list.clear();
+            in_list = false;
+        }
+        if trimmed.is_empty() {
+            if !in_list {
+                markdown.push_str("\n\n");
+            }
+            continue;
+        }
+
+        // --financial で変換した表の行は、空行を挟まずに続けてそのまま出力する
+        if tables::is_table_row(trimmed) {
+            if !in_table && !markdown.is_empty() && !markdown.ends_with("\n\n") {
+                markdown.push_str("\n\n");
+            }
+            markdown.push_str(trimmed);
+            markdown.push('\n');
+            in_table = true;
+            current_block_type = "h";
+            continue;
+        }
+
+        // --placeholders の目印、欄外の注の引用ブロック、図のページの画像、二言語の文書の言語の見出しはそのまま独立した段落にする
+        if diagnostics::is_placeholder(trimmed) || margin_notes::is_aside(trimmed) || graphics::is_image_line(trimmed) || bilingual::is_label(trimmed) {
+            if !markdown.is_empty() && !markdown.ends_with("\n\n") {
+                markdown.push_str("\n\n");
+            }
+            // 注意書きの1行目（> [!WARNING]）に続く行は、同じ引用ブロックにする
+            if margin_notes::is_aside(trimmed) && markdown.trim_end().lines().last().is_some_and(colors::is_admonition_marker) {
+                markdown.pop();
+            }
+            markdown.push_str(trimmed);
+            markdown.push_str("\n\n");
+            current_block_type = "h";
+            continue;
+        }
+
+        // 箇条書きの項目（行頭の字下げの深さを入れ子の深さとする）
+        if let Some(item) = lists::list_item(trimmed) {
+            if !in_list && !markdown.is_empty() && !markdown.ends_with("\n\n") {
+                markdown.push_str("\n\n");
+            }
+            let depth = (line.len() - line.trim_start().len()) / 2;
+            list.push_item(&mut markdown, depth, &item, push_text);
+            in_list = true;
+            current_block_type = "h";
+            continue;
+        }
+
+        // 発言者で始まる行は、見出しとしては扱わずに発言として整形する
+        if let Some((style, caps)) = options.transcript.and_then(|style| Some((style, transcript::speaker_turn(trimmed)?))) {
+            if !markdown.is_empty() && !markdown.ends_with("\n\n") {
+                markdown.push_str("\n\n");
+            }
+            transcript::push_turn(&mut markdown, style, &caps, push_text);
+            markdown.push_str("\n\n");
+            current_block_type = "h";
+            continue;
+        }
+
+        // 見出しの検出（プロファイルの規則などの判定方法を優先し、いずれも一致しなければ単純化した汎用の判定を行う）
+        let line = HeadingLine { text: trimmed, page: None, style: None };
+        let detected = options.headings.iter().find_map(|detector| detector.detect(&line));
+        let generic = || detect_heading(trimmed).filter(|_| !(options.font_headings || options.ignore_guessed_headings) || trimmed.starts_with('#'));
+        if let Some((heading_level, text)) = detected.or_else(generic) {
+            markdown.extend(std::iter::repeat_n('#', heading_level));
+            markdown.push(' ');
+            markdown.push_str(text);
+            markdown.push_str("\n\n");
+            current_block_type = "h";
+            continue;
+        }
+
+        // 段落の処理（強調などの書式を付けながら書き足す）
+        if current_block_type == "p" {
+            // 継続する段落かどうかを判断
+            if !markdown.ends_with("\n\n") && !markdown.is_empty() {
+                markdown.push(' ');
+            }
+            push_text(&mut markdown, trimmed);
+        } else {
+            push_text(&mut markdown, trimmed);
+            markdown.push_str("\n\n");
+            current_block_type = "p";
+        }
+    }
+
+    Ok(markdown)
+}
+
+/// 見出しの接頭辞（番号や #）と本文を分ける正規表現
+static HEADING_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(\d+\.\s+|#+\s+)?(.+)$").unwrap());
+
+/// 前後の空白を除いた行が見出しであれば、見出しレベルと見出しテキストを返す
+fn detect_heading(trimmed: &str) -> Option<(usize, &str)> {
+    let caps = HEADING_REGEX.captures(trimmed)?;
+    let prefix = caps.get(1).map_or("", |m| m.as_str());
+    let text = caps.get(2).map_or(trimmed, |m| m.as_str());
+
+    // 既に Markdown の見出しの行（フォントサイズの規則で見出しにした行など）は、# の数をレベルとする
+    let hashes = prefix.trim_end();
+    if hashes.starts_with('#') && hashes.len() <= 6 {
+        return Some((hashes.len(), text));
+    }
+
+    // 数字+ドットで始まるか、大きなフォントサイズの場合は見出しと推定
+    if prefix.contains('.') || is_likely_heading(trimmed) {
+        Some((determine_heading_level(prefix, trimmed), text))
+    } else {
+        None
+    }
+}
+
+/// 行が見出しである可能性を判定（単純化）
+fn is_likely_heading(line: &str) -> bool {
+    // この実装は単純化しています。実際はPDFのフォントサイズ等を見る必要があります
+    // 日本語の句点と読点も、英語のピリオドとカンマと同じに扱う
+    line.len() < 100 && !line.ends_with(['.', '。']) && !line.contains([',', '、'])
+}
+
+/// 見出しレベルを決定（単純化）
+fn determine_heading_level(prefix: &str, text: &str) -> usize {
+    // この実装は単純化しています。実際はPDFの階層構造を見る必要があります
+    if prefix.starts_with("1.") {
+        1
+    } else if prefix.starts_with("1.1") || prefix.starts_with("2.") {
+        2
+    } else if text.len() < 30 && text.to_uppercase() == text {
+        1 // 短くて全て大文字の場合はH1と推定
+    } else {
+        3
+    }
+}
+
+/// テキスト内の強調などの書式を検出し、Markdown形式にして出力に書き足す（語は元のテキストの部分文字列のまま書き足す）
+fn push_formatted(markdown: &mut String, text: &str) {
+    // この実装は単純化しています。実際はPDFのスタイル情報を見る必要があります
+    // ここでは仮に、全て大文字のワードを強調（太字）とする
+    for (index, word) in text.split_whitespace().enumerate() {
+        if index > 0 {
+            markdown.push(' ');
+        }
+        // 文字の色などで既に太字にした語はそのままにする
+        let formatted = word.starts_with("**") || word.ends_with("**");
+        // 大文字と小文字の区別が無い文字（かなや漢字）だけの語は対象にしない
+        let upper_case = word.chars().all(|c| !c.is_lowercase()) && word.chars().any(char::is_uppercase);
+        if !formatted && upper_case && word.len() > 1 {
+            markdown.push_str("**");
+            markdown.push_str(word);
+            markdown.push_str("**");
+        } else {
+            markdown.push_str(word);
+        }
+    }
+}
+
+/// テキストの語を、書式を付けずに空白1つで区切って出力に書き足す（設定ファイルで uppercase_bold = false の場合）
+fn push_words(markdown: &mut String, text: &str) {
+    for (index, word) in text.split_whitespace().enumerate() {
+        if index > 0 {
+            markdown.push(' ');
+        }
+        markdown.push_str(word);
+    }
+}
+
+/// 一時ファイルを置くディレクトリ（同じプロセスで並行して変換しても重ならないよう、呼び出すたびに別の名前にする）
+fn work_dir(kind: &str) -> PathBuf {
+    static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!("pdf2md-{}-{}-{}", kind, std::process::id(), SEQUENCE.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Markdownをファイルに書き込む
+fn write_to_file(path: &Path, content: &str) -> Result<()> {
+    write_encoded(path, content, OutputEncoding::Utf8)
+}
+
+/// Markdown を指定した文字コードでファイルに書き込む（変換できない文字がある場合は、ファイルを作らずにエラーにする）
+fn write_encoded(path: &Path, content: &str, encoding: OutputEncoding) -> Result<()> {
+    let bytes = encoding.encode(content).with_context(|| format!("出力ファイルに書き込めません: {:?}", path))?;
+    let mut file = File::create(path)
+        .with_context(|| format!("出力ファイルの作成に失敗しました: {:?}", path))?;
+
+    file.write_all(&bytes)
+        .with_context(|| "ファイルへの書き込みに失敗しました")?;
+
+    Ok(())
+}
+
+/// 標準出力に書き出す（出力先に - を指定した場合）
+fn write_to_stdout(content: &str, encoding: OutputEncoding) -> Result<()> {
+    let bytes = encoding.encode(content).context("標準出力に書き出せません")?;
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(&bytes)
+        .and_then(|()| stdout.flush())
+        .context("標準出力への書き込みに失敗しました")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_pdf::{Font, PdfBuilder};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// スレッドごとにメモリの確保の回数を数えるアロケーター（並行して動く他のテストの確保は数えない）
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// f を実行する間にこのスレッドで確保したメモリの回数
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let value = f();
+        (value, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    /// 1ページに段落を1行ずつ置いた PDF
+    fn sample_pdf(lines: &[&str]) -> Vec<u8> {
+        let mut pdf = PdfBuilder::new();
+        for (index, line) in lines.iter().enumerate() {
+            pdf.text(72.0, 720.0 - 40.0 * index as f64, 12.0, Font::Regular, line);
+        }
+        pdf.build()
+    }
+
+    // 単体テスト: ライブラリとしての変換
+    #[test]
+    fn test_convert_bytes() {
+        let markdown = convert_bytes(&sample_pdf(&["1. INTRODUCTION", "This is a sample text."])).unwrap();
+        assert_eq!(markdown.trim(), "# INTRODUCTION\n\nThis is a sample text.");
+        assert!(convert_bytes(b"not a pdf").is_err());
+    }
+
+    // 単体テスト: バイト列の PDF からの抽出
+    #[test]
+    fn test_extract_pdf_bytes() {
+        let pdf = sample_pdf(&["Body text from a pipe."]);
+        let input = PdfInput::Bytes(&pdf, Path::new("<stdin>"));
+        let extracted = extract_pdf_content(input, &ExtractOptions::default(), &CancellationToken::new()).unwrap();
+        assert_eq!(extracted.text.trim(), "Body text from a pipe.");
+        assert_eq!(input.open(None).unwrap().get_pages().len(), 1);
+
+        let Err(error) = extract_pdf_content(PdfInput::Bytes(b"<html>", Path::new("<stdin>")), &ExtractOptions::default(), &CancellationToken::new()) else {
+            panic!("PDF ではないバイト列を読み込みました");
+        };
+        assert!(error.downcast_ref::<NotPdf>().is_some());
+    }
+
+    // 単体テスト: --pages で指定したページだけの抽出
+    #[test]
+    fn test_extract_page_ranges() {
+        let mut pdf = PdfBuilder::new();
+        for text in ["First page.", "Second page.", "Third page."] {
+            pdf.page().paragraph(&[text]);
+        }
+        let pdf = pdf.build();
+        let input = PdfInput::Bytes(&pdf, Path::new("<stdin>"));
+        let options = ExtractOptions { pages: Some("2-".parse().unwrap()), ..Default::default() };
+        let extracted = extract_pdf_content(input, &options, &CancellationToken::new()).unwrap();
+        assert!(!extracted.text.contains("First page."));
+        assert!(extracted.text.contains("Second page.") && extracted.text.contains("Third page."));
+
+        // 文書に無いページだけを指定した場合はエラーにする
+        let options = ExtractOptions { pages: Some("5-9".parse().unwrap()), ..Default::default() };
+        let Err(error) = extract_pdf_content(input, &options, &CancellationToken::new()) else {
+            panic!("文書に無いページを変換しました");
+        };
+        assert!(format!("{:#}", error).contains("指定されたページ範囲に変換するページがありません（全 3 ページ）"));
+    }
+
+    // 単体テスト: 変換の中止
+    #[test]
+    fn test_cancellation() {
+        let pdf = sample_pdf(&["Body text."]);
+        let cancel = CancellationToken::new();
+        let clone = cancel.clone();
+        clone.cancel();
+        let error = Converter::new().convert_bytes_cancellable(&pdf, &cancel).unwrap_err();
+        assert_eq!(error.downcast_ref::<Cancelled>(), Some(&Cancelled));
+
+        // 読み込んだ後のページの抽出でも、文脈を付けずに Cancelled を返す
+        let doc = lopdf::Document::load_mem(&pdf).unwrap();
+        let Err(error) = extract_content(&doc, None, Path::new("sample.pdf"), &ExtractOptions::default(), &cancel) else {
+            panic!("中止されていません");
+        };
+        assert_eq!(error.to_string(), "変換が中止されました");
+    }
+
+    // 単体テスト: pdftoppm に渡す、復号した文書の一時ファイル
+    #[test]
+    fn test_write_decrypted() {
+        let mut doc = lopdf::Document::load_mem(&sample_pdf(&["Secret report"])).unwrap();
+        let encrypt = doc.add_object(lopdf::dictionary! { "Filter" => "Standard" });
+        doc.trailer.set("Encrypt", encrypt);
+        assert!(doc.is_encrypted());
+
+        let copy = TempPdf::write_decrypted(&doc).unwrap();
+        let written = lopdf::Document::load(copy.path()).unwrap();
+        assert!(!written.is_encrypted() && written.get_object(encrypt).is_err());
+        assert_eq!(written.extract_text(&[1]).unwrap().trim(), "Secret report");
+        let dir = copy.dir.clone();
+        drop(copy);
+        assert!(!dir.exists());
+    }
+
+    // 単体テスト: ページごとの書き出し
+    #[test]
+    fn test_write_bytes() {
+        let pdf = sample_pdf(&["1. INTRODUCTION", "This is a sample text."]);
+        let converter = Converter::new();
+        let mut writer = MarkdownWriter::new(Vec::new(), FlushPolicy::Page);
+        converter.write_bytes(&pdf, &mut writer, &CancellationToken::new()).unwrap();
+        // ページの目印は書き出さない
+        assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), converter.convert_bytes(&pdf).unwrap());
+    }
+
+    // 単体テスト: Markdown への変換が語の数に比例してメモリを確保しないこと
+    #[test]
+    fn test_convert_allocations() {
+        // 行の数が同じで、1行の語の数が違うテキスト
+        let content = |words: usize| {
+            let line = (0..words).map(|i| if i % 3 == 0 { "WORD" } else { "word," }).collect::<Vec<_>>().join(" ");
+            vec![line; 40].join("\n")
+        };
+        let options = MarkdownOptions::default();
+        // 正規表現のキャッシュなど、初回だけの確保を済ませておく
+        convert_to_markdown(content(10), &options).unwrap();
+
+        let (short, short_allocations) = count_allocations(|| convert_to_markdown(content(10), &options).unwrap());
+        let (long, long_allocations) = count_allocations(|| convert_to_markdown(content(500), &options).unwrap());
+        assert!(long.len() > short.len() * 40);
+        // 語が約2万増えても、増えるのは文字列を伸ばすときの確保（長さの対数の回数）だけ
+        assert!(long_allocations < short_allocations + 20, "{} → {}", short_allocations, long_allocations);
+
+        // 書式を付けた語は、元のテキストの部分文字列のまま出力に書き足す
+        let text = content(500);
+        let mut markdown = String::with_capacity(text.len() * 2);
+        let ((), allocations) = count_allocations(|| push_formatted(&mut markdown, &text));
+        assert!(markdown.starts_with("**WORD** word, word, **WORD**"));
+        assert_eq!(allocations, 0);
+    }
+
+    struct UpperCaseDetector;
+
+    impl HeadingDetector for UpperCaseDetector {
+        fn detect<'a>(&self, line: &HeadingLine<'a>) -> Option<(usize, &'a str)> {
+            line.text.starts_with("SECTION").then_some((2, line.text))
+        }
+    }
+
+    // 単体テスト: 複数のスレッドで共有する変換処理
+    #[test]
+    fn test_converter_shared_across_threads() {
+        let converter = Converter::with_heading_detectors(vec![Box::new(UpperCaseDetector)]);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let converter = converter.clone();
+                let pdf = sample_pdf(&[&format!("SECTION {}", i), "Body text, continued."]);
+                std::thread::spawn(move || converter.convert_bytes(&pdf).unwrap())
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap().trim(), format!("## SECTION {}\n\nBody text, continued.", i));
+        }
+    }
+}
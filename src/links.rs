@@ -0,0 +1,180 @@
+use crate::destinations::{self, Destination};
+use crate::highlights;
+use crate::layout::PageLayout;
+use crate::detect_heading;
+
+/// リンクの注釈が覆う文字を Markdown のリンクにし、リンクにした数を返す
+///
+/// 外部へのリンク（URI アクション）は URI へ、文書内へのリンク（GoTo、名前付きの移動先を含む）は移動先の見出しへのリンクにする。
+/// 移動先のページが変換の対象外の場合や、移動先より前に見出しが無い場合はリンクにしない。
+pub fn apply_links(pages: &mut [PageLayout]) -> usize {
+    let headings = page_headings(pages);
+    let mut linked = 0;
+
+    for page in pages.iter_mut() {
+        let links: Vec<(usize, String)> = page
+            .annotations
+            .iter()
+            .enumerate()
+            .filter(|(_, annotation)| annotation.subtype == "Link")
+            .filter_map(|(index, annotation)| match &annotation.uri {
+                Some(uri) => Some((index, link_destination(uri)?)),
+                None => Some((index, format!("#{}", anchor(&headings, annotation.target?)?))),
+            })
+            .collect();
+
+        for (index, destination) in links {
+            let covering: Vec<Option<usize>> = page
+                .glyphs
+                .iter()
+                .map(|glyph| {
+                    let (x, y) = (glyph.x + glyph.width / 2.0, glyph.y - glyph.font_size * 0.3);
+                    let covered = page.annotations[index].areas.iter().any(|area| area.contains(x, y, 1.0));
+                    (covered && !glyph.text.trim().is_empty()).then_some(index)
+                })
+                .collect();
+            // 1つの注釈が行をまたいで複数の範囲を覆う場合も、1つのリンクにする
+            let runs = highlights::highlighted_runs(&page.glyphs, &covering);
+            let (Some(first), Some(last)) = (runs.first(), runs.last()) else {
+                continue;
+            };
+            let (start, end) = (first.start, last.end - 1);
+            page.glyphs[start].text.insert(0, '[');
+            page.glyphs[end].text.push_str(&format!("]({})", destination));
+            linked += 1;
+        }
+    }
+
+    linked
+}
+
+/// Markdown のリンク先に書ける形にした URI（スクリプトを実行する javascript: の URI はリンクにしない）
+///
+/// 空白と括弧はリンク先の終わりと区別できるように % で符号化する。
+fn link_destination(uri: &str) -> Option<String> {
+    if uri.get(..11).is_some_and(|scheme| scheme.eq_ignore_ascii_case("javascript:")) {
+        return None;
+    }
+    let mut destination = String::with_capacity(uri.len());
+    for c in uri.chars() {
+        match c {
+            ' ' => destination.push_str("%20"),
+            '(' => destination.push_str("%28"),
+            ')' => destination.push_str("%29"),
+            '<' => destination.push_str("%3C"),
+            '>' => destination.push_str("%3E"),
+            c if c.is_control() => {}
+            c => destination.push(c),
+        }
+    }
+    Some(destination)
+}
+
+/// ページごとの見出しの (ページ内の y 座標、アンカー)（y 下向きで、上から順）
+struct PageHeadings {
+    number: u32,
+    height: f64,
+    headings: Vec<(f64, String)>,
+}
+
+fn page_headings(pages: &[PageLayout]) -> Vec<PageHeadings> {
+    pages
+        .iter()
+        .map(|page| PageHeadings {
+            number: page.number,
+            height: page.height,
+            headings: page
+                .lines()
+                .into_iter()
+                .filter_map(|line| {
+                    let (_, text) = detect_heading(line.text.trim())?;
+                    // 見出しの上端（移動先の位置はふつう見出しの少し上を指す）
+                    Some((line.y - line.font_size * 1.5, destinations::slug(text)))
+                })
+                .filter(|(_, anchor)| !anchor.is_empty())
+                .collect(),
+        })
+        .collect()
+}
+
+/// 移動先を含む節の見出しのアンカー（移動先の位置より前にある最後の見出し。前のページまでさかのぼる）
+///
+/// 位置の指定が無い移動先（/Fit など）は、そのページの最初の見出しとする。
+fn anchor(headings: &[PageHeadings], dest: Destination) -> Option<String> {
+    let index = headings.iter().position(|page| page.number == dest.page)?;
+    let target = &headings[index];
+    match dest.top {
+        Some(top) => {
+            let y = target.height - top;
+            target
+                .headings
+                .iter()
+                .rev()
+                .find(|(heading_y, _)| *heading_y <= y)
+                .or_else(|| headings[..index].iter().rev().find_map(|page| page.headings.last()))
+                .map(|(_, anchor)| anchor.clone())
+        }
+        None => target.headings.first().or_else(|| headings[..index].iter().rev().find_map(|page| page.headings.last())).map(|(_, anchor)| anchor.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{Annotation, Area};
+    use crate::layout::Glyph;
+
+    fn line(text: &str, y: f64, order: usize) -> Glyph {
+        Glyph { text: text.to_string(), x: 72.0, y, width: text.len() as f64 * 5.0, font_size: 10.0, word_start: true, order, color: None, bold: false, monospace: false, confidence: None }
+    }
+
+    // 単体テスト: 文書内と外部へのリンク
+    #[test]
+    fn test_apply_links() {
+        let link = |y0: f64, page: u32, top: Option<f64>, uri: Option<&str>| Annotation {
+            id: None,
+            subtype: "Link".to_string(),
+            areas: vec![Area { x0: 70.0, y0, x1: 200.0, y1: y0 + 12.0 }],
+            contents: None,
+            author: None,
+            modified: None,
+            in_reply_to: None,
+            target: Some(Destination { page, top }),
+            uri: uri.map(str::to_string),
+        };
+        let toc = PageLayout {
+            number: 1,
+            height: 800.0,
+            glyphs: vec![
+                line("Contents", 100.0, 0),
+                line("Methods overview", 120.0, 1),
+                line("Results", 140.0, 2),
+                line("Gone", 160.0, 3),
+                line("Project site", 180.0, 4),
+                line("Script", 200.0, 5),
+            ],
+            annotations: vec![
+                link(110.0, 2, Some(710.0), None),
+                link(130.0, 2, Some(400.0), None),
+                link(150.0, 9, None, None),
+                link(170.0, 1, None, Some("https://example.com/a b (1)")),
+                link(190.0, 1, None, Some("JavaScript:alert(1)")),
+            ],
+            ..Default::default()
+        };
+        let body = PageLayout {
+            number: 2,
+            height: 800.0,
+            glyphs: vec![line("2. Methods", 100.0, 0), line("We measured it.", 120.0, 1), line("3. Results", 380.0, 2)],
+            ..Default::default()
+        };
+        let mut pages = vec![toc, body];
+
+        assert_eq!(apply_links(&mut pages), 3);
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["Contents", "[Methods overview](#methods)", "[Results](#results)", "Gone", "[Project site](https://example.com/a%20b%20%281%29)", "Script"]
+        );
+    }
+}
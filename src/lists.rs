@@ -0,0 +1,90 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::slides;
+
+/// 「1)」「2）」の形式の番号で始まる行の正規表現（特許の書誌事項の「(10)」のような括弧で囲んだ番号は含めない）
+static ORDERED_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{1,3})[)）]\s+(\S.*)$").unwrap());
+
+/// 箇条書きの項目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListItem<'a> {
+    /// 番号付きの項目の番号（行頭記号の項目は None）
+    pub number: Option<u32>,
+    /// 行頭記号や番号を除いた本文
+    pub text: &'a str,
+}
+
+/// 行頭記号（•、‣、- など）か「1)」の形式の番号で始まる行であれば、箇条書きの項目を返す
+pub fn list_item(text: &str) -> Option<ListItem<'_>> {
+    if let Some(text) = slides::bullet_text(text) {
+        return Some(ListItem { number: None, text });
+    }
+    let caps = ORDERED_REGEX.captures(text.trim())?;
+    Some(ListItem { number: caps[1].parse().ok(), text: caps.get(2)?.as_str().trim_end() })
+}
+
+/// Markdown の箇条書きの項目の行（- か 1. で始まる行）かどうか
+pub fn is_markdown_item(line: &str) -> bool {
+    let line = line.trim_start();
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    line.starts_with("- ") || (digits > 0 && line[digits..].starts_with(". "))
+}
+
+/// 箇条書きを Markdown の項目にする（入れ子の項目は、親の項目の本文の位置まで字下げする）
+#[derive(Debug, Default)]
+pub struct ListWriter {
+    /// 開いている階層ごとの、項目の記号の幅（「- 」は 2、「10. 」は 4）
+    widths: Vec<usize>,
+}
+
+impl ListWriter {
+    /// 項目を1行書き足す（depth は字下げの深さ。開いている階層より2つ以上深い場合は1つ深い階層とする）
+    pub fn push_item(&mut self, markdown: &mut String, depth: usize, item: &ListItem, push_text: fn(&mut String, &str)) {
+        self.widths.truncate(depth);
+        let indent: usize = self.widths.iter().sum();
+        let marker = match item.number {
+            Some(number) => format!("{}. ", number),
+            None => "- ".to_string(),
+        };
+        markdown.extend(std::iter::repeat_n(' ', indent));
+        markdown.push_str(&marker);
+        push_text(markdown, item.text);
+        markdown.push('\n');
+        self.widths.push(marker.len());
+    }
+
+    /// 箇条書きを終える
+    pub fn clear(&mut self) {
+        self.widths.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 箇条書きの項目の判定と入れ子の字下げ
+    #[test]
+    fn test_list_items() {
+        assert_eq!(list_item("• First point"), Some(ListItem { number: None, text: "First point" }));
+        assert_eq!(list_item("‣ Sub point"), Some(ListItem { number: None, text: "Sub point" }));
+        assert_eq!(list_item("12) Twelfth"), Some(ListItem { number: Some(12), text: "Twelfth" }));
+        assert_eq!(list_item("3） Third"), Some(ListItem { number: Some(3), text: "Third" }));
+        assert_eq!(list_item("(10) Patent No.: US 10,123,456 B2"), None);
+        assert_eq!(list_item("-1 is not a bullet"), None);
+        assert_eq!(list_item("1. Introduction"), None);
+        assert_eq!(list_item("2020) was a year"), None);
+
+        let mut writer = ListWriter::default();
+        let mut markdown = String::new();
+        let push = |markdown: &mut String, text: &str| markdown.push_str(text);
+        writer.push_item(&mut markdown, 0, &ListItem { number: Some(1), text: "Setup" }, push);
+        writer.push_item(&mut markdown, 1, &ListItem { number: None, text: "Install" }, push);
+        writer.push_item(&mut markdown, 3, &ListItem { number: None, text: "Too deep" }, push);
+        writer.push_item(&mut markdown, 0, &ListItem { number: Some(10), text: "Run" }, push);
+        writer.push_item(&mut markdown, 1, &ListItem { number: None, text: "Check" }, push);
+        assert_eq!(markdown, "1. Setup\n   - Install\n     - Too deep\n10. Run\n    - Check\n");
+        assert!(is_markdown_item("   - Install") && is_markdown_item("10. Run") && !is_markdown_item("-1 is not"));
+    }
+}
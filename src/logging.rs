@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::{Level, Metadata};
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, Layer, Registry};
+
+/// 標準エラー出力に表示し、--log-file のログにも同じ内容を書く
+///
+/// 1つ目の引数はログの重要度（Error、Warn、Info など）。--progress-format jsonl の場合は message のイベントとして表示し、
+/// --log-format json の場合は JSON のログの行として表示する。
+#[macro_export]
+macro_rules! console {
+    (@level Error) => { tracing::Level::ERROR };
+    (@level Warn) => { tracing::Level::WARN };
+    (@level Info) => { tracing::Level::INFO };
+    (@level Debug) => { tracing::Level::DEBUG };
+    (@level Trace) => { tracing::Level::TRACE };
+    ($level:ident, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        tracing::event!($crate::console!(@level $level), "{}", message);
+        $crate::progress::console_message(log::Level::$level, &message);
+    }};
+}
+
+/// 標準エラー出力に表示するログの形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// 人が読むメッセージ（-v の詳細は重要度と出どころを付けた行）
+    #[default]
+    Text,
+    /// 1行に1つの JSON のログ（時刻、重要度、出どころ、メッセージと項目。パイプラインで扱う向け）
+    Json,
+}
+
+/// ログの設定
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogOptions<'a> {
+    /// デバッグの詳細まで含めたログを追記するファイル
+    pub file: Option<&'a Path>,
+    /// 標準エラー出力に表示する詳細の度合い（1 で pdf2md のデバッグの詳細、2 以上で PDF の読み込みのライブラリを含むすべて）
+    pub verbose: u8,
+    pub format: LogFormat,
+}
+
+/// JSON のログを表示するかどうか（プロセス全体で1つ）
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// ログのファイルの時刻の形式
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// ログの書き出し先を決める（PDF の読み込みのライブラリが log に書いたログも受け取る）
+pub fn init(options: &LogOptions) -> Result<()> {
+    JSON.store(options.format == LogFormat::Json, Ordering::Relaxed);
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+
+    if let Some(path) = options.file {
+        let file = OpenOptions::new().create(true).append(true).open(path).with_context(|| format!("ログファイルを開けません: {:?}", path))?;
+        let layer = fmt::layer().with_ansi(false).with_timer(ChronoLocal::new(TIME_FORMAT.to_string())).with_writer(Mutex::new(file));
+        layers.push(layer.with_filter(LevelFilter::DEBUG).boxed());
+    }
+
+    let (json, verbose) = (options.format == LogFormat::Json, options.verbose);
+    if json || verbose > 0 {
+        // 人が読む形式では、console! のメッセージ（Info 以上）はそのまま表示するので、詳細だけを表示する
+        let shown = move |metadata: &Metadata| {
+            let level = *metadata.level();
+            let ours = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+            let detail = match verbose {
+                0 => false,
+                1 => ours && level == Level::DEBUG,
+                _ => level > Level::INFO,
+            };
+            detail || (json && level <= Level::INFO)
+        };
+        let layer = match json {
+            true => fmt::layer().json().flatten_event(true).with_timer(ChronoLocal::rfc_3339()).with_writer(StderrWriter).boxed(),
+            false => fmt::layer().with_ansi(false).without_time().with_writer(StderrWriter).boxed(),
+        };
+        layers.push(layer.with_filter(filter_fn(shown)).boxed());
+    }
+
+    tracing_subscriber::registry().with(layers).try_init().context("ロガーを設定できません")?;
+    Ok(())
+}
+
+/// JSON のログを表示するかどうか（console! のメッセージをそのまま表示しない）
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// 重要度を実行時に決めるメッセージをログに書く
+pub fn event(level: log::Level, message: &str) {
+    match level {
+        log::Level::Error => tracing::error!("{}", message),
+        log::Level::Warn => tracing::warn!("{}", message),
+        log::Level::Info => tracing::info!("{}", message),
+        log::Level::Debug => tracing::debug!("{}", message),
+        log::Level::Trace => tracing::trace!("{}", message),
+    }
+}
+
+/// 標準エラー出力の書き出し先（進捗バーを表示している場合は、バーの上に書く）
+struct StderrWriter;
+
+impl Write for StderrWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        crate::progress::eprintln_above_bars(text.trim_end_matches('\n'));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for StderrWriter {
+    type Writer = StderrWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        StderrWriter
+    }
+}
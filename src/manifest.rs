@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::diagnostics::{self, PageCoverage, Warning};
+
+/// 変換結果の記録（--manifest で JSON として書き出す）
+#[derive(Debug, Serialize)]
+pub struct Manifest<'a> {
+    pub input: String,
+    pub output: String,
+    /// 変換したページ数
+    pub pages: usize,
+    /// 文書全体の変換率（0.0〜1.0）
+    pub coverage: f64,
+    /// ページごとの変換率
+    pub page_coverage: Vec<PageCoverageEntry>,
+    pub warnings: &'a [Warning],
+}
+
+/// ページごとの変換率（面積は小数点以下を丸める）
+#[derive(Debug, Serialize)]
+pub struct PageCoverageEntry {
+    pub page: u32,
+    pub coverage: f64,
+    pub text_area: f64,
+    pub converted_area: f64,
+}
+
+impl<'a> Manifest<'a> {
+    pub fn new(input: &Path, output: &Path, coverage: &[PageCoverage], warnings: &'a [Warning]) -> Self {
+        Manifest {
+            input: input.to_string_lossy().into_owned(),
+            output: output.to_string_lossy().into_owned(),
+            pages: coverage.len(),
+            coverage: round(diagnostics::total_coverage(coverage), 4),
+            page_coverage: coverage
+                .iter()
+                .map(|page| PageCoverageEntry {
+                    page: page.page,
+                    coverage: round(page.ratio(), 4),
+                    text_area: round(page.text_area, 1),
+                    converted_area: round(page.converted_area, 1),
+                })
+                .collect(),
+            warnings,
+        }
+    }
+
+    /// JSON に変換する
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("マニフェストの JSON への変換に失敗しました")
+    }
+}
+
+fn round(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits);
+    (value * scale).round() / scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: マニフェストの JSON 出力
+    #[test]
+    fn test_manifest_json() {
+        let coverage = vec![
+            PageCoverage { page: 1, text_area: 300.0, converted_area: 200.0 },
+            PageCoverage { page: 2, text_area: 100.0, converted_area: 100.0 },
+        ];
+        let manifest = Manifest::new(Path::new("in.pdf"), Path::new("in.md"), &coverage, &[]);
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["pages"], 2);
+        assert_eq!(json["coverage"], 0.75);
+        assert_eq!(json["page_coverage"][0]["coverage"], 0.6667);
+        assert_eq!(json["warnings"], serde_json::json!([]));
+    }
+}
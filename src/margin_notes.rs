@@ -0,0 +1,95 @@
+use clap::ValueEnum;
+
+use crate::layout::PageLayout;
+
+/// 欄外の注の出力方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MarginNoteStyle {
+    /// 取り出さずに本文と同じ流れで出力する（従来の動作）
+    #[default]
+    Inline,
+    /// 近くの本文の行に脚注参照を付け、注の本文を文書末尾の脚注にする
+    Footnotes,
+    /// 注の位置の段落の後に引用ブロックとして出力する
+    Aside,
+    /// 文書末尾にページ番号付きの一覧として出力する
+    Appendix,
+}
+
+/// 欄外の注を出力方法に応じて本文に配置し、文書末尾に追加する Markdown を返す
+///
+/// 各ページの注は事前に PageLayout::take_margin_notes で取り出しておく。
+pub fn place_margin_notes(pages: &mut [PageLayout], style: MarginNoteStyle) -> String {
+    let mut trailer = String::new();
+    let mut footnote = 0;
+
+    for page in pages.iter_mut() {
+        for note in std::mem::take(&mut page.margin_notes) {
+            match style {
+                MarginNoteStyle::Inline => {}
+                MarginNoteStyle::Footnotes => {
+                    footnote += 1;
+                    let label = format!("[^m{}]", footnote);
+                    // 注の先頭行に最も近い本文の行の末尾に参照を付ける
+                    let nearest = page
+                        .glyphs
+                        .iter()
+                        .map(|glyph| (glyph.y - note.y).abs())
+                        .fold(f64::INFINITY, f64::min);
+                    match page.glyphs.iter().rposition(|glyph| (glyph.y - note.y).abs() == nearest) {
+                        Some(index) => page.glyphs[index].text.push_str(&label),
+                        None => trailer.push_str(&format!("{}\n\n", label)),
+                    }
+                    trailer.push_str(&format!("{}: {}\n\n", label, note.text));
+                }
+                // 引用ブロックは段落の区切りに出力する（paragraphs::pages_to_text）
+                MarginNoteStyle::Aside => page.margin_notes.push(note),
+                MarginNoteStyle::Appendix => {
+                    if trailer.is_empty() {
+                        trailer.push_str("## 欄外の注\n\n");
+                    }
+                    trailer.push_str(&format!("- p.{}: {}\n", page.number, note.text));
+                }
+            }
+        }
+    }
+
+    trailer
+}
+
+/// 行が欄外の注の引用ブロックかどうか
+pub fn is_aside(line: &str) -> bool {
+    line.starts_with("> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{Glyph, MarginNote};
+
+    fn page_with_note() -> Vec<PageLayout> {
+        let glyph = |text: &str, y: f64| Glyph { text: text.to_string(), x: 100.0, y, width: 30.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        vec![PageLayout {
+            number: 4,
+            glyphs: vec![glyph("first", 100.0), glyph("second", 112.0)],
+            margin_notes: vec![MarginNote { y: 111.0, text: "see also".to_string() }],
+            ..Default::default()
+        }]
+    }
+
+    // 単体テスト: 出力方法ごとの欄外の注の配置
+    #[test]
+    fn test_place_margin_notes() {
+        let mut pages = page_with_note();
+        assert_eq!(place_margin_notes(&mut pages, MarginNoteStyle::Footnotes), "[^m1]: see also\n\n");
+        assert_eq!(pages[0].glyphs[1].text, "second[^m1]");
+
+        let mut pages = page_with_note();
+        assert_eq!(place_margin_notes(&mut pages, MarginNoteStyle::Aside), "");
+        assert_eq!(pages[0].margin_notes.len(), 1);
+
+        let mut pages = page_with_note();
+        assert_eq!(place_margin_notes(&mut pages, MarginNoteStyle::Appendix), "## 欄外の注\n\n- p.4: see also\n");
+        assert_eq!(pages[0].glyphs.len(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use lopdf::{Document, Object, ObjectId, Stream};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::console;
+
+/// 一時ファイルに書き出す画像の大きさの下限（小さな画像は書き出しても効果が小さい）
+const MIN_SPILL_BYTES: usize = 64 * 1024;
+
+/// 変換中にメモリに置くデータの上限（例: 512M、2G）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudget {
+    pub bytes: u64,
+}
+
+impl FromStr for MemoryBudget {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        let (number, unit) = spec.split_at(spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len()));
+        let scale: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+            "" => 1,
+            "K" => 1 << 10,
+            "M" => 1 << 20,
+            "G" => 1 << 30,
+            _ => return Err(format!("メモリの上限の単位は K、M、G のいずれかで指定してください: {}", spec)),
+        };
+        let number: u64 = number.parse().ok().filter(|&n| n > 0).ok_or_else(|| format!("メモリの上限は1以上の整数で指定してください: {}", spec))?;
+        Ok(MemoryBudget { bytes: number.saturating_mul(scale) })
+    }
+}
+
+/// 一時ファイルに書き出した画像のデータ（破棄すると一時ファイルを削除する）
+#[derive(Debug)]
+pub struct SpilledImages {
+    dir: PathBuf,
+    /// データを一時ファイルに移した画像と、その一時ファイル（文書の辞書には書き込まない）
+    paths: HashMap<ObjectId, PathBuf>,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+impl SpilledImages {
+    /// 一時ファイルに移した画像のデータを読み戻したストリーム（この変換処理で移していない画像はそのまま）
+    pub fn restore<'a>(&self, id: ObjectId, stream: &'a Stream) -> Result<Cow<'a, Stream>> {
+        let Some(path) = self.paths.get(&id) else {
+            return Ok(Cow::Borrowed(stream));
+        };
+        let content = fs::read(path).with_context(|| format!("一時ファイルから画像のデータを読み戻せません: {:?}", path))?;
+        Ok(Cow::Owned(Stream::new(stream.dict.clone(), content)))
+    }
+}
+
+impl Drop for SpilledImages {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// 文書のストリームのデータの合計が上限を超える場合に、大きい画像から順にデータを一時ファイルに移してメモリを空ける
+///
+/// 書き出した画像は、画像を書き出す処理で SpilledImages::restore を通して一時ファイルから読み戻す。テキストの抽出に使う
+/// ページの内容やフォントはメモリに残す。読み込んだ時点の文書そのものは上限に関係なくメモリに載る。
+pub fn spill_images(doc: &mut Document, budget: MemoryBudget) -> Result<SpilledImages> {
+    let mut spilled = SpilledImages { dir: crate::work_dir("spill"), paths: HashMap::new(), count: 0, bytes: 0 };
+
+    let resident: u64 = doc.objects.values().filter_map(|object| object.as_stream().ok()).map(|stream| stream.content.len() as u64).sum();
+    if resident <= budget.bytes {
+        return Ok(spilled);
+    }
+
+    let mut images: Vec<(ObjectId, usize)> = doc
+        .objects
+        .iter()
+        .filter_map(|(id, object)| Some((*id, object.as_stream().ok()?)))
+        .filter(|(_, stream)| stream.dict.get(b"Subtype").and_then(Object::as_name).is_ok_and(|name| name == b"Image"))
+        .map(|(id, stream)| (id, stream.content.len()))
+        .filter(|(_, len)| *len >= MIN_SPILL_BYTES)
+        .collect();
+    images.sort_by_key(|(_, len)| std::cmp::Reverse(*len));
+
+    fs::create_dir_all(&spilled.dir).with_context(|| format!("一時ディレクトリを作成できません: {:?}", spilled.dir))?;
+    let mut excess = resident - budget.bytes;
+    for (id, len) in images {
+        if excess == 0 {
+            break;
+        }
+        let Some(Object::Stream(stream)) = doc.objects.get_mut(&id) else {
+            continue;
+        };
+        let path = spilled.dir.join(format!("{}-{}.bin", id.0, id.1));
+        fs::write(&path, &stream.content).with_context(|| format!("画像のデータを一時ファイルに書き出せません: {:?}", path))?;
+        stream.content = Vec::new();
+        spilled.paths.insert(id, path);
+        spilled.count += 1;
+        spilled.bytes += len as u64;
+        excess = excess.saturating_sub(len as u64);
+    }
+    if excess > 0 {
+        console!(Warn, "画像を一時ファイルに移しても、文書のデータがメモリの上限を {} バイト超えています", excess);
+    }
+
+    Ok(spilled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    // 単体テスト: 画像のデータの一時ファイルへの退避
+    #[test]
+    fn test_spill_images() {
+        let budget = |spec: &str| spec.parse::<MemoryBudget>().map(|budget| budget.bytes);
+        assert_eq!(budget("512M"), Ok(512 << 20));
+        assert_eq!(budget("2GiB"), Ok(2 << 30));
+        assert!(budget("10X").is_err());
+
+        let mut doc = Document::with_version("1.5");
+        let image = |len: usize| Stream::new(dictionary! {"Subtype" => "Image"}, vec![7; len]);
+        let large = doc.add_object(image(300 * 1024));
+        let small = doc.add_object(image(100 * 1024));
+        let page = doc.add_object(Stream::new(dictionary! {}, vec![0; 200 * 1024]));
+        let forged = doc.add_object(Stream::new(dictionary! {"Subtype" => "Image", "Filter" => "DCTDecode", "PDF2MDSpilled" => Object::string_literal("/etc/hostname")}, Vec::new()));
+
+        // 上限を超えた分だけ、大きい画像から移す（ページの内容は移さない）
+        let spilled = spill_images(&mut doc, MemoryBudget { bytes: 400 * 1024 }).unwrap();
+        assert_eq!((spilled.count, spilled.bytes), (1, 300 * 1024));
+        let stream = |id: ObjectId| doc.get_object(id).unwrap().as_stream().unwrap();
+        assert!(stream(large).content.is_empty());
+        assert_eq!((stream(small).content.len(), stream(page).content.len()), (100 * 1024, 200 * 1024));
+        assert_eq!(spilled.restore(large, stream(large)).unwrap().content, vec![7; 300 * 1024]);
+        assert_eq!(spilled.restore(small, stream(small)).unwrap().content.len(), 100 * 1024);
+
+        // 一時ファイルの場所を文書の辞書に書いた画像は、読み戻さない（PDF からローカルのファイルを読み込ませないため）
+        assert!(spilled.restore(forged, stream(forged)).unwrap().content.is_empty());
+
+        // 破棄すると一時ファイルを削除する
+        let dir = spilled.dir.clone();
+        drop(spilled);
+        assert!(!dir.exists());
+    }
+}
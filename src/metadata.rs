@@ -0,0 +1,331 @@
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object};
+use std::collections::BTreeMap;
+
+use crate::PdfInput;
+
+/// PDFの文書情報辞書（Info）から読み取ったメタデータ
+#[derive(Debug, Default)]
+pub struct PdfMetadata {
+    /// Title（文書の題名）
+    pub title: Option<String>,
+    /// Author（作成者）
+    pub author: Option<String>,
+    /// Subject（文書の主題）
+    pub subject: Option<String>,
+    /// Keywords フィールドを分割したキーワード一覧
+    pub keywords: Vec<String>,
+    /// CreationDate を ISO 8601 に変換した作成日時
+    pub created: Option<String>,
+    /// ModDate を ISO 8601 に変換した更新日時
+    pub modified: Option<String>,
+}
+
+/// PDF の文書情報辞書を読み取る（暗号化された PDF は password で復号して読む）
+pub fn read_metadata(input: PdfInput, password: Option<&str>) -> Result<PdfMetadata> {
+    let doc = input.open(password).with_context(|| format!("PDFの読み込みに失敗しました: {:?}", input.path()))?;
+
+    let mut metadata = PdfMetadata::default();
+
+    // Info 辞書が無いPDFも多いため、その場合は空のメタデータを返す
+    let Some(info) = info_dictionary(&doc) else {
+        return Ok(metadata);
+    };
+
+    // 空の値や空白だけの値は出力しない
+    let text = |key: &[u8]| info_string(&doc, info, key).map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+    metadata.title = text(b"Title");
+    metadata.author = text(b"Author");
+    metadata.subject = text(b"Subject");
+
+    if let Some(keywords) = info_string(&doc, info, b"Keywords") {
+        metadata.keywords = parse_keywords(&keywords);
+    }
+
+    // 日付は解析できない場合は出力しない
+    metadata.created = info_string(&doc, info, b"CreationDate").and_then(|d| parse_pdf_date(&d));
+    metadata.modified = info_string(&doc, info, b"ModDate").and_then(|d| parse_pdf_date(&d));
+
+    Ok(metadata)
+}
+
+/// 暗号化辞書の権限フラグ（/P）でテキストのコピー（抽出）が許可されているかどうか
+///
+/// 暗号化されていないPDFは常に許可とみなす。復号すると暗号化辞書が取り除かれるため、復号前に呼び出すこと。
+pub fn allows_copying(doc: &Document) -> bool {
+    let Ok(encrypt) = doc.get_encrypted() else {
+        return true;
+    };
+
+    match encrypt.get(b"P").and_then(Object::as_i64) {
+        // ビット5（値 16）が「テキストとグラフィックのコピー・抽出」の許可
+        Ok(permissions) => permissions_allow_copying(permissions),
+        Err(_) => true,
+    }
+}
+
+/// 権限フラグの値からコピーの可否を判定する
+fn permissions_allow_copying(permissions: i64) -> bool {
+    permissions & 0x10 != 0
+}
+
+/// トレーラーから Info 辞書を取得する
+fn info_dictionary(doc: &Document) -> Option<&Dictionary> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, object) = doc.dereference(info).ok()?;
+    object.as_dict().ok()
+}
+
+/// Info 辞書（または注釈などの辞書）の文字列フィールドをデコードして取得する
+pub fn info_string(doc: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+    let (_, object) = doc.dereference(info.get(key).ok()?).ok()?;
+    match object {
+        Object::String(..) => lopdf::decode_text_string(object).ok(),
+        _ => None,
+    }
+}
+
+/// 数値ツリーの入れ子の上限（循環参照対策）
+const MAX_NUMBER_TREE_DEPTH: usize = 32;
+
+/// カタログの PageLabels からページ番号（1 始まり）ごとのページラベル（「iv」「A-3」など）を求める
+///
+/// PageLabels の無い文書や、範囲の指定が無いページは含めない。
+pub fn page_labels(doc: &Document) -> BTreeMap<u32, String> {
+    let mut ranges: Vec<(i64, &Dictionary)> = Vec::new();
+    if let Some(tree) = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"PageLabels").ok())
+        .and_then(|tree| doc.dereference(tree).ok())
+        .and_then(|(_, tree)| tree.as_dict().ok())
+    {
+        collect_number_tree(doc, tree, 0, &mut ranges);
+    }
+    ranges.sort_by_key(|(start, _)| *start);
+
+    let mut labels = BTreeMap::new();
+    for index in 0..doc.get_pages().len() as i64 {
+        let Some((start, range)) = ranges.iter().rev().find(|(start, _)| *start <= index) else {
+            continue;
+        };
+        let first = range.get(b"St").and_then(Object::as_i64).unwrap_or(1);
+        let prefix = info_string(doc, range, b"P").unwrap_or_default();
+        let style = range.get(b"S").and_then(Object::as_name).ok();
+        labels.insert(index as u32 + 1, format_page_label(style, &prefix, first + index - start));
+    }
+    labels
+}
+
+/// 数値ツリーの葉（Nums）の [開始位置, ラベルの範囲の辞書] を集める
+fn collect_number_tree<'a>(doc: &'a Document, node: &'a Dictionary, depth: usize, ranges: &mut Vec<(i64, &'a Dictionary)>) {
+    if depth >= MAX_NUMBER_TREE_DEPTH {
+        return;
+    }
+    if let Ok(nums) = node.get(b"Nums").and_then(Object::as_array) {
+        for pair in nums.chunks(2) {
+            let (Some(Ok(start)), Some(range)) = (pair.first().map(Object::as_i64), pair.get(1)) else {
+                continue;
+            };
+            if let Ok((_, Object::Dictionary(range))) = doc.dereference(range) {
+                ranges.push((start, range));
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                collect_number_tree(doc, kid, depth + 1, ranges);
+            }
+        }
+    }
+}
+
+/// ページラベルの番号をスタイル（D: 算用数字、R / r: ローマ数字、A / a: アルファベット）に従って書式化する
+fn format_page_label(style: Option<&[u8]>, prefix: &str, number: i64) -> String {
+    let number = number.max(1) as usize;
+    let numeral = match style {
+        Some(b"D") => number.to_string(),
+        Some(b"R") => roman_numeral(number),
+        Some(b"r") => roman_numeral(number).to_lowercase(),
+        // 26 を超えると AA、BB ... のように同じ文字を重ねる
+        Some(b"A") => ((b'A' + ((number - 1) % 26) as u8) as char).to_string().repeat((number - 1) / 26 + 1),
+        Some(b"a") => ((b'a' + ((number - 1) % 26) as u8) as char).to_string().repeat((number - 1) / 26 + 1),
+        _ => String::new(),
+    };
+    format!("{}{}", prefix, numeral)
+}
+
+/// ローマ数字（大文字）
+fn roman_numeral(mut number: usize) -> String {
+    const NUMERALS: &[(usize, &str)] =
+        &[(1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"), (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I")];
+    let mut result = String::new();
+    for &(value, numeral) in NUMERALS {
+        while number >= value {
+            result.push_str(numeral);
+            number -= value;
+        }
+    }
+    result
+}
+
+/// Keywords フィールド（カンマ・セミコロン区切り）をキーワード一覧に分割する
+pub fn parse_keywords(raw: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+
+    for keyword in raw.split([',', ';']) {
+        let keyword = keyword.trim();
+        // 空の要素と重複は取り除く
+        if !keyword.is_empty() && !keywords.iter().any(|k| k == keyword) {
+            keywords.push(keyword.to_string());
+        }
+    }
+
+    keywords
+}
+
+/// PDFの日付文字列（D:YYYYMMDDHHmmSSOHH'mm'）を ISO 8601 形式に変換する
+///
+/// 後半の要素の省略や、アポストロフィの欠落・`Z` 表記などのよくある崩れた形式も受け付けます。
+pub fn parse_pdf_date(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let raw = raw.strip_prefix("D:").unwrap_or(raw);
+
+    // 先頭の数字部分（日時）とタイムゾーン部分に分ける
+    let digits_end = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (digits, zone) = raw.split_at(digits_end);
+
+    // 年は必須、以降は2桁ずつ（月・日・時・分・秒）
+    if digits.len() < 4 || (digits.len() - 4) % 2 != 0 || digits.len() > 14 {
+        return None;
+    }
+    let year = &digits[0..4];
+    let field = |start: usize, default: u32| -> u32 {
+        digits.get(start..start + 2).and_then(|v| v.parse().ok()).unwrap_or(default)
+    };
+    let (month, day) = (field(4, 1), field(6, 1));
+    let (hour, minute, second) = (field(8, 0), field(10, 0), field(12, 0));
+
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year.parse().ok()?, month)).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // 省略された要素は ISO 8601 の精度を下げた表記で返す
+    if digits.len() == 4 {
+        return Some(year.to_string());
+    }
+    if digits.len() == 6 {
+        return Some(format!("{}-{:02}", year, month));
+    }
+    if digits.len() == 8 {
+        return Some(format!("{}-{:02}-{:02}", year, month, day));
+    }
+
+    let mut iso = format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hour, minute, second);
+    if let Some(offset) = parse_pdf_timezone(zone) {
+        iso.push_str(&offset);
+    }
+    Some(iso)
+}
+
+/// 月の日数（グレゴリオ暦のうるう年を考慮する）
+pub fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// タイムゾーン部分（Z, +09'00', -0500 など）を ISO 8601 のオフセットに変換する
+fn parse_pdf_timezone(zone: &str) -> Option<String> {
+    let zone = zone.trim();
+    let sign = zone.chars().next()?;
+
+    match sign {
+        'Z' | 'z' => Some("Z".to_string()),
+        '+' | '-' => {
+            let digits: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+            let hours: u32 = digits.get(0..2)?.parse().ok()?;
+            let minutes: u32 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            // +00'00' は Z と同じ意味だが、元の表記に近い形で出力する
+            Some(format!("{}{:02}:{:02}", sign, hours, minutes))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: キーワードの分割
+    #[test]
+    fn test_parse_keywords() {
+        let test_cases = vec![
+            ("rust, pdf; markdown", vec!["rust", "pdf", "markdown"], "カンマとセミコロンの混在"),
+            (" a ;; b , a ", vec!["a", "b"], "空要素と重複の除去"),
+            ("", vec![], "空文字列"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(parse_keywords(input), expected, "Test failed: {}", desc);
+        }
+    }
+
+    // 単体テスト: 権限フラグの判定
+    #[test]
+    fn test_permissions_allow_copying() {
+        // -4 は全操作許可、-20 はコピーのみ禁止（符号付き32ビットで格納される）
+        assert!(permissions_allow_copying(-4));
+        assert!(!permissions_allow_copying(-20));
+        assert!(!permissions_allow_copying(0));
+    }
+
+    // 単体テスト: ページラベルの書式化
+    #[test]
+    fn test_format_page_label() {
+        let test_cases = vec![
+            (Some(&b"D"[..]), "", 12, "12", "算用数字"),
+            (Some(&b"r"[..]), "", 4, "iv", "小文字のローマ数字"),
+            (Some(&b"R"[..]), "", 1994, "MCMXCIV", "大文字のローマ数字"),
+            (Some(&b"A"[..]), "", 28, "BB", "アルファベット"),
+            (Some(&b"D"[..]), "A-", 3, "A-3", "接頭辞付き"),
+            (None, "Cover", 1, "Cover", "接頭辞のみ"),
+        ];
+
+        for (style, prefix, number, expected, desc) in test_cases {
+            assert_eq!(format_page_label(style, prefix, number), expected, "Test failed: {}", desc);
+        }
+    }
+
+    // 単体テスト: 日付文字列の変換
+    #[test]
+    fn test_parse_pdf_date() {
+        let test_cases = vec![
+            ("D:20240115123000+09'00'", Some("2024-01-15T12:30:00+09:00"), "標準形式"),
+            ("D:20240115123000Z", Some("2024-01-15T12:30:00Z"), "UTC"),
+            ("D:20240115123000-05'00", Some("2024-01-15T12:30:00-05:00"), "末尾のアポストロフィ欠落"),
+            ("20240115123000+0900", Some("2024-01-15T12:30:00+09:00"), "D: 無しとアポストロフィ無し"),
+            ("D:202401151230", Some("2024-01-15T12:30:00"), "秒とタイムゾーンの省略"),
+            ("D:2024", Some("2024"), "年のみ"),
+            ("D:202403", Some("2024-03"), "年月のみ"),
+            ("D:20241345", None, "範囲外の月"),
+            ("D:20240231", None, "月に無い日"),
+            ("D:20240229", Some("2024-02-29"), "うるう年の2月29日"),
+            ("D:20230229", None, "うるう年でない年の2月29日"),
+            ("D:21000229", None, "100で割り切れる年の2月29日"),
+            ("D:2024011", None, "桁数の崩れ"),
+            ("yesterday", None, "日付ではない文字列"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(parse_pdf_date(input).as_deref(), expected, "Test failed: {}", desc);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(feature = "ocr")]
+use crate::console;
+#[cfg(feature = "ocr")]
+use crate::graphics;
+#[cfg(feature = "ocr")]
+use crate::layout::{Glyph, PageLayout};
+#[cfg(feature = "ocr")]
+use crate::spelling::{self, Dictionary};
+#[cfg(feature = "ocr")]
+use std::fs;
+
+/// tesseract で画像の文字を認識し、認識した文字列を返す
+///
+/// lang は tesseract の言語指定（eng、jpn、eng+jpn など）。
+pub fn recognize_image(path: &Path, lang: &str) -> Result<String> {
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .args(["-l", lang])
+        .output()
+        .context("tesseract を実行できません（tesseract-ocr をインストールしてください）")?;
+    if !output.status.success() {
+        bail!("tesseract が {:?} の文字認識に失敗しました: {}", path, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// tesseract が認識した1語（座標は画像のピクセル単位）
+#[cfg(feature = "ocr")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrWord {
+    pub text: String,
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+    /// 認識の確からしさ（0〜100）
+    pub confidence: f64,
+}
+
+/// tesseract で画像の文字を語ごとに認識し、語の位置と確からしさを返す
+#[cfg(feature = "ocr")]
+pub fn recognize_words(path: &Path, lang: &str) -> Result<Vec<OcrWord>> {
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .args(["-l", lang, "tsv"])
+        .output()
+        .context("tesseract を実行できません（tesseract-ocr をインストールしてください）")?;
+    if !output.status.success() {
+        bail!("tesseract が {:?} の文字認識に失敗しました: {}", path, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// 向きの判定を信用する、tesseract の向きの確からしさの下限
+#[cfg(feature = "ocr")]
+const MIN_ORIENTATION_CONFIDENCE: f64 = 2.0;
+
+/// tesseract の向きと書字の判定（--psm 0）で、画像を正しい向きにするために時計回りに回す角度を求める
+///
+/// 判定に必要な osd の言語データが無い場合や、文字が少なく判定できない場合は None を返す。
+#[cfg(feature = "ocr")]
+fn detect_orientation(path: &Path) -> Option<u32> {
+    let output = Command::new("tesseract").arg(path).arg("stdout").args(["--psm", "0"]).output().ok()?;
+    if !output.status.success() {
+        tracing::debug!("ページの向きを判定できませんでした: {}", String::from_utf8_lossy(&output.stderr).trim());
+        return None;
+    }
+    parse_osd(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// tesseract の向きと書字の判定の出力（Rotate: 180、Orientation confidence: 8.5 などの行）から、回す角度を取り出す
+#[cfg(feature = "ocr")]
+fn parse_osd(osd: &str) -> Option<u32> {
+    let field = |name: &str| osd.lines().find_map(|line| line.strip_prefix(name)?.trim().parse::<f64>().ok());
+    let rotate = field("Rotate:")? as u32;
+    let confident = field("Orientation confidence:").is_some_and(|confidence| confidence >= MIN_ORIENTATION_CONFIDENCE);
+    (matches!(rotate, 90 | 180 | 270) && confident).then_some(rotate)
+}
+
+/// PNG の画像を時計回りに degrees 度（90 の倍数）回して上書きする
+#[cfg(feature = "ocr")]
+fn rotate_png(path: &Path, degrees: u32) -> Result<()> {
+    let raster = graphics::read_png(path)?;
+    let (rotated, width, height) = rotate_pixels(&raster.data, raster.width, raster.height, raster.color.samples(), degrees);
+
+    let file = fs::File::create(path).with_context(|| format!("ページ画像を書き出せません: {:?}", path))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(raster.color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().with_context(|| format!("ページ画像を書き出せません: {:?}", path))?;
+    writer.write_image_data(&rotated).with_context(|| format!("ページ画像を書き出せません: {:?}", path))?;
+    Ok(())
+}
+
+/// 1画素 bytes バイトの画素の並びを時計回りに degrees 度回し、回した画素と幅・高さを返す
+#[cfg(feature = "ocr")]
+fn rotate_pixels(data: &[u8], width: u32, height: u32, bytes: usize, degrees: u32) -> (Vec<u8>, u32, u32) {
+    let (w, h) = (width as usize, height as usize);
+    let (new_width, new_height) = if degrees == 90 || degrees == 270 { (h, w) } else { (w, h) };
+    let mut rotated = vec![0; data.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let (nx, ny) = match degrees {
+                90 => (h - 1 - y, x),
+                180 => (w - 1 - x, h - 1 - y),
+                270 => (y, w - 1 - x),
+                _ => (x, y),
+            };
+            let (from, to) = ((y * w + x) * bytes, (ny * new_width + nx) * bytes);
+            rotated[to..to + bytes].copy_from_slice(&data[from..from + bytes]);
+        }
+    }
+    (rotated, new_width as u32, new_height as u32)
+}
+
+/// tesseract の TSV 出力（level page_num block_num par_num line_num word_num left top width height conf text）から語を取り出す
+#[cfg(feature = "ocr")]
+fn parse_tsv(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(12, '\t').collect();
+            let [level, _, _, _, _, _, left, top, width, height, conf, text] = fields[..] else {
+                return None;
+            };
+            let number = |field: &str| field.trim().parse::<f64>().ok();
+            // level 5 が語の行（それ以外はブロックや行の外接矩形）
+            (level == "5" && !text.trim().is_empty()).then_some(())?;
+            Some(OcrWord {
+                text: text.trim().to_string(),
+                left: number(left)?,
+                top: number(top)?,
+                width: number(width)?,
+                height: number(height)?,
+                confidence: number(conf)?,
+            })
+        })
+        .collect()
+}
+
+/// 文字認識を行うページ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrPages {
+    /// 文字のレイヤーが無く、画像のあるページだけ
+    Missing,
+    /// すべてのページ（文字のレイヤーは認識結果で置き換える）
+    All,
+}
+
+/// 文字認識の設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrOptions {
+    pub pages: OcrPages,
+    /// tesseract の言語（eng、jpn、eng+jpn など）
+    pub lang: String,
+    /// 確からしさ（0〜100）がこの値より低い語を {?語?} の形で目印を付けて出力する（誤認識の校正用）
+    pub mark_below: Option<f64>,
+    /// 辞書と取り違えやすい文字の組で認識結果を補正する
+    pub correct: bool,
+    /// 補正に使う単語の一覧のファイル（英語の場合、指定が無ければシステムの単語の一覧を使う）
+    pub dictionaries: Vec<PathBuf>,
+}
+
+/// 文字認識の結果（認識したページ数と、確からしさが低いため目印を付けた語の数、補正した語の数）
+#[cfg(feature = "ocr")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Recognized {
+    pub pages: usize,
+    pub marked_words: usize,
+    pub corrected_words: usize,
+}
+
+/// ページを画像にするときの解像度（dpi）
+#[cfg(feature = "ocr")]
+const OCR_DPI: u32 = 300;
+
+/// スキャンした紙面とみなす、ページに占める画像の面積の割合
+#[cfg(feature = "ocr")]
+const MIN_SCAN_RATIO: f64 = 0.5;
+
+/// 文字認識の対象のページを画像にして tesseract で認識し、ページの文字を認識結果に置き換える
+///
+/// 文字の大きさと位置は認識した語の外接矩形から求めるため、以降の段落や見出しの判定もそのまま使える。
+/// 文字のレイヤーが無いページだけを対象にする場合は、pdftoppm か tesseract が無ければ警告して認識をやめる。
+#[cfg(feature = "ocr")]
+pub fn recognize_pages(pdf_path: &Path, pages: &mut [PageLayout], options: &OcrOptions) -> Result<Recognized> {
+    let dictionary = options.correct.then(|| Dictionary::load(&options.dictionaries, &options.lang)).transpose()?;
+    if dictionary.as_ref().is_some_and(Dictionary::is_empty) {
+        console!(Warn, "文字認識の補正に使う辞書がありません（--ocr-dictionary で単語の一覧を指定してください）。文書の中で繰り返し現れる語だけで補正します");
+    }
+    let work_dir = crate::work_dir("ocr");
+    let mut recognized = Recognized::default();
+    // 補正では文書全体の語を辞書に加えるので、全ページを認識してから文字に置き換える
+    let mut page_words: Vec<(usize, Vec<OcrWord>)> = Vec::new();
+
+    let result = (|| -> Result<()> {
+        for (index, page) in pages.iter_mut().enumerate() {
+            let has_text = page.glyphs.iter().any(|glyph| !glyph.text.trim().is_empty());
+            if options.pages == OcrPages::Missing && (has_text || page.images.is_empty()) {
+                continue;
+            }
+            let image = graphics::rasterize_page(pdf_path, page.number, OCR_DPI, &work_dir.join(format!("page-{:03}.png", page.number)))?;
+            // 逆さまや横倒しにスキャンされたページは、画像とページを正しい向きに回してから認識する
+            if let Some(rotation) = detect_orientation(&image) {
+                rotate_png(&image, rotation)?;
+                page.rotate(rotation);
+                tracing::debug!(page = page.number, rotation, "文字認識の前にページを回しました");
+            }
+            page_words.push((index, recognize_words(&image, &options.lang)?));
+            // 紙面全体のスキャン画像は、図として出力しない
+            let page_area = page.width * page.height;
+            page.images.retain(|image| (image.x1 - image.x0) * (image.y1 - image.y0) < page_area * MIN_SCAN_RATIO);
+            recognized.pages += 1;
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&work_dir);
+
+    if let Some(mut dictionary) = dictionary {
+        let confident = page_words.iter().flat_map(|(_, words)| words).filter(|word| word.confidence >= spelling::MIN_DOCUMENT_CONFIDENCE);
+        dictionary.add_document_words(confident.map(|word| word.text.as_str()));
+        for (index, words) in page_words.iter_mut() {
+            recognized.corrected_words += correct_words(words, &dictionary, pages[*index].number);
+        }
+    }
+    for (index, words) in &page_words {
+        let page = &mut pages[*index];
+        page.glyphs = words_to_glyphs(words, OCR_DPI, options.mark_below);
+        recognized.marked_words += page.glyphs.iter().filter(|glyph| is_marked(&glyph.text)).count();
+    }
+
+    match result {
+        Err(e) if options.pages == OcrPages::Missing => {
+            console!(Warn, "文字のレイヤーが無いページの文字認識をやめました: {:#}", e);
+            Ok(recognized)
+        }
+        Err(e) => Err(e),
+        Ok(()) => Ok(recognized),
+    }
+}
+
+/// 認識した語を辞書で補正し、補正した語をすべてログに記録して、補正した語の数を返す
+///
+/// 補正した語は確からしさが低いものとして扱わない（目印を付けない）。
+#[cfg(feature = "ocr")]
+fn correct_words(words: &mut [OcrWord], dictionary: &Dictionary, page: u32) -> usize {
+    let mut corrected = 0;
+    for word in words.iter_mut() {
+        if let Some(correction) = spelling::correct_word(&word.text, dictionary) {
+            tracing::info!("ページ {}: 文字認識の結果「{}」を「{}」に補正しました（確からしさ {}）", page, word.text, correction, word.confidence);
+            word.text = correction;
+            word.confidence = 100.0;
+            corrected += 1;
+        }
+    }
+    corrected
+}
+
+/// 認識した語を、ページ座標（ポイント、y 下向き）の文字にする（ベースラインは語の外接矩形の下端とする）
+///
+/// mark_below を指定した場合は、確からしさがそれより低い語を {?語?} にする。
+#[cfg(feature = "ocr")]
+fn words_to_glyphs(words: &[OcrWord], dpi: u32, mark_below: Option<f64>) -> Vec<Glyph> {
+    let scale = 72.0 / f64::from(dpi);
+    words
+        .iter()
+        .enumerate()
+        .map(|(order, word)| Glyph {
+            text: match mark_below {
+                Some(threshold) if word.confidence < threshold => format!("{{?{}?}}", word.text),
+                _ => word.text.clone(),
+            },
+            x: word.left * scale,
+            y: (word.top + word.height) * scale,
+            width: word.width * scale,
+            font_size: word.height * scale,
+            word_start: true,
+            order,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence: Some(word.confidence),
+        })
+        .collect()
+}
+
+/// 確からしさが低いため目印を付けた語かどうか
+#[cfg(feature = "ocr")]
+fn is_marked(text: &str) -> bool {
+    text.starts_with("{?") && text.ends_with("?}")
+}
+
+/// 認識結果を行に分け、空行や記号だけの行（罫線や矢印の誤認識）を除く
+pub fn clean_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| line.chars().any(char::is_alphanumeric))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 認識結果の整理
+    #[test]
+    fn test_clean_lines() {
+        let text = "Revenue  (USD)\n\n|  — |\nQ1   Q2 Q3\n\u{c}";
+        assert_eq!(clean_lines(text), vec!["Revenue (USD)", "Q1 Q2 Q3"]);
+    }
+
+    // 単体テスト: 語ごとの認識結果の読み取り
+    #[cfg(feature = "ocr")]
+    #[test]
+    fn test_parse_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   4\t1\t1\t1\t1\t0\t300\t600\t400\t50\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t300\t600\t150\t50\t96.5\tScanned\n\
+                   5\t1\t1\t1\t1\t2\t480\t600\t220\t50\t41\tpage\n\
+                   5\t1\t1\t1\t1\t3\t720\t600\t10\t50\t95\t \n";
+        let words = parse_tsv(tsv);
+        assert_eq!(words.iter().map(|word| (word.text.as_str(), word.confidence)).collect::<Vec<_>>(), vec![("Scanned", 96.5), ("page", 41.0)]);
+
+        // 300dpi のピクセルをポイントにする
+        let glyphs = words_to_glyphs(&words, 300, None);
+        assert_eq!((glyphs[0].x, glyphs[0].y, glyphs[0].font_size), (72.0, 156.0, 12.0));
+        assert_eq!(crate::layout::glyphs_to_text(&glyphs).trim(), "Scanned page");
+        // 語の確からしさは文字に残す
+        assert_eq!(glyphs.iter().map(|glyph| glyph.confidence).collect::<Vec<_>>(), [Some(96.5), Some(41.0)]);
+
+        // 確からしさが低い語に目印を付ける
+        let marked = words_to_glyphs(&words, 300, Some(60.0));
+        assert_eq!(crate::layout::glyphs_to_text(&marked).trim(), "Scanned {?page?}");
+        assert!(!is_marked(&marked[0].text) && is_marked(&marked[1].text));
+    }
+
+    // 単体テスト: 向きの判定結果の読み取りと画像の回転
+    #[cfg(feature = "ocr")]
+    #[test]
+    fn test_parse_osd() {
+        let osd = |rotate: u32, confidence: f64| {
+            format!("Page number: 0\nOrientation in degrees: {}\nRotate: {}\nOrientation confidence: {}\nScript: Latin\nScript confidence: 3.1\n", (360 - rotate) % 360, rotate, confidence)
+        };
+        let test_cases = vec![
+            (osd(180, 12.5), Some(180), "逆さまのページ"),
+            (osd(90, 4.0), Some(90), "横倒しのページ"),
+            (osd(0, 15.0), None, "正しい向き"),
+            (osd(180, 0.4), None, "確からしさが低い"),
+            ("Too few characters. Skipping this page".to_string(), None, "判定できない"),
+        ];
+        for (input, expected, desc) in test_cases {
+            assert_eq!(parse_osd(&input), expected, "Test failed: {}", desc);
+        }
+
+        // 2x1 の画素（a b）を回す
+        assert_eq!(rotate_pixels(b"ab", 2, 1, 1, 90), (b"ab".to_vec(), 1, 2));
+        assert_eq!(rotate_pixels(b"ab", 2, 1, 1, 180), (b"ba".to_vec(), 2, 1));
+        assert_eq!(rotate_pixels(b"ab", 2, 1, 1, 270), (b"ba".to_vec(), 1, 2));
+    }
+}
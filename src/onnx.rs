@@ -0,0 +1,206 @@
+use anyhow::{bail, Result};
+use std::path::Path;
+
+#[cfg(feature = "onnx")]
+use anyhow::Context;
+#[cfg(feature = "onnx")]
+use tract_onnx::prelude::*;
+
+#[cfg(feature = "onnx")]
+use crate::graphics;
+
+/// DocLayNet の領域の種類（モデルの出力のクラスの順）
+#[cfg(feature = "onnx")]
+const DOCLAYNET_LABELS: [&str; 11] =
+    ["caption", "footnote", "formula", "list-item", "page-footer", "page-header", "picture", "section-header", "table", "text", "title"];
+
+/// モデルの入力の大きさが決まっていない場合の、入力画像の一辺の画素数
+#[cfg(feature = "onnx")]
+const DEFAULT_INPUT_SIZE: usize = 1024;
+
+/// 候補にする領域の確信度の下限（採用するかどうかは layout_model::parse_regions で決める）
+#[cfg(feature = "onnx")]
+const MIN_CANDIDATE_SCORE: f32 = 0.25;
+
+/// 同じ領域とみなす、同じ種類の領域どうしの重なりの割合（IoU）
+#[cfg(feature = "onnx")]
+const NMS_IOU: f32 = 0.45;
+
+/// DocLayNet で学習した YOLO 形式の領域の判定のモデル（DocLayout-YOLO や YOLOv8 を ONNX に書き出したものなど）
+///
+/// 入力は [1, 3, 高さ, 幅] の 0〜1 の RGB の画像、出力は [1, 4 + 11, 候補の数]（または [1, 候補の数, 4 + 11]）の、
+/// 中心・幅・高さと DocLayNet の 11 種類の確信度とする。
+#[cfg(feature = "onnx")]
+pub struct OnnxModel {
+    model: TypedRunnableModel<TypedModel>,
+    /// 入力画像の一辺の画素数
+    size: usize,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxModel {
+    /// モデルを読み込んで最適化する（文書ごとに一度だけ行う）
+    pub fn load(path: &Path) -> Result<Self> {
+        let model = tract_onnx::onnx().model_for_path(path).with_context(|| format!("ONNX のモデルを読み込めません: {:?}", path))?;
+        let size = model.input_fact(0)?.shape.as_concrete_finite()?.and_then(|shape| shape.get(2).copied()).unwrap_or(DEFAULT_INPUT_SIZE);
+        let model = model
+            .with_input_fact(0, f32::fact([1, 3, size, size]).into())?
+            .into_optimized()
+            .and_then(|model| model.into_runnable())
+            .with_context(|| format!("ONNX のモデルを実行の準備ができません: {:?}", path))?;
+        Ok(OnnxModel { model, size })
+    }
+
+    /// ページ画像の領域を判定し、layout_model::parse_regions で読める JSON（bbox はページ画像のピクセル座標）を返す
+    pub fn detect(&self, image: &Path) -> Result<String> {
+        let raster = graphics::read_png(image)?;
+        let letterbox = Letterbox::new(raster.width, raster.height, self.size);
+        let samples = raster.color.samples();
+        let input = tract_ndarray::Array4::from_shape_fn((1, 3, self.size, self.size), |(_, channel, y, x)| {
+            match letterbox.source(x, y) {
+                // 灰色の画像は、R・G・B に同じ値を使う
+                Some((sx, sy)) => f32::from(raster.data[(sy * raster.width as usize + sx) * samples + if samples >= 3 { channel } else { 0 }]) / 255.0,
+                None => 114.0 / 255.0,
+            }
+        });
+        let outputs = self.model.run(tvec!(Tensor::from(input).into())).context("ONNX のモデルの実行に失敗しました")?;
+        let output = outputs[0].to_array_view::<f32>()?;
+        let detections = non_max_suppression(decode_detections(output.as_slice().context("ONNX のモデルの出力を読み込めません")?, output.shape())?);
+
+        let regions: Vec<serde_json::Value> = detections
+            .iter()
+            .map(|detection| {
+                let [x0, y0, x1, y1] = detection.bbox;
+                let (x0, y0) = letterbox.to_image(x0, y0);
+                let (x1, y1) = letterbox.to_image(x1, y1);
+                serde_json::json!({"label": DOCLAYNET_LABELS[detection.class], "bbox": [x0, y0, x1, y1], "score": detection.score})
+            })
+            .collect();
+        Ok(serde_json::Value::Array(regions).to_string())
+    }
+}
+
+/// 縦横比を保ったままモデルの入力の大きさに縮め、余白を灰色で埋める変換（YOLO の letterbox）
+#[cfg(feature = "onnx")]
+struct Letterbox {
+    scale: f32,
+    pad_x: f32,
+    pad_y: f32,
+    width: u32,
+    height: u32,
+}
+
+#[cfg(feature = "onnx")]
+impl Letterbox {
+    fn new(width: u32, height: u32, size: usize) -> Self {
+        let scale = (size as f32 / width as f32).min(size as f32 / height as f32);
+        let pad_x = (size as f32 - width as f32 * scale) / 2.0;
+        let pad_y = (size as f32 - height as f32 * scale) / 2.0;
+        Letterbox { scale, pad_x, pad_y, width, height }
+    }
+
+    /// 入力画像の画素に対応するページ画像の画素（余白は None）
+    fn source(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let (sx, sy) = ((x as f32 + 0.5 - self.pad_x) / self.scale, (y as f32 + 0.5 - self.pad_y) / self.scale);
+        (sx >= 0.0 && sy >= 0.0 && sx < self.width as f32 && sy < self.height as f32).then_some((sx as usize, sy as usize))
+    }
+
+    /// 入力画像の座標をページ画像の座標にする
+    fn to_image(&self, x: f32, y: f32) -> (f32, f32) {
+        (((x - self.pad_x) / self.scale).clamp(0.0, self.width as f32), ((y - self.pad_y) / self.scale).clamp(0.0, self.height as f32))
+    }
+}
+
+/// モデルが判定した領域の候補（bbox は [左, 上, 右, 下] の入力画像の座標）
+#[cfg(feature = "onnx")]
+#[derive(Debug, Clone, PartialEq)]
+struct Detection {
+    class: usize,
+    score: f32,
+    bbox: [f32; 4],
+}
+
+/// モデルの出力を、確信度の最も高い種類ごとの領域の候補にする
+#[cfg(feature = "onnx")]
+fn decode_detections(output: &[f32], shape: &[usize]) -> Result<Vec<Detection>> {
+    let attributes = 4 + DOCLAYNET_LABELS.len();
+    let (count, value): (usize, Box<dyn Fn(usize, usize) -> f32>) = match shape {
+        [1, a, n] if *a == attributes => (*n, Box::new(move |candidate, attribute| output[attribute * n + candidate])),
+        [1, n, a] if *a == attributes => (*n, Box::new(move |candidate, attribute| output[candidate * attributes + attribute])),
+        _ => bail!("ONNX のモデルの出力の形 {:?} が DocLayNet の領域の判定のモデル（[1, {}, 候補の数]）と異なります", shape, attributes),
+    };
+    Ok((0..count)
+        .filter_map(|candidate| {
+            let (class, score) = (0..DOCLAYNET_LABELS.len()).map(|class| (class, value(candidate, 4 + class))).max_by(|a, b| a.1.total_cmp(&b.1))?;
+            if score < MIN_CANDIDATE_SCORE {
+                return None;
+            }
+            let [cx, cy, w, h] = [0, 1, 2, 3].map(|attribute| value(candidate, attribute));
+            Some(Detection { class, score, bbox: [cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0] })
+        })
+        .collect())
+}
+
+/// 同じ種類で大きく重なる候補のうち、確信度の最も高いものだけを残す
+#[cfg(feature = "onnx")]
+fn non_max_suppression(mut detections: Vec<Detection>) -> Vec<Detection> {
+    let iou = |a: &[f32; 4], b: &[f32; 4]| {
+        let intersection = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0) * (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+        let area = |r: &[f32; 4]| (r[2] - r[0]) * (r[3] - r[1]);
+        intersection / (area(a) + area(b) - intersection).max(f32::EPSILON)
+    };
+    detections.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Detection> = Vec::new();
+    for detection in detections {
+        if !kept.iter().any(|other| other.class == detection.class && iou(&other.bbox, &detection.bbox) > NMS_IOU) {
+            kept.push(detection);
+        }
+    }
+    kept
+}
+
+/// onnx 機能を有効にしていないビルドの ONNX のモデル（読み込もうとするとエラーにする）
+#[cfg(not(feature = "onnx"))]
+pub enum OnnxModel {}
+
+#[cfg(not(feature = "onnx"))]
+impl OnnxModel {
+    pub fn load(path: &Path) -> Result<Self> {
+        bail!("--layout-model の ONNX のモデルを使うには onnx 機能を有効にして pdf2md をビルドしてください: {:?}", path)
+    }
+
+    pub fn detect(&self, _image: &Path) -> Result<String> {
+        match *self {}
+    }
+}
+
+#[cfg(all(test, feature = "onnx"))]
+mod tests {
+    use super::*;
+
+    // 単体テスト: モデルの出力の読み取りと重なった候補の除去
+    #[test]
+    fn test_decode_detections() {
+        // [1, 15, 3] の出力（候補ごとに中心・幅・高さと 11 種類の確信度）
+        let candidates: [([f32; 4], usize, f32); 3] = [([100.0, 50.0, 200.0, 40.0], 10, 0.9), ([102.0, 51.0, 200.0, 40.0], 10, 0.8), ([300.0, 400.0, 100.0, 100.0], 8, 0.7)];
+        let mut output = vec![0.0; 15 * 3];
+        for (index, (bbox, class, score)) in candidates.iter().enumerate() {
+            for (attribute, value) in bbox.iter().enumerate() {
+                output[attribute * 3 + index] = *value;
+            }
+            output[(4 + class) * 3 + index] = *score;
+        }
+
+        let detections = non_max_suppression(decode_detections(&output, &[1, 15, 3]).unwrap());
+        let labels: Vec<(&str, [f32; 4])> = detections.iter().map(|detection| (DOCLAYNET_LABELS[detection.class], detection.bbox)).collect();
+        assert_eq!(labels, vec![("title", [0.0, 30.0, 200.0, 70.0]), ("table", [250.0, 350.0, 350.0, 450.0])]);
+
+        assert!(decode_detections(&output, &[1, 3, 15]).is_ok());
+        assert!(decode_detections(&output, &[1, 9, 5]).is_err());
+
+        // 横長のページ画像は上下に余白を入れて縮める
+        let letterbox = Letterbox::new(2000, 1000, 1000);
+        assert_eq!(letterbox.to_image(500.0, 500.0), (1000.0, 500.0));
+        assert_eq!((letterbox.source(0, 0), letterbox.source(0, 300)), (None, Some((1, 101))));
+    }
+}
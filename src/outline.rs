@@ -0,0 +1,136 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::destinations::Bookmark;
+use crate::layout::{self, PageLayout};
+use crate::detect_heading;
+
+/// アウトラインの出力形式
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutlineFormat {
+    /// インデント付きのテキスト
+    Text,
+    /// 入れ子構造の JSON
+    Json,
+}
+
+/// 検出した見出し1件と、その配下の見出し
+#[derive(Debug, Serialize)]
+pub struct OutlineEntry {
+    pub level: usize,
+    pub text: String,
+    pub page: u32,
+    pub children: Vec<OutlineEntry>,
+}
+
+/// ページごとのテキストから見出しを検出し、階層構造に組み立てる
+pub fn build_outline(pages: &[PageLayout]) -> Vec<OutlineEntry> {
+    let mut headings = Vec::new();
+
+    for page in pages {
+        let text = layout::glyphs_to_text(&page.glyphs);
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some((level, heading)) = detect_heading(trimmed) {
+                headings.push(OutlineEntry { level, text: heading.to_string(), page: page.number, children: Vec::new() });
+            }
+        }
+    }
+
+    nest_headings(headings)
+}
+
+/// PDF のしおりを、検出した見出しと同じ階層構造にする
+///
+/// 移動先の無い項目（章をまとめるだけの項目など）は、後に続く項目の移動先のページとする。
+pub fn bookmark_outline(bookmarks: &[Bookmark]) -> Vec<OutlineEntry> {
+    let headings = bookmarks
+        .iter()
+        .enumerate()
+        .map(|(index, bookmark)| {
+            let page = bookmarks[index..].iter().find_map(|item| item.page).unwrap_or(1);
+            OutlineEntry { level: bookmark.level, text: bookmark.title.clone(), page, children: Vec::new() }
+        })
+        .collect();
+    nest_headings(headings)
+}
+
+/// 平坦な見出しの並びを、レベルに従って入れ子にする
+fn nest_headings(headings: Vec<OutlineEntry>) -> Vec<OutlineEntry> {
+    let mut roots: Vec<OutlineEntry> = Vec::new();
+    // 現在たどっている親見出しの列（末尾が直近の親）
+    let mut stack: Vec<OutlineEntry> = Vec::new();
+
+    for heading in headings {
+        // 同じレベル以上の見出しが来たら、スタック上の見出しを確定させる
+        while stack.last().is_some_and(|parent| parent.level >= heading.level) {
+            let finished = stack.pop().unwrap();
+            attach(&mut roots, &mut stack, finished);
+        }
+        stack.push(heading);
+    }
+    while let Some(finished) = stack.pop() {
+        attach(&mut roots, &mut stack, finished);
+    }
+
+    roots
+}
+
+/// 確定した見出しを親（なければルート）に追加する
+fn attach(roots: &mut Vec<OutlineEntry>, stack: &mut [OutlineEntry], entry: OutlineEntry) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry),
+    }
+}
+
+/// アウトラインを指定の形式の文字列にする
+pub fn render_outline(entries: &[OutlineEntry], format: OutlineFormat) -> Result<String> {
+    match format {
+        OutlineFormat::Text => {
+            let mut text = String::new();
+            render_text(entries, 0, &mut text);
+            Ok(text)
+        }
+        OutlineFormat::Json => Ok(serde_json::to_string_pretty(entries)? + "\n"),
+    }
+}
+
+fn render_text(entries: &[OutlineEntry], depth: usize, text: &mut String) {
+    for entry in entries {
+        text.push_str(&format!("{}{} {} (p.{})\n", "  ".repeat(depth), "#".repeat(entry.level), entry.text, entry.page));
+        render_text(&entry.children, depth + 1, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(level: usize, text: &str, page: u32) -> OutlineEntry {
+        OutlineEntry { level, text: text.to_string(), page, children: Vec::new() }
+    }
+
+    // 単体テスト: 見出しの入れ子とテキスト出力
+    #[test]
+    fn test_nest_headings() {
+        let outline = nest_headings(vec![
+            heading(1, "A", 1),
+            heading(2, "A-1", 1),
+            heading(3, "A-1-a", 2),
+            heading(2, "A-2", 3),
+            heading(1, "B", 4),
+        ]);
+
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].children.len(), 2);
+        assert_eq!(
+            render_outline(&outline, OutlineFormat::Text).unwrap(),
+            "# A (p.1)\n  ## A-1 (p.1)\n    ### A-1-a (p.2)\n  ## A-2 (p.3)\n# B (p.4)\n"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+
+/// 上限に収まるところまでで省略した出力の末尾に入れる目印
+pub const TRUNCATION_MARKER: &str = "<!-- pdf2md: 出力の大きさの上限を超えたため、以降を省略しました -->";
+
+/// 出力が --max-output-bytes を超えた場合の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OversizePolicy {
+    /// エラーにして書き出さない
+    #[default]
+    Error,
+    /// 上限に収まるところまでを書き出し、末尾に省略の目印を入れる
+    Truncate,
+    /// 上限に収まる大きさの複数のファイルに分けて書き出す
+    Split,
+}
+
+/// 出力の大きさの上限と、超えた場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLimit {
+    pub max_bytes: usize,
+    pub policy: OversizePolicy,
+}
+
+impl OutputLimit {
+    /// 上限に収まるようにした出力（split の場合は分けたファイルごとの内容。上限に収まる出力はそのまま）
+    ///
+    /// size は書き出すときの大きさを測る。つないだ文字列の大きさが、それぞれの大きさの和になるものを渡す。
+    pub fn apply(&self, markdown: &str, size: impl Fn(&str) -> usize) -> Result<Vec<String>> {
+        let total = size(markdown);
+        if total <= self.max_bytes {
+            return Ok(vec![markdown.to_string()]);
+        }
+        match self.policy {
+            OversizePolicy::Error => bail!(
+                "出力の大きさ（{} バイト）が --max-output-bytes の上限（{} バイト）を超えています（--oversize truncate か split で、上限に収めて書き出せます）",
+                total,
+                self.max_bytes
+            ),
+            OversizePolicy::Truncate => {
+                let tail = format!("\n\n{}\n", TRUNCATION_MARKER);
+                let Some(limit) = self.max_bytes.checked_sub(size(&tail)) else {
+                    bail!("--max-output-bytes の上限（{} バイト）が小さすぎて、省略の目印を入れられません", self.max_bytes);
+                };
+                let kept = chunks(markdown, limit, &size).into_iter().next().unwrap_or_default();
+                Ok(vec![format!("{}{}", kept.trim_end(), tail)])
+            }
+            OversizePolicy::Split => {
+                let limit = self.max_bytes.saturating_sub(size("\n")).max(1);
+                Ok(chunks(markdown, limit, &size).into_iter().map(|chunk| format!("{}\n", chunk.trim_end_matches('\n'))).collect())
+            }
+        }
+    }
+}
+
+/// Markdown のブロック（空行で区切った部分。コードブロックの中の空行では区切らない）
+fn blocks(markdown: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let (mut start, mut offset, mut fenced) = (0, 0, false);
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+        }
+        offset += line.len();
+        if !fenced && line.trim().is_empty() && offset - line.len() > start {
+            blocks.push(markdown[start..offset - line.len()].trim_end_matches(['\r', '\n']));
+            start = offset;
+        } else if line.trim().is_empty() && offset - line.len() == start {
+            start = offset;
+        }
+    }
+    if start < markdown.len() {
+        blocks.push(&markdown[start..]);
+    }
+    blocks
+}
+
+/// size で測った大きさがそれぞれ limit 以下になるように、Markdown をブロックの境目で分ける
+///
+/// 1つで上限を超えるブロックは行の境目で、1つで上限を超える行は文字の境目で分ける。
+fn chunks(markdown: &str, limit: usize, size: &impl Fn(&str) -> usize) -> Vec<String> {
+    let blocks = blocks(markdown);
+    let (units, separator): (Vec<&str>, &str) = if blocks.len() > 1 {
+        (blocks, "\n\n")
+    } else if markdown.trim_end().contains('\n') {
+        (markdown.trim_end().split('\n').collect(), "\n")
+    } else {
+        (markdown.char_indices().map(|(index, c)| &markdown[index..index + c.len_utf8()]).collect(), "")
+    };
+    let separator_size = size(separator);
+
+    let mut chunks = Vec::new();
+    let (mut current, mut current_size) = (String::new(), 0);
+    for unit in units {
+        let unit_size = size(unit);
+        let pieces = if unit_size > limit && unit.chars().nth(1).is_some() { chunks_of(unit, limit, size) } else { vec![(unit.to_string(), unit_size)] };
+        for (piece, piece_size) in pieces {
+            if current.is_empty() {
+                (current, current_size) = (piece, piece_size);
+            } else if current_size + separator_size + piece_size <= limit {
+                current.push_str(separator);
+                current.push_str(&piece);
+                current_size += separator_size + piece_size;
+            } else {
+                chunks.push(std::mem::replace(&mut current, piece));
+                current_size = piece_size;
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 分けた部分と、その大きさ
+fn chunks_of(unit: &str, limit: usize, size: &impl Fn(&str) -> usize) -> Vec<(String, usize)> {
+    chunks(unit, limit, size).into_iter().map(|chunk| (size(&chunk), chunk)).map(|(chunk_size, chunk)| (chunk, chunk_size)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 上限を超える出力のエラー、省略、分割
+    #[test]
+    fn test_output_limit() {
+        let markdown = "# Title\n\nfirst paragraph\n\n```\ncode\n\nmore code\n```\n\n## Next\n\nlast paragraph\n";
+        let size = |text: &str| text.len();
+        let limit = |max_bytes, policy| OutputLimit { max_bytes, policy };
+        assert_eq!(limit(1000, OversizePolicy::Error).apply(markdown, size).unwrap(), [markdown]);
+        assert!(limit(40, OversizePolicy::Error).apply(markdown, size).unwrap_err().to_string().contains("上限（40 バイト）"));
+
+        let max_bytes = "# Title\n\nfirst paragraph\n\n".len() + TRUNCATION_MARKER.len() + 1;
+        let long = format!("{}\n{}\n", markdown, "long paragraph ".repeat(20));
+        let truncated = limit(max_bytes, OversizePolicy::Truncate).apply(&long, size).unwrap();
+        assert_eq!(truncated, [format!("# Title\n\nfirst paragraph\n\n{}\n", TRUNCATION_MARKER)]);
+        assert!(limit(20, OversizePolicy::Truncate).apply(&long, size).is_err());
+
+        // コードブロックの中の空行では分けない
+        let parts = limit(40, OversizePolicy::Split).apply(markdown, size).unwrap();
+        assert_eq!(parts, ["# Title\n\nfirst paragraph\n", "```\ncode\n\nmore code\n```\n\n## Next\n", "last paragraph\n"]);
+        // 1つで上限を超える行は、文字の境目で分ける
+        let parts = limit(8, OversizePolicy::Split).apply("あいうえおか\n", size).unwrap();
+        assert_eq!(parts, ["あい\n", "うえ\n", "おか\n"]);
+        assert!(parts.iter().all(|part| part.len() <= 8));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::hyphenation::{Dehyphenator, Join};
+use crate::layout::{PageLayout, TextLine};
+use crate::lists;
+
+/// ページの行を、行間・字下げ・行末の位置から段落にまとめたテキストにする
+///
+/// 段落ごとに1行にまとめ、段落の間は空行、ページの間も空行で区切る。
+/// 箇条書きの項目は、行頭記号の位置の階層ごとに2つの空白で字下げする。
+/// ページに残っている欄外の注は、注の位置を含む段落の後に引用ブロックとして出力する。
+/// dehyphenator を指定した場合は、行末のハイフンで分けた語を、ページの言語の分綴のパターンで確かめてつなぐ。
+pub fn pages_to_text(pages: &[PageLayout], dehyphenator: Option<&Dehyphenator>) -> String {
+    let mut paragraphs: Vec<String> = Vec::new();
+
+    for page in pages {
+        let lines = page.lines();
+        let breaks = paragraph_breaks(&lines);
+        let mut notes = page.margin_notes.iter().peekable();
+
+        // 箇条書きの階層は、ページの行頭記号の位置を左から順に並べたときの順位
+        let mut item_positions: Vec<f64> = Vec::new();
+        for line in lines.iter().filter(|line| lists::list_item(&line.text).is_some()) {
+            if !item_positions.iter().any(|&x| (x - line.x0).abs() <= line.font_size) {
+                item_positions.push(line.x0);
+            }
+        }
+
+        let language = dehyphenator.and_then(|_| Dehyphenator::detect_language(&lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join(" ")));
+        let mut current = String::new();
+        let mut last_y = f64::NEG_INFINITY;
+        for (line, starts_paragraph) in lines.iter().zip(breaks) {
+            if starts_paragraph && !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+                while let Some(note) = notes.next_if(|note| note.y <= last_y + line.font_size * 0.5) {
+                    paragraphs.push(format!("> {}", note.text));
+                }
+            }
+            last_y = line.y;
+            // 日本語の文字どうしは、行をつなぐときに空白を入れない
+            let joins_cjk = current.chars().last().is_some_and(is_cjk) && line.text.trim().chars().next().is_some_and(is_cjk);
+            let join = dehyphenator.filter(|_| !starts_paragraph).and_then(|dehyphenator| dehyphenator.join(&current, &line.text, language));
+            if join == Some(Join::Word) {
+                current.pop();
+            } else if !current.is_empty() && !joins_cjk && join != Some(Join::Hyphenated) {
+                current.push(' ');
+            } else if lists::list_item(&line.text).is_some() {
+                let depth = item_positions.iter().filter(|&&x| x < line.x0 - line.font_size).count();
+                current.extend(std::iter::repeat_n(' ', depth * 2));
+            }
+            current.push_str(line.text.trim());
+        }
+        if !current.is_empty() {
+            paragraphs.push(current);
+        }
+        paragraphs.extend(notes.map(|note| format!("> {}", note.text)));
+    }
+
+    paragraphs.join("\n\n")
+}
+
+/// 日本語の文字（かな、漢字、全角の記号）かどうか
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x30FF | 0x4E00..=0x9FFF | 0xFF00..=0xFFEF)
+}
+
+/// 各行が新しい段落の先頭かどうかを判定する
+pub fn paragraph_breaks(lines: &[TextLine]) -> Vec<bool> {
+    let line_spacing = typical_line_spacing(lines);
+
+    let mut breaks = vec![true; lines.len()];
+    // 段落が箇条書きの項目であれば、その先頭行
+    let mut item = lines.first().filter(|line| lists::list_item(&line.text).is_some());
+    for i in 1..lines.len() {
+        let (prev, next) = (&lines[i - 1], &lines[i]);
+        let font_size = prev.font_size.max(next.font_size);
+        let gap = next.y - prev.y;
+
+        // 上に戻る（次の段に移る）場合や、行間が通常より広い場合
+        let wide_gap = gap > font_size * line_spacing.map_or(1.5, |spacing| spacing * 1.4);
+        // 文字の大きさが変わる場合（見出しと本文など）
+        let size_changed = (prev.font_size - next.font_size).abs() > font_size * 0.15;
+        // 段落の先頭行の字下げ（全角の空白で始まる行は、日本語の段落の字下げ）
+        let indented = (next.x0 > prev.x0 + next.font_size * 0.8 && next.x0 < prev.x0 + next.font_size * 6.0) || next.text.starts_with('\u{3000}');
+
+        // 小文字で始まる行は前の行の続きとみなし、字下げや行末の位置では区切らない
+        let continues = next.text.trim_start().starts_with(|c: char| c.is_lowercase());
+        // 箇条書きの項目は常に新しい段落にし、項目の行頭記号より右から始まる行は項目の続きとする
+        let next_item = lists::list_item(&next.text).is_some();
+        let hanging = item.is_some_and(|item| next.x0 > item.x0 + next.font_size * 0.3);
+
+        breaks[i] = next_item
+            || gap < -font_size * 0.5
+            || wide_gap
+            || size_changed
+            || (!continues && !hanging && (indented || ends_early(lines, prev, next)));
+        if breaks[i] {
+            item = next_item.then_some(next);
+        }
+    }
+
+    breaks
+}
+
+/// 同じ段落の続きなら次の行の先頭の単語が入ったはずの余白を残して、前の行が終わっているかどうか
+///
+/// 両端揃えでも左揃えでも、段落の最終行は右端より手前で終わることを利用する。
+fn ends_early(lines: &[TextLine], prev: &TextLine, next: &TextLine) -> bool {
+    // 前の行と同じ段（左端が近い行）の右端
+    let right = lines
+        .iter()
+        .filter(|line| (line.x0 - prev.x0).abs() <= prev.font_size * 3.0)
+        .map(|line| line.x1)
+        .fold(prev.x1, f64::max);
+
+    let chars = next.text.chars().count().max(1) as f64;
+    let char_width = (next.x1 - next.x0) / chars;
+    let first_word = next.text.split_whitespace().next().map_or(0, |word| word.chars().count()) as f64;
+
+    right - prev.x1 > (first_word + 1.0) * char_width
+}
+
+/// 同じ大きさの文字が続く行の、フォントサイズに対する行送りの比の中央値（標準的な行間）
+fn typical_line_spacing(lines: &[TextLine]) -> Option<f64> {
+    let mut ratios: Vec<f64> = lines
+        .windows(2)
+        .filter(|pair| (pair[0].font_size - pair[1].font_size).abs() <= pair[0].font_size * 0.15)
+        .map(|pair| (pair[1].y - pair[0].y) / pair[0].font_size)
+        .filter(|&ratio| ratio > 0.0 && ratio < 2.5)
+        .collect();
+    if ratios.is_empty() {
+        return None;
+    }
+    ratios.sort_by(f64::total_cmp);
+    Some(ratios[ratios.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    fn line(text: &str, x0: f64, x1: f64, y: f64, font_size: f64) -> TextLine {
+        TextLine { text: text.to_string(), x0, x1, y, font_size, confidence: None }
+    }
+
+    // 単体テスト: 行の形状による段落の区切り
+    #[test]
+    fn test_paragraph_breaks() {
+        let lines = vec![
+            line("Title", 50.0, 120.0, 60.0, 18.0),
+            line("First paragraph wraps", 50.0, 300.0, 100.0, 10.0),
+            line("onto the next line and ends.", 50.0, 200.0, 112.0, 10.0),
+            line("Second paragraph is indented", 70.0, 300.0, 124.0, 10.0),
+            line("and justified to the edge", 50.0, 300.0, 136.0, 10.0),
+            line("after a wide gap.", 50.0, 300.0, 170.0, 10.0),
+        ];
+
+        let expected = vec![true, true, false, true, false, true];
+        assert_eq!(paragraph_breaks(&lines), expected);
+    }
+
+    // 単体テスト: 段落ごとのテキストの組み立て
+    #[test]
+    fn test_pages_to_text() {
+        let glyph = |text: &str, x: f64, width: f64, y: f64| Glyph { text: text.to_string(), x, y, width, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None };
+        let page = |number, glyphs| PageLayout { number, glyphs, ..Default::default() };
+        let pages = vec![
+            page(1, vec![glyph("one two three four five", 50.0, 250.0, 100.0), glyph("six.", 50.0, 30.0, 112.0), glyph("Next", 50.0, 24.0, 124.0)]),
+            page(2, vec![glyph("Page two", 50.0, 60.0, 100.0)]),
+        ];
+
+        assert_eq!(pages_to_text(&pages, None), "one two three four five six.\n\nNext\n\nPage two");
+    }
+}
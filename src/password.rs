@@ -0,0 +1,170 @@
+use anyhow::{bail, Context, Result};
+use lopdf::encryption::DecryptionError;
+use lopdf::{Document, Object};
+use md5::{Digest, Md5};
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// パスワードを 32 バイトにするための詰め物（PDF の仕様の Algorithm 3.2）
+const PAD_BYTES: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80,
+    0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// 文書を開いた権限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// 暗号化されていない
+    Unencrypted,
+    /// ユーザーパスワード（空のパスワードを含む）で復号した
+    User,
+    /// オーナーパスワードで復号した（権限設定の制限を受けない）
+    Owner,
+}
+
+/// 復号にパスワードが必要なことを表すエラー（anyhow::Error の downcast_ref で判別し、パスワードを尋ねて変換をやり直せる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordRequired;
+
+impl fmt::Display for PasswordRequired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PDFがパスワードで保護されています（--password でパスワードを指定してください）")
+    }
+}
+
+impl std::error::Error for PasswordRequired {}
+
+/// 暗号化された文書を復号する
+///
+/// パスワードの指定が無い場合は空のユーザーパスワードで復号し、開けなければ PasswordRequired を返す。
+/// 指定された場合はオーナーパスワード、ユーザーパスワードの順に試す。
+pub fn decrypt(doc: &mut Document, password: Option<&str>) -> Result<Access> {
+    if !doc.is_encrypted() {
+        return Ok(Access::Unencrypted);
+    }
+    let Some(password) = password else {
+        return match doc.decrypt("") {
+            Ok(()) => Ok(Access::User),
+            Err(lopdf::Error::Decryption(DecryptionError::IncorrectPassword)) => Err(PasswordRequired.into()),
+            Err(e) => Err(e).context("PDFを復号できません"),
+        };
+    };
+
+    if let Some(user_password) = user_password_from_owner(doc, password.as_bytes()) {
+        if doc.decrypt(&user_password).is_ok() {
+            return Ok(Access::Owner);
+        }
+    }
+    match doc.decrypt(password) {
+        Ok(()) => Ok(Access::User),
+        Err(lopdf::Error::Decryption(DecryptionError::IncorrectPassword)) => bail!("PDFのパスワードが正しくありません"),
+        Err(e) => Err(e).context("PDFを復号できません"),
+    }
+}
+
+/// オーナーパスワードから、/O に暗号化して記録されたユーザーパスワードを取り出す（PDF の仕様の Algorithm 3.7。RC4 の版 2、3 のみ）
+fn user_password_from_owner(doc: &Document, owner_password: &[u8]) -> Option<Vec<u8>> {
+    let encrypt = doc.get_encrypted().ok()?;
+    let revision = encrypt.get(b"R").and_then(Object::as_i64).ok()?;
+    if !(2..=3).contains(&revision) {
+        return None;
+    }
+    let key_len = match revision {
+        2 => 5,
+        _ => encrypt.get(b"Length").and_then(Object::as_i64).map_or(5, |bits| (bits / 8) as usize).clamp(5, 16),
+    };
+    let owner_entry = encrypt.get(b"O").and_then(Object::as_str).ok()?;
+
+    let len = owner_password.len().min(32);
+    let mut padded = owner_password[..len].to_vec();
+    padded.extend_from_slice(&PAD_BYTES[..32 - len]);
+    let mut digest = Md5::digest(&padded).to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            digest = Md5::digest(&digest[..key_len]).to_vec();
+        }
+    }
+    let key = &digest[..key_len];
+
+    Some(match revision {
+        2 => rc4(key, owner_entry),
+        _ => (0..=19u8).rev().fold(owner_entry.to_vec(), |data, i| rc4(&key.iter().map(|byte| byte ^ i).collect::<Vec<u8>>(), &data)),
+    })
+}
+
+/// RC4 で暗号化・復号する（暗号化と復号は同じ処理）
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// 端末でパスワードの入力を求める（Unix では入力した文字を表示しない）
+pub fn prompt(pdf_path: &Path) -> Result<String> {
+    eprint!("{:?} のパスワード: ", pdf_path);
+    std::io::stderr().flush().ok();
+    set_echo(false);
+    let mut line = String::new();
+    let read = std::io::stdin().lock().read_line(&mut line);
+    set_echo(true);
+    eprintln!();
+    read.context("パスワードを読み込めません")?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// 端末の入力の表示を切り替える（stty が無い場合は何もしない）
+#[cfg(unix)]
+fn set_echo(echo: bool) {
+    let _ = std::process::Command::new("stty").arg(if echo { "echo" } else { "-echo" }).stdin(std::process::Stdio::inherit()).status();
+}
+
+#[cfg(not(unix))]
+fn set_echo(_echo: bool) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+
+    // 単体テスト: オーナーパスワードからのユーザーパスワードの取り出し
+    #[test]
+    fn test_user_password_from_owner() {
+        // Algorithm 3.3 で /O を作り、取り出したユーザーパスワードが元に戻ることを確かめる
+        let (owner, user) = (b"owner-secret".as_slice(), b"user".as_slice());
+        let pad = |password: &[u8]| [password, &PAD_BYTES[..32 - password.len()]].concat();
+        let mut digest = Md5::digest(pad(owner)).to_vec();
+        for _ in 0..50 {
+            digest = Md5::digest(&digest[..16]).to_vec();
+        }
+        let key = &digest[..16];
+        let entry = (0..=19u8).fold(pad(user), |data, i| rc4(&key.iter().map(|byte| byte ^ i).collect::<Vec<u8>>(), &data));
+
+        let mut doc = Document::with_version("1.5");
+        let encrypt = doc.add_object(dictionary! {
+            "Filter" => "Standard",
+            "V" => 2,
+            "R" => 3,
+            "Length" => 128,
+            "O" => Object::String(entry, StringFormat::Hexadecimal),
+        });
+        doc.trailer.set("Encrypt", encrypt);
+        assert_eq!(user_password_from_owner(&doc, owner), Some(pad(user)));
+        assert_ne!(user_password_from_owner(&doc, b"wrong"), Some(pad(user)));
+
+        // RC4 の既知の値（鍵 "Key"、平文 "Plaintext"）
+        assert_eq!(rc4(b"Key", b"Plaintext"), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+    }
+}
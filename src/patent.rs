@@ -0,0 +1,391 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::invoice;
+use crate::layout::{PageLayout, TextLine};
+
+/// 特許文献を変換した結果
+#[derive(Debug, Default, PartialEq)]
+pub struct Patent {
+    pub markdown: String,
+    /// 表紙の書誌事項（フロントマターに出力する名前と値）
+    pub bibliography: Vec<(String, String)>,
+}
+
+/// INID コード（(11) や (54) など）とフロントマターの名前の対応
+const INID_FIELDS: &[(&str, &str)] = &[
+    ("10", "publication_number"),
+    ("11", "publication_number"),
+    ("21", "application_number"),
+    ("22", "filing_date"),
+    ("30", "priority"),
+    ("43", "publication_date"),
+    ("45", "publication_date"),
+    ("51", "classification"),
+    ("54", "title"),
+    ("71", "applicant"),
+    ("72", "inventors"),
+    ("73", "assignee"),
+    ("74", "agent"),
+];
+
+/// 日本の公報の【】の見出しとフロントマターの名前の対応
+const LABEL_FIELDS: &[(&str, &str)] = &[
+    ("公開番号", "publication_number"),
+    ("公表番号", "publication_number"),
+    ("特許番号", "publication_number"),
+    ("出願番号", "application_number"),
+    ("出願日", "filing_date"),
+    ("優先権主張番号", "priority"),
+    ("公開日", "publication_date"),
+    ("発行日", "publication_date"),
+    ("国際特許分類", "classification"),
+    ("発明の名称", "title"),
+    ("出願人", "applicant"),
+    ("特許権者", "applicant"),
+    ("発明者", "inventors"),
+    ("代理人", "agent"),
+];
+
+/// [0042] や【0042】の段落番号
+static NUMBERED_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[\[【]([0-9０-９]{4,5})[\]】]\s*(.*)$").unwrap());
+
+/// 請求項の番号（1. や【請求項1】）
+static CLAIM_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:(\d+)\s*\.\s+|【請求項([0-9０-９]+)】\s*)(.*)$").unwrap());
+
+/// 特許請求の範囲の見出し
+static CLAIMS_HEADING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i)(?:what is claimed is|i claim|we claim|claims|the invention claimed is)\s*[:：.]?$|^【?特許請求の範囲】?$").unwrap()
+});
+
+/// 要約の見出し
+static ABSTRACT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i)(?:\(57\)\s*)?(?:【要約】|abstract(?:\s+of\s+the\s+disclosure)?\b)\s*(.*)$|^\(57\)\s*(?:【要約】)?\s*(.*)$").unwrap()
+});
+
+/// 段落番号でない【】の見出し
+static LABEL_HEADING_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^【([^】0-9０-９]+)】\s*(.*)$").unwrap());
+
+/// 書誌事項の INID コード
+static INID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\((\d{2})\)\s*(.*)$").unwrap());
+
+/// 書誌事項の【】の見出し
+static LABEL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^【([^】]+)】\s*(.*)$").unwrap());
+
+/// 書誌事項の値の前の英語の見出し（「Appl. No.:」「Inventors:」など）
+static ENGLISH_LABEL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z .']{0,30}[:：]\s*").unwrap());
+
+/// 図の説明のブロックの先頭（段落番号の後の「FIG. 1 is」「【図1】」）
+static FIGURE_DESCRIPTION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\*\*\[\d+\]\*\* )?(?:FIG(?:URE)?\.?\s*(\d+[A-Z]?)\s+(?:is|shows|illustrates|depicts)\b|【図([0-9０-９]+[A-Z]?)】)").unwrap()
+});
+
+/// 本文中の図の参照（FIG. 1、図1）
+static FIGURE_REFERENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bFIG(?:URE)?S?\.?\s*(\d+[A-Z]?)\b|図([0-9０-９]+[A-Z]?)").unwrap());
+
+/// 文書の区分
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    /// 表紙の書誌事項
+    Front,
+    Abstract,
+    Body,
+    Claims,
+}
+
+/// 文書の表紙に INID コードか【公開番号】などの書誌事項があるかどうか
+pub fn looks_like_patent(lines: &[TextLine]) -> bool {
+    let fields = lines.iter().filter(|line| bibliographic_field(line.text.trim()).is_some()).count();
+    fields >= 3
+}
+
+/// 特許文献を Markdown にする
+///
+/// 表紙の書誌事項をフロントマターに、[0042] や【0042】の段落番号ごとに段落を分け、
+/// 特許請求の範囲を番号付きリストに、図面の簡単な説明の「FIG. 1 is ...」に付けたアンカーへ
+/// 本文中の図の参照からリンクする。
+pub fn render_patent(pages: &[PageLayout]) -> Patent {
+    let lines: Vec<TextLine> = pages.iter().flat_map(|page| page.lines()).collect();
+    render_lines(&lines)
+}
+
+fn render_lines(lines: &[TextLine]) -> Patent {
+    let mut patent = Patent::default();
+    let mut blocks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    // 表紙の書誌事項の続きの行を足すフィールド
+    let mut field: Option<usize> = None;
+    let mut section = if looks_like_patent(lines) { Section::Front } else { Section::Body };
+
+    let flush = |blocks: &mut Vec<String>, current: &mut String| {
+        if !current.is_empty() {
+            blocks.push(std::mem::take(current));
+        }
+    };
+
+    for line in lines {
+        let text = line.text.trim();
+        // 空行と、米国特許の段の間にある行番号（5、10、15 ...）
+        if text.is_empty() || text.parse::<u32>().is_ok_and(|n| n % 5 == 0 && n <= 70) {
+            continue;
+        }
+
+        if section == Section::Front {
+            if let Some((name, value)) = bibliographic_field(text) {
+                match patent.bibliography.iter().position(|(n, _)| n == name) {
+                    // 同じ名前の2つ目以降は無視する（(11) と (10) の両方がある場合など）
+                    Some(_) => field = None,
+                    None => {
+                        patent.bibliography.push((name.to_string(), value));
+                        field = Some(patent.bibliography.len() - 1);
+                    }
+                }
+                continue;
+            }
+        }
+
+        if let Some(caps) = ABSTRACT_REGEX.captures(text) {
+            if section == Section::Front {
+                flush(&mut blocks, &mut current);
+                blocks.push(if text.contains("要約") { "## 要約".to_string() } else { "## Abstract".to_string() });
+                current = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str()).trim().to_string();
+                section = Section::Abstract;
+                continue;
+            }
+        }
+
+        if CLAIMS_HEADING_REGEX.is_match(text) {
+            flush(&mut blocks, &mut current);
+            blocks.push(format!("## {}", text.trim_matches(['【', '】']).trim_end_matches([':', '：', '.'])));
+            section = Section::Claims;
+            continue;
+        }
+
+        if section == Section::Claims {
+            if let Some(caps) = CLAIM_REGEX.captures(text) {
+                flush(&mut blocks, &mut current);
+                let number = caps.get(1).or(caps.get(2)).unwrap().as_str();
+                current = format!("{}. {}", to_ascii_digits(number), caps[3].trim()).trim_end().to_string();
+                continue;
+            }
+        }
+
+        if let Some(caps) = NUMBERED_REGEX.captures(text) {
+            flush(&mut blocks, &mut current);
+            current = format!("**[{}]** {}", to_ascii_digits(&caps[1]), caps[2].trim()).trim_end().to_string();
+            if section != Section::Claims {
+                section = Section::Body;
+            }
+            continue;
+        }
+
+        // 表紙の書誌事項以外の行（「United States Patent」など）は見出しにしない
+        if let Some(heading) = section_heading(text).filter(|_| section != Section::Front) {
+            flush(&mut blocks, &mut current);
+            blocks.push(format!("## {}", heading));
+            if let Some(rest) = LABEL_HEADING_REGEX.captures(text).map(|caps| caps[2].trim().to_string()).filter(|rest| !rest.is_empty()) {
+                current = rest;
+            }
+            section = Section::Body;
+            continue;
+        }
+
+        match section {
+            // 書誌事項の折り返した続き（それ以外の表紙の行は書誌事項に含めない）
+            Section::Front => {
+                if let Some(index) = field {
+                    append_line(&mut patent.bibliography[index].1, text);
+                }
+            }
+            _ => append_line(&mut current, text),
+        }
+    }
+    flush(&mut blocks, &mut current);
+
+    patent.markdown = link_figures(&blocks).join("\n\n");
+    patent
+}
+
+/// 表紙の書誌事項の行であれば、フロントマターの名前と値を返す
+fn bibliographic_field(text: &str) -> Option<(&'static str, String)> {
+    let (mut name, mut value) = (None, text.to_string());
+    if let Some(caps) = INID_REGEX.captures(text) {
+        name = INID_FIELDS.iter().find(|(code, _)| *code == &caps[1]).map(|(_, name)| *name);
+        value = caps[2].to_string();
+    }
+    // (11)【公開番号】のように INID コードと見出しの両方がある場合も、見出しだけの場合もある
+    if let Some(caps) = LABEL_REGEX.captures(value.trim()) {
+        name = name.or_else(|| LABEL_FIELDS.iter().find(|(l, _)| *l == &caps[1]).map(|(_, name)| *name));
+        value = caps[2].to_string();
+    }
+    let name = name?;
+
+    // 「Appl. No.:」「Inventors:」のような見出しを除く
+    let value = ENGLISH_LABEL_REGEX.replace(value.trim(), "").trim().to_string();
+
+    let value = if name.ends_with("_date") { invoice::parse_date(&value).unwrap_or(value) } else { value };
+    Some((name, value))
+}
+
+/// 節の見出しの行であれば見出しの文字列を返す（大文字だけの行、【技術分野】のような段落番号でない【】の行）
+fn section_heading(text: &str) -> Option<String> {
+    if let Some(caps) = LABEL_HEADING_REGEX.captures(text) {
+        // 図の説明（【図1】）は見出しにしない
+        if !caps[1].starts_with('図') {
+            return Some(caps[1].to_string());
+        }
+    }
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    let is_heading = letters >= 6
+        && text.split_whitespace().count() <= 8
+        && text.is_ascii()
+        && text.chars().all(|c| !c.is_lowercase())
+        && !text.starts_with("FIG");
+    is_heading.then(|| text.to_string())
+}
+
+/// 段落の末尾に行を足す（日本語の文字どうしの間には空白を入れない）
+fn append_line(paragraph: &mut String, text: &str) {
+    let is_cjk = |c: char| matches!(c as u32, 0x3000..=0x30FF | 0x4E00..=0x9FFF | 0xFF00..=0xFFEF);
+    let joins_cjk = paragraph.chars().last().is_some_and(is_cjk) && text.chars().next().is_some_and(is_cjk);
+    if !paragraph.is_empty() && !joins_cjk {
+        paragraph.push(' ');
+    }
+    paragraph.push_str(text);
+}
+
+/// 全角の数字を半角にし、先頭の 0 は残す
+fn to_ascii_digits(number: &str) -> String {
+    number
+        .chars()
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap(),
+            c => c,
+        })
+        .collect()
+}
+
+/// 図面の説明のブロックにアンカーを付け、他のブロックの図の参照（FIG. 1、図1）をそのアンカーへのリンクにする
+fn link_figures(blocks: &[String]) -> Vec<String> {
+    // 段落番号の後で「FIG. 1 is」「【図1】」から始まるブロックを図の説明とみなす
+    let mut anchors: Vec<(usize, String)> = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        if let Some(caps) = FIGURE_DESCRIPTION_REGEX.captures(block) {
+            let id = to_ascii_digits(caps.get(1).or(caps.get(2)).unwrap().as_str()).to_lowercase();
+            if !anchors.iter().any(|(_, existing)| *existing == id) {
+                anchors.push((index, id));
+            }
+        }
+    }
+
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| {
+            if let Some((_, id)) = anchors.iter().find(|(i, _)| *i == index) {
+                return format!("<a id=\"fig-{}\"></a>{}", id, block);
+            }
+            // 見出しは書き換えない
+            if block.starts_with('#') {
+                return block.clone();
+            }
+            FIGURE_REFERENCE_REGEX
+                .replace_all(block, |caps: &regex::Captures| {
+                    let id = to_ascii_digits(caps.get(1).or(caps.get(2)).unwrap().as_str()).to_lowercase();
+                    if anchors.iter().any(|(_, existing)| *existing == id) {
+                        format!("[{}](#fig-{})", &caps[0], id)
+                    } else {
+                        caps[0].to_string()
+                    }
+                })
+                .into_owned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(texts: &[&str]) -> Vec<TextLine> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| TextLine { text: text.to_string(), x0: 50.0, x1: 500.0, y: 50.0 + i as f64 * 12.0, font_size: 10.0, confidence: None })
+            .collect()
+    }
+
+    // 単体テスト: 書誌事項・段落番号・請求項・図の参照
+    #[test]
+    fn test_render_lines() {
+        let patent = render_lines(&lines(&[
+            "United States Patent",
+            "(10) Patent No.: US 10,123,456 B2",
+            "(45) Date of Patent: Mar. 5, 2019",
+            "(54) METHOD FOR CONVERTING",
+            "DOCUMENTS",
+            "(21) Appl. No.: 15/123,456",
+            "(57) ABSTRACT",
+            "A method converts documents.",
+            "BRIEF DESCRIPTION OF THE DRAWINGS",
+            "[0010] FIG. 1 is a flow chart.",
+            "[0011] As shown in FIG. 1, the method",
+            "starts with parsing.",
+            "What is claimed is:",
+            "1. A method comprising:",
+            "parsing a document.",
+            "2. The method of claim 1.",
+        ]));
+
+        assert_eq!(
+            patent.bibliography,
+            vec![
+                ("publication_number".to_string(), "US 10,123,456 B2".to_string()),
+                ("publication_date".to_string(), "2019-03-05".to_string()),
+                ("title".to_string(), "METHOD FOR CONVERTING DOCUMENTS".to_string()),
+                ("application_number".to_string(), "15/123,456".to_string()),
+            ]
+        );
+        assert_eq!(
+            patent.markdown,
+            "## Abstract\n\nA method converts documents.\n\n## BRIEF DESCRIPTION OF THE DRAWINGS\n\n\
+             <a id=\"fig-1\"></a>**[0010]** FIG. 1 is a flow chart.\n\n\
+             **[0011]** As shown in [FIG. 1](#fig-1), the method starts with parsing.\n\n\
+             ## What is claimed is\n\n1. A method comprising: parsing a document.\n\n2. The method of claim 1."
+        );
+    }
+
+    // 単体テスト: 日本の公報の書誌事項と段落番号
+    #[test]
+    fn test_render_japanese() {
+        let patent = render_lines(&lines(&[
+            "(19)【発行国】日本国特許庁(JP)",
+            "(11)【公開番号】特開2020-123456",
+            "(43)【公開日】令和2年8月13日",
+            "(54)【発明の名称】文書変換装置",
+            "(57)【要約】",
+            "文書を変換する。",
+            "【特許請求の範囲】",
+            "【請求項１】",
+            "文書を読み込む手段を備える装置。",
+            "【発明の詳細な説明】",
+            "【技術分野】",
+            "【０００１】",
+            "本発明は文書の変換に関する。",
+        ]));
+
+        assert_eq!(
+            patent.bibliography,
+            vec![
+                ("publication_number".to_string(), "特開2020-123456".to_string()),
+                ("publication_date".to_string(), "令和2年8月13日".to_string()),
+                ("title".to_string(), "文書変換装置".to_string()),
+            ]
+        );
+        assert_eq!(
+            patent.markdown,
+            "## 要約\n\n文書を変換する。\n\n## 特許請求の範囲\n\n1. 文書を読み込む手段を備える装置。\n\n\
+             ## 発明の詳細な説明\n\n## 技術分野\n\n**[0001]** 本発明は文書の変換に関する。"
+        );
+    }
+}
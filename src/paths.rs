@@ -0,0 +1,64 @@
+use std::path::{Component, Path, PathBuf};
+
+/// ディレクトリ from から to への、/ で区切った相対パス
+///
+/// Windows では、\\?\ の形式で指定した長いパスや UNC パスも通常の形式にそろえ、大文字と小文字を区別せずに比べる。
+/// ドライブや共有フォルダが異なり相対パスにできない場合は、to の絶対パスを / で区切って返す。
+pub fn relative_link(from: &Path, to: &Path) -> String {
+    let absolute = |path: &Path| {
+        let path = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+        without_verbatim_prefix(&std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+    };
+    let (from, to) = (absolute(from), absolute(to));
+    let same = |a: &Component, b: &Component| if cfg!(windows) { a.as_os_str().eq_ignore_ascii_case(b.as_os_str()) } else { a == b };
+    if let (Some(a @ Component::Prefix(_)), Some(b @ Component::Prefix(_))) = (from.components().next(), to.components().next()) {
+        if !same(&a, &b) {
+            return to.to_string_lossy().replace('\\', "/");
+        }
+    }
+    let common = from.components().zip(to.components()).take_while(|(a, b)| same(a, b)).count();
+    let parts: Vec<String> = std::iter::repeat_n("..".to_string(), from.components().count() - common)
+        .chain(to.components().skip(common).map(|component| component.as_os_str().to_string_lossy().into_owned()))
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+/// Windows の \\?\C:\... や \\?\UNC\server\share\... の形式のパスを、C:\... や \\server\share\... の形式にする（Windows 以外ではそのまま）
+pub fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    match path.to_str().filter(|_| cfg!(windows)).and_then(strip_verbatim_prefix) {
+        Some(path) => PathBuf::from(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// \\?\ の接頭辞を取り除いたパス（ドライブ文字か UNC で始まるもののみ。\\?\Volume{...} などは None）
+fn strip_verbatim_prefix(path: &str) -> Option<String> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return Some(format!(r"\\{}", rest));
+    }
+    let rest = path.strip_prefix(r"\\?\")?;
+    let drive = rest.as_bytes();
+    (drive.len() >= 2 && drive[0].is_ascii_alphabetic() && drive[1] == b':').then(|| rest.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 相対パスと Windows の長いパスの接頭辞
+    #[test]
+    fn test_relative_link() {
+        assert_eq!(relative_link(Path::new("out"), Path::new("out/images")), "images");
+        assert_eq!(relative_link(Path::new("out/docs"), Path::new("assets")), "../../assets");
+        assert_eq!(relative_link(Path::new(""), Path::new(".")), ".");
+
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\Users\a\report.md").as_deref(), Some(r"C:\Users\a\report.md"));
+        assert_eq!(strip_verbatim_prefix(r"\\?\UNC\server\share\docs").as_deref(), Some(r"\\server\share\docs"));
+        assert_eq!(strip_verbatim_prefix(r"\\?\Volume{1234}\docs"), None);
+        assert_eq!(strip_verbatim_prefix(r"\\server\share\docs"), None);
+    }
+}
@@ -0,0 +1,123 @@
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object};
+
+use crate::layout;
+
+/// すべてのページにテキストレイヤーがある場合の終了コード
+pub const EXIT_ALL_TEXT: i32 = 0;
+/// テキストレイヤーの無いページが一部にある場合の終了コード（エラー時の 1、引数エラーの 2 と区別する）
+pub const EXIT_PARTIAL_TEXT: i32 = 3;
+/// どのページにもテキストレイヤーが無い場合の終了コード
+pub const EXIT_NO_TEXT: i32 = 4;
+
+/// Form XObject の入れ子の上限（循環参照対策）
+const MAX_FORM_DEPTH: usize = 16;
+
+/// 1ページ分の判定結果
+#[derive(Debug)]
+pub struct PageProbe {
+    pub page: u32,
+    /// テキスト表示命令で描かれる文字（空白以外）のバイト数
+    pub chars: usize,
+}
+
+/// 各ページのテキスト表示命令を数える（フォントの解釈やレイアウト解析は行わない）
+pub fn probe_text_layer(doc: &Document) -> Vec<PageProbe> {
+    doc.get_pages()
+        .into_iter()
+        .map(|(page, page_id)| {
+            let resources = layout::inherited_resources(doc, page_id);
+            let chars = doc
+                .get_page_content(page_id)
+                .map(|content| count_text_bytes(doc, &content, resources, 0))
+                .unwrap_or(0);
+            PageProbe { page, chars }
+        })
+        .collect()
+}
+
+/// 内容ストリーム中の Tj / TJ / ' / " 命令の文字列を数える
+fn count_text_bytes(doc: &Document, content: &[u8], resources: Option<&Dictionary>, depth: usize) -> usize {
+    let Ok(content) = Content::decode(content) else {
+        return 0;
+    };
+
+    let mut count = 0;
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "Tj" | "TJ" | "'" | "\"" => count += operation.operands.iter().map(string_bytes).sum::<usize>(),
+            "Do" if depth < MAX_FORM_DEPTH => {
+                if let Some((_, form)) = layout::xobject(doc, resources, operation.operands.first()) {
+                    if form.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form") {
+                        let form_content = form.decompressed_content().unwrap_or_else(|_| form.content.clone());
+                        let form_resources = layout::form_resources(doc, form, resources);
+                        count += count_text_bytes(doc, &form_content, form_resources, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    count
+}
+
+/// 文字列（または TJ の配列内の文字列）の空白以外のバイト数
+fn string_bytes(object: &Object) -> usize {
+    match object {
+        Object::String(bytes, _) => bytes.iter().filter(|b| !b.is_ascii_whitespace()).count(),
+        Object::Array(items) => items.iter().map(string_bytes).sum(),
+        _ => 0,
+    }
+}
+
+/// 判定結果から終了コードを決める（ページが無い文書は、文字のあるページが無いものとする）
+pub fn exit_code(probes: &[PageProbe], min_chars: usize) -> i32 {
+    let with_text = probes.iter().filter(|p| p.chars >= min_chars).count();
+    if probes.is_empty() {
+        EXIT_NO_TEXT
+    } else if with_text == probes.len() {
+        EXIT_ALL_TEXT
+    } else if with_text == 0 {
+        EXIT_NO_TEXT
+    } else {
+        EXIT_PARTIAL_TEXT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::Operation;
+
+    // 単体テスト: テキスト表示命令の文字数の集計
+    #[test]
+    fn test_count_text_bytes() {
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tj", vec![Object::string_literal("Hello world")]),
+                Operation::new(
+                    "TJ",
+                    vec![Object::Array(vec![Object::string_literal("ab"), 120.into(), Object::string_literal("c")])],
+                ),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let doc = Document::with_version("1.5");
+
+        assert_eq!(count_text_bytes(&doc, &content.encode().unwrap(), None, 0), 13);
+    }
+
+    // 単体テスト: 終了コードの判定
+    #[test]
+    fn test_exit_code() {
+        let probes = |counts: &[usize]| -> Vec<PageProbe> {
+            counts.iter().enumerate().map(|(i, &chars)| PageProbe { page: i as u32 + 1, chars }).collect()
+        };
+
+        assert_eq!(exit_code(&probes(&[100, 50]), 20), EXIT_ALL_TEXT);
+        assert_eq!(exit_code(&probes(&[100, 3]), 20), EXIT_PARTIAL_TEXT);
+        assert_eq!(exit_code(&probes(&[0, 3]), 20), EXIT_NO_TEXT);
+        assert_eq!(exit_code(&probes(&[]), 20), EXIT_NO_TEXT);
+    }
+}
@@ -0,0 +1,260 @@
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::diagnostics::{Severity, Warning};
+use crate::report::FileStatus;
+
+/// 進み具合の表示の形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// 人が読むメッセージ
+    #[default]
+    Text,
+    /// 1行に1つの JSON のイベント（変換を組み込むツールや GUI 向け。人が読むメッセージも message のイベントにする）
+    Jsonl,
+}
+
+/// JSON Lines で表示するかどうか（プロセス全体で1つ）
+static JSONL: AtomicBool = AtomicBool::new(false);
+
+/// 端末に表示する進捗バー（標準エラー出力が端末で、人が読むメッセージを表示する場合だけ。プロセス全体で1つ）
+static BARS: OnceLock<Option<MultiProgress>> = OnceLock::new();
+
+/// イベントを受け取る処理
+pub type Observer = Box<dyn Fn(&Event)>;
+
+thread_local! {
+    /// このスレッドで変換中のファイル（ページのイベントに付ける）
+    static CURRENT_FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    /// このスレッドのイベントを受け取る処理（--stdio-server で要求ごとの通知にする）
+    static OBSERVER: RefCell<Option<Observer>> = const { RefCell::new(None) };
+}
+
+/// 進み具合の表示の形式を決める
+pub fn init(format: ProgressFormat) {
+    JSONL.store(format == ProgressFormat::Jsonl, Ordering::Relaxed);
+    BARS.get_or_init(|| (format == ProgressFormat::Text && std::io::stderr().is_terminal()).then(MultiProgress::new));
+}
+
+/// 進み具合のイベント（標準エラー出力に1行ずつ JSON で書く）
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// ファイルの変換を始めた（index は一括変換での 1 始まりの順番）
+    FileStarted { file: &'a Path, index: usize, total: usize },
+    /// ページのレイアウトを抽出した（pages は文書の全ページ数）
+    PageDone { file: Option<&'a Path>, page: u32, pages: usize },
+    /// 変換で失われる内容の警告
+    Warning {
+        file: Option<&'a Path>,
+        kind: &'static str,
+        severity: &'static str,
+        page: u32,
+        message: &'a str,
+    },
+    /// ファイルの変換を終えた（status は converted、skipped、failed のいずれか。error は飛ばした理由かエラーのメッセージ）
+    FileFinished {
+        file: &'a Path,
+        status: &'static str,
+        seconds: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+    },
+    /// 人が読むメッセージ（console! で表示するもの）
+    Message { file: Option<&'a Path>, level: &'static str, message: &'a str },
+}
+
+/// JSON Lines で表示するかどうか
+pub fn is_jsonl() -> bool {
+    JSONL.load(Ordering::Relaxed)
+}
+
+/// イベントを知らせるかどうか（JSON Lines の場合か、スレッドにイベントを受け取る処理がある場合）
+fn enabled() -> bool {
+    is_jsonl() || OBSERVER.with(|observer| observer.borrow().is_some())
+}
+
+/// イベントを知らせる（スレッドにイベントを受け取る処理があればそこに渡し、無ければ JSON Lines の場合に書く）
+pub fn emit(event: &Event) {
+    let observed = OBSERVER.with(|observer| observer.borrow().as_ref().map(|observer| observer(event)).is_some());
+    if observed || !is_jsonl() {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(event) {
+        eprintln!("{}", line);
+    }
+}
+
+/// console! のメッセージを表示する（イベントを知らせる場合は message のイベントにする）
+pub fn console_message(level: log::Level, message: &str) {
+    if enabled() {
+        with_current_file(|file| emit(&Event::Message { file, level: level_name(level), message }));
+    } else if !crate::logging::is_json() {
+        eprintln_above_bars(message);
+    }
+}
+
+/// 標準エラー出力にメッセージを書く（進捗バーを表示している場合は、バーを消してから書き、書いた後にバーを描き直す）
+pub fn eprintln_above_bars(message: &str) {
+    match bars() {
+        Some(bars) => bars.suspend(|| eprintln!("{}", message)),
+        None => eprintln!("{}", message),
+    }
+}
+
+fn bars() -> Option<&'static MultiProgress> {
+    BARS.get().and_then(Option::as_ref)
+}
+
+/// 進捗バー（表示しない場合は何もしない。破棄するとバーを消す）
+pub struct Bar(Option<ProgressBar>);
+
+impl Bar {
+    /// ページの進み具合
+    pub fn pages(total: usize) -> Self {
+        Bar::new(total, "{spinner} ページ [{bar:30}] {pos}/{len}（残り {eta}）")
+    }
+
+    /// 一括変換のファイルの進み具合
+    pub fn files(total: usize) -> Self {
+        Bar::new(total, "{spinner} ファイル [{bar:30}] {pos}/{len}（残り {eta}）")
+    }
+
+    fn new(total: usize, template: &str) -> Self {
+        // イベントを受け取る処理があるスレッド（--stdio-server）では表示しない
+        let observed = OBSERVER.with(|observer| observer.borrow().is_some());
+        Bar(bars().filter(|_| !observed).map(|bars| {
+            let style = ProgressStyle::with_template(template).unwrap_or_else(|_| ProgressStyle::default_bar()).progress_chars("=> ");
+            bars.add(ProgressBar::new(total as u64).with_style(style))
+        }))
+    }
+
+    pub fn inc(&self) {
+        if let Some(bar) = &self.0 {
+            bar.inc(1);
+        }
+    }
+}
+
+impl Drop for Bar {
+    fn drop(&mut self) {
+        if let (Some(bar), Some(bars)) = (self.0.take(), bars()) {
+            bar.finish_and_clear();
+            bars.remove(&bar);
+        }
+    }
+}
+
+/// ファイルの変換を始めたことを知らせる
+pub fn file_started(file: &Path, index: usize, total: usize) {
+    emit(&Event::FileStarted { file, index, total });
+}
+
+/// ファイルの変換を終えたことを知らせる
+pub fn file_finished(file: &Path, status: &FileStatus, seconds: f64) {
+    let (status, error) = match status {
+        FileStatus::Converted => ("converted", None),
+        FileStatus::Skipped(reason) => ("skipped", Some(reason.as_str())),
+        FileStatus::Failed(error) => ("failed", Some(error.as_str())),
+    };
+    emit(&Event::FileFinished { file, status, seconds, error });
+}
+
+/// ページのレイアウトを抽出したことを知らせる
+pub fn page_done(page: u32, pages: usize) {
+    if enabled() {
+        with_current_file(|file| emit(&Event::PageDone { file, page, pages }));
+    }
+}
+
+/// 警告を知らせる
+pub fn warning(warning: &Warning, severity: Severity) {
+    if enabled() {
+        let severity = if severity == Severity::Deny { "error" } else { "warning" };
+        with_current_file(|file| {
+            emit(&Event::Warning { file, kind: warning.kind.name(), severity, page: warning.page, message: &warning.message })
+        });
+    }
+}
+
+/// スレッドで変換中のファイルを記録する（破棄すると元に戻す。変換中に同じスレッドで別のファイルを変換しても崩れない）
+pub struct FileScope {
+    previous: Option<PathBuf>,
+}
+
+impl FileScope {
+    pub fn enter(file: &Path) -> Self {
+        FileScope { previous: CURRENT_FILE.with(|current| current.replace(Some(file.to_path_buf()))) }
+    }
+}
+
+impl Drop for FileScope {
+    fn drop(&mut self) {
+        CURRENT_FILE.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+/// スレッドのイベントを受け取る処理を決める（破棄すると元に戻す）
+pub struct ObserverScope {
+    previous: Option<Observer>,
+}
+
+impl ObserverScope {
+    pub fn enter(observer: Observer) -> Self {
+        ObserverScope { previous: OBSERVER.with(|current| current.replace(Some(observer))) }
+    }
+}
+
+impl Drop for ObserverScope {
+    fn drop(&mut self) {
+        OBSERVER.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+fn with_current_file<F: FnOnce(Option<&Path>)>(f: F) {
+    CURRENT_FILE.with(|current| f(current.borrow().as_deref()));
+}
+
+fn level_name(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warning",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: イベントの JSON とスレッドごとのファイルの記録
+    #[test]
+    fn test_events() {
+        let line = |event: &Event| serde_json::to_string(event).unwrap();
+        assert_eq!(
+            line(&Event::FileStarted { file: Path::new("docs/a.pdf"), index: 1, total: 3 }),
+            r#"{"event":"file_started","file":"docs/a.pdf","index":1,"total":3}"#
+        );
+        assert_eq!(
+            line(&Event::FileFinished { file: Path::new("docs/a.pdf"), status: "converted", seconds: 0.5, error: None }),
+            r#"{"event":"file_finished","file":"docs/a.pdf","status":"converted","seconds":0.5}"#
+        );
+
+        let outer = FileScope::enter(Path::new("a.pdf"));
+        {
+            let _inner = FileScope::enter(Path::new("b.pdf"));
+            with_current_file(|file| assert_eq!(file, Some(Path::new("b.pdf"))));
+        }
+        with_current_file(|file| assert_eq!(file, Some(Path::new("a.pdf"))));
+        drop(outer);
+        with_current_file(|file| assert_eq!(file, None));
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// マスク対象にできる個人情報の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiKind {
+    /// メールアドレス
+    Emails,
+    /// 電話番号
+    Phones,
+    /// 米国社会保障番号（123-45-6789 形式）
+    Ssn,
+}
+
+impl PiiKind {
+    /// 検出用の正規表現（初めて使うときに一度だけコンパイルする）
+    fn regex(self) -> &'static Regex {
+        static EMAILS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+        // 区切り文字（ハイフン・ドット・空白）を含む番号のみを対象にして、単なる数値の誤検出を避ける
+        static PHONES: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?:\+\d{1,3}[\s-]?)?(?:\(\d{1,4}\)\s?|\b\d{2,4}[\s.-])\d{2,4}[\s.-]\d{3,4}\b").unwrap());
+        static SSN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap());
+        match self {
+            PiiKind::Emails => &EMAILS,
+            PiiKind::Phones => &PHONES,
+            PiiKind::Ssn => &SSN,
+        }
+    }
+
+    /// 置換後に表示するラベル
+    fn label(self) -> &'static str {
+        match self {
+            PiiKind::Emails => "EMAIL",
+            PiiKind::Phones => "PHONE",
+            PiiKind::Ssn => "SSN",
+        }
+    }
+}
+
+/// 出力テキストから個人情報をマスクする
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    /// 組み込みの種類と利用者定義の正規表現からマスク処理を構築する
+    pub fn new(kinds: &[PiiKind], custom_patterns: &[String]) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        // SSN は電話番号の形式とも一致するため先に処理する
+        let mut kinds = kinds.to_vec();
+        kinds.sort_by_key(|kind| *kind != PiiKind::Ssn);
+        kinds.dedup();
+
+        for kind in kinds {
+            rules.push((kind.regex().clone(), format!("[REDACTED {}]", kind.label())));
+        }
+
+        for pattern in custom_patterns {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("マスク用の正規表現が不正です: {}", pattern))?;
+            rules.push((regex, "[REDACTED]".to_string()));
+        }
+
+        Ok(Redactor { rules })
+    }
+
+    /// マスク対象が1つも無いかどうか
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// テキスト中の一致箇所をすべて置換する
+    pub fn redact(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for (regex, replacement) in &self.rules {
+            result = regex.replace_all(&result, replacement.as_str()).into_owned();
+        }
+        result
+    }
+
+    /// JSON の文字列の値をすべてマスクする（キーはそのまま）
+    pub fn redact_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.redact(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| self.redact_json(field)),
+            _ => {}
+        }
+    }
+
+    /// 値を JSON に変換し、文字列の値をマスクして整形した文字列にする
+    pub fn to_json_pretty<T: Serialize>(&self, value: &T) -> serde_json::Result<String> {
+        let mut json = serde_json::to_value(value)?;
+        self.redact_json(&mut json);
+        serde_json::to_string_pretty(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::Comment;
+
+    // 単体テスト: 組み込みパターンのマスク
+    #[test]
+    fn test_redact_builtin() {
+        let redactor = Redactor::new(&[PiiKind::Emails, PiiKind::Phones, PiiKind::Ssn], &[]).unwrap();
+
+        let test_cases = vec![
+            ("mail: alice@example.com", "mail: [REDACTED EMAIL]", "メールアドレス"),
+            ("tel 03-1234-5678.", "tel [REDACTED PHONE].", "電話番号"),
+            ("call +1 (555) 123-4567", "call [REDACTED PHONE]", "国番号付きの電話番号"),
+            ("SSN 123-45-6789", "SSN [REDACTED SSN]", "社会保障番号"),
+            ("total 12345 in 2024", "total 12345 in 2024", "単なる数値はマスクしない"),
+        ];
+
+        for (input, expected, desc) in test_cases {
+            assert_eq!(redactor.redact(input), expected, "Test failed: {}", desc);
+        }
+
+        // comments.json に書き出すコメントの作成者と本文（返信も含む）
+        let comment = |author: Option<&str>, content: &str, replies: Vec<Comment>| Comment {
+            page: 1,
+            kind: "Text".to_string(),
+            author: author.map(str::to_string),
+            date: None,
+            content: content.to_string(),
+            excerpt: None,
+            replies,
+        };
+        let comment = comment(Some("alice@example.com"), "SSN 123-45-6789", vec![comment(None, "tel 03-1234-5678", vec![])]);
+        let json = redactor.to_json_pretty(&vec![comment]).unwrap();
+        assert!(json.contains("\"author\": \"[REDACTED EMAIL]\""), "{}", json);
+        assert!(json.contains("\"content\": \"SSN [REDACTED SSN]\""), "{}", json);
+        assert!(json.contains("\"content\": \"tel [REDACTED PHONE]\""), "{}", json);
+    }
+
+    // 単体テスト: 利用者定義パターンと不正な正規表現
+    #[test]
+    fn test_redact_custom() {
+        let redactor = Redactor::new(&[], &[r"社員番号\d+".to_string()]).unwrap();
+        assert_eq!(redactor.redact("担当: 社員番号1234"), "担当: [REDACTED]");
+
+        assert!(Redactor::new(&[], &["(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 変換結果の報告の形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// JUnit の XML（CI のテスト結果の画面で表示できる）
+    Junit,
+}
+
+/// --report で指定された報告の形式と書き出し先（例: junit:report.xml）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportSpec {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (format, path) = spec.split_once(':').ok_or_else(|| format!("報告の指定は 形式:パス の形式で指定してください（例: junit:report.xml）: {}", spec))?;
+        let format = match format.trim() {
+            "junit" => ReportFormat::Junit,
+            other => return Err(format!("不明な報告の形式です（junit のみ）: {}", other)),
+        };
+        if path.is_empty() {
+            return Err(format!("報告の書き出し先を指定してください: {}", spec));
+        }
+        Ok(ReportSpec { format, path: PathBuf::from(path) })
+    }
+}
+
+/// 1つのファイルの変換の結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    Converted,
+    /// 変換せずに飛ばした（理由）
+    Skipped(String),
+    /// 変換できなかった（エラーのメッセージ）
+    Failed(String),
+}
+
+/// 報告に書く、1つのファイルの変換の記録
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub input: PathBuf,
+    pub status: FileStatus,
+    /// 表示した警告（色を付けずに描画したもの）
+    pub warnings: Vec<String>,
+    /// 変換にかかった秒数
+    pub seconds: f64,
+}
+
+/// 変換の記録を指定の形式で書き出す
+pub fn write_report(spec: &ReportSpec, reports: &[FileReport]) -> Result<()> {
+    let content = match spec.format {
+        ReportFormat::Junit => render_junit(reports),
+    };
+    if let Some(dir) = spec.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("報告の書き出し先のディレクトリを作成できません: {:?}", dir))?;
+    }
+    std::fs::write(&spec.path, content).with_context(|| format!("変換結果の報告を書き出せません: {:?}", spec.path))
+}
+
+/// JUnit の XML にする（ファイルごとに testcase を1つとし、警告は system-out に書く）
+pub fn render_junit(reports: &[FileReport]) -> String {
+    let count = |matches: fn(&FileStatus) -> bool| reports.iter().filter(|report| matches(&report.status)).count();
+    let failures = count(|status| matches!(status, FileStatus::Failed(_)));
+    let skipped = count(|status| matches!(status, FileStatus::Skipped(_)));
+    let seconds: f64 = reports.iter().map(|report| report.seconds).sum();
+    let totals = format!(r#"tests="{}" failures="{}" errors="0" skipped="{}" time="{:.3}""#, reports.len(), failures, skipped, seconds);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, r#"<testsuites name="pdf2md" {}>"#, totals);
+    let _ = writeln!(xml, r#"  <testsuite name="pdf2md" {}>"#, totals);
+    for report in reports {
+        let name = escape(&display_path(&report.input));
+        let _ = write!(xml, r#"    <testcase classname="pdf2md" name="{}" time="{:.3}""#, name, report.seconds);
+        if report.status == FileStatus::Converted && report.warnings.is_empty() {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        match &report.status {
+            FileStatus::Converted => {}
+            FileStatus::Skipped(reason) => {
+                let _ = writeln!(xml, r#"      <skipped message="{}"/>"#, escape(reason));
+            }
+            FileStatus::Failed(message) => {
+                let first_line = message.lines().next().unwrap_or_default();
+                let _ = writeln!(xml, r#"      <failure message="{}">{}</failure>"#, escape(first_line), escape(message));
+            }
+        }
+        if !report.warnings.is_empty() {
+            let _ = writeln!(xml, "      <system-out>{}</system-out>", escape(&report.warnings.join("\n")));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// 区切りを / に揃えたパス（CI の画面で OS によって表示が変わらないようにする）
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// XML の文字データと属性値に使えるようにする（XML で使えない制御文字は取り除く）
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c if c.is_control() && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: JUnit の XML の組み立て
+    #[test]
+    fn test_render_junit() {
+        assert_eq!("junit:out/report.xml".parse(), Ok(ReportSpec { format: ReportFormat::Junit, path: PathBuf::from("out/report.xml") }));
+        assert!("html:report.html".parse::<ReportSpec>().is_err());
+        assert!("report.xml".parse::<ReportSpec>().is_err());
+
+        let report = |input: &str, status: FileStatus, warnings: &[&str]| FileReport {
+            input: PathBuf::from(input),
+            status,
+            warnings: warnings.iter().map(|warning| warning.to_string()).collect(),
+            seconds: 0.5,
+        };
+        let reports = vec![
+            report("docs/a.pdf", FileStatus::Converted, &[]),
+            report("docs/b.pdf", FileStatus::Converted, &["warning: ページ 2 の図 <fig> は出力されません"]),
+            report("docs/c.pdf", FileStatus::Failed("PDFからのテキスト抽出に失敗しました: \"docs/c.pdf\"\n原因".to_string()), &[]),
+            report("docs/d.pdf", FileStatus::Skipped("PDF ではありません".to_string()), &[]),
+        ];
+        let xml = render_junit(&reports);
+
+        assert!(xml.contains(r#"<testsuite name="pdf2md" tests="4" failures="1" errors="0" skipped="1" time="2.000">"#));
+        assert!(xml.contains(r#"<testcase classname="pdf2md" name="docs/a.pdf" time="0.500"/>"#));
+        assert!(xml.contains("<system-out>warning: ページ 2 の図 &lt;fig&gt; は出力されません</system-out>"));
+        assert!(xml.contains(r#"<failure message="PDFからのテキスト抽出に失敗しました: &quot;docs/c.pdf&quot;">"#));
+        assert!(xml.contains(r#"<skipped message="PDF ではありません"/>"#));
+    }
+}
@@ -0,0 +1,297 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::layout::{self, PageLayout, TextLine};
+use crate::paragraphs;
+use crate::slides;
+
+/// 履歴書・職務経歴書でよく使われる節の名前
+const SECTION_NAMES: &[&str] = &[
+    "summary",
+    "profile",
+    "objective",
+    "experience",
+    "work experience",
+    "professional experience",
+    "employment",
+    "education",
+    "skills",
+    "technical skills",
+    "projects",
+    "certifications",
+    "languages",
+    "awards",
+    "publications",
+    "interests",
+    "contact",
+    "references",
+    "職務要約",
+    "職務経歴",
+    "職歴",
+    "学歴",
+    "資格",
+    "免許・資格",
+    "スキル",
+    "活かせる経験・知識・技術",
+    "自己pr",
+    "語学",
+    "連絡先",
+];
+
+/// 期間（2019 – Present、Apr 2018 - Mar 2020、2020年4月〜現在 など）の正規表現の文字列
+const DATE_RANGE: &str = r"(?i)(?:(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+)?\d{4}(?:\s*[/.年]\s*\d{1,2}\s*月?)?\s*(?:-|–|—|~|〜|～|to)\s*(?:(?:(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+)?\d{4}(?:\s*[/.年]\s*\d{1,2}\s*月?)?|present|current|now|現在|現職)";
+
+/// 経歴の期間
+static DATE_RANGE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(DATE_RANGE).unwrap());
+
+/// 期間だけの区間（括弧で囲んだものを含む）
+static DATE_ONLY_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!(r"^\(?{}\)?$", DATE_RANGE)).unwrap());
+
+/// ページが履歴書・職務経歴書らしいかどうか（よく使われる節の名前の行が3種類以上ある）
+pub fn looks_like_resume(page: &PageLayout) -> bool {
+    let mut names: Vec<String> = page_lines(page)
+        .iter()
+        .map(|line| line.text.trim().trim_end_matches([':', '：']).to_lowercase())
+        .filter(|text| SECTION_NAMES.contains(&text.as_str()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names.len() >= 3
+}
+
+/// 履歴書・職務経歴書を Markdown にする
+///
+/// 最も大きな文字の行を氏名の H1 に、続く連絡先を1つの段落に、節の見出しを H2 にし、
+/// 期間のある行を経歴の項目（その下の行は子の項目）とする。2段組みの場合は、
+/// 広い方の段の後に狭い方の段（スキルなどのサイドバー）を続ける。
+pub fn render_resume(pages: &[PageLayout]) -> String {
+    let mut main: Vec<TextLine> = Vec::new();
+    let mut sidebar: Vec<TextLine> = Vec::new();
+    for page in pages {
+        let (page_main, page_sidebar) = split_sidebar(page_lines(page));
+        main.extend(page_main);
+        sidebar.extend(page_sidebar);
+    }
+
+    let mut sizes: Vec<f64> = main.iter().chain(&sidebar).map(|line| line.font_size).collect();
+    sizes.sort_by(f64::total_cmp);
+    let body_size = sizes.get(sizes.len() / 2).copied().unwrap_or(0.0);
+
+    let mut blocks: Vec<String> = Vec::new();
+    // 氏名: 1ページ目の最初の方にある、本文より十分大きな文字の行
+    if let Some(index) = main.iter().take(5).position(|line| line.font_size >= body_size * 1.3 && !is_section_heading(line, body_size)) {
+        blocks.push(format!("# {}", main.remove(index).text));
+
+        // 最初の節の見出しまでの行は連絡先
+        let contact_end = main.iter().position(|line| is_section_heading(line, body_size)).unwrap_or(main.len());
+        let contact: Vec<String> = main.drain(index..contact_end.max(index)).map(|line| line.text).collect();
+        if !contact.is_empty() {
+            blocks.push(contact.join(" · "));
+        }
+    }
+
+    blocks.extend(render_column(&main, body_size, false));
+    blocks.extend(render_column(&sidebar, body_size, true));
+    blocks.join("\n\n")
+}
+
+/// ページの行を、段の間の大きな空白で区切った区間ごとに求める（右寄せの期間は同じ行の左の区間につなげる）
+fn page_lines(page: &PageLayout) -> Vec<TextLine> {
+    let mut lines: Vec<TextLine> = Vec::new();
+
+    for segment in layout::split_segments(&page.glyphs) {
+        let text = layout::glyphs_to_text(&page.glyphs[segment.start..segment.end]).trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        let row = lines
+            .iter_mut()
+            .filter(|line| (line.y - segment.y).abs() <= segment.font_size * 0.5 && line.x1 <= segment.x0)
+            .max_by(|a, b| a.x1.total_cmp(&b.x1));
+        match row {
+            Some(line) if DATE_ONLY_REGEX.is_match(&text) => {
+                line.text = format!("{} {}", line.text, text);
+                line.x1 = segment.x1;
+            }
+            _ => lines.push(TextLine { text, x0: segment.x0, x1: segment.x1, y: segment.y, font_size: segment.font_size, confidence: None }),
+        }
+    }
+
+    lines.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x0.total_cmp(&b.x0)));
+    lines
+}
+
+/// 左右の段に分かれたページを、広い方の段（本文）と狭い方の段（サイドバー）に分ける
+///
+/// 段をまたぐ行（氏名など）は2行まで許し、本文の段に含める。
+fn split_sidebar(lines: Vec<TextLine>) -> (Vec<TextLine>, Vec<TextLine>) {
+    let mut best: Option<(f64, usize)> = None;
+    for boundary in lines.iter().map(|line| line.x0) {
+        let left = lines.iter().filter(|line| line.x1 <= boundary).count();
+        let right = lines.iter().filter(|line| line.x0 >= boundary).count();
+        let spanning = lines.len() - left - right;
+        if left >= 3 && right >= 3 && spanning <= 2 && best.is_none_or(|(_, count)| left.min(right) > count) {
+            best = Some((boundary, left.min(right)));
+        }
+    }
+    let Some((boundary, _)) = best else {
+        return (lines, Vec::new());
+    };
+
+    let (right, left): (Vec<TextLine>, Vec<TextLine>) = lines.into_iter().partition(|line| line.x0 >= boundary);
+    let width = |column: &[TextLine]| {
+        column.iter().map(|l| l.x1).fold(f64::NEG_INFINITY, f64::max) - column.iter().map(|l| l.x0).fold(f64::INFINITY, f64::min)
+    };
+    if width(&right) > width(&left) {
+        (right, left)
+    } else {
+        (left, right)
+    }
+}
+
+/// 節の見出しの行かどうか（よく使われる節の名前、大文字だけの短い行、本文より少し大きな短い行）
+///
+/// 大文字だけの行は、SQL や AWS のようなスキル名と区別するため6文字以上のものに限る。
+fn is_section_heading(line: &TextLine, body_size: f64) -> bool {
+    let text = line.text.trim().trim_end_matches([':', '：']);
+    let words = text.split_whitespace().count();
+    if text.is_empty() || words > 4 {
+        return false;
+    }
+    SECTION_NAMES.contains(&text.to_lowercase().as_str())
+        || (text.chars().filter(|c| c.is_alphabetic()).count() >= 6 && text.chars().all(|c| !c.is_lowercase()) && text.is_ascii())
+        || (line.font_size > body_size * 1.15 && line.font_size < body_size * 1.3)
+}
+
+/// 1つの段の行を、節の見出し・経歴の項目・箇条書き・段落のブロックにする
+fn render_column(lines: &[TextLine], body_size: f64, sidebar: bool) -> Vec<String> {
+    let breaks = paragraphs::paragraph_breaks(lines);
+    let mut blocks: Vec<String> = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // 直前の項目の左端（折り返した続きの行の判定に使う）
+    let mut item_x: Option<f64> = None;
+    let mut in_entry = false;
+
+    let flush = |blocks: &mut Vec<String>, list: &mut Vec<String>, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            blocks.push(paragraph.join(" "));
+            paragraph.clear();
+        }
+        if !list.is_empty() {
+            blocks.push(list.join("\n"));
+            list.clear();
+        }
+    };
+
+    for (line, starts_paragraph) in lines.iter().zip(breaks) {
+        let text = line.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if is_section_heading(line, body_size) {
+            flush(&mut blocks, &mut list, &mut paragraph);
+            blocks.push(format!("## {}", text.trim_end_matches([':', '：'])));
+            in_entry = false;
+            item_x = None;
+        } else if let Some(dates) = DATE_RANGE_REGEX.find(text) {
+            // 期間を除いた残りを項目の名前にする（勤務先・役職・学校名など）
+            let title = format!("{}{}", &text[..dates.start()], &text[dates.end()..]);
+            let title = title.trim().trim_matches(|c: char| c == ',' || c == '|' || c == '(' || c == ')' || c == '-' || c.is_whitespace());
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut list, &mut paragraph);
+            }
+            list.push(if title.is_empty() {
+                format!("- {}", dates.as_str())
+            } else {
+                format!("- **{}** ({})", title, dates.as_str())
+            });
+            in_entry = true;
+            item_x = None;
+        } else if let Some(item) = slides::bullet_text(text) {
+            if !paragraph.is_empty() {
+                flush(&mut blocks, &mut list, &mut paragraph);
+            }
+            list.push(format!("{}- {}", if in_entry { "  " } else { "" }, item));
+            item_x = Some(line.x0);
+        } else if item_x.is_some_and(|x| line.x0 > x + line.font_size * 0.3) || (in_entry && item_x.is_some() && !starts_paragraph) {
+            // 箇条書きの項目の折り返し
+            let item = list.last_mut().unwrap();
+            item.push(' ');
+            item.push_str(text);
+        } else if in_entry || sidebar {
+            // 経歴の項目の下の説明や、サイドバーの行はそれぞれ項目にする
+            list.push(format!("{}- {}", if in_entry { "  " } else { "" }, text));
+            item_x = Some(line.x0);
+        } else {
+            if !list.is_empty() || (starts_paragraph && !paragraph.is_empty()) {
+                flush(&mut blocks, &mut list, &mut paragraph);
+            }
+            paragraph.push(text);
+        }
+    }
+    flush(&mut blocks, &mut list, &mut paragraph);
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    fn line(text: &str, x0: f64, y: f64, font_size: f64) -> TextLine {
+        TextLine { text: text.to_string(), x0, x1: x0 + 300.0, y, font_size, confidence: None }
+    }
+
+    // 単体テスト: 節の見出し・経歴の項目・箇条書き
+    #[test]
+    fn test_render_column() {
+        let lines = vec![
+            line("EXPERIENCE", 50.0, 100.0, 10.0),
+            line("Software Engineer, Acme 2019 – Present", 50.0, 120.0, 10.0),
+            line("• Built the billing system", 60.0, 134.0, 10.0),
+            line("• Led a team of four", 60.0, 148.0, 10.0),
+            line("Education", 50.0, 180.0, 10.0),
+            line("BSc Computer Science, Tokyo University 2015 - 2019", 50.0, 200.0, 10.0),
+        ];
+
+        assert_eq!(
+            render_column(&lines, 10.0, false),
+            vec![
+                "## EXPERIENCE",
+                "- **Software Engineer, Acme** (2019 – Present)\n  - Built the billing system\n  - Led a team of four",
+                "## Education",
+                "- **BSc Computer Science, Tokyo University** (2015 - 2019)",
+            ]
+        );
+    }
+
+    // 単体テスト: 氏名・連絡先とサイドバー
+    #[test]
+    fn test_render_resume() {
+        let mut glyphs = Vec::new();
+        let mut push = |text: &str, x: f64, y: f64, font_size: f64| {
+            let width = text.chars().count() as f64 * font_size * 0.5;
+            glyphs.push(Glyph { text: text.to_string(), x, y, width, font_size, word_start: true, order: glyphs.len(), color: None, bold: false, monospace: false, confidence: None });
+        };
+        push("Hanako Suzuki", 50.0, 40.0, 24.0);
+        push("hanako@example.com", 50.0, 70.0, 10.0);
+        push("Summary", 50.0, 100.0, 10.0);
+        push("Skills", 400.0, 100.0, 10.0);
+        push("Engineer with ten years of experience.", 50.0, 114.0, 10.0);
+        push("Rust", 400.0, 114.0, 10.0);
+        push("Experience", 50.0, 140.0, 10.0);
+        push("SQL", 400.0, 128.0, 10.0);
+        push("Acme Corp", 50.0, 154.0, 10.0);
+        push("2020 – Present", 250.0, 154.0, 10.0);
+        let pages = vec![PageLayout { number: 1, glyphs, ..Default::default() }];
+
+        assert_eq!(
+            render_resume(&pages),
+            "# Hanako Suzuki\n\nhanako@example.com\n\n## Summary\n\nEngineer with ten years of experience.\n\n## Experience\n\n\
+             - **Acme Corp** (2020 – Present)\n\n## Skills\n\n- Rust\n- SQL"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::bilingual;
+use crate::console;
+use crate::graphics;
+use crate::layout::{Glyph, PageLayout};
+
+/// 確認用の HTML に載せるページ画像の解像度（dpi）
+const REVIEW_IMAGE_DPI: u32 = 100;
+
+/// ページの区切りの目印（変換中だけ入れ、出力する前に取り除く）
+const PAGE_MARKER_PREFIX: &str = "[[pdf2md-page ";
+
+/// 各ページの先頭に、ページ番号の目印を独立した段落として入れる
+///
+/// 目印は --placeholders の目印と同じく段落の区切りになるため、ページをまたぐ段落やリストはページの境目で分かれる。
+pub fn insert_page_markers(pages: &mut [PageLayout]) {
+    for page in pages.iter_mut() {
+        let (font_size, order) = page.glyphs.first().map_or((10.0, 0), |glyph| (glyph.font_size, glyph.order));
+        let text = format!("\n\n{}{}]]\n\n", PAGE_MARKER_PREFIX, page.number);
+        page.glyphs.insert(0, Glyph { text, x: 0.0, y: 0.0, width: 0.0, font_size, word_start: true, order, color: None, bold: false, monospace: false, confidence: None });
+    }
+}
+
+/// Markdown からページの目印の行を取り除き、目印を除いた Markdown と、ページごとの Markdown を返す
+///
+/// 最初の目印より前の内容は最初のページに含める。目印が無い場合は全体を1ページ目とする。
+pub fn split_pages(markdown: &str) -> (String, Vec<(u32, String)>) {
+    let mut pages: Vec<(u32, String)> = Vec::new();
+    let mut preamble = String::new();
+    let mut stripped = String::new();
+
+    let mut after_marker = false;
+    for line in markdown.split_inclusive('\n') {
+        let number = line.trim().strip_prefix(PAGE_MARKER_PREFIX).and_then(|rest| rest.strip_suffix("]]")).and_then(|n| n.parse().ok());
+        // 目印の行と、その後の空行を取り除く
+        let skip = number.is_some() || (after_marker && line.trim().is_empty());
+        after_marker = number.is_some();
+        if let Some(number) = number {
+            pages.push((number, std::mem::take(&mut preamble)));
+        } else if !skip {
+            pages.last_mut().map_or(&mut preamble, |(_, content)| content).push_str(line);
+            stripped.push_str(line);
+        }
+    }
+    if pages.is_empty() {
+        pages.push((1, preamble));
+    }
+
+    let pages = pages.into_iter().map(|(number, content)| (number, content.trim().to_string())).collect();
+    (stripped, pages)
+}
+
+/// ページ画像と変換した Markdown を左右に並べた確認用の HTML を書き出す
+///
+/// ページ画像は HTML と同じ場所の「HTML のファイル名_pages」に書き出す。画像にできないページは画像の代わりに注記を表示する。
+/// lang_tags の場合は、段落ごとに判定した言語を lang 属性にする。
+pub fn write_review_html(html_path: &Path, pdf_path: &Path, pages: &[(u32, String)], lang_tags: bool) -> Result<()> {
+    let stem = html_path.file_stem().unwrap_or_default().to_string_lossy();
+    let image_dir = format!("{}_pages", stem);
+    let mut rows = String::new();
+    let mut rasterize_error = None;
+
+    for (number, markdown) in pages {
+        let image_path = html_path.with_file_name(&image_dir).join(format!("page-{:03}.png", number));
+        // pdftoppm が無いなど、1ページ目で失敗した場合は残りのページも試さない
+        let mut image = None;
+        if rasterize_error.is_none() {
+            match graphics::rasterize_page(pdf_path, *number, REVIEW_IMAGE_DPI, &image_path) {
+                Ok(path) => image = Some(path),
+                Err(e) => rasterize_error = Some(e),
+            }
+        }
+        let left = match image {
+            Some(path) => format!(
+                "<img src=\"{}/{}\" alt=\"p.{}\">",
+                escape(&image_dir),
+                escape(&path.file_name().unwrap_or_default().to_string_lossy()),
+                number
+            ),
+            None => "<p class=\"missing\">ページ画像を用意できませんでした</p>".to_string(),
+        };
+        let blocks: String = markdown
+            .split("\n\n")
+            .filter(|block| !block.trim().is_empty())
+            .map(|block| {
+                let lang = bilingual::language_of(block).filter(|_| lang_tags).map(|language| format!(" lang=\"{}\"", language.code())).unwrap_or_default();
+                format!("<pre class=\"block\"{}>{}</pre>\n", lang, escape(block.trim_end()))
+            })
+            .collect();
+        rows.push_str(&format!(
+            "<section id=\"p{0}\"><h2>p.{0}</h2><div class=\"page\">{1}</div><div class=\"markdown\">\n{2}</div></section>\n",
+            number, left, blocks
+        ));
+    }
+    if let Some(e) = rasterize_error {
+        console!(Warn, "確認用の HTML のページ画像を書き出せませんでした: {:#}", e);
+    }
+
+    let title = escape(&pdf_path.file_name().unwrap_or_default().to_string_lossy());
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n<title>{0} の変換結果</title>\n<style>\n{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n{2}</body>\n</html>\n",
+        title, STYLE, rows
+    );
+    fs::write(html_path, html).with_context(|| format!("確認用の HTML を書き出せません: {:?}", html_path))
+}
+
+/// 確認用の HTML のスタイル（ページごとに、左にページ画像、右に Markdown の段落を並べる）
+const STYLE: &str = "body { font-family: sans-serif; margin: 1em; }
+section { display: grid; grid-template-columns: 1fr 1fr; gap: 1em; border-top: 1px solid #ccc; padding: 1em 0; }
+section h2 { grid-column: 1 / 3; margin: 0; font-size: 1em; color: #666; }
+.page img { width: 100%; border: 1px solid #ddd; }
+.missing { color: #a00; }
+.block { white-space: pre-wrap; margin: 0 0 0.5em; padding: 0.5em; background: #f6f8fa; border-left: 3px solid #4a90d9; }
+";
+
+/// HTML の特殊文字を文字参照にする
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: ページの目印による分割
+    #[test]
+    fn test_split_pages() {
+        let markdown = "# Title\n\n[[pdf2md-page 1]]\n\nIntro <b>text</b>.\n\n[[pdf2md-page 3]]\n\n- item\n";
+        let (stripped, pages) = split_pages(markdown);
+        assert_eq!(stripped, "# Title\n\nIntro <b>text</b>.\n\n- item\n");
+        assert_eq!(pages, vec![(1, "# Title\n\nIntro <b>text</b>.".to_string()), (3, "- item".to_string())]);
+        assert_eq!(escape("Intro <b>\"x\" & y</b>"), "Intro &lt;b&gt;&quot;x&quot; &amp; y&lt;/b&gt;");
+    }
+}
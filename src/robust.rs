@@ -0,0 +1,222 @@
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashSet;
+use std::io::Read;
+
+/// 信頼できない PDF を変換する場合の上限（--robust）
+///
+/// 上限を超える文書は、抽出を始める前にエラーにする。細工された PDF で、抽出中にメモリを使い果たしたり、
+/// 再帰の深さでスタックがあふれたりしないようにする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RobustLimits {
+    /// 文書のオブジェクトの数
+    pub max_objects: usize,
+    /// 配列や辞書の入れ子、ページツリー、Form XObject の入れ子の深さ
+    pub max_depth: usize,
+    /// 1つのストリームを展開した大きさ（バイト）
+    pub max_stream_bytes: u64,
+    /// すべてのストリームを展開した大きさの合計（バイト）
+    pub max_total_bytes: u64,
+}
+
+impl Default for RobustLimits {
+    fn default() -> Self {
+        RobustLimits { max_objects: 500_000, max_depth: 32, max_stream_bytes: 256 << 20, max_total_bytes: 1 << 30 }
+    }
+}
+
+/// 文書が上限に収まるかどうかを確かめる（復号した後の文書を渡す）
+pub fn check_document(doc: &Document, limits: &RobustLimits) -> Result<()> {
+    if doc.objects.len() > limits.max_objects {
+        bail!("オブジェクトの数が上限（{} 個）を超えています: {} 個", limits.max_objects, doc.objects.len());
+    }
+
+    for (id, object) in &doc.objects {
+        if nesting_depth(object) > limits.max_depth {
+            bail!("オブジェクト {} {} の配列や辞書の入れ子が上限（{}）より深くなっています", id.0, id.1, limits.max_depth);
+        }
+    }
+
+    if let Ok(pages) = doc.catalog().and_then(|catalog| catalog.get(b"Pages")).and_then(Object::as_reference) {
+        check_page_tree(doc, pages, 0, &mut HashSet::new(), limits)?;
+    }
+    let mut finished = HashSet::new();
+    for (id, object) in &doc.objects {
+        if let Object::Stream(stream) = object {
+            if is_form(stream) {
+                check_forms(doc, *id, stream, 0, &mut Vec::new(), &mut finished, limits)?;
+            }
+        }
+    }
+
+    let mut total: u64 = 0;
+    for (id, object) in &doc.objects {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        let Some(size) = inflated_size(stream, limits.max_stream_bytes) else {
+            continue;
+        };
+        if size > limits.max_stream_bytes {
+            bail!("オブジェクト {} {} のストリームを展開した大きさが上限（{} バイト）を超えています", id.0, id.1, limits.max_stream_bytes);
+        }
+        total += size;
+        if total > limits.max_total_bytes {
+            bail!("ストリームを展開した大きさの合計が上限（{} バイト）を超えています", limits.max_total_bytes);
+        }
+    }
+    Ok(())
+}
+
+/// 配列や辞書の入れ子の深さ（再帰せずに数える）
+fn nesting_depth(object: &Object) -> usize {
+    let mut deepest = 0;
+    let mut stack = vec![(object, 0)];
+    while let Some((object, depth)) = stack.pop() {
+        deepest = deepest.max(depth);
+        match object {
+            Object::Array(items) => stack.extend(items.iter().map(|item| (item, depth + 1))),
+            Object::Dictionary(dict) => stack.extend(dict.iter().map(|(_, value)| (value, depth + 1))),
+            Object::Stream(stream) => stack.extend(stream.dict.iter().map(|(_, value)| (value, depth + 1))),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+/// ページツリーの深さと循環を確かめる（pdf-extract と lopdf はページツリーを再帰してたどる）
+fn check_page_tree(doc: &Document, node: ObjectId, depth: usize, visited: &mut HashSet<ObjectId>, limits: &RobustLimits) -> Result<()> {
+    if depth > limits.max_depth {
+        bail!("ページツリーが上限（{}）より深くなっています", limits.max_depth);
+    }
+    if !visited.insert(node) {
+        bail!("ページツリーが循環しています（オブジェクト {} {}）", node.0, node.1);
+    }
+    let Ok(kids) = doc.get_dictionary(node).and_then(|dict| dict.get(b"Kids")).and_then(Object::as_array) else {
+        return Ok(());
+    };
+    for kid in kids.iter().filter_map(|kid| kid.as_reference().ok()) {
+        check_page_tree(doc, kid, depth + 1, visited, limits)?;
+    }
+    Ok(())
+}
+
+fn is_form(stream: &Stream) -> bool {
+    stream.dict.get(b"Subtype").and_then(Object::as_name).is_ok_and(|name| name == b"Form")
+}
+
+/// Form XObject の入れ子の深さと循環を確かめる（pdf-extract は Form XObject を上限なく再帰して描画する）
+///
+/// path はたどっている途中の Form XObject、finished は入れ子を確かめ終えた Form XObject。
+fn check_forms(
+    doc: &Document,
+    id: ObjectId,
+    form: &Stream,
+    depth: usize,
+    path: &mut Vec<ObjectId>,
+    finished: &mut HashSet<ObjectId>,
+    limits: &RobustLimits,
+) -> Result<()> {
+    if finished.contains(&id) {
+        return Ok(());
+    }
+    if path.contains(&id) {
+        bail!("Form XObject が自身を描画しています（オブジェクト {} {}）", id.0, id.1);
+    }
+    if depth > limits.max_depth {
+        bail!("Form XObject の入れ子が上限（{}）より深くなっています", limits.max_depth);
+    }
+    path.push(id);
+    for (child, stream) in child_forms(doc, form) {
+        check_forms(doc, child, stream, depth + 1, path, finished, limits)?;
+    }
+    path.pop();
+    finished.insert(id);
+    Ok(())
+}
+
+/// Form XObject の Resources から描画できる Form XObject
+fn child_forms<'a>(doc: &'a Document, form: &'a Stream) -> Vec<(ObjectId, &'a Stream)> {
+    let xobjects = (|| -> Option<&Dictionary> {
+        let resources = doc.dereference(form.dict.get(b"Resources").ok()?).ok()?.1.as_dict().ok()?;
+        doc.dereference(resources.get(b"XObject").ok()?).ok()?.1.as_dict().ok()
+    })();
+    let Some(xobjects) = xobjects else {
+        return Vec::new();
+    };
+    xobjects
+        .iter()
+        .filter_map(|(_, value)| {
+            let id = value.as_reference().ok()?;
+            let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+            is_form(stream).then_some((id, stream))
+        })
+        .collect()
+}
+
+/// FlateDecode で圧縮したストリームを展開した大きさ（limit を超えた時点で展開をやめる。ほかの形式は None）
+fn inflated_size(stream: &Stream, limit: u64) -> Option<u64> {
+    let filter = match stream.dict.get(b"Filter").ok()? {
+        Object::Array(filters) => filters.first()?.as_name().ok()?,
+        filter => filter.as_name().ok()?,
+    };
+    if filter != b"FlateDecode" {
+        return None;
+    }
+    let mut decoder = ZlibDecoder::new(stream.content.as_slice()).take(limit + 1);
+    // 展開できないデータは、抽出の際にそのまま扱われるので圧縮したままの大きさとする
+    match std::io::copy(&mut decoder, &mut std::io::sink()) {
+        Ok(size) => Some(size),
+        Err(_) => Some(stream.content.len() as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use lopdf::dictionary;
+    use std::io::Write;
+
+    // 単体テスト: 上限を超える文書の判定
+    #[test]
+    fn test_check_document() {
+        let limits = RobustLimits { max_objects: 10, max_depth: 4, max_stream_bytes: 1024, max_total_bytes: 1536 };
+
+        // 自身を描画する Form XObject
+        let mut doc = Document::with_version("1.5");
+        let form = doc.new_object_id();
+        let stream = Stream::new(dictionary! {"Subtype" => "Form", "Resources" => dictionary! {"XObject" => dictionary! {"X" => form}}}, b"/X Do".to_vec());
+        doc.objects.insert(form, Object::Stream(stream));
+        assert!(check_document(&doc, &limits).unwrap_err().to_string().contains("自身を描画"));
+
+        // 入れ子の深い配列
+        let mut doc = Document::with_version("1.5");
+        let nested = (0..6).fold(Object::Null, |object, _| Object::Array(vec![object]));
+        doc.add_object(nested);
+        assert!(check_document(&doc, &limits).unwrap_err().to_string().contains("入れ子"));
+
+        // 展開すると大きくなるストリーム（上限までは合計も数える）
+        let compressed = |len: usize| {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&vec![0; len]).unwrap();
+            Stream::new(dictionary! {"Filter" => "FlateDecode"}, encoder.finish().unwrap())
+        };
+        let mut doc = Document::with_version("1.5");
+        doc.add_object(compressed(1000));
+        assert!(check_document(&doc, &limits).is_ok());
+        doc.add_object(compressed(1000));
+        assert!(check_document(&doc, &limits).unwrap_err().to_string().contains("合計"));
+        let mut doc = Document::with_version("1.5");
+        doc.add_object(compressed(4096));
+        assert!(check_document(&doc, &limits).unwrap_err().to_string().contains("1024 バイト"));
+
+        let mut doc = Document::with_version("1.5");
+        for i in 0..11 {
+            doc.add_object(Object::Integer(i));
+        }
+        assert!(check_document(&doc, &limits).unwrap_err().to_string().contains("オブジェクトの数"));
+    }
+}
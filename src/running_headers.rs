@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::layout::{self, PageLayout};
+
+/// 柱とノンブルを探す、ページの上端と下端からの範囲（ページの高さに対する割合）
+const MARGIN_RATIO: f64 = 0.12;
+
+/// 柱とみなすのに必要な、同じ位置に同じ文字が現れるページの数の下限（これに加えて、文書の半分より多くのページに現れる必要がある）
+const MIN_PAGES: usize = 3;
+
+/// 同じ位置とみなす、上端（柱）と下端（ノンブル）からの距離の差（pt）
+const POSITION_TOLERANCE: f64 = 2.0;
+
+/// 上下の余白の行のうち、柱の候補になる行
+struct Candidate {
+    page: usize,
+    range: std::ops::Range<usize>,
+    /// 上端からの距離（柱）か、下端からの距離（脚）。脚は負の値にして柱と区別する
+    position: f64,
+}
+
+/// 多くのページの同じ位置に繰り返し現れる柱（文書の題名、章の名前）とノンブルを取り除き、取り除いた行の数を返す
+///
+/// ページの上下の余白にある行を、数字を同じものとして比べる（ページ番号の違いを無視するため）。
+/// 「Chapter 1」「Chapter 2」のような番号付きの見出しを取り除かないよう、文書の半分より多くのページに現れる行だけを柱とし、
+/// 本文より大きな文字や、本文が太字でないときの太字の行は候補にしない。
+pub fn remove_running_headers(pages: &mut [PageLayout]) -> usize {
+    if pages.len() < MIN_PAGES {
+        return 0;
+    }
+    let (body_size, body_bold) = body_style(pages);
+
+    // 数字を除いた行の文字ごとに、余白の行を集める
+    let mut candidates: HashMap<String, Vec<Candidate>> = HashMap::new();
+    for (index, page) in pages.iter().enumerate() {
+        let margin = page.height * MARGIN_RATIO;
+        for range in layout::line_ranges(&page.glyphs) {
+            let y = page.glyphs[range.start].y;
+            let position = match y {
+                y if y <= margin => y,
+                y if y >= page.height - margin => -(page.height - y),
+                _ => continue,
+            };
+            let glyphs = &page.glyphs[range.clone()];
+            if glyphs.iter().any(|glyph| glyph.font_size > body_size + 0.5) || (!body_bold && glyphs.iter().all(|glyph| glyph.bold)) {
+                continue;
+            }
+            let text = layout::glyphs_to_text(glyphs);
+            let key: String = text.split_whitespace().collect::<Vec<_>>().join(" ").chars().map(|c| if c.is_ascii_digit() { '#' } else { c }).collect();
+            if !key.is_empty() {
+                candidates.entry(key).or_default().push(Candidate { page: index, range, position });
+            }
+        }
+    }
+
+    // 同じ位置に並ぶ行をまとめ、十分な数のページに現れるものを取り除く
+    let mut removed: Vec<Vec<std::ops::Range<usize>>> = vec![Vec::new(); pages.len()];
+    for mut group in candidates.into_values() {
+        group.sort_by(|a, b| a.position.total_cmp(&b.position));
+        let mut start = 0;
+        for end in 1..=group.len() {
+            if end < group.len() && group[end].position - group[end - 1].position <= POSITION_TOLERANCE {
+                continue;
+            }
+            let cluster = &group[start..end];
+            let mut page_numbers: Vec<usize> = cluster.iter().map(|candidate| candidate.page).collect();
+            page_numbers.sort_unstable();
+            page_numbers.dedup();
+            if page_numbers.len() >= MIN_PAGES && page_numbers.len() * 2 > pages.len() {
+                for candidate in cluster {
+                    removed[candidate.page].push(candidate.range.clone());
+                }
+            }
+            start = end;
+        }
+    }
+
+    let mut count = 0;
+    for (page, ranges) in pages.iter_mut().zip(removed) {
+        if ranges.is_empty() {
+            continue;
+        }
+        count += ranges.len();
+        let mut index = 0;
+        page.glyphs.retain(|_| {
+            index += 1;
+            !ranges.iter().any(|range| range.contains(&(index - 1)))
+        });
+    }
+    count
+}
+
+/// 本文の文字サイズ（最も多くの文字に使われている大きさ）と、本文が太字かどうか（半分より多くの文字が太字か）
+fn body_style(pages: &[PageLayout]) -> (f64, bool) {
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    let (mut bold, mut total) = (0, 0);
+    for glyph in pages.iter().flat_map(|page| &page.glyphs) {
+        match counts.iter_mut().find(|(size, _)| (size - glyph.font_size).abs() < 0.5) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((glyph.font_size, 1)),
+        }
+        bold += usize::from(glyph.bold);
+        total += 1;
+    }
+    let size = counts.into_iter().max_by_key(|&(_, count)| count).map_or(0.0, |(size, _)| size);
+    (size, bold * 2 > total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    fn line(y: f64, text: &str) -> Vec<Glyph> {
+        text.split(' ')
+            .enumerate()
+            .map(|(i, word)| Glyph { text: word.to_string(), x: 72.0 + i as f64 * 40.0, y, width: 30.0, font_size: 10.0, word_start: true, order: 0, color: None, bold: false, monospace: false, confidence: None })
+            .collect()
+    }
+
+    fn page(number: u32, lines: &[(f64, &str)]) -> PageLayout {
+        let glyphs = lines.iter().flat_map(|&(y, text)| line(y, text)).collect();
+        PageLayout { number, width: 612.0, height: 792.0, glyphs, ..Default::default() }
+    }
+
+    // 単体テスト: 柱とノンブルの除去
+    #[test]
+    fn test_remove_running_headers() {
+        let mut pages: Vec<PageLayout> = (1..=4)
+            .map(|n| {
+                let chapter = if n <= 3 { "Chapter One" } else { "Chapter Two" };
+                let number = format!("Page {} of 4", n);
+                // 柱の位置は、ページごとに少しずれることがある
+                page(n, &[(40.0 + n as f64 * 0.5, chapter), (300.0, "Body text of the page"), (750.0, &number)])
+            })
+            .collect();
+        // 本文の中で繰り返される行は取り除かない
+        pages[1].glyphs.extend(line(400.0, "Page 2 of 4"));
+
+        assert_eq!(remove_running_headers(&mut pages), 7);
+        let texts: Vec<String> = pages.iter().map(|page| layout::glyphs_to_text(&page.glyphs).split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(texts, ["Body text of the page", "Body text of the page Page 2 of 4", "Body text of the page", "Chapter Two Body text of the page"]);
+
+        // ページの少ない文書では取り除かない
+        let mut short = vec![page(1, &[(40.0, "Title"), (300.0, "Body")]), page(2, &[(40.0, "Title"), (300.0, "Body")])];
+        assert_eq!(remove_running_headers(&mut short), 0);
+    }
+
+    // 単体テスト: 番号付きの章の見出しは柱として取り除かない
+    #[test]
+    fn test_keep_numbered_headings() {
+        // 本文と同じ大きさでも、半分以下のページにしか現れない行は残す
+        let mut pages: Vec<PageLayout> = (1..=8u32)
+            .map(|n| {
+                let heading = format!("Chapter {}", n.div_ceil(3));
+                let lines: &[(f64, &str)] = if n % 3 == 1 { &[(40.0, &heading), (300.0, "Body text")] } else { &[(300.0, "Body text")] };
+                page(n, lines)
+            })
+            .collect();
+        assert_eq!(remove_running_headers(&mut pages), 0);
+
+        // 大きな文字や太字の見出しは、すべてのページに現れても残す
+        let mut pages: Vec<PageLayout> = (1..=4)
+            .map(|n| {
+                let mut page = page(n, &[(40.0, &format!("Chapter {}", n)), (300.0, "Body text of the page"), (320.0, "More body text")]);
+                for glyph in page.glyphs.iter_mut().filter(|glyph| glyph.y == 40.0) {
+                    if n % 2 == 0 {
+                        glyph.font_size = 18.0;
+                    } else {
+                        glyph.bold = true;
+                    }
+                }
+                page
+            })
+            .collect();
+        assert_eq!(remove_running_headers(&mut pages), 0);
+    }
+}
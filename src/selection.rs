@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// ページ範囲の指定（例: 1-5,8,12-）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRanges {
+    /// (開始, 終了) の組。終了が None の場合は最終ページまで
+    ranges: Vec<(u32, Option<u32>)>,
+}
+
+impl FromStr for PageRanges {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parse_page = |value: &str| -> Result<u32, String> {
+            value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("ページ番号は1以上の整数で指定してください: {}", value))
+        };
+
+        let mut ranges = Vec::new();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let range = match item.split_once('-') {
+                Some((start, "")) => (parse_page(start)?, None),
+                Some((start, end)) => {
+                    let (start, end) = (parse_page(start)?, parse_page(end)?);
+                    if start > end {
+                        return Err(format!("ページ範囲の開始が終了より後になっています: {}", item));
+                    }
+                    (start, Some(end))
+                }
+                None => {
+                    let page = parse_page(item)?;
+                    (page, Some(page))
+                }
+            };
+            ranges.push(range);
+        }
+
+        if ranges.is_empty() {
+            return Err("ページ範囲が指定されていません".to_string());
+        }
+        Ok(PageRanges { ranges })
+    }
+}
+
+impl PageRanges {
+    /// 1ページだけの範囲
+    pub fn single(page: u32) -> Self {
+        PageRanges { ranges: vec![(page, Some(page))] }
+    }
+
+    /// ページ番号が範囲に含まれるかどうか
+    pub fn contains(&self, page: u32) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| page >= start && end.is_none_or(|end| page <= end))
+    }
+}
+
+/// ページの抜き取り規則
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SampleRule {
+    /// 1ページ目から N ページおき
+    Every(u32),
+    /// 先頭の N ページ
+    First(u32),
+    /// 末尾の N ページ
+    Last(u32),
+}
+
+/// --sample で指定されたページの抜き取り方（例: every:10、first:5,last:5）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSample {
+    rules: Vec<SampleRule>,
+}
+
+impl FromStr for PageSample {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut rules = Vec::new();
+
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (kind, count) = item
+                .split_once(':')
+                .ok_or_else(|| format!("抜き取り方の形式が不正です（例: every:10, first:5）: {}", item))?;
+            let count: u32 = count
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("ページ数は1以上の整数で指定してください: {}", item))?;
+
+            rules.push(match kind.trim() {
+                "every" => SampleRule::Every(count),
+                "first" => SampleRule::First(count),
+                "last" => SampleRule::Last(count),
+                other => return Err(format!("不明な抜き取り方です（every, first, last のいずれか）: {}", other)),
+            });
+        }
+
+        if rules.is_empty() {
+            return Err("抜き取り方が指定されていません".to_string());
+        }
+        Ok(PageSample { rules })
+    }
+}
+
+impl PageSample {
+    /// 全ページ数 total のうち、抜き取るページ番号（1始まり）を返す
+    pub fn select(&self, total: u32) -> BTreeSet<u32> {
+        let mut pages = BTreeSet::new();
+
+        for rule in &self.rules {
+            match *rule {
+                SampleRule::Every(step) => pages.extend((1..=total).step_by(step as usize)),
+                SampleRule::First(count) => pages.extend(1..=count.min(total)),
+                SampleRule::Last(count) => pages.extend(total.saturating_sub(count) + 1..=total),
+            }
+        }
+
+        pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: ページ範囲の判定
+    #[test]
+    fn test_page_ranges() {
+        let ranges: PageRanges = "1-3, 8,12-".parse().unwrap();
+
+        for page in [1, 2, 3, 8, 12, 500] {
+            assert!(ranges.contains(page), "Test failed: {}", page);
+        }
+        for page in [4, 7, 9, 11] {
+            assert!(!ranges.contains(page), "Test failed: {}", page);
+        }
+
+        for spec in ["", "0", "5-3", "a-b", "-3"] {
+            assert!(spec.parse::<PageRanges>().is_err(), "Test failed: {}", spec);
+        }
+    }
+
+    // 単体テスト: 抜き取るページの決定
+    #[test]
+    fn test_page_sample_select() {
+        let test_cases = vec![
+            ("every:10", 25, vec![1, 11, 21], "一定間隔"),
+            ("first:2,last:2", 10, vec![1, 2, 9, 10], "先頭と末尾"),
+            ("first:5,last:5", 6, vec![1, 2, 3, 4, 5, 6], "重複するページ"),
+            ("last:3", 2, vec![1, 2], "全ページ数を超える指定"),
+        ];
+
+        for (spec, total, expected, desc) in test_cases {
+            let sample: PageSample = spec.parse().unwrap();
+            assert_eq!(sample.select(total).into_iter().collect::<Vec<_>>(), expected, "Test failed: {}", desc);
+        }
+    }
+
+    // 単体テスト: 不正な指定
+    #[test]
+    fn test_page_sample_invalid() {
+        for spec in ["", "every", "every:0", "random:3", "first:x"] {
+            assert!(spec.parse::<PageSample>().is_err(), "Test failed: {}", spec);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::password::PasswordRequired;
+use crate::progress::{self, Event};
+use crate::sniff::NotPdf;
+
+/// JSON-RPC のエラーコード（仕様で決められたもの）
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// 変換に失敗した
+const CONVERSION_FAILED: i64 = -32000;
+/// 中止の要求により変換を中止した（LSP と同じ値）
+const REQUEST_CANCELLED: i64 = -32800;
+
+/// convert の要求の引数
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConvertParams {
+    /// 入力の PDF ファイルのパス
+    pub input: PathBuf,
+    /// 出力の Markdown ファイルのパス（無ければ結果の markdown で返す）
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    /// コマンドラインと同じ形式の変換のオプション（例: ["--front-matter", "--toc"]）
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// convert の要求の引数が受け付けられないことを表すエラー（変換の関数が返すと、INVALID_PARAMS の応答にする）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidParams(pub String);
+
+impl fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidParams {}
+
+/// cancel の要求の引数
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CancelParams {
+    /// 中止する convert の要求の id
+    id: Value,
+}
+
+/// 受け取った要求（id の無いものは通知で、応答を返さない）
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// 1行に1つの JSON-RPC 2.0 のメッセージで、変換の要求を受け付ける
+///
+/// 使えるメソッドは convert（変換。結果を返すまでの進み具合は progress の通知で知らせる）、cancel（変換の中止）、shutdown（受け付けを終える）。
+/// 変換は要求ごとにスレッドで行い、変換中もほかの要求を受け付ける。入力が終わるか shutdown の後は、変換中の要求を終えてから戻る。
+pub fn serve<R, W, F>(reader: R, writer: W, convert: F) -> Result<()>
+where
+    R: BufRead,
+    W: Write + Send,
+    F: Fn(ConvertParams, &CancellationToken) -> Result<Value> + Sync,
+{
+    let (sender, receiver) = mpsc::channel::<Value>();
+    // 変換中の要求の id（JSON の文字列）と中止の要求
+    let running: Mutex<HashMap<String, CancellationToken>> = Mutex::new(HashMap::new());
+
+    std::thread::scope(|scope| {
+        // 応答と通知は1つのスレッドで書き、行が混ざらないようにする
+        let output = scope.spawn(move || -> Result<()> {
+            let mut writer = writer;
+            for message in receiver {
+                writeln!(writer, "{}", message)?;
+                writer.flush()?;
+            }
+            Ok(())
+        });
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request: Request = match serde_json::from_str::<Value>(&line) {
+                Err(e) => {
+                    send(&sender, error_response(Value::Null, PARSE_ERROR, &format!("JSON として読めません: {}", e), None));
+                    continue;
+                }
+                Ok(value) => match serde_json::from_value(value.clone()) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        let id = value.get("id").cloned().unwrap_or(Value::Null);
+                        send(&sender, error_response(id, INVALID_REQUEST, &format!("JSON-RPC の要求ではありません: {}", e), None));
+                        continue;
+                    }
+                },
+            };
+            let id = request.id.clone();
+            let reply = |response: Value| {
+                if id.is_some() {
+                    send(&sender, response);
+                }
+            };
+            if request.jsonrpc != "2.0" {
+                reply(error_response(id.clone().unwrap_or_default(), INVALID_REQUEST, "jsonrpc は \"2.0\" を指定してください", None));
+                continue;
+            }
+
+            match request.method.as_str() {
+                "convert" => {
+                    let params: ConvertParams = match serde_json::from_value(request.params) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            reply(error_response(id.clone().unwrap_or_default(), INVALID_PARAMS, &format!("convert の引数が正しくありません: {}", e), None));
+                            continue;
+                        }
+                    };
+                    let cancel = CancellationToken::new();
+                    let key = id.as_ref().map(Value::to_string);
+                    if let Some(key) = &key {
+                        running.lock().unwrap().insert(key.clone(), cancel.clone());
+                    }
+                    let (sender, convert, running) = (sender.clone(), &convert, &running);
+                    scope.spawn(move || {
+                        let notify_id = id.clone().unwrap_or_default();
+                        let notifier = sender.clone();
+                        let _observer = progress::ObserverScope::enter(Box::new(move |event: &Event| {
+                            if let Ok(Value::Object(mut params)) = serde_json::to_value(event) {
+                                params.insert("id".to_string(), notify_id.clone());
+                                send(&notifier, json!({"jsonrpc": "2.0", "method": "progress", "params": params}));
+                            }
+                        }));
+                        let result = std::panic::catch_unwind(AssertUnwindSafe(|| convert(params, &cancel)));
+                        if let Some(key) = &key {
+                            running.lock().unwrap().remove(key);
+                        }
+                        let Some(id) = id else {
+                            return;
+                        };
+                        let response = match result {
+                            Ok(Ok(result)) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                            Ok(Err(e)) => conversion_error(id, &e),
+                            Err(_) => error_response(id, CONVERSION_FAILED, "変換中に内部エラーが発生しました", None),
+                        };
+                        send(&sender, response);
+                    });
+                }
+                "cancel" => match serde_json::from_value::<CancelParams>(request.params) {
+                    Ok(params) => {
+                        let token = running.lock().unwrap().get(&params.id.to_string()).cloned();
+                        if let Some(token) = &token {
+                            token.cancel();
+                        }
+                        reply(json!({"jsonrpc": "2.0", "id": id, "result": token.is_some()}));
+                    }
+                    Err(e) => reply(error_response(id.clone().unwrap_or_default(), INVALID_PARAMS, &format!("cancel の引数が正しくありません: {}", e), None)),
+                },
+                "shutdown" => {
+                    reply(json!({"jsonrpc": "2.0", "id": id, "result": null}));
+                    break;
+                }
+                method => reply(error_response(id.clone().unwrap_or_default(), METHOD_NOT_FOUND, &format!("不明なメソッドです: {}", method), None)),
+            }
+        }
+
+        // 変換中のスレッドが持つ送り口が無くなると、書き出しのスレッドも終わる
+        drop(sender);
+        output.join().unwrap_or_else(|_| anyhow::bail!("応答を書き出すスレッドが異常終了しました"))
+    })
+}
+
+fn send(sender: &Sender<Value>, message: Value) {
+    // 書き出しのスレッドが終わっている（出力先が閉じられた）場合は捨てる
+    let _ = sender.send(message);
+}
+
+fn error_response(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({"code": code, "message": message});
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({"jsonrpc": "2.0", "id": id, "error": error})
+}
+
+/// 変換のエラーの応答（パスワードが必要な場合と PDF ではない場合は、data の kind で判別できるようにする）
+fn conversion_error(id: Value, error: &anyhow::Error) -> Value {
+    if error.downcast_ref::<Cancelled>().is_some() {
+        return error_response(id, REQUEST_CANCELLED, &error.to_string(), None);
+    }
+    if let Some(InvalidParams(message)) = error.downcast_ref::<InvalidParams>() {
+        return error_response(id, INVALID_PARAMS, &format!("convert の引数が正しくありません: {}", message), None);
+    }
+    let kind = if error.downcast_ref::<PasswordRequired>().is_some() {
+        Some("password_required")
+    } else if error.downcast_ref::<NotPdf>().is_some() {
+        Some("not_pdf")
+    } else {
+        None
+    };
+    error_response(id, CONVERSION_FAILED, &format!("{:#}", error), kind.map(|kind| json!({"kind": kind})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    // 単体テスト: 要求の受け付け、進み具合の通知、中止
+    #[test]
+    fn test_serve() {
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"convert","params":{"input":"a.pdf","args":["--toc"]}}"#,
+            r#"{"jsonrpc":"2.0","id":"slow","method":"convert","params":{"input":"slow.pdf"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"cancel","params":{"id":"slow"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"convert","params":{"input":"locked.pdf"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"convert","params":{}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"render"}"#,
+            r#"{"jsonrpc":"2.0","id":8,"method":"convert","params":{"input":"-"}}"#,
+            "not json",
+            r#"{"jsonrpc":"2.0","id":6,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","id":7,"method":"convert","params":{"input":"ignored.pdf"}}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        serve(requests.as_bytes(), &mut output, |params: ConvertParams, cancel: &CancellationToken| {
+            match params.input.to_str() {
+                Some("slow.pdf") => {
+                    let start = Instant::now();
+                    while start.elapsed() < Duration::from_secs(10) {
+                        cancel.check()?;
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                    Ok(json!("timeout"))
+                }
+                Some("locked.pdf") => Err(PasswordRequired.into()),
+                Some("-") => Err(InvalidParams("標準入力（-）は入力にできません".to_string()).into()),
+                _ => {
+                    progress::page_done(1, 2);
+                    Ok(json!({"markdown": "# A\n", "args": params.args}))
+                }
+            }
+        })
+        .unwrap();
+
+        let messages: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let response = |id: Value| messages.iter().find(|message| message["id"] == id && message.get("method").is_none()).cloned().unwrap();
+
+        assert_eq!(response(json!(1))["result"], json!({"markdown": "# A\n", "args": ["--toc"]}));
+        let progress = messages.iter().find(|message| message["method"] == "progress").unwrap();
+        assert_eq!(progress["params"], json!({"id": 1, "event": "page_done", "file": null, "page": 1, "pages": 2}));
+        assert_eq!(response(json!(2))["result"], json!(true));
+        assert_eq!(response(json!("slow"))["error"]["code"], json!(REQUEST_CANCELLED));
+        assert_eq!(response(json!(3))["error"]["data"], json!({"kind": "password_required"}));
+        assert_eq!(response(json!(4))["error"]["code"], json!(INVALID_PARAMS));
+        assert_eq!(response(json!(5))["error"]["code"], json!(METHOD_NOT_FOUND));
+        // 変換の関数が受け付けない引数（標準入力の入力）
+        assert_eq!(response(json!(8))["error"]["code"], json!(INVALID_PARAMS));
+        assert_eq!(response(Value::Null)["error"]["code"], json!(PARSE_ERROR));
+        assert_eq!(response(json!(6))["result"], Value::Null);
+        // shutdown の後の要求は受け付けない
+        assert!(messages.iter().all(|message| message["id"] != json!(7)));
+    }
+}
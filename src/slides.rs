@@ -0,0 +1,203 @@
+use lopdf::{Document, Object, ObjectId};
+
+use crate::layout::{PageLayout, TextLine};
+use crate::metadata;
+use crate::paragraphs;
+
+/// 箇条書きの行頭記号
+const BULLETS: &[char] = &['•', '◦', '▪', '▫', '●', '○', '■', '□', '◆', '◇', '・', '‣', '⁃', '–', '-', '*', '»'];
+
+/// 各ページを1枚のスライドとして Markdown に変換する（Marp / reveal.js 向けにスライドの間を --- で区切る）
+///
+/// ページのタイトル（上部で最も大きな文字の行）を H2 に、行頭記号と字下げから箇条書きの階層を組み立て、
+/// ページに付いたテキスト注釈（発表者ノート）を引用ブロックにする。
+pub fn render_slides(doc: &Document, pages: &[PageLayout]) -> String {
+    let page_ids = doc.get_pages();
+
+    let slides: Vec<String> = pages
+        .iter()
+        .map(|page| {
+            let notes = page_ids.get(&page.number).map(|&id| speaker_notes(doc, id)).unwrap_or_default();
+            render_slide(page, &notes)
+        })
+        .collect();
+
+    slides.join("\n\n---\n\n")
+}
+
+/// 1枚分のスライドを Markdown にする
+fn render_slide(page: &PageLayout, notes: &[String]) -> String {
+    let lines = page.lines();
+    let mut blocks: Vec<String> = Vec::new();
+
+    // タイトル: ページ上部（上から 40% まで）の最も大きな文字の行。続く同じ大きさの行もタイトルに含める
+    let top_limit = if page.height > 0.0 { page.height * 0.4 } else { f64::INFINITY };
+    let title_size = lines.iter().filter(|l| l.y <= top_limit).map(|l| l.font_size).fold(0.0, f64::max);
+    let title_start = lines.iter().position(|l| l.y <= top_limit && l.font_size == title_size);
+    let mut body_start = 0;
+    if let Some(start) = title_start {
+        let end = lines[start..]
+            .iter()
+            .position(|l| (l.font_size - title_size).abs() > 0.5)
+            .map_or(lines.len(), |n| start + n);
+        let title: Vec<&str> = lines[start..end].iter().map(|l| l.text.trim()).collect();
+        blocks.push(format!("## {}", title.join(" ")));
+        blocks.extend(body_blocks(&lines[..start]));
+        body_start = end;
+    }
+    blocks.extend(body_blocks(&lines[body_start..]));
+
+    for note in notes {
+        let quoted: Vec<String> = note.lines().map(|line| format!("> {}", line.trim()).trim_end().to_string()).collect();
+        blocks.push(quoted.join("\n"));
+    }
+
+    blocks.join("\n\n")
+}
+
+/// タイトル以外の行を、箇条書きと段落のブロックにする
+fn body_blocks(lines: &[TextLine]) -> Vec<String> {
+    // 箇条書きの階層は、行頭記号の位置を左から順に並べたときの順位
+    let mut bullet_positions: Vec<f64> = Vec::new();
+    for line in lines.iter().filter(|l| bullet_text(&l.text).is_some()) {
+        if !bullet_positions.iter().any(|&x| (x - line.x0).abs() <= line.font_size) {
+            bullet_positions.push(line.x0);
+        }
+    }
+    bullet_positions.sort_by(f64::total_cmp);
+
+    let mut blocks: Vec<String> = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut last_bullet_x: Option<f64> = None;
+    let breaks = paragraphs::paragraph_breaks(lines);
+
+    for (line, starts_paragraph) in lines.iter().zip(breaks) {
+        let text = line.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match bullet_text(text) {
+            Some(item) => {
+                if !paragraph.is_empty() {
+                    blocks.push(paragraph.join(" "));
+                    paragraph.clear();
+                }
+                let level = bullet_positions.iter().filter(|&&x| x < line.x0 - line.font_size).count();
+                list.push(format!("{}- {}", "  ".repeat(level), item));
+                last_bullet_x = Some(line.x0);
+            }
+            // 行頭記号より右から始まる行は、直前の項目の続き
+            None if last_bullet_x.is_some_and(|x| line.x0 > x + line.font_size * 0.3) => {
+                let item = list.last_mut().unwrap();
+                item.push(' ');
+                item.push_str(text);
+            }
+            None => {
+                if !list.is_empty() {
+                    blocks.push(list.join("\n"));
+                    list.clear();
+                }
+                if starts_paragraph && !paragraph.is_empty() {
+                    blocks.push(paragraph.join(" "));
+                    paragraph.clear();
+                }
+                last_bullet_x = None;
+                paragraph.push(text);
+            }
+        }
+    }
+    if !list.is_empty() {
+        blocks.push(list.join("\n"));
+    }
+    if !paragraph.is_empty() {
+        blocks.push(paragraph.join(" "));
+    }
+
+    blocks
+}
+
+/// 行頭記号で始まる行であれば、記号を除いた本文を返す
+pub fn bullet_text(text: &str) -> Option<&str> {
+    let rest = text.trim_start().strip_prefix(BULLETS)?;
+    // 「-1」のような数値や、記号だけの行は箇条書きとみなさない
+    if !rest.starts_with(char::is_whitespace) || rest.trim().is_empty() {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+/// ページのテキスト注釈（付箋・フリーテキスト）の内容を発表者ノートとして読み込む
+fn speaker_notes(doc: &Document, page_id: ObjectId) -> Vec<String> {
+    let Ok(annotations) = doc.get_page_annotations(page_id) else {
+        return Vec::new();
+    };
+
+    annotations
+        .into_iter()
+        .filter(|annotation| {
+            matches!(
+                annotation.get(b"Subtype").and_then(Object::as_name).ok(),
+                Some(b"Text") | Some(b"FreeText")
+            )
+        })
+        .filter_map(|annotation| metadata::info_string(doc, annotation, b"Contents"))
+        .filter(|contents| !contents.trim().is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, x0: f64, y: f64, font_size: f64) -> TextLine {
+        TextLine { text: text.to_string(), x0, x1: x0 + 200.0, y, font_size, confidence: None }
+    }
+
+    // 単体テスト: 箇条書きの階層の組み立て
+    #[test]
+    fn test_body_blocks() {
+        let lines = vec![
+            line("Intro text", 50.0, 100.0, 14.0),
+            line("• First point", 50.0, 130.0, 14.0),
+            line("that wraps", 64.0, 146.0, 14.0),
+            line("– Sub point", 80.0, 162.0, 12.0),
+            line("• Second point", 50.0, 178.0, 14.0),
+            line("-1 is not a bullet", 50.0, 210.0, 14.0),
+        ];
+
+        assert_eq!(
+            body_blocks(&lines),
+            vec!["Intro text", "- First point that wraps\n  - Sub point\n- Second point", "-1 is not a bullet"]
+        );
+    }
+
+    // 単体テスト: タイトルと発表者ノート
+    #[test]
+    fn test_render_slide() {
+        let glyph = |text: &str, y: f64, font_size: f64| crate::layout::Glyph {
+            text: text.to_string(),
+            x: 50.0,
+            y,
+            width: 100.0,
+            font_size,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence: None,
+        };
+        let page = PageLayout {
+            number: 1,
+            height: 540.0,
+            glyphs: vec![glyph("Quarterly Results", 60.0, 32.0), glyph("• Revenue up", 150.0, 18.0)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_slide(&page, &["Mention the new market.".to_string()]),
+            "## Quarterly Results\n\n- Revenue up\n\n> Mention the new market."
+        );
+    }
+}
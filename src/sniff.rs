@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 入力が PDF ではない場合の終了コード（エラー時の 1、引数エラーの 2、has-text の 3、4 と区別する）
+pub const EXIT_NOT_PDF: i32 = 5;
+
+/// PDF の目印（%PDF-）を探す先頭の範囲（仕様では先頭 1024 バイト以内にあればよい）
+const HEADER_SEARCH_BYTES: usize = 1024;
+
+/// 先頭のバイト列から判定したファイルの内容の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Pdf,
+    Html,
+    Xml,
+    /// ZIP（Word や Excel の文書を含む）
+    Zip,
+    Png,
+    Jpeg,
+    Gif,
+    /// 上のどれでもないテキスト
+    Text,
+    Empty,
+    Unknown,
+}
+
+impl fmt::Display for ContentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContentKind::Pdf => "PDF",
+            ContentKind::Html => "HTML（エラーページを保存したものなど）",
+            ContentKind::Xml => "XML",
+            ContentKind::Zip => "ZIP（Word や Excel の文書を含む）",
+            ContentKind::Png => "PNG 画像",
+            ContentKind::Jpeg => "JPEG 画像",
+            ContentKind::Gif => "GIF 画像",
+            ContentKind::Text => "テキスト",
+            ContentKind::Empty => "空のファイル",
+            ContentKind::Unknown => "不明な形式",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 入力が PDF ではないことを表すエラー（anyhow::Error の downcast_ref で判別できる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotPdf {
+    pub kind: ContentKind,
+}
+
+impl fmt::Display for NotPdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PDF ではありません（内容は{}です）", self.kind)
+    }
+}
+
+impl std::error::Error for NotPdf {}
+
+/// 先頭のバイト列からファイルの内容の種類を判定する
+pub fn sniff(head: &[u8]) -> ContentKind {
+    let head = &head[..head.len().min(HEADER_SEARCH_BYTES)];
+    if head.windows(5).any(|window| window == b"%PDF-") {
+        return ContentKind::Pdf;
+    }
+    match head {
+        [] => return ContentKind::Empty,
+        [0x89, b'P', b'N', b'G', ..] => return ContentKind::Png,
+        [0xFF, 0xD8, 0xFF, ..] => return ContentKind::Jpeg,
+        [b'G', b'I', b'F', b'8', ..] => return ContentKind::Gif,
+        [b'P', b'K', 0x03, 0x04, ..] => return ContentKind::Zip,
+        _ => {}
+    }
+
+    let Ok(text) = std::str::from_utf8(head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head)) else {
+        // 途中で切った UTF-8 の文字は、テキストとして扱う
+        return match std::str::from_utf8(head) {
+            Err(e) if e.error_len().is_none() => ContentKind::Text,
+            _ => ContentKind::Unknown,
+        };
+    };
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") || start.contains("<head") || start.contains("<body") {
+        ContentKind::Html
+    } else if start.starts_with("<?xml") {
+        ContentKind::Xml
+    } else if text.chars().all(|c| !c.is_control() || c.is_whitespace()) {
+        ContentKind::Text
+    } else {
+        ContentKind::Unknown
+    }
+}
+
+/// ファイルの先頭を読んで内容の種類を判定する
+pub fn sniff_file(path: &Path) -> Result<ContentKind> {
+    let mut head = Vec::with_capacity(HEADER_SEARCH_BYTES);
+    File::open(path)
+        .and_then(|file| file.take(HEADER_SEARCH_BYTES as u64).read_to_end(&mut head))
+        .with_context(|| format!("入力ファイルを読み込めません: {:?}", path))?;
+    Ok(sniff(&head))
+}
+
+/// PDF でなければ NotPdf のエラーにする
+pub fn ensure_pdf(head: &[u8]) -> Result<(), NotPdf> {
+    match sniff(head) {
+        ContentKind::Pdf => Ok(()),
+        kind => Err(NotPdf { kind }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 先頭のバイト列による内容の判定
+    #[test]
+    fn test_sniff() {
+        let test_cases: Vec<(&[u8], ContentKind, &str)> = vec![
+            (b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n", ContentKind::Pdf, "PDF"),
+            (b"\r\n\r\n%PDF-1.4\n", ContentKind::Pdf, "目印の前に余分なデータがある PDF"),
+            (b"\n<!DOCTYPE html>\n<html><body>404 Not Found</body></html>", ContentKind::Html, "エラーページ"),
+            (b"<?xml version=\"1.0\"?><Error><Code>AccessDenied</Code></Error>", ContentKind::Xml, "XML のエラー応答"),
+            (b"PK\x03\x04\x14\x00", ContentKind::Zip, "ZIP"),
+            (b"\x89PNG\r\n\x1a\n", ContentKind::Png, "PNG"),
+            (b"Access denied\n", ContentKind::Text, "テキスト"),
+            (b"", ContentKind::Empty, "空"),
+            (b"\x00\x01\x02\x03", ContentKind::Unknown, "不明"),
+        ];
+
+        for (head, expected, desc) in test_cases {
+            assert_eq!(sniff(head), expected, "Test failed: {}", desc);
+        }
+        assert_eq!(ensure_pdf(b"<html>").unwrap_err().to_string(), "PDF ではありません（内容はHTML（エラーページを保存したものなど）です）");
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 英語（eng）の文字認識で、辞書の指定が無い場合に使うシステムの単語の一覧
+const SYSTEM_DICTIONARY: &str = "/usr/share/dict/words";
+
+/// 文書の中の語を辞書に加える、確からしさ（0〜100）の下限
+pub const MIN_DOCUMENT_CONFIDENCE: f64 = 90.0;
+
+/// 文書の中の語を辞書に加える、現れる回数の下限
+const MIN_DOCUMENT_COUNT: usize = 2;
+
+/// 文字認識で取り違えやすい文字の組（誤認識した文字、正しい文字）
+const CONFUSIONS: &[(&str, &str)] = &[
+    ("rn", "m"),
+    ("m", "rn"),
+    ("cl", "d"),
+    ("d", "cl"),
+    ("vv", "w"),
+    ("li", "h"),
+    ("ii", "u"),
+    ("0", "o"),
+    ("0", "O"),
+    ("1", "l"),
+    ("1", "I"),
+    ("5", "s"),
+    ("5", "S"),
+    ("8", "B"),
+    ("l", "i"),
+    ("c", "e"),
+    ("e", "c"),
+];
+
+/// 数字の中で取り違えやすい文字（誤認識した文字、正しい数字）
+const DIGIT_CONFUSIONS: &[(char, char)] = &[('O', '0'), ('o', '0'), ('D', '0'), ('l', '1'), ('I', '1'), ('|', '1'), ('S', '5'), ('B', '8')];
+
+/// 文字認識の補正に使う単語の辞書（小文字で持つ）
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// 単語の一覧のファイル（1行に1語）を読み込む（指定が無く、言語が英語を含む場合はシステムの単語の一覧があれば使う）
+    pub fn load(paths: &[PathBuf], lang: &str) -> Result<Self> {
+        let mut dictionary = Dictionary::default();
+        for path in paths {
+            dictionary.load_file(path)?;
+        }
+        let system = Path::new(SYSTEM_DICTIONARY);
+        if paths.is_empty() && lang.split('+').any(|lang| lang == "eng") && system.exists() {
+            dictionary.load_file(system)?;
+        }
+        Ok(dictionary)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path).with_context(|| format!("文字認識の補正に使う辞書 {:?} を読み込めません", path))?;
+        self.words.extend(text.lines().map(str::trim).filter(|word| !word.is_empty() && !word.starts_with('#')).map(str::to_lowercase));
+        Ok(())
+    }
+
+    /// 文書の中で繰り返し現れる語（確からしさの高いもの）を辞書に加える（固有名詞や専門用語を、誤りとして直さないように）
+    pub fn add_document_words<'a, I: IntoIterator<Item = &'a str>>(&mut self, words: I) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in words {
+            let core = core_of(word).1;
+            if core.chars().all(char::is_alphabetic) {
+                *counts.entry(core.to_lowercase()).or_default() += 1;
+            }
+        }
+        self.words.extend(counts.into_iter().filter(|(_, count)| *count >= MIN_DOCUMENT_COUNT).map(|(word, _)| word));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+}
+
+/// 語の前後の記号と、間の語の部分（前の記号、語、後の記号）
+fn core_of(word: &str) -> (&str, &str, &str) {
+    let is_mark = |c: char| !c.is_alphanumeric() && c != '|';
+    let trimmed = word.trim_start_matches(is_mark);
+    let core = trimmed.trim_end_matches(is_mark);
+    (&word[..word.len() - trimmed.len()], core, &trimmed[core.len()..])
+}
+
+/// 認識した語を補正する（補正しない場合は None）
+///
+/// 数字が2文字以上で半分以上を占める語は数字と取り違えやすい文字を数字にし、辞書に無い語は取り違えやすい文字の組を1か所ずつ置き換えて、
+/// 辞書にある語がただ1つ見つかった場合にその語にする。
+pub fn correct_word(word: &str, dictionary: &Dictionary) -> Option<String> {
+    let (before, core, after) = core_of(word);
+    if core.chars().count() < 2 {
+        return None;
+    }
+
+    let digits = core.chars().filter(char::is_ascii_digit).count();
+    let confusable = |c: char| DIGIT_CONFUSIONS.iter().any(|&(from, _)| from == c);
+    if digits >= 2 && digits * 2 >= core.chars().count() && core.chars().all(|c| c.is_ascii_digit() || confusable(c)) {
+        let number: String = core.chars().map(|c| DIGIT_CONFUSIONS.iter().find(|&&(from, _)| from == c).map_or(c, |&(_, to)| to)).collect();
+        return (number != core).then(|| format!("{}{}{}", before, number, after));
+    }
+
+    if dictionary.contains(core) || !core.chars().all(char::is_alphanumeric) {
+        return None;
+    }
+    let mut candidates: Vec<String> = Vec::new();
+    for &(from, to) in CONFUSIONS {
+        for (index, _) in core.match_indices(from) {
+            let candidate = format!("{}{}{}", &core[..index], to, &core[index + from.len()..]);
+            if dictionary.contains(&candidate) && !candidates.iter().any(|c| c.eq_ignore_ascii_case(&candidate)) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    match &candidates[..] {
+        [candidate] => Some(format!("{}{}{}", before, candidate, after)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 取り違えやすい文字の組と数字の補正
+    #[test]
+    fn test_correct_word() {
+        let mut dictionary = Dictionary::default();
+        dictionary.words.extend(["modern", "the", "word"].map(String::from));
+        dictionary.add_document_words(["Kubernetes", "Kubernetes,", "Zephyr"]);
+
+        assert_eq!(correct_word("rnodern", &dictionary).as_deref(), Some("modern"));
+        assert_eq!(correct_word("(Tbe", &dictionary), None);
+        assert_eq!(correct_word("vvord.", &dictionary).as_deref(), Some("word."));
+        assert_eq!(correct_word("2O24", &dictionary).as_deref(), Some("2024"));
+        assert_eq!(correct_word("$15O", &dictionary).as_deref(), Some("$150"));
+        // 辞書にある語、繰り返し現れる語、候補の無い語、候補が1つに決まらない語はそのまま
+        assert_eq!(correct_word("modern", &dictionary), None);
+        assert_eq!(correct_word("Kubernetes", &dictionary), None);
+        assert_eq!(correct_word("Zephyr", &dictionary), None);
+        dictionary.words.extend(["lamb", "iamb"].map(String::from));
+        assert_eq!(correct_word("1amb", &dictionary), None);
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use regex::Regex;
+use std::sync::LazyLock;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::destinations::{self, Bookmark};
+use crate::layout::PageLayout;
+
+/// 出力を複数のファイルに分ける区切り
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    /// 検出した最上位の見出し（# 見出し）ごとに分ける
+    Heading,
+    /// PDF のしおりの最上位の項目ごとに、項目の移動先のページで分ける（しおりが無い場合は見出しで分ける）
+    Outline,
+}
+
+/// 分割した出力の1ファイル分
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub title: String,
+    /// 先頭のページ（見出しで分けた場合は None）
+    pub page: Option<u32>,
+    pub content: String,
+}
+
+/// Markdown を最上位の見出しの行ごとに分け、最初の見出しより前の内容と、見出しごとの内容を返す
+pub fn split_markdown(markdown: &str) -> (String, Vec<Part>) {
+    let mut preamble = String::new();
+    let mut parts: Vec<Part> = Vec::new();
+    let mut in_code = false;
+
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if let Some(title) = line.strip_prefix("# ").filter(|_| !in_code) {
+            parts.push(Part { title: title.trim().to_string(), page: None, content: String::new() });
+        }
+        match parts.last_mut() {
+            Some(part) => part.content.push_str(line),
+            None => preamble.push_str(line),
+        }
+    }
+
+    (preamble, parts)
+}
+
+/// しおりの最上位の項目ごとの (題名, 先頭のページ, ページの範囲)
+///
+/// 範囲は pages の添字で、項目の移動先のページから次の項目の前のページまで。最初の項目より前のページは含めない。
+/// 同じページに移動する項目が続く場合は、最初の項目にまとめる。
+pub fn outline_ranges(bookmarks: &[Bookmark], pages: &[PageLayout]) -> Vec<(String, u32, Range<usize>)> {
+    let mut starts: Vec<(String, u32)> = Vec::new();
+    for bookmark in bookmarks.iter().filter(|bookmark| bookmark.level == 1) {
+        let Some(page) = bookmark.page else {
+            continue;
+        };
+        // 前の項目より前のページに戻る項目（付録から本文への参照など）は区切りにしない
+        if starts.last().is_none_or(|(_, last)| page > *last) {
+            starts.push((bookmark.title.clone(), page));
+        }
+    }
+
+    let index_of = |number: u32| pages.iter().position(|page| page.number >= number).unwrap_or(pages.len());
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (title, page))| {
+            let end = starts.get(i + 1).map_or(pages.len(), |(_, next)| index_of(*next));
+            (title.clone(), *page, index_of(*page)..end)
+        })
+        .filter(|(_, _, range)| !range.is_empty())
+        .collect()
+}
+
+/// 文書内へのリンク（[文字](#アンカー)）のアンカー
+static LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\(#([^)\s]+)\)").unwrap());
+
+/// 文書内へのリンク（[文字](#アンカー)）のうち、見出しが別のファイルに移ったものを「ファイル名#アンカー」に書き換える
+///
+/// preamble は分割前の出力ファイル（file_names の各ファイルへの一覧を書くファイル）に残す内容。
+pub fn retarget_links(preamble: &mut String, parts: &mut [Part], file_names: &[String]) {
+    let mut files: HashMap<String, usize> = HashMap::new();
+    for (index, part) in parts.iter().enumerate() {
+        for line in part.content.lines().filter(|line| line.starts_with('#')) {
+            files.entry(destinations::slug(line.trim_start_matches('#'))).or_insert(index);
+        }
+    }
+
+    let rewrite = |content: &str, current: Option<usize>| {
+        LINK_REGEX
+            .replace_all(content, |caps: &regex::Captures| match files.get(&caps[1]) {
+                Some(&index) if Some(index) != current => format!("]({}#{})", file_names[index], &caps[1]),
+                _ => caps[0].to_string(),
+            })
+            .into_owned()
+    };
+    *preamble = rewrite(preamble, None);
+    for (index, part) in parts.iter_mut().enumerate() {
+        part.content = rewrite(&part.content, Some(index));
+    }
+}
+
+/// 雛形の変数（{title} など）の正規表現
+static VARIABLE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([a-z_]*)\}").unwrap());
+
+/// 分割したファイルの雛形で使える変数
+const VARIABLES: &[&str] = &["title", "part", "parts", "part_title", "prev", "next", "index", "prev_url", "next_url", "index_url"];
+
+/// 分割した各ファイルの末尾に入れる、前後のファイルと一覧へのリンクの行の書式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Navigation {
+    /// リンクの行を入れない
+    None,
+    /// Markdown のリンク（[題名](ファイル名)）
+    #[default]
+    Markdown,
+    /// Hugo の ref ショートコードを使ったリンク（[題名]({{< ref "ファイル名" >}})）
+    Hugo,
+}
+
+impl Navigation {
+    /// 前後のファイルと一覧へのリンクの行（None の場合は空）
+    fn render(self, context: &PartContext) -> String {
+        let target = |file: &str| match self {
+            Navigation::Hugo => format!("{{{{< ref \"{}\" >}}}}", file),
+            _ => file.to_string(),
+        };
+        let prev = context.prev.map(|(title, file)| format!("← [{}]({})", title, target(file)));
+        let index = Some(format!("[{}]({})", context.index.0, target(context.index.1)));
+        let next = context.next.map(|(title, file)| format!("[{}]({}) →", title, target(file)));
+        match self {
+            Navigation::None => String::new(),
+            _ => [prev, index, next].into_iter().flatten().collect::<Vec<_>>().join(" | "),
+        }
+    }
+}
+
+/// 分割したファイルの先頭と末尾に書き足す内容の雛形
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartTemplate {
+    pub header: Option<String>,
+    pub footer: Option<String>,
+    /// 末尾（footer の前）に入れるリンクの行の書式
+    pub navigation: Navigation,
+}
+
+/// 雛形の変数に入れる、分割したファイルの情報
+#[derive(Debug, Clone, Copy)]
+pub struct PartContext<'a> {
+    /// 文書の題名
+    pub title: &'a str,
+    /// ファイルの番号（1 始まり）と数
+    pub part: usize,
+    pub parts: usize,
+    pub part_title: &'a str,
+    /// 前後のファイルの (題名, ファイル名)
+    pub prev: Option<(&'a str, &'a str)>,
+    pub next: Option<(&'a str, &'a str)>,
+    /// ファイルの一覧を書く出力ファイルの (題名, ファイル名)
+    pub index: (&'a str, &'a str),
+}
+
+impl PartTemplate {
+    /// 雛形を確かめる（知らない変数があればエラーにする）
+    pub fn validate(&self) -> Result<()> {
+        for template in self.header.iter().chain(&self.footer) {
+            if let Some(caps) = VARIABLE_REGEX.captures_iter(template).find(|caps| !VARIABLES.contains(&&caps[1])) {
+                bail!("分割したファイルの雛形に知らない変数があります: {}（使える変数: {}）", &caps[0], VARIABLES.iter().map(|name| format!("{{{}}}", name)).collect::<Vec<_>>().join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    /// 分割したファイルの内容に、雛形から作った先頭と末尾の内容を加える
+    pub fn apply(&self, content: &str, context: &PartContext) -> String {
+        let mut result = String::new();
+        if let Some(header) = &self.header {
+            result.push_str(&render(header, context));
+            result.push_str("\n\n");
+        }
+        result.push_str(content);
+        let navigation = self.navigation.render(context);
+        if !navigation.is_empty() {
+            result.push_str("\n\n");
+            result.push_str(&navigation);
+            result.push('\n');
+        }
+        if let Some(footer) = &self.footer {
+            result.push_str("\n\n");
+            result.push_str(&render(footer, context));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// 雛形の変数を置き換える（前後のファイルが無い場合の {prev} と {next} は空にする）
+fn render(template: &str, context: &PartContext) -> String {
+    let link = |target: Option<(&str, &str)>| target.map(|(title, file)| format!("[{}]({})", title, file)).unwrap_or_default();
+    VARIABLE_REGEX
+        .replace_all(template, |caps: &regex::Captures| match &caps[1] {
+            "title" => context.title.to_string(),
+            "part" => context.part.to_string(),
+            "parts" => context.parts.to_string(),
+            "part_title" => context.part_title.to_string(),
+            "prev" => link(context.prev),
+            "next" => link(context.next),
+            "index" => link(Some(context.index)),
+            "prev_url" => context.prev.map(|(_, file)| file.to_string()).unwrap_or_default(),
+            "next_url" => context.next.map(|(_, file)| file.to_string()).unwrap_or_default(),
+            "index_url" => context.index.1.to_string(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 見出しによる分割
+    #[test]
+    fn test_split_markdown() {
+        let (preamble, parts) = split_markdown("Title page\n\n# One\n\nText\n\n```\n# not a heading\n```\n\n## Sub\n\n# Two\n\nMore\n");
+        assert_eq!(preamble, "Title page\n\n");
+        let titles: Vec<&str> = parts.iter().map(|part| part.title.as_str()).collect();
+        assert_eq!(titles, vec!["One", "Two"]);
+        assert_eq!(parts[0].content, "# One\n\nText\n\n```\n# not a heading\n```\n\n## Sub\n\n");
+
+        // 別のファイルに移った見出しへのリンク
+        let (mut preamble, mut parts) = split_markdown("See [Sub](#sub).\n\n# One\n\n## Sub\n\nBack to [One](#one), on to [Two](#two).\n\n# Two\n");
+        retarget_links(&mut preamble, &mut parts, &["a-01.md".to_string(), "a-02.md".to_string()]);
+        assert_eq!(preamble, "See [Sub](a-01.md#sub).\n\n");
+        assert!(parts[0].content.ends_with("Back to [One](#one), on to [Two](a-02.md#two).\n\n"));
+    }
+
+    // 単体テスト: 分割したファイルの先頭と末尾の雛形
+    #[test]
+    fn test_part_template() {
+        let template = PartTemplate {
+            header: Some("*{title}* — {part}/{parts}: {part_title}".to_string()),
+            footer: Some("{prev} | {index} | {next}".to_string()),
+            navigation: Navigation::None,
+        };
+        assert!(template.validate().is_ok());
+        let context = PartContext {
+            title: "Manual",
+            part: 1,
+            parts: 2,
+            part_title: "One",
+            prev: None,
+            next: Some(("Two", "manual-02.md")),
+            index: ("Manual", "manual.md"),
+        };
+        assert_eq!(template.apply("# One\n", &context), "*Manual* — 1/2: One\n\n# One\n\n\n | [Manual](manual.md) | [Two](manual-02.md)\n");
+
+        // 前後のファイルへのリンクの行
+        let navigation = PartTemplate { navigation: Navigation::Markdown, ..Default::default() };
+        assert_eq!(navigation.apply("# One\n", &context), "# One\n\n\n[Manual](manual.md) | [Two](manual-02.md) →\n");
+        let context = PartContext { prev: Some(("One", "manual-01.md")), next: None, ..context };
+        let hugo = PartTemplate { navigation: Navigation::Hugo, ..Default::default() };
+        assert_eq!(hugo.apply("", &context), "\n\n← [One]({{< ref \"manual-01.md\" >}}) | [Manual]({{< ref \"manual.md\" >}})\n");
+
+        let invalid = PartTemplate { header: Some("{chapter}".to_string()), ..Default::default() };
+        assert!(invalid.validate().unwrap_err().to_string().starts_with("分割したファイルの雛形に知らない変数があります: {chapter}"));
+    }
+
+    // 単体テスト: しおりによるページの範囲
+    #[test]
+    fn test_outline_ranges() {
+        let bookmark = |level: usize, title: &str, page: Option<u32>| Bookmark { level, title: title.to_string(), page };
+        let pages: Vec<PageLayout> = (1..=8).map(|number| PageLayout { number, ..Default::default() }).collect();
+        let bookmarks = vec![
+            bookmark(1, "Preface", Some(2)),
+            bookmark(1, "Part I", None),
+            bookmark(1, "Chapter 1", Some(3)),
+            bookmark(2, "1.1 Basics", Some(4)),
+            bookmark(1, "Chapter 2", Some(6)),
+            bookmark(1, "See Chapter 1", Some(3)),
+        ];
+
+        assert_eq!(
+            outline_ranges(&bookmarks, &pages),
+            vec![("Preface".to_string(), 2, 1..2), ("Chapter 1".to_string(), 3, 2..5), ("Chapter 2".to_string(), 6, 5..8)]
+        );
+    }
+}
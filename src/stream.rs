@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::io::Write;
+
+/// 書き出し先に渡さずに貯めておく Markdown の大きさの上限（超えた分は方針にかかわらず書き出す）
+const MAX_BUFFER_BYTES: usize = 64 * 1024;
+
+/// 書き出し先をフラッシュする時機
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FlushPolicy {
+    /// ページごと（応答を逐次返すサーバーやパイプ向け）
+    #[default]
+    Page,
+    /// 貯めた Markdown が上限に達するごと
+    Buffer,
+    /// 最後にまとめて（ファイルへの書き出し向け）
+    End,
+}
+
+/// Markdown をページごとに書き出し先へ書き出す
+///
+/// ページの間には空行を1つ入れる。貯めておく大きさは MAX_BUFFER_BYTES までとし、
+/// フラッシュは方針に従う（End でも貯めきれない分は書き出すが、フラッシュは finish まで行わない）。
+pub struct MarkdownWriter<W: Write> {
+    sink: W,
+    policy: FlushPolicy,
+    buffer: Vec<u8>,
+    /// 書き出したページの数
+    pages: usize,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    pub fn new(sink: W, policy: FlushPolicy) -> Self {
+        MarkdownWriter { sink, policy, buffer: Vec::new(), pages: 0 }
+    }
+
+    /// 1ページ分の Markdown を書き出す（空のページは飛ばす）
+    pub fn write_page(&mut self, markdown: &str) -> Result<()> {
+        let markdown = markdown.trim_matches('\n');
+        if markdown.is_empty() {
+            return Ok(());
+        }
+        if self.pages > 0 {
+            self.buffer.extend_from_slice(b"\n");
+        }
+        self.buffer.extend_from_slice(markdown.as_bytes());
+        self.buffer.push(b'\n');
+        self.pages += 1;
+
+        match self.policy {
+            FlushPolicy::Page => self.flush(),
+            _ if self.buffer.len() >= MAX_BUFFER_BYTES => {
+                self.drain()?;
+                match self.policy {
+                    FlushPolicy::End => Ok(()),
+                    _ => self.sink.flush().context("Markdown の書き出しに失敗しました"),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 貯めた Markdown を書き出してフラッシュし、書き出し先を返す
+    pub fn finish(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.sink)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.drain()?;
+        self.sink.flush().context("Markdown の書き出しに失敗しました")
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        self.sink.write_all(&self.buffer).context("Markdown の書き出しに失敗しました")?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 書き込みとフラッシュの回数を数える書き出し先
+    #[derive(Default)]
+    struct Recorder {
+        written: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    // 単体テスト: ページごとの書き出しとフラッシュの時機
+    #[test]
+    fn test_markdown_writer() {
+        let mut writer = MarkdownWriter::new(Recorder::default(), FlushPolicy::Page);
+        writer.write_page("# Title\n\nIntro.\n").unwrap();
+        assert_eq!((writer.sink.written.len(), writer.sink.flushes), (16, 1));
+        writer.write_page("\n").unwrap();
+        writer.write_page("Second page.\n").unwrap();
+        let sink = writer.finish().unwrap();
+        assert_eq!(String::from_utf8(sink.written).unwrap(), "# Title\n\nIntro.\n\nSecond page.\n");
+
+        // 最後にまとめる場合も、上限を超えた分は書き出す
+        let mut writer = MarkdownWriter::new(Recorder::default(), FlushPolicy::End);
+        writer.write_page("short").unwrap();
+        assert!(writer.sink.written.is_empty());
+        writer.write_page(&"x".repeat(MAX_BUFFER_BYTES)).unwrap();
+        assert_eq!((writer.sink.written.len(), writer.sink.flushes), (MAX_BUFFER_BYTES + 8, 0));
+        assert_eq!(writer.finish().unwrap().flushes, 1);
+    }
+}
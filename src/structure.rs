@@ -0,0 +1,339 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::graphics;
+use crate::layout::{PageLayout, TextLine};
+use crate::lists;
+use crate::tables;
+
+/// 先頭の行を探すときに、前の段落の次から先読みする行の数
+const MAX_SKIPPED_LINES: usize = 5;
+
+/// 出力の形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Markdown
+    #[default]
+    Markdown,
+    /// 見出し・段落・リスト・表などの構造と、ページ番号・位置を表す JSON
+    Json,
+}
+
+/// 文書の構造
+#[derive(Debug, Serialize)]
+pub struct StructuredDocument {
+    pub pages: Vec<StructuredPage>,
+}
+
+/// ページの構造
+#[derive(Debug, Serialize)]
+pub struct StructuredPage {
+    pub number: u32,
+    /// ページの幅と高さ（ポイント）
+    pub width: f64,
+    pub height: f64,
+    pub blocks: Vec<Block>,
+}
+
+/// ページの中の要素（段落などの本文は Markdown の書式を含む）
+///
+/// confidence は、要素の行にある文字認識した語の確からしさ（0〜100）の最小値（文字認識していない要素には出力しない）。
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Heading {
+        level: usize,
+        text: String,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    Paragraph {
+        text: String,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    List {
+        items: Vec<ListEntry>,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    Table {
+        rows: Vec<Vec<String>>,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    Code {
+        text: String,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    Quote {
+        text: String,
+        bbox: Option<BoundingBox>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        confidence: Option<f64>,
+    },
+    Image { alt: String, src: String, bbox: Option<BoundingBox> },
+}
+
+/// リストの項目
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ListEntry {
+    /// 入れ子の深さ（0 が最上位）
+    pub depth: usize,
+    /// 番号付きの項目の番号
+    pub number: Option<u32>,
+    pub text: String,
+}
+
+/// 要素の位置（ページの左上を原点とするポイント単位の矩形。文字の高さはフォントサイズから求める）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoundingBox {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
+
+impl BoundingBox {
+    /// 行の位置（小数第2位までに丸める）
+    fn of(line: &TextLine) -> Self {
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        BoundingBox { x0: round(line.x0), y0: round(line.y - line.font_size * 0.8), x1: round(line.x1), y1: round(line.y + line.font_size * 0.2) }
+    }
+
+    fn union(self, other: BoundingBox) -> Self {
+        BoundingBox { x0: self.x0.min(other.x0), y0: self.y0.min(other.y0), x1: self.x1.max(other.x1), y1: self.y1.max(other.y1) }
+    }
+}
+
+/// ページごとの Markdown と、表などを置き換える前のページの文字の配置から、文書の構造を組み立てる
+///
+/// 要素の位置は、要素の本文に含まれる行を、ページの行の順に前から照らし合わせて求める（見つからない要素は null）。
+pub fn build_document(pages: &[(u32, String)], layouts: &[PageLayout]) -> StructuredDocument {
+    let pages = pages
+        .iter()
+        .map(|(number, markdown)| {
+            let layout = layouts.iter().find(|layout| layout.number == *number);
+            let mut blocks = parse_blocks(markdown);
+            if let Some(layout) = layout {
+                locate_blocks(&mut blocks, &layout.lines());
+            }
+            StructuredPage { number: *number, width: layout.map_or(0.0, |layout| layout.width), height: layout.map_or(0.0, |layout| layout.height), blocks }
+        })
+        .collect();
+    StructuredDocument { pages }
+}
+
+/// Markdown の要素の種類ごとの数
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BlockCounts {
+    pub headings: usize,
+    pub paragraphs: usize,
+    pub lists: usize,
+    pub tables: usize,
+    pub images: usize,
+}
+
+/// Markdown の要素を種類ごとに数える（--dry-run の要約に使う）
+pub fn count_blocks(markdown: &str) -> BlockCounts {
+    let mut counts = BlockCounts::default();
+    for block in parse_blocks(markdown) {
+        match block {
+            Block::Heading { .. } => counts.headings += 1,
+            Block::Paragraph { .. } => counts.paragraphs += 1,
+            Block::List { .. } => counts.lists += 1,
+            Block::Table { .. } => counts.tables += 1,
+            Block::Image { .. } => counts.images += 1,
+            Block::Code { .. } | Block::Quote { .. } => {}
+        }
+    }
+    counts
+}
+
+/// Markdown を要素に分ける（位置は locate_blocks で求める）
+fn parse_blocks(markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines = markdown.lines().peekable();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph { text: paragraph.join(" "), bbox: None, confidence: None });
+            paragraph.clear();
+        }
+    };
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+        if trimmed.is_empty() || trimmed.starts_with("<!--") {
+            flush(&mut paragraph, &mut blocks);
+        } else if trimmed.starts_with("```") {
+            flush(&mut paragraph, &mut blocks);
+            let code: Vec<&str> = lines.by_ref().take_while(|line| !line.trim_start().starts_with("```")).collect();
+            blocks.push(Block::Code { text: code.join("\n"), bbox: None, confidence: None });
+        } else if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading { level: hashes, text: trimmed[hashes..].trim().to_string(), bbox: None, confidence: None });
+        } else if tables::is_table_row(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let mut rows = vec![table_cells(trimmed)];
+            while let Some(row) = lines.next_if(|line| tables::is_table_row(line.trim())) {
+                rows.push(table_cells(row.trim()));
+            }
+            // 見出しの行と本文の行の区切り（| --- |）は除く
+            rows.retain(|row| !row.iter().all(|cell| !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':'))));
+            blocks.push(Block::Table { rows, bbox: None, confidence: None });
+        } else if lists::is_markdown_item(line) {
+            flush(&mut paragraph, &mut blocks);
+            let mut items = Vec::new();
+            let mut indents: Vec<usize> = Vec::new();
+            let mut item = Some(line);
+            while let Some(line) = item {
+                let indent = line.len() - line.trim_start().len();
+                while indents.last().is_some_and(|&last| last > indent) {
+                    indents.pop();
+                }
+                if indents.last() != Some(&indent) {
+                    indents.push(indent);
+                }
+                let text = line.trim_start();
+                let (number, text) = match text.strip_prefix("- ") {
+                    Some(text) => (None, text),
+                    None => text.split_once(". ").map_or((None, text), |(number, text)| (number.parse().ok(), text)),
+                };
+                items.push(ListEntry { depth: indents.len() - 1, number, text: text.trim().to_string() });
+                item = lines.next_if(|line| lists::is_markdown_item(line));
+            }
+            blocks.push(Block::List { items, bbox: None, confidence: None });
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut blocks);
+            let mut quoted = vec![quote.trim()];
+            while let Some(line) = lines.next_if(|line| line.trim_start().starts_with('>')) {
+                quoted.push(line.trim_start()[1..].trim());
+            }
+            blocks.push(Block::Quote { text: quoted.join("\n"), bbox: None, confidence: None });
+        } else if let Some((alt, src)) = graphics::is_image_line(trimmed).then(|| image_link(trimmed)).flatten() {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Image { alt, src, bbox: None });
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+/// 表の行のセル
+fn table_cells(row: &str) -> Vec<String> {
+    row.trim_matches('|').split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+/// 画像の行（![代替テキスト](パス)）の代替テキストとパス
+fn image_link(line: &str) -> Option<(String, String)> {
+    let (alt, rest) = line.strip_prefix("![")?.split_once("](")?;
+    Some((alt.to_string(), rest.strip_suffix(')')?.to_string()))
+}
+
+/// 要素の本文に含まれる行をページの行から探し、要素の位置にする
+fn locate_blocks(blocks: &mut [Block], lines: &[TextLine]) {
+    let keys: Vec<String> = lines.iter().map(|line| match_key(&line.text)).collect();
+    let mut cursor = 0;
+    for block in blocks.iter_mut() {
+        let (text, bbox, confidence) = match block {
+            Block::Heading { text, bbox, confidence, .. }
+            | Block::Paragraph { text, bbox, confidence }
+            | Block::Code { text, bbox, confidence }
+            | Block::Quote { text, bbox, confidence } => (text.clone(), bbox, confidence),
+            Block::List { items, bbox, confidence } => (items.iter().map(|item| item.text.as_str()).collect::<Vec<_>>().join(" "), bbox, confidence),
+            Block::Table { rows, bbox, confidence } => (rows.iter().flatten().map(String::as_str).collect::<Vec<_>>().join(" "), bbox, confidence),
+            Block::Image { .. } => continue,
+        };
+        let key = match_key(&text);
+        if key.is_empty() {
+            continue;
+        }
+        let end = (cursor + MAX_SKIPPED_LINES).min(lines.len());
+        let Some(first) = (cursor..end).find(|&i| !keys[i].is_empty() && key.contains(keys[i].as_str())) else {
+            continue;
+        };
+        let mut found = BoundingBox::of(&lines[first]);
+        let mut matched = keys[first].len();
+        cursor = first + 1;
+        while cursor < lines.len() && matched < key.len() && (keys[cursor].is_empty() || key.contains(keys[cursor].as_str())) {
+            found = found.union(BoundingBox::of(&lines[cursor]));
+            matched += keys[cursor].len();
+            cursor += 1;
+        }
+        *bbox = Some(found);
+        *confidence = lines[first..cursor].iter().filter_map(|line| line.confidence).reduce(f64::min);
+    }
+}
+
+/// 照らし合わせに使う、文字と数字だけを小文字にしたテキスト（Markdown の記号や空白を除く）
+fn match_key(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    // 単体テスト: Markdown の要素への分割と位置の照らし合わせ
+    #[test]
+    fn test_build_document() {
+        let glyph = |text: &str, x: f64, y: f64, confidence: Option<f64>| Glyph {
+            text: text.to_string(),
+            x,
+            y,
+            width: 6.0 * text.chars().count() as f64,
+            font_size: 10.0,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence,
+        };
+        let layout = PageLayout {
+            number: 2,
+            width: 595.0,
+            height: 842.0,
+            glyphs: vec![
+                glyph("Results", 72.0, 100.0, None),
+                // 文字認識した段落の語の確からしさ
+                glyph("Sales", 72.0, 130.0, Some(91.0)),
+                glyph("grew.", 110.0, 130.0, Some(88.5)),
+                glyph("Still", 72.0, 142.0, Some(52.0)),
+                glyph("growing.", 110.0, 142.0, Some(97.0)),
+            ],
+            ..Default::default()
+        };
+        let markdown = "## Results\n\nSales **grew.** Still growing.\n\n- First\n  - Nested\n2. Second\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n\n![Chart](doc_assets/fig-01.png)";
+        let document = build_document(&[(2, markdown.to_string())], &[layout]);
+        let blocks = &document.pages[0].blocks;
+
+        assert_eq!(blocks[0], Block::Heading { level: 2, text: "Results".to_string(), bbox: Some(BoundingBox { x0: 72.0, y0: 92.0, x1: 114.0, y1: 102.0 }), confidence: None });
+        assert_eq!(blocks[1], Block::Paragraph { text: "Sales **grew.** Still growing.".to_string(), bbox: Some(BoundingBox { x0: 72.0, y0: 122.0, x1: 158.0, y1: 144.0 }), confidence: Some(52.0) });
+        let Block::List { items, bbox: None, .. } = &blocks[2] else {
+            panic!("リストになりません: {:?}", blocks[2]);
+        };
+        assert_eq!(items.iter().map(|item| (item.depth, item.number)).collect::<Vec<_>>(), [(0, None), (1, None), (0, Some(2))]);
+        assert_eq!(blocks[3], Block::Table { rows: vec![vec!["A".to_string(), "B".to_string()], vec!["1".to_string(), "2".to_string()]], bbox: None, confidence: None });
+        assert_eq!(blocks[4], Block::Image { alt: "Chart".to_string(), src: "doc_assets/fig-01.png".to_string(), bbox: None });
+
+        assert_eq!(count_blocks(markdown), BlockCounts { headings: 1, paragraphs: 1, lists: 1, tables: 1, images: 1 });
+
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["pages"][0]["number"], 2);
+        assert_eq!(json["pages"][0]["blocks"][0]["type"], "heading");
+        assert!(json["pages"][0]["blocks"][0].get("confidence").is_none());
+        assert_eq!(json["pages"][0]["blocks"][1]["confidence"], 52.0);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::financial;
+use crate::layout::{self, Glyph, PageLayout, Segment, TableRegion};
+
+/// 罫線とみなす線の太さの上限（細い塗りつぶしの矩形も罫線として扱う）
+const MAX_RULE_THICKNESS: f64 = 2.0;
+
+/// 罫線とみなす横線の長さの下限
+const MIN_RULE_LENGTH: f64 = 30.0;
+
+/// 罫線で区切られた表とみなす、2つ以上のセルに区切られた行の数の下限
+const MIN_RULED_ROWS: usize = 2;
+
+/// 各ページの表を Markdown の表に置き換え、置き換えた表の数を返す
+pub fn convert_tables(pages: &mut [PageLayout]) -> usize {
+    replace_tables(pages, |_, _, _| {})
+}
+
+/// 各ページの表を、1行目を見出しの行にした Markdown の表に置き換え、置き換えた表の数を返す
+///
+/// inspect には置き換える前のページと表の領域、セルの文字を渡す（合計の検算などに使う）。
+pub fn replace_tables<F: FnMut(&PageLayout, &TableRegion, &[Vec<String>])>(pages: &mut [PageLayout], mut inspect: F) -> usize {
+    let mut replaced = 0;
+
+    for page in pages.iter_mut() {
+        for region in find_tables(page) {
+            let rows = table_rows(page, &region);
+            inspect(page, &region, &rows);
+
+            // 表の文字を取り除き、最初の文字の位置に表全体を1文字分として挿入する（前後の空行で段落を区切る）
+            let in_region = |glyph: &Glyph| glyph.y >= region.y0 - glyph.font_size * 0.5 && glyph.y <= region.y1 + glyph.font_size * 0.5;
+            let Some(index) = page.glyphs.iter().position(in_region) else {
+                continue;
+            };
+            let first = page.glyphs[index].clone();
+            page.glyphs.retain(|glyph| !in_region(glyph));
+            page.glyphs.insert(
+                index.min(page.glyphs.len()),
+                Glyph { text: format!("\n\n{}\n\n", render_table(&rows)), x: 0.0, width: 0.0, word_start: true, color: None, bold: false, monospace: false, ..first },
+            );
+            replaced += 1;
+        }
+    }
+
+    replaced
+}
+
+/// ページの表の領域（罫線で区切られた領域と、大きな空白で3つ以上に区切られた行が続く領域。上から順）
+pub fn find_tables(page: &PageLayout) -> Vec<TableRegion> {
+    let mut regions = ruled_regions(page);
+    for region in page.table_regions() {
+        if !regions.iter().any(|ruled| region.y0 <= ruled.y1 && ruled.y0 <= region.y1) {
+            regions.push(region);
+        }
+    }
+    regions.sort_by(|a, b| a.y0.total_cmp(&b.y0));
+    regions
+}
+
+/// 表の領域の文字を Markdown の表にする
+pub fn table_markdown(page: &PageLayout, region: &TableRegion) -> String {
+    render_table(&table_rows(page, region))
+}
+
+/// 行が Markdown の表の行かどうか
+pub fn is_table_row(line: &str) -> bool {
+    line.len() >= 2 && line.starts_with('|') && line.ends_with('|')
+}
+
+/// 表の領域の文字を、列をそろえた行ごと・セルごとのテキストにする
+fn table_rows(page: &PageLayout, region: &TableRegion) -> Vec<Vec<String>> {
+    pad_rows(page.table_cells(region))
+}
+
+/// 横罫線の (y 座標, 左端, 右端)（上から順。同じ高さで途切れずに続く線分は1本にまとめる）
+fn horizontal_rules(page: &PageLayout) -> Vec<(f64, f64, f64)> {
+    let lines = page.ruled_lines.iter().map(|line| (line.x0, line.y0, line.x1, line.y1));
+    let fills = page.fills.iter().map(|fill| (fill.x0, fill.y0, fill.x1, fill.y1));
+    let mut rules: Vec<(f64, f64, f64)> = lines
+        .chain(fills)
+        .filter(|(x0, y0, x1, y1)| y1 - y0 <= MAX_RULE_THICKNESS && x1 > x0)
+        .map(|(x0, y0, x1, y1)| ((y0 + y1) / 2.0, x0, x1))
+        .collect();
+    rules.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let mut merged: Vec<(f64, f64, f64)> = Vec::new();
+    for (y, x0, x1) in rules {
+        match merged.last_mut() {
+            Some(last) if (last.0 - y).abs() <= MAX_RULE_THICKNESS && x0 <= last.2 + MAX_RULE_THICKNESS => last.2 = last.2.max(x1),
+            _ => merged.push((y, x0, x1)),
+        }
+    }
+    merged.retain(|(_, x0, x1)| x1 - x0 >= MIN_RULE_LENGTH);
+    merged.sort_by(|a, b| a.0.total_cmp(&b.0));
+    merged
+}
+
+/// 横罫線で上下を区切られた表の領域
+///
+/// 同じくらいの幅の横罫線を上から順にたどり、罫線の間の行が2つ以上のセルに区切られている間は同じ表とする。
+/// 空白で3つ以上に区切られていない2列の表も、罫線があれば表として扱う。
+fn ruled_regions(page: &PageLayout) -> Vec<TableRegion> {
+    let rules = horizontal_rules(page);
+    let mut segments = layout::split_segments(&page.glyphs);
+    segments.sort_by(|a, b| a.y.total_cmp(&b.y));
+
+    // 罫線の左右の範囲に収まる区間を、ベースラインごとに数える (y 座標, セルの数)
+    let rows_between = |top: f64, bottom: f64, x0: f64, x1: f64| -> Vec<(f64, usize)> {
+        let mut rows: Vec<(f64, usize)> = Vec::new();
+        for segment in segments.iter().filter(|s| s.y > top && s.y < bottom && within(s, x0, x1)) {
+            match rows.last_mut() {
+                Some((y, cells)) if (segment.y - *y).abs() <= segment.font_size * 0.5 => *cells += 1,
+                _ => rows.push((segment.y, 1)),
+            }
+        }
+        rows
+    };
+
+    let mut regions = Vec::new();
+    let mut index = 0;
+    while index < rules.len() {
+        let (top, x0, x1) = rules[index];
+        let mut bottom = index;
+        for (next, &(y, a0, a1)) in rules.iter().enumerate().skip(index + 1) {
+            // 幅の違う罫線は別の図形とみなす
+            let overlap = x1.min(a1) - x0.max(a0);
+            if overlap < (x1 - x0).min(a1 - a0) * 0.8 {
+                continue;
+            }
+            let rows = rows_between(rules[bottom].0, y, x0, x1);
+            if !rows.is_empty() && rows.iter().all(|(_, cells)| *cells < 2) {
+                break;
+            }
+            bottom = next;
+        }
+
+        let rows = rows_between(top, rules[bottom].0, x0, x1);
+        if bottom > index && rows.iter().filter(|(_, cells)| *cells >= 2).count() >= MIN_RULED_ROWS {
+            regions.push(TableRegion { y0: rows[0].0, y1: rows[rows.len() - 1].0, rows: rows.len() });
+            index = bottom + 1;
+        } else {
+            index += 1;
+        }
+    }
+
+    regions
+}
+
+/// 区間が罫線の左右の範囲に収まっているかどうか
+fn within(segment: &Segment, x0: f64, x1: f64) -> bool {
+    segment.x0 >= x0 - segment.font_size && segment.x1 <= x1 + segment.font_size
+}
+
+/// セルの数が足りない行に、先頭のセル（項目名）の後ろへ空のセルを足して列をそろえる（数値は右寄せで並ぶため）
+fn pad_rows(mut rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        while row.len() < columns {
+            row.insert(1.min(row.len()), String::new());
+        }
+    }
+    rows
+}
+
+/// 1行目を見出しにした Markdown の表にする（数値の列は右寄せ）
+fn render_table(rows: &[Vec<String>]) -> String {
+    let Some(header) = rows.first() else {
+        return String::new();
+    };
+    let row_text = |row: &[String]| format!("| {} |", row.iter().map(|cell| cell.replace('|', "\\|")).collect::<Vec<_>>().join(" | "));
+
+    let alignments: Vec<&str> = (0..header.len())
+        .map(|column| {
+            let cells: Vec<&String> = rows[1..].iter().map(|row| &row[column]).filter(|cell| !cell.is_empty()).collect();
+            let numeric = cells.iter().filter(|cell| financial::parse_amount(cell).is_some()).count();
+            if column > 0 && !cells.is_empty() && numeric * 2 > cells.len() {
+                "---:"
+            } else {
+                "---"
+            }
+        })
+        .collect();
+
+    let mut lines = vec![row_text(header), format!("| {} |", alignments.join(" | "))];
+    lines.extend(rows[1..].iter().map(|row| row_text(row)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::RuledLine;
+
+    fn cell(text: &str, x: f64, y: f64, order: usize) -> Glyph {
+        Glyph { text: text.to_string(), x, y, width: text.len() as f64 * 5.0, font_size: 10.0, word_start: true, order, color: None, bold: false, monospace: false, confidence: None }
+    }
+
+    fn rule(y: f64) -> RuledLine {
+        RuledLine { x0: 60.0, y0: y, x1: 300.0, y1: y }
+    }
+
+    // 単体テスト: 罫線で区切られた表の変換
+    #[test]
+    fn test_convert_tables() {
+        let glyphs = vec![
+            cell("Intro text above the table.", 72.0, 80.0, 0),
+            cell("Name", 72.0, 110.0, 1),
+            cell("Role", 200.0, 110.0, 2),
+            cell("Alice", 72.0, 126.0, 3),
+            cell("Editor", 200.0, 126.0, 4),
+            cell("Bob", 72.0, 140.0, 5),
+            cell("Author", 200.0, 140.0, 6),
+            cell("Closing remarks.", 72.0, 180.0, 7),
+        ];
+        let mut pages = vec![PageLayout { number: 1, glyphs, ruled_lines: vec![rule(98.0), rule(114.0), rule(146.0)], ..Default::default() }];
+
+        assert_eq!(convert_tables(&mut pages), 1);
+        let texts: Vec<&str> = pages[0].glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["Intro text above the table.", "\n\n| Name | Role |\n| --- | --- |\n| Alice | Editor |\n| Bob | Author |\n\n", "Closing remarks."]
+        );
+
+        // 罫線の無い2列の行は表にしない
+        let glyphs = vec![cell("Name", 72.0, 110.0, 0), cell("Role", 200.0, 110.0, 1), cell("Alice", 72.0, 126.0, 2), cell("Editor", 200.0, 126.0, 3)];
+        let mut plain = vec![PageLayout { number: 1, glyphs, ..Default::default() }];
+        assert_eq!(convert_tables(&mut plain), 0);
+    }
+
+    // 単体テスト: 表の出力
+    #[test]
+    fn test_render_table() {
+        let table = pad_rows(
+            [&["Item", "2023", "2024"][..], &["Sales", "1,000", "1,200"], &["Other", "(5)"]]
+                .iter()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect(),
+        );
+        assert_eq!(
+            render_table(&table),
+            "| Item | 2023 | 2024 |\n| --- | ---: | ---: |\n| Sales | 1,000 | 1,200 |\n| Other |  | (5) |"
+        );
+    }
+}
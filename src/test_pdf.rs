@@ -0,0 +1,227 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+
+/// ページの大きさ（US Letter、ポイント）
+const PAGE_WIDTH: f64 = 612.0;
+const PAGE_HEIGHT: f64 = 792.0;
+
+/// 左の余白と、最初の行の y
+const LEFT: f64 = 72.0;
+const TOP: f64 = 720.0;
+
+/// 本文の文字の大きさと行の間隔
+const BODY_SIZE: f64 = 11.0;
+const BODY_LEADING: f64 = 14.0;
+
+/// 表の列の間隔
+const COLUMN_WIDTH: f64 = 120.0;
+
+/// テスト用の PDF の標準フォント（埋め込まない Type1 の14書体）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Mono];
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Mono => "Courier",
+        }
+    }
+
+    /// ページのリソースの中のフォントの名前
+    fn resource_name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+}
+
+/// 見出しや表、フォントの分かっている小さな PDF を組み立てる（実際の PDF ファイルを使わずに構造の判定を試すため）
+///
+/// heading や paragraph などは、前の要素の下に上から順に置く。text は位置を指定して置く。
+#[derive(Debug, Default)]
+pub struct PdfBuilder {
+    /// ページごとの文字を描く命令
+    pages: Vec<Vec<Operation>>,
+    /// 次の要素を置く y（PDF の座標なので下に行くほど小さい）
+    cursor: f64,
+}
+
+impl PdfBuilder {
+    pub fn new() -> Self {
+        PdfBuilder::default()
+    }
+
+    /// 新しいページを始める
+    pub fn page(&mut self) -> &mut Self {
+        self.pages.push(Vec::new());
+        self.cursor = TOP;
+        self
+    }
+
+    /// 位置、大きさ、フォントを指定して1行の文字を置く
+    pub fn text(&mut self, x: f64, y: f64, size: f64, font: Font, text: &str) -> &mut Self {
+        if self.pages.is_empty() {
+            self.page();
+        }
+        let operations = self.pages.last_mut().unwrap();
+        operations.push(Operation::new("BT", vec![]));
+        operations.push(Operation::new("Tf", vec![font.resource_name().into(), size.into()]));
+        operations.push(Operation::new("Td", vec![x.into(), y.into()]));
+        operations.push(Operation::new("Tj", vec![Object::string_literal(text)]));
+        operations.push(Operation::new("ET", vec![]));
+        self
+    }
+
+    /// 黒い矩形を、塗りつぶしの命令（f、f*、B など）を指定して描く（それまでに置いた文字の上に重なる）
+    pub fn fill(&mut self, x: f64, y: f64, width: f64, height: f64, operator: &str) -> &mut Self {
+        if self.pages.is_empty() {
+            self.page();
+        }
+        let operations = self.pages.last_mut().unwrap();
+        operations.push(Operation::new("g", vec![0.into()]));
+        operations.push(Operation::new("re", vec![x.into(), y.into(), width.into(), height.into()]));
+        operations.push(Operation::new(operator, vec![]));
+        self
+    }
+
+    /// 見出しの大きさ（1 が最も大きい）の太字の行を置く
+    pub fn heading(&mut self, level: usize, text: &str) -> &mut Self {
+        let size = match level {
+            1 => 24.0,
+            2 => 16.0,
+            _ => 13.0,
+        };
+        self.next_line(size * 1.5, size, Font::Bold, text);
+        self.cursor -= size;
+        self
+    }
+
+    /// 本文の大きさの行を続けて置く（1つの段落）
+    pub fn paragraph(&mut self, lines: &[&str]) -> &mut Self {
+        for line in lines {
+            self.next_line(BODY_LEADING, BODY_SIZE, Font::Regular, line);
+        }
+        self.cursor -= BODY_LEADING;
+        self
+    }
+
+    /// 等幅のフォントの行を置く（行頭の空白もそのまま）
+    pub fn code(&mut self, lines: &[&str]) -> &mut Self {
+        for line in lines {
+            self.next_line(12.0, 10.0, Font::Mono, line);
+        }
+        self.cursor -= BODY_LEADING;
+        self
+    }
+
+    /// 列を揃えたセルの表を置く（1行目は太字の見出しの行）
+    pub fn table(&mut self, rows: &[&[&str]]) -> &mut Self {
+        for (index, row) in rows.iter().enumerate() {
+            self.cursor -= BODY_LEADING;
+            let font = if index == 0 { Font::Bold } else { Font::Regular };
+            for (column, cell) in row.iter().enumerate() {
+                let y = self.cursor;
+                self.text(LEFT + COLUMN_WIDTH * column as f64, y, BODY_SIZE - 1.0, font, cell);
+            }
+        }
+        self.cursor -= BODY_LEADING * 2.0;
+        self
+    }
+
+    fn next_line(&mut self, leading: f64, size: f64, font: Font, text: &str) {
+        if self.pages.is_empty() {
+            self.page();
+        }
+        self.cursor -= leading;
+        let y = self.cursor;
+        self.text(LEFT, y, size, font, text);
+    }
+
+    /// 組み立てた PDF の文書
+    pub fn document(&self) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut fonts = lopdf::Dictionary::new();
+        for font in Font::ALL {
+            let id = doc.add_object(dictionary! {"Type" => "Font", "Subtype" => "Type1", "BaseFont" => font.base_font()});
+            fonts.set(font.resource_name(), id);
+        }
+        let resources_id = doc.add_object(dictionary! {"Font" => fonts});
+
+        let empty = [Vec::new()];
+        let pages = if self.pages.is_empty() { &empty[..] } else { &self.pages[..] };
+        let kids: Vec<Object> = pages
+            .iter()
+            .map(|operations| {
+                let content_id = doc.add_object(Stream::new(dictionary! {}, Content { operations: operations.clone() }.encode().unwrap()));
+                let page_id: ObjectId = doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => resources_id,
+                });
+                page_id.into()
+            })
+            .collect();
+        let count = kids.len() as i64;
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => count,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {"Type" => "Catalog", "Pages" => pages_id});
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    /// 組み立てた PDF のバイト列
+    pub fn build(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.document().save_to(&mut bytes).unwrap();
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 組み立てた PDF の見出し、段落、表、コードの変換
+    #[test]
+    fn test_pdf_builder() {
+        let pdf = PdfBuilder::new()
+            .heading(1, "Annual Report")
+            .heading(2, "1. Introduction")
+            .paragraph(&["This is the first line of a paragraph that wraps", "onto a second line of the same paragraph."])
+            .table(&[&["Item", "Qty", "Amount"], &["Paper", "10", "1,200.00"], &["Ink", "2", "300.00"]])
+            .page()
+            .heading(2, "2. Results")
+            .code(&["fn main() {", "    println!(1);", "}"])
+            .paragraph(&["The results are summarised in the table above, and the", "program prints the first value on the console."])
+            .build();
+        let doc = Document::load_mem(&pdf).unwrap();
+        assert_eq!(doc.get_pages().len(), 2);
+
+        let markdown = crate::convert_bytes(&pdf).unwrap();
+        let blocks: Vec<&str> = markdown.split("\n\n").map(str::trim).collect();
+        assert_eq!(blocks[0], "# Annual Report");
+        assert_eq!(blocks[1], "## 1. Introduction");
+        assert_eq!(blocks[2], "This is the first line of a paragraph that wraps onto a second line of the same paragraph.");
+        assert_eq!(blocks[3], "| Item | Qty | Amount |\n| --- | ---: | ---: |\n| Paper | 10 | 1,200.00 |\n| Ink | 2 | 300.00 |");
+        assert!(markdown.contains("## 2. Results\n\n```\nfn main() {\n    println!(1);\n}\n```"), "{}", markdown);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::destinations;
+
+/// Markdown の見出し（コードブロックの中は除く）
+struct Heading<'a> {
+    level: usize,
+    text: &'a str,
+    /// GitHub と同じ形式のアンカー（同じ見出しが続く場合は -1、-2 を付ける）
+    anchor: String,
+    /// 見出しの行の終わりの位置（改行の後）
+    end: usize,
+}
+
+/// 見出しからリンク付きの目次を作り、題名（最初の見出しである唯一の H1）の後に入れる（題名が無ければ文書の先頭）
+///
+/// 目次には、題名より下の見出しを max_depth の階層まで含める（題名が無い場合は H1 から）。
+/// 目次に含める見出しが無い場合はそのまま返す。
+pub fn insert_toc(markdown: &str, max_depth: usize) -> String {
+    let headings = headings(markdown);
+    // H1 が1つだけなら題名とみなす（章ごとに H1 がある場合は、H1 も目次に含めて先頭に入れる）
+    let title = headings.first().filter(|heading| heading.level == 1 && headings.iter().filter(|heading| heading.level == 1).count() == 1);
+    let top = if title.is_some() { 2 } else { 1 };
+    let entries: Vec<&Heading> = headings.iter().skip(title.map_or(0, |_| 1)).filter(|heading| (top..=max_depth).contains(&heading.level)).collect();
+    let Some(base) = entries.iter().map(|heading| heading.level).min() else {
+        return markdown.to_string();
+    };
+
+    let mut toc = String::new();
+    for heading in &entries {
+        toc.push_str(&format!("{}- [{}](#{})\n", "  ".repeat(heading.level - base), heading.text, heading.anchor));
+    }
+
+    let at = title.map_or(0, |title| title.end);
+    let (before, after) = markdown.split_at(at);
+    let separator = if before.is_empty() || before.ends_with("\n\n") { "" } else if before.ends_with('\n') { "\n" } else { "\n\n" };
+    format!("{}{}{}\n{}", before, separator, toc, after.trim_start_matches('\n'))
+}
+
+/// 文書の見出しを順に集める（アンカーの重複は、目次に含めない見出しも含めて数える）
+fn headings(markdown: &str) -> Vec<Heading<'_>> {
+    let mut headings = Vec::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut fence: Option<&str> = None;
+    let mut end = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        end += line.len();
+        let trimmed = line.trim();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                Some(open) => Some(open),
+                None => Some(marker),
+            };
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        let Some(text) = trimmed[level..].strip_prefix(' ').map(str::trim).filter(|_| (1..=6).contains(&level)) else {
+            continue;
+        };
+        let text = text.trim_end_matches('#').trim_end();
+        let slug = destinations::slug(text);
+        let count = used.entry(slug.clone()).or_insert(0);
+        let anchor = if *count == 0 { slug } else { format!("{}-{}", slug, count) };
+        *count += 1;
+        headings.push(Heading { level, text, anchor, end });
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 目次の生成と挿入
+    #[test]
+    fn test_insert_toc() {
+        let markdown = "# Annual Report\n\nIntro.\n\n## 1. Introduction\n\nText.\n\n### Scope\n\n```\n## not a heading\n```\n\n#### Detail\n\n## Results\n\n### Scope\n";
+        assert_eq!(
+            insert_toc(markdown, 3),
+            "# Annual Report\n\n- [1. Introduction](#1-introduction)\n  - [Scope](#scope)\n- [Results](#results)\n  - [Scope](#scope-1)\n\nIntro.\n\n## 1. Introduction\n\nText.\n\n### Scope\n\n```\n## not a heading\n```\n\n#### Detail\n\n## Results\n\n### Scope\n"
+        );
+
+        // 題名が無い場合は先頭に入れ、H1 から含める
+        assert_eq!(insert_toc("## A\n\n## B\n", 2), "- [A](#a)\n- [B](#b)\n\n## A\n\n## B\n");
+        assert_eq!(insert_toc("# Part 1\n\n## A\n\n# Part 2\n", 1), "- [Part 1](#part-1)\n- [Part 2](#part-2)\n\n# Part 1\n\n## A\n\n# Part 2\n");
+        // 目次に含める見出しが無ければそのまま
+        assert_eq!(insert_toc("# Title\n\nBody.\n", 3), "# Title\n\nBody.\n");
+    }
+}
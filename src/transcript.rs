@@ -0,0 +1,127 @@
+use clap::ValueEnum;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// 発言者の書式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptStyle {
+    /// **MR. TANAKA:** 発言
+    Bold,
+    /// 定義リスト（発言者の行の次に「:   発言」を続け、発言をぶら下げて字下げする）
+    Definition,
+}
+
+/// 発言者のラベル（MR. TANAKA: / THE WITNESS: / Q: / A: / 田中：）と発言を分ける正規表現
+static SPEAKER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^((?:(?:MR|MS|MRS|DR|PROF)\.\s+)?[A-Z][A-Z'\-]*(?:\s+[A-Z][A-Z'\-]*){0,3}|Q|A|[\p{Han}\p{Hiragana}\p{Katakana}ー]{1,8})\s*[:：]\s*(.*)$",
+    )
+    .unwrap()
+});
+
+/// 発言者のラベルと同じ形だが、注記や図表の見出しに使うラベル（WARNING: や TABLE A: など。最初の語で判定する）
+const NON_SPEAKER_LABELS: [&str; 28] = [
+    "ABSTRACT", "APPENDIX", "ATTENTION", "CAUTION", "CHAPTER", "DANGER", "DATE", "EXAMPLE", "EXHIBIT", "FIG", "FIGURE", "FROM", "IMPORTANT", "NB",
+    "NOTE", "NOTICE", "PART", "RE", "SECTION", "SOURCE", "STEP", "SUBJECT", "SUMMARY", "TABLE", "TIP", "TITLE", "TO", "WARNING",
+];
+
+/// 発言の行であれば、発言者（1番目）と発言（2番目）に分けた一致を返す
+pub fn speaker_turn(line: &str) -> Option<Captures<'_>> {
+    let caps = SPEAKER_REGEX.captures(line)?;
+    let first_word = caps[1].split_whitespace().next().unwrap_or_default();
+    (!NON_SPEAKER_LABELS.contains(&first_word)).then_some(caps)
+}
+
+/// 文末の記号と、その後の空白
+static SENTENCE_BOUNDARY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([.?!。？！])\s+").unwrap());
+
+/// 行末の敬称（敬称の後のドットは文末ではない）
+static HONORIFIC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|\s)(?:MR|MS|MRS|DR|PROF)\.$").unwrap());
+
+/// 段落の途中（文末の直後）から始まる発言を、別の段落に分ける
+pub fn split_turns(content: &str) -> String {
+    let mut result = String::new();
+
+    for line in content.lines() {
+        let mut start = 0;
+        for caps in SENTENCE_BOUNDARY.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+            let sentence_end = whole.start() + caps[1].len();
+            if !HONORIFIC.is_match(&line[..sentence_end]) && speaker_turn(&line[whole.end()..]).is_some() {
+                result.push_str(&line[start..sentence_end]);
+                result.push_str("\n\n");
+                start = whole.end();
+            }
+        }
+        result.push_str(&line[start..]);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// speaker_turn で分けた発言の行を、発言者と発言に分けて書式に従って出力に書き足す（format は発言部分の書式の変換）
+pub fn push_turn<F: Fn(&mut String, &str)>(markdown: &mut String, style: TranscriptStyle, caps: &Captures, format: F) {
+    let speaker = caps[1].trim();
+    let speech = &caps[2];
+
+    match style {
+        TranscriptStyle::Bold => {
+            markdown.push_str("**");
+            markdown.push_str(speaker);
+            markdown.push_str(":**");
+            if !speech.trim().is_empty() {
+                markdown.push(' ');
+                format(markdown, speech);
+            }
+        }
+        TranscriptStyle::Definition => {
+            markdown.push_str(speaker);
+            markdown.push_str("\n:   ");
+            format(markdown, speech);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 発言の整形
+    #[test]
+    fn test_format_turn() {
+        let test_cases = vec![
+            ("MR. TANAKA: Good morning.", TranscriptStyle::Bold, Some("**MR. TANAKA:** Good morning."), "敬称付きの発言者"),
+            ("THE WITNESS: Yes.", TranscriptStyle::Bold, Some("**THE WITNESS:** Yes."), "複数語の発言者"),
+            ("Q: Where were you?", TranscriptStyle::Definition, Some("Q\n:   Where were you?"), "定義リスト"),
+            ("田中：はい。", TranscriptStyle::Bold, Some("**田中:** はい。"), "日本語の発言者"),
+            ("Note: this is prose.", TranscriptStyle::Bold, None, "大文字でないラベルは対象外"),
+            ("WARNING: Do not unplug.", TranscriptStyle::Bold, None, "注意書きのラベルは対象外"),
+            ("NOTE: See below.", TranscriptStyle::Bold, None, "注記のラベルは対象外"),
+            ("TABLE A: Results", TranscriptStyle::Bold, None, "表の見出しは対象外"),
+            ("TABLE 3: Results", TranscriptStyle::Bold, None, "番号付きの表の見出しは対象外"),
+        ];
+
+        for (line, style, expected, desc) in test_cases {
+            let turn = speaker_turn(line).map(|caps| {
+                let mut markdown = String::new();
+                push_turn(&mut markdown, style, &caps, |markdown, speech| markdown.push_str(speech));
+                markdown
+            });
+            assert_eq!(turn.as_deref(), expected, "Test failed: {}", desc);
+        }
+    }
+
+    // 単体テスト: 段落の途中から始まる発言の分割
+    #[test]
+    fn test_split_turns() {
+        assert_eq!(
+            split_turns("Q: Did you see it? A: I did. It was late."),
+            "Q: Did you see it?\n\nA: I did. It was late.\n"
+        );
+        assert_eq!(split_turns("MR. TANAKA: Hello."), "MR. TANAKA: Hello.\n");
+        assert_eq!(split_turns("Keep it dry. WARNING: Do not unplug."), "Keep it dry. WARNING: Do not unplug.\n");
+    }
+}
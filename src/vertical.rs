@@ -0,0 +1,119 @@
+use lopdf::{Dictionary, Document, Object};
+
+use crate::layout::PageLayout;
+
+/// 縦書きのフォントであることを記録する、フォント辞書のキー（PDF には書き戻さない）
+const VERTICAL_KEY: &[u8] = b"PDF2MDVertical";
+
+/// 縦書きのページとみなす、縦に並んだ文字の組の数の下限
+const MIN_VERTICAL_PAIRS: usize = 8;
+
+/// 縦書きの CID フォント（Encoding が Identity-V）を、pdf-extract が読める Identity-H にして、縦書きであることを記録する
+///
+/// Identity-V と Identity-H は文字コードから CID への対応が同じで、違いは書字方向だけなので、
+/// 文字の位置は抽出時に縦書きとして補正する。置き換えたフォントの数を返す。
+pub fn prepare_vertical_fonts(doc: &mut Document) -> usize {
+    let mut replaced = 0;
+    for object in doc.objects.values_mut() {
+        let Object::Dictionary(font) = object else {
+            continue;
+        };
+        let identity_v = font.get(b"Type").and_then(Object::as_name).is_ok_and(|name| name == b"Font")
+            && font.get(b"Encoding").and_then(Object::as_name).is_ok_and(|name| name == b"Identity-V");
+        if identity_v {
+            font.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+            font.set(VERTICAL_KEY, Object::Boolean(true));
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+/// 縦書きのフォントかどうか（Identity-V から置き換えたもの、名前が -V で終わる CMap、WMode が 1 の埋め込みの CMap）
+pub fn is_vertical_font(doc: &Document, font: &Dictionary) -> bool {
+    if font.get(VERTICAL_KEY).and_then(Object::as_bool).unwrap_or(false) {
+        return true;
+    }
+    match font.get(b"Encoding").ok().and_then(|encoding| doc.dereference(encoding).ok()).map(|(_, encoding)| encoding) {
+        Some(Object::Name(name)) => name.ends_with(b"-V"),
+        Some(Object::Stream(cmap)) => cmap.dict.get(b"WMode").and_then(Object::as_i64).is_ok_and(|mode| mode == 1),
+        _ => false,
+    }
+}
+
+/// 文字の位置から縦書きのページかを判定し、縦書きであれば横書きの読み順にする（右の列から順に、列の中は上から下へ）
+///
+/// 列が行になるようにページを回し、同じ列で続く文字の間には空白を入れない。縦書きにしたかどうかを返す。
+pub fn apply_vertical_writing(page: &mut PageLayout) -> bool {
+    if !is_vertical_writing(page) {
+        return false;
+    }
+    page.rotate(270);
+    // 回した後は列が行になる（x が列の中の位置、y が列の位置）
+    for i in 1..page.glyphs.len() {
+        let (previous, glyph) = (&page.glyphs[i - 1], &page.glyphs[i]);
+        let font_size = previous.font_size.max(glyph.font_size);
+        let gap = glyph.x - previous.x - previous.width;
+        if (glyph.y - previous.y).abs() < font_size * 0.3 && gap > -font_size * 0.5 && gap < font_size * 0.5 {
+            page.glyphs[i].word_start = false;
+        }
+    }
+    true
+}
+
+/// 描画順で続く文字の多くが、同じ x の位置で上から下に並んでいるかどうか
+fn is_vertical_writing(page: &PageLayout) -> bool {
+    let visible: Vec<_> = page.glyphs.iter().filter(|glyph| !glyph.text.trim().is_empty()).collect();
+    let (mut vertical, mut horizontal) = (0, 0);
+    for pair in visible.windows(2) {
+        let font_size = pair[0].font_size.max(pair[1].font_size);
+        let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        if dx.abs() < font_size * 0.3 && dy > font_size * 0.5 && dy < font_size * 2.0 {
+            vertical += 1;
+        } else if dy.abs() < font_size * 0.3 && dx > 0.0 && dx < font_size * 2.0 {
+            horizontal += 1;
+        }
+    }
+    vertical >= MIN_VERTICAL_PAIRS && vertical > horizontal * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Glyph;
+
+    // 単体テスト: 縦書きのページの読み順
+    #[test]
+    fn test_apply_vertical_writing() {
+        let glyph = |text: char, x: f64, y: f64| Glyph {
+            text: text.to_string(),
+            x,
+            y,
+            width: 10.0,
+            font_size: 10.0,
+            word_start: true,
+            order: 0,
+            color: None,
+            bold: false,
+            monospace: false,
+            confidence: None,
+        };
+        // 右の列から順に描かれた2列（2列目は1文字下げた段落の始まり）
+        let mut glyphs = Vec::new();
+        for (column, text) in ["吾輩は猫である。", "名前はまだ無い。"].iter().enumerate() {
+            let x = 500.0 - column as f64 * 16.0;
+            let top = if column == 1 { 110.0 } else { 100.0 };
+            glyphs.extend(text.chars().enumerate().map(|(i, c)| glyph(c, x, top + i as f64 * 10.0)));
+        }
+        let mut page = PageLayout { width: 600.0, height: 800.0, glyphs, ..Default::default() };
+
+        assert!(apply_vertical_writing(&mut page));
+        let lines: Vec<String> = page.lines().into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, ["吾輩は猫である。", "名前はまだ無い。"]);
+        assert_eq!((page.width, page.height), (800.0, 600.0));
+
+        // 横書きのページはそのまま
+        let mut page = PageLayout { glyphs: "横書きの本文です。".chars().enumerate().map(|(i, c)| glyph(c, 72.0 + i as f64 * 10.0, 100.0)).collect(), ..Default::default() };
+        assert!(!apply_vertical_writing(&mut page));
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{bail, Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::batch::{self, BatchInput};
+
+/// 変換を待つ PDF が無い場合に、監視の通知を待つ時間の上限
+const IDLE_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// 変更を受け取った PDF と、最後に変更を受け取った時刻
+#[derive(Debug, Default)]
+struct Pending {
+    changed: HashMap<PathBuf, Instant>,
+}
+
+impl Pending {
+    fn add(&mut self, path: PathBuf, now: Instant) {
+        self.changed.insert(path, now);
+    }
+
+    /// 最後の変更から debounce 以上経った PDF を、パスの順に取り出す（書き込み中の PDF は変更が続くので残る）
+    fn take_ready(&mut self, now: Instant, debounce: Duration) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self.changed.iter().filter(|(_, &changed)| now.duration_since(changed) >= debounce).map(|(path, _)| path.clone()).collect();
+        ready.sort();
+        for path in &ready {
+            self.changed.remove(path);
+        }
+        ready
+    }
+
+    /// 次の PDF を変換できるようになるまでの時間
+    fn timeout(&self, now: Instant, debounce: Duration) -> Duration {
+        self.changed.values().map(|&changed| debounce.saturating_sub(now.duration_since(changed))).min().unwrap_or(IDLE_TIMEOUT)
+    }
+}
+
+/// 監視する PDF であれば、一括変換と同じ入力にする（拡張子が .pdf で、途中に隠しディレクトリを含まないもの）
+fn watched_input(dir: &Path, path: &Path) -> Option<BatchInput> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let hidden = relative.parent().is_some_and(|parent| parent.components().any(|component| matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))));
+    let pdf = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    (pdf && !hidden).then(|| BatchInput { path: path.to_path_buf(), relative: relative.to_path_buf() })
+}
+
+/// 出力が無いか、PDF より古い PDF（監視を始めるときに変換する）
+pub fn outdated(dir: &Path, output_dir: Option<&Path>) -> Result<Vec<BatchInput>> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let inputs = batch::collect_inputs(dir)?;
+    Ok(inputs.into_iter().filter(|input| modified(&batch::output_path(input, output_dir)) < modified(&input.path)).collect())
+}
+
+/// ディレクトリ（サブディレクトリを含む）を監視し、PDF が追加・変更されるたびに convert を呼ぶ
+///
+/// 変更の通知が debounce の間途切れてから呼ぶので、コピー中の PDF を途中で変換することはない。監視を続けられなくなるまで戻らない。
+pub fn watch(dir: &Path, debounce: Duration, mut convert: impl FnMut(&BatchInput)) -> Result<()> {
+    let dir = dir.canonicalize().with_context(|| format!("監視するディレクトリが見つかりません: {:?}", dir))?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("ディレクトリの監視を始められません")?;
+    watcher.watch(&dir, RecursiveMode::Recursive).with_context(|| format!("ディレクトリを監視できません: {:?}", dir))?;
+
+    let mut pending = Pending::default();
+    loop {
+        match receiver.recv_timeout(pending.timeout(Instant::now(), debounce)) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if watched_input(&dir, &path).is_some() {
+                            pending.add(path, Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => tracing::warn!("ディレクトリの監視でエラーが発生しました: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => bail!("ディレクトリの監視が終了しました: {:?}", dir),
+        }
+        for path in pending.take_ready(Instant::now(), debounce) {
+            // 変換する前に削除されたか、別の名前に変えられた PDF は飛ばす
+            if let Some(input) = watched_input(&dir, &path).filter(|input| input.path.is_file()) {
+                convert(&input);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 監視する PDF の判定と、変更が落ち着いた PDF の取り出し
+    #[test]
+    fn test_pending() {
+        let dir = Path::new("/inbox");
+        let input = watched_input(dir, Path::new("/inbox/2024/report.PDF")).unwrap();
+        assert_eq!(input.relative, Path::new("2024/report.PDF"));
+        assert!(watched_input(dir, Path::new("/inbox/report.md")).is_none());
+        assert!(watched_input(dir, Path::new("/inbox/.cache/report.pdf")).is_none());
+        assert!(watched_input(dir, Path::new("/other/report.pdf")).is_none());
+
+        let debounce = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut pending = Pending::default();
+        assert_eq!(pending.timeout(start, debounce), IDLE_TIMEOUT);
+        pending.add(PathBuf::from("/inbox/b.pdf"), start);
+        pending.add(PathBuf::from("/inbox/a.pdf"), start);
+        pending.add(PathBuf::from("/inbox/copying.pdf"), start + Duration::from_millis(400));
+        assert_eq!(pending.timeout(start + Duration::from_millis(100), debounce), Duration::from_millis(400));
+        assert!(pending.take_ready(start + Duration::from_millis(300), debounce).is_empty());
+        assert_eq!(pending.take_ready(start + Duration::from_millis(600), debounce), [PathBuf::from("/inbox/a.pdf"), PathBuf::from("/inbox/b.pdf")]);
+        // 書き込みが続いている PDF は、最後の変更から待ち時間が経つまで残る
+        pending.add(PathBuf::from("/inbox/copying.pdf"), start + Duration::from_millis(700));
+        assert!(pending.take_ready(start + Duration::from_millis(1000), debounce).is_empty());
+        assert_eq!(pending.take_ready(start + Duration::from_millis(1200), debounce), [PathBuf::from("/inbox/copying.pdf")]);
+    }
+}
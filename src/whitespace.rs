@@ -0,0 +1,181 @@
+use clap::ValueEnum;
+
+use crate::lists;
+
+/// 行末の空白の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TrailingSpaces {
+    /// 取り除く
+    #[default]
+    Strip,
+    /// そのまま残す（Markdown の行末2空白による改行を使う場合）
+    Keep,
+}
+
+/// 書き出す Markdown の改行
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LineEnding {
+    /// LF（\n）
+    #[default]
+    Lf,
+    /// CRLF（\r\n。LF の改行を表示できない古いエディタやツール向け）
+    Crlf,
+    /// 実行している OS の既定（Windows では CRLF、それ以外では LF）
+    Native,
+}
+
+impl LineEnding {
+    /// 改行を変換する（変換後の Markdown の改行は LF。元の PDF の文字に含まれていた CRLF も揃える）
+    pub fn apply(self, text: String) -> String {
+        match self {
+            LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+            LineEnding::Native if cfg!(windows) => LineEnding::Crlf.apply(text),
+            _ => text,
+        }
+    }
+}
+
+/// 空白と空行の正規化のオプション
+#[derive(Debug, Clone)]
+pub struct WhitespaceOptions {
+    /// 連続する空行の最大数
+    pub max_blank_lines: usize,
+    /// ブロック内の2行目以降の字下げ（空白の数）。None の場合は元の字下げのまま
+    pub continuation_indent: Option<usize>,
+    pub trailing_spaces: TrailingSpaces,
+    /// 書き出すときの改行（正規化した後の Markdown に目次やフロントマターを加えてから変換する）
+    pub line_ending: LineEnding,
+    /// 段落を折り返す幅（文字数）。None の場合は折り返さない
+    pub wrap: Option<usize>,
+}
+
+impl Default for WhitespaceOptions {
+    fn default() -> Self {
+        WhitespaceOptions { max_blank_lines: 1, continuation_indent: None, trailing_spaces: TrailingSpaces::Strip, line_ending: LineEnding::Lf, wrap: None }
+    }
+}
+
+/// Markdown の空白と空行を正規化する（コードフェンスの内側はそのまま残す）
+///
+/// 先頭と末尾の空行は取り除き、末尾は改行1つで終える。
+pub fn normalize(markdown: &str, options: &WhitespaceOptions) -> String {
+    let mut result = String::new();
+    let mut blank_lines = 0;
+    let mut in_block = false;
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        if in_fence || is_fence {
+            push_pending_blank_lines(&mut result, &mut blank_lines, options);
+            result.push_str(line);
+            result.push('\n');
+            in_fence ^= is_fence;
+            in_block = !in_fence;
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            in_block = false;
+            continue;
+        }
+
+        push_pending_blank_lines(&mut result, &mut blank_lines, options);
+
+        let line = match options.trailing_spaces {
+            TrailingSpaces::Strip => line.trim_end(),
+            TrailingSpaces::Keep => line,
+        };
+        for line in wrap_line(line, options.wrap) {
+            match options.continuation_indent {
+                // 入れ子の箇条書きの字下げは変えない
+                Some(indent) if in_block && !lists::is_markdown_item(line) => {
+                    result.push_str(&" ".repeat(indent));
+                    result.push_str(line.trim_start());
+                }
+                _ => result.push_str(line),
+            }
+            result.push('\n');
+            in_block = true;
+        }
+    }
+
+    result
+}
+
+/// 段落の行を、幅に収まるよう空白の位置で分ける（見出し・表・引用・箇条書き・画像の行と、幅より長い語はそのまま）
+///
+/// 行末の空白（Markdown の行末2空白による改行）は最後の行に残す。
+fn wrap_line(line: &str, width: Option<usize>) -> Vec<&str> {
+    let Some(width) = width.filter(|width| line.chars().count() > *width) else {
+        return vec![line];
+    };
+    let text = line.trim_start();
+    if text.starts_with(['#', '|', '>', '<', '!']) || lists::is_markdown_item(text) {
+        return vec![line];
+    }
+
+    let mut lines = Vec::new();
+    // 行の途中で分ける位置（語の先頭と末尾。行頭の字下げは最初の語に含める）
+    let (mut start, mut end, mut offset) = (0, 0, 0);
+    for word in line.split(' ') {
+        let (from, to) = (offset, offset + word.len());
+        offset = to + 1;
+        if word.is_empty() {
+            continue;
+        }
+        if end > start && line[start..to].chars().count() > width {
+            lines.push(&line[start..end]);
+            start = from;
+        }
+        end = to;
+    }
+    lines.push(&line[start..]);
+    lines
+}
+
+/// 溜まった空行を上限まで出力する（文書の先頭では出力しない）
+fn push_pending_blank_lines(result: &mut String, blank_lines: &mut usize, options: &WhitespaceOptions) {
+    if !result.is_empty() {
+        (0..(*blank_lines).min(options.max_blank_lines)).for_each(|_| result.push('\n'));
+    }
+    *blank_lines = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 単体テスト: 空白と空行の正規化
+    #[test]
+    fn test_normalize() {
+        let options = |max_blank_lines, continuation_indent, trailing_spaces| WhitespaceOptions {
+            max_blank_lines,
+            continuation_indent,
+            trailing_spaces,
+            line_ending: LineEnding::Lf,
+            wrap: None,
+        };
+
+        let test_cases = vec![
+            ("\n\n# Title\n\n\n\n\nText  \n\n\n", options(1, None, TrailingSpaces::Strip), "# Title\n\nText\n", "空行の上限と行末の空白"),
+            ("a\n\n\n\nb", options(2, None, TrailingSpaces::Strip), "a\n\n\nb\n", "空行2行まで"),
+            ("a  \nb", options(1, None, TrailingSpaces::Keep), "a  \nb\n", "行末の空白を残す"),
+            ("- item\n      more\nnext", options(1, Some(2), TrailingSpaces::Strip), "- item\n  more\n  next\n", "2行目以降の字下げ"),
+            ("```\n  code  \n\n\n\n```\n\n\n\nafter", options(1, Some(0), TrailingSpaces::Strip), "```\n  code  \n\n\n\n```\n\nafter\n", "コードフェンスの内側"),
+        ];
+
+        for (input, options, expected, desc) in test_cases {
+            assert_eq!(normalize(input, &options), expected, "Test failed: {}", desc);
+        }
+
+        // 段落の折り返し（見出しと箇条書きは折り返さない）
+        let wrap = WhitespaceOptions { wrap: Some(12), ..Default::default() };
+        assert_eq!(normalize("one two three four five\n\n# A long heading line\n- a long list item", &wrap), "one two\nthree four\nfive\n\n# A long heading line\n- a long list item\n");
+        assert_eq!(wrap_line("a verylongwordhere b  ", Some(8)), ["a", "verylongwordhere", "b  "]);
+
+        assert_eq!(LineEnding::Crlf.apply("# A\r\n\nB\n".to_string()), "# A\r\n\r\nB\r\n");
+        assert_eq!(LineEnding::Lf.apply("a\nb\n".to_string()), "a\nb\n");
+    }
+}